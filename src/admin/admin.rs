@@ -5,6 +5,7 @@
 use crate::{
 	Context,
 	appservice::{self, AppserviceCommand},
+	check::{self, CheckCommand},
 	debug::{self, DebugCommand},
 	federation::{self, FederationCommand},
 	media::{self, MediaCommand},
@@ -61,6 +62,10 @@ pub(super) enum AdminCommand {
 	/// - Commands for managing media
 	Media(MediaCommand),
 
+	#[command(subcommand)]
+	/// - Commands for checking the integrity of the database
+	Check(CheckCommand),
+
 	#[command(subcommand)]
 	/// - Commands for debugging things
 	Debug(DebugCommand),
@@ -85,6 +90,7 @@ pub(super) async fn process(command: AdminCommand, context: &Context<'_>) -> Res
 		| Rooms(command) => room::process(command, context).await,
 		| Federation(command) => federation::process(command, context).await,
 		| Server(command) => server::process(command, context).await,
+		| Check(command) => check::process(command, context).await,
 		| Debug(command) => debug::process(command, context).await,
 		| Query(command) => query::process(command, context).await,
 		| Token(command) => token::process(command, context).await,
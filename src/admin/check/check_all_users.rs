@@ -0,0 +1,78 @@
+use std::fmt::Write;
+
+use futures::StreamExt;
+use tokio::time::Instant;
+use tuwunel_core::{
+	Result,
+	utils::{hash, stream::ReadyExt},
+};
+use tuwunel_service::users::{PASSWORD_DISABLED, PASSWORD_SENTINEL};
+
+use crate::admin_command;
+
+#[admin_command]
+pub(super) async fn check_all_users(&self) -> Result {
+	let timer = Instant::now();
+	let users: Vec<_> = self
+		.services
+		.users
+		.stream()
+		.ready_filter(|user_id| self.services.globals.user_is_local(user_id))
+		.map(ToOwned::to_owned)
+		.collect()
+		.await;
+
+	let (mut malformed, mut deactivated, mut deviceless) = (0_usize, 0_usize, 0_usize);
+	let mut flagged = String::new();
+	for user_id in &users {
+		let has_devices = self
+			.services
+			.users
+			.all_device_ids(user_id)
+			.ready_any(|_| true)
+			.await;
+
+		// Guests and appservice users have no password either; only an account
+		// deactivated as such is expected to be logged out.
+		let problem = match self.services.users.password_hash(user_id).await {
+			| Ok(hash)
+				if hash == PASSWORD_DISABLED
+					&& has_devices && self.services.users.was_deactivated(user_id).await =>
+			{
+				deactivated = deactivated.saturating_add(1);
+				"deactivated but still logged in"
+			},
+			| Ok(hash) if hash == PASSWORD_DISABLED => continue,
+			| Ok(hash) if hash != PASSWORD_SENTINEL && !hash::is_password_hash(&hash) => {
+				malformed = malformed.saturating_add(1);
+				"malformed password hash"
+			},
+			| Err(_) => {
+				malformed = malformed.saturating_add(1);
+				"missing password hash"
+			},
+			| Ok(_) if !has_devices => {
+				deviceless = deviceless.saturating_add(1);
+				"no devices"
+			},
+			| Ok(_) => continue,
+		};
+
+		writeln!(flagged, "{user_id}\t{problem}")?;
+	}
+
+	let query_time = timer.elapsed();
+	let total = users.len();
+	let flagged_count = malformed
+		.saturating_add(deactivated)
+		.saturating_add(deviceless);
+
+	write!(
+		self,
+		"Checked {total} users in {query_time:?}:\n\n```\n{flagged}```\nMissing or malformed \
+		 password hashes: {malformed}\nDeactivated but still logged in: {deactivated}\nNo \
+		 devices: {deviceless}\nValid users: {valid}",
+		valid = total.saturating_sub(flagged_count),
+	)
+	.await
+}
@@ -0,0 +1,16 @@
+mod check_all_users;
+
+use clap::Subcommand;
+use tuwunel_core::Result;
+
+use crate::admin_command_dispatch;
+
+#[admin_command_dispatch]
+#[derive(Debug, Subcommand)]
+pub(crate) enum CheckCommand {
+	/// - Scan every local user for accounts in an inconsistent state
+	///
+	/// Flags missing or malformed password hashes, deactivated accounts which
+	/// still have devices, and accounts without any devices.
+	CheckAllUsers,
+}
@@ -1,28 +1,21 @@
-use tracing_subscriber::EnvFilter;
-use tuwunel_core::{Result, err};
+use tuwunel_core::{Error, Result, err};
 
-use crate::admin_command;
+use crate::{admin_command, utils::reload_log_filter};
 
 #[admin_command]
 pub(super) async fn change_log_level(&self, filter: Option<String>, reset: bool) -> Result {
-	let handles = &["console"];
-
 	let filter = reset
 		.then_some(&self.services.config.log)
 		.or(filter.as_ref())
 		.ok_or_else(|| err!("No log level was specified."))?;
 
-	let filter_layer = EnvFilter::try_new(filter).map_err(|e| {
-		let source = if !reset { "specified" } else { "found in config" };
-		err!("Invalid log level filter {source}: {e}")
+	reload_log_filter(self.services, filter).map_err(|e| match e {
+		| Error::TracingFilter(e) => {
+			let source = if !reset { "specified" } else { "found in config" };
+			err!("Invalid log level filter {source}: {e}")
+		},
+		| e => err!("Failed to modify and reload the global tracing log level: {e}"),
 	})?;
 
-	self.services
-		.server
-		.log
-		.reload
-		.reload(&filter_layer, Some(handles))
-		.map_err(|e| err!("Failed to modify and reload the global tracing log level: {e}"))?;
-
 	write!(self, "Successfully changed log level to {filter}").await
 }
@@ -0,0 +1,33 @@
+use tuwunel_core::{Result, metrics::PduOutcome};
+
+use crate::admin_command;
+
+#[admin_command]
+pub(super) async fn incoming_pdu_metrics(&self) -> Result {
+	let metrics = &self.services.server.metrics.incoming_pdu;
+
+	writeln!(self, "| outcome | count | mean | buckets (ms) |").await?;
+	writeln!(self, "| ------- | ----- | ---- | ------------ |").await?;
+	for outcome in PduOutcome::ALL {
+		let histogram = metrics.get(outcome);
+		let count = histogram.count();
+		let mean = histogram
+			.sum()
+			.checked_div(u32::try_from(count).unwrap_or(u32::MAX))
+			.unwrap_or_default();
+
+		let buckets = histogram
+			.buckets()
+			.filter(|&(_, samples)| samples > 0)
+			.map(|(bound, samples)| match bound {
+				| Some(bound) => format!("<{bound}: {samples}"),
+				| None => format!("more: {samples}"),
+			})
+			.collect::<Vec<_>>()
+			.join(", ");
+
+		writeln!(self, "| {outcome} | {count} | {mean:?} | {buckets} |").await?;
+	}
+
+	Ok(())
+}
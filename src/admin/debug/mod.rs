@@ -17,11 +17,16 @@
 mod get_short_pdu;
 mod get_signing_keys;
 mod get_verify_keys;
+mod incoming_pdu_metrics;
 mod latest_pdu_in_room;
 mod list_dependencies;
 mod memory_stats;
+mod outgoing_federation_metrics;
 mod parse_pdu;
 mod ping;
+mod prune_outliers;
+mod resolve_alias;
+mod resolve_state;
 mod resolve_true_destination;
 mod resync_database;
 mod runtime_interval;
@@ -34,9 +39,10 @@
 mod trim_memory;
 mod verify_json;
 mod verify_pdu;
+mod verify_room_servers;
 
 use clap::Subcommand;
-use ruma::{OwnedEventId, OwnedRoomId, OwnedRoomOrAliasId, OwnedServerName};
+use ruma::{OwnedEventId, OwnedRoomAliasId, OwnedRoomId, OwnedRoomOrAliasId, OwnedServerName};
 use tuwunel_core::Result;
 use tuwunel_service::rooms::short::ShortRoomId;
 
@@ -66,6 +72,14 @@ pub(super) enum DebugCommand {
 	/// the command.
 	ParsePdu,
 
+	/// - Prune outliers older than a duration which no accepted event depends
+	///   on
+	///
+	/// The duration is given as e.g. "30d" or "12h".
+	PruneOutliers {
+		older_than: String,
+	},
+
 	/// - Retrieve and print a PDU by EventID from the tuwunel database
 	GetPdu {
 		/// An event ID (a $ followed by the base64 reference hash)
@@ -170,11 +184,23 @@ pub(super) enum DebugCommand {
 
 	/// - Verify PDU
 	///
-	/// This re-verifies a PDU existing in the database found by ID.
+	/// This re-verifies a PDU existing in the database found by ID, reporting
+	/// the outcome of each signature and of the content hash.
 	VerifyPdu {
 		event_id: OwnedEventId,
 	},
 
+	/// - Cross-check the forward and reverse server maps of a room
+	///
+	/// This scans the rooms of every known server.
+	VerifyRoomServers {
+		room_id: OwnedRoomId,
+
+		/// Restore or remove mismatched entries based on joined members
+		#[arg(long)]
+		repair: bool,
+	},
+
 	/// - Prints the very first PDU in the specified room (typically
 	///   m.room.create)
 	FirstPduInRoom {
@@ -210,6 +236,23 @@ pub(super) enum DebugCommand {
 		server_name: OwnedServerName,
 	},
 
+	/// - Resolve a room alias, showing each step of the resolution
+	///
+	/// Local aliases are looked up in the database and then offered to any
+	/// appservice whose namespace matches; remote aliases are queried from the
+	/// alias's server over federation.
+	ResolveAlias {
+		alias: OwnedRoomAliasId,
+	},
+
+	/// - Re-run state resolution over a room's forward extremities
+	///
+	/// The result is compared to the stored current state of the room and any
+	/// divergence is reported. Nothing is changed.
+	ResolveState {
+		room_id: OwnedRoomId,
+	},
+
 	/// - Runs a server name through tuwunel's true destination resolution
 	///   process
 	///
@@ -240,6 +283,16 @@ pub(super) enum DebugCommand {
 	/// - Print detailed tokio task metrics accumulated in total.
 	TaskMetrics,
 
+	/// - Print processing latency of incoming federation PDUs by outcome.
+	IncomingPduMetrics,
+
+	/// - Print counts of PDUs and EDUs delivered over federation, in total and
+	///   for the busiest destinations.
+	OutgoingFederationMetrics {
+		#[arg(short, long, default_value("10"))]
+		limit: usize,
+	},
+
 	/// - Print detailed tokio task metrics accumulated since last command
 	///   invocation.
 	TaskInterval,
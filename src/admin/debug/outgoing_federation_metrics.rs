@@ -0,0 +1,27 @@
+use tuwunel_core::{Result, metrics::OutgoingKind};
+
+use crate::admin_command;
+
+#[admin_command]
+pub(super) async fn outgoing_federation_metrics(&self, limit: usize) -> Result {
+	let metrics = &self.services.server.metrics.outgoing;
+
+	let header = OutgoingKind::ALL
+		.map(OutgoingKind::as_str)
+		.join(" | ");
+	let rule = OutgoingKind::ALL.map(|_| "---").join(" | ");
+	writeln!(self, "| destination | {header} |").await?;
+	writeln!(self, "| ----------- | {rule} |").await?;
+
+	let totals = OutgoingKind::ALL
+		.map(|kind| metrics.total(kind).to_string())
+		.join(" | ");
+
+	writeln!(self, "| (total) | {totals} |").await?;
+	for (dest, counts) in metrics.destinations().into_iter().take(limit) {
+		let counts = counts.map(|count| count.to_string()).join(" | ");
+		writeln!(self, "| {dest} | {counts} |").await?;
+	}
+
+	Ok(())
+}
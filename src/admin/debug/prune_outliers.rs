@@ -0,0 +1,15 @@
+use tuwunel_core::{Result, utils::time::parse_duration};
+
+use crate::admin_command;
+
+#[admin_command]
+pub(super) async fn prune_outliers(&self, older_than: String) -> Result {
+	let older_than = parse_duration(&older_than)?;
+	let pruned = self
+		.services
+		.timeline
+		.prune_outliers(older_than)
+		.await;
+
+	write!(self, "Pruned {pruned} outlier(s).").await
+}
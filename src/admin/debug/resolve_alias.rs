@@ -0,0 +1,38 @@
+use ruma::OwnedRoomAliasId;
+use tuwunel_core::Result;
+
+use crate::admin_command;
+
+#[admin_command]
+pub(super) async fn resolve_alias(&self, alias: OwnedRoomAliasId) -> Result {
+	let aliases = &self.services.alias;
+
+	if self.services.globals.alias_is_local(&alias) {
+		writeln!(self, "{alias} is local; checking the database...").await?;
+		match aliases.resolve_local_alias(&alias).await {
+			| Ok(room_id) => return write!(self, "Resolved locally to {room_id}").await,
+			| Err(e) => writeln!(self, "Not found locally: {e}").await?,
+		}
+
+		writeln!(self, "Asking matching appservices...").await?;
+		return match aliases.resolve_appservice_alias(&alias).await {
+			| Ok(room_id) => write!(self, "Resolved by appservice to {room_id}").await,
+			| Err(e) => write!(self, "Not resolved by any appservice: {e}").await,
+		};
+	}
+
+	let server = alias.server_name();
+	writeln!(self, "{alias} is remote; querying {server} over federation...").await?;
+	let (room_id, servers) = match aliases.remote_resolve(&alias).await {
+		| Ok(resolved) => resolved,
+		| Err(e) => return write!(self, "Remote resolution via {server} failed: {e}").await,
+	};
+
+	let servers = servers
+		.iter()
+		.map(ToString::to_string)
+		.collect::<Vec<_>>()
+		.join(", ");
+
+	write!(self, "Resolved remotely to {room_id}\nServers returned by {server}: {servers}").await
+}
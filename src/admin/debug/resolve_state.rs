@@ -0,0 +1,75 @@
+use std::time::Instant;
+
+use futures::StreamExt;
+use ruma::OwnedRoomId;
+use tuwunel_core::{Err, Result, utils::calculate_hash};
+
+use crate::admin_command;
+
+#[admin_command]
+pub(super) async fn resolve_state(&self, room_id: OwnedRoomId) -> Result {
+	let extremities = self
+		.services
+		.state
+		.get_forward_extremities(&room_id)
+		.count()
+		.await;
+
+	let timer = Instant::now();
+	let resolved = self
+		.services
+		.event_handler
+		.resolve_forward_extremities(&room_id)
+		.await?;
+
+	let elapsed = timer.elapsed();
+	let state_hash = calculate_hash(resolved.iter().map(|bytes| &bytes[..]));
+	let resolved_hash = self
+		.services
+		.short
+		.get_shortstatehash(&state_hash)
+		.await
+		.map_or_else(|_| "not yet stored".to_owned(), |hash| hash.to_string());
+
+	writeln!(
+		self,
+		"Resolved state of {room_id} across {extremities} forward extremities in {elapsed:?}: \
+		 {} events, shortstatehash {resolved_hash}.",
+		resolved.len(),
+	)
+	.await?;
+
+	let Ok(current) = self
+		.services
+		.state
+		.get_room_shortstatehash(&room_id)
+		.await
+	else {
+		return Err!("Room {room_id} has no stored current state to compare against.");
+	};
+
+	let stored = self
+		.services
+		.state_compressor
+		.load_shortstatehash_info(current)
+		.await?
+		.pop()
+		.map(|info| info.full_state)
+		.unwrap_or_default();
+
+	writeln!(self, "Stored current state: {} events, shortstatehash {current}.", stored.len())
+		.await?;
+
+	if *stored == *resolved {
+		return write!(self, "The resolved state matches the stored current state.").await;
+	}
+
+	let only_resolved = resolved.difference(&stored).count();
+	let only_stored = stored.difference(&resolved).count();
+	write!(
+		self,
+		"DIVERGED: {only_resolved} entries only in the resolved state, {only_stored} only in \
+		 the stored current state.",
+	)
+	.await
+}
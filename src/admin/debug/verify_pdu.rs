@@ -1,26 +1,68 @@
-use ruma::{OwnedEventId, signatures::Verified};
-use tuwunel_core::Result;
+use ruma::{CanonicalJsonValue, OwnedEventId, RoomId};
+use tuwunel_core::{Err, Result, err, matrix::room_version};
+use tuwunel_service::server_keys::{HashCheck, SignatureCheck};
 
 use crate::admin_command;
 
 #[admin_command]
 pub(super) async fn verify_pdu(&self, event_id: OwnedEventId) -> Result {
-	let mut event = self
+	let Ok(mut event) = self
 		.services
 		.timeline
 		.get_pdu_json(&event_id)
+		.await
+	else {
+		return Err!("Event not found.");
+	};
+
+	let room_id_str = event
+		.get("room_id")
+		.and_then(CanonicalJsonValue::as_str)
+		.ok_or_else(|| err!(Database("Invalid event in database")))?;
+
+	let room_id = <&RoomId>::try_from(room_id_str)
+		.map_err(|_| err!(Database("Invalid room id field in event in database")))?;
+
+	let room_version = self
+		.services
+		.state
+		.get_room_version(room_id)
 		.await?;
 
-	event.remove("event_id");
-	let msg = match self
+	// For v3+ rooms the event_id is added locally and is not covered by hashes.
+	if !room_version::rules(&room_version)?
+		.event_format
+		.require_event_id
+	{
+		event.remove("event_id");
+	}
+
+	let verification = self
 		.services
 		.server_keys
-		.verify_event(&event, None)
-		.await?
-	{
-		| Verified::Signatures => "signatures OK, but content hash failed (redaction).",
-		| Verified::All => "signatures and hashes OK.",
+		.check_event(&event, &room_version)
+		.await?;
+
+	let required_keys = if verification.required_keys_exist { "yes" } else { "no" };
+	writeln!(self, "Room version: {room_version}").await?;
+	writeln!(self, "Required keys cached: {required_keys}").await?;
+
+	for (server, key_id, check) in &verification.signatures {
+		let result = match check {
+			| SignatureCheck::Valid => "OK",
+			| SignatureCheck::Invalid => "FAILED",
+			| SignatureCheck::KeyNotFound => "key not found",
+			| SignatureCheck::Malformed => "malformed",
+		};
+
+		writeln!(self, "Signature {server} {key_id}: {result}").await?;
+	}
+
+	let content_hash = match verification.content_hash {
+		| HashCheck::Valid => "OK",
+		| HashCheck::Mismatch => "MISMATCH (redacted or tampered)",
+		| HashCheck::Missing => "missing",
 	};
 
-	self.write_str(msg).await
+	write!(self, "Content hash: {content_hash}").await
 }
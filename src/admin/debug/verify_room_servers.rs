@@ -0,0 +1,31 @@
+use ruma::OwnedRoomId;
+use tuwunel_core::Result;
+
+use crate::admin_command;
+
+#[admin_command]
+pub(super) async fn verify_room_servers(&self, room_id: OwnedRoomId, repair: bool) -> Result {
+	let report = self
+		.services
+		.state_cache
+		.verify_server_room_consistency(&room_id, repair)
+		.await?;
+
+	if report.is_consistent() {
+		return write!(self, "Server maps of {room_id} are consistent.").await;
+	}
+
+	for server in &report.missing_reverse {
+		writeln!(self, "{server} is listed for the room but has no reverse entry.").await?;
+	}
+
+	for server in &report.missing_forward {
+		writeln!(self, "{server} lists the room but has no forward entry.").await?;
+	}
+
+	if report.repaired {
+		write!(self, "Repaired.").await
+	} else {
+		write!(self, "Run again with --repair to fix.").await
+	}
+}
@@ -0,0 +1,142 @@
+use std::fmt::Write;
+
+use futures::future::join;
+use ruma::{
+	MilliSecondsSinceUnixEpoch, OwnedServerName, ServerName,
+	api::federation::discovery::{ServerSigningKeys, get_server_version},
+};
+use tuwunel_core::{Err, Result, err, utils::time};
+
+use crate::admin_command;
+
+#[admin_command]
+pub(super) async fn info(&self, server: OwnedServerName) -> Result {
+	if self.services.globals.server_is_ours(&server) {
+		return Err!("This is our own server; ask a remote server instead.");
+	}
+
+	let version = self
+		.services
+		.federation
+		.execute(&server, get_server_version::v1::Request {});
+
+	let keys = self.services.server_keys.server_request(&server);
+
+	let (version, keys) = join(version, keys).await;
+	let version = version.map_err(|e| err!("Failed fetching the version of {server}:\n\n{e}"))?;
+
+	let keys = keys.map_err(|e| err!("Failed fetching the keys of {server}:\n\n{e}"))?;
+
+	self.write_str(&format_info(&server, &version, &keys)?)
+		.await
+}
+
+fn format_info(
+	server: &ServerName,
+	version: &get_server_version::v1::Response,
+	keys: &ServerSigningKeys,
+) -> Result<String> {
+	let software = version.server.as_ref();
+	let name = software
+		.and_then(|software| software.name.as_deref())
+		.unwrap_or("unknown software");
+
+	let version = software
+		.and_then(|software| software.version.as_deref())
+		.unwrap_or("of unknown version");
+
+	let mut out = format!("{server} runs {name} {version}.\n\n");
+
+	writeln!(out, "Active keys, valid until {}:", timestamp(keys.valid_until_ts))?;
+
+	for key_id in keys.verify_keys.keys() {
+		writeln!(out, "- {key_id}")?;
+	}
+
+	if !keys.old_verify_keys.is_empty() {
+		writeln!(out, "\nOld keys:")?;
+		for (key_id, old) in &keys.old_verify_keys {
+			writeln!(out, "- {key_id}, expired {}", timestamp(old.expired_ts))?;
+		}
+	}
+
+	Ok(out)
+}
+
+fn timestamp(ts: MilliSecondsSinceUnixEpoch) -> String {
+	ts.to_system_time()
+		.map_or_else(|| ts.get().to_string(), |ts| time::format(ts, "%+"))
+}
+
+#[cfg(test)]
+mod tests {
+	use ruma::{
+		api::{
+			IncomingResponse,
+			federation::discovery::{ServerSigningKeys, get_server_version},
+		},
+		server_name,
+	};
+	use tuwunel_core::http;
+
+	use super::format_info;
+
+	fn version(body: &str) -> get_server_version::v1::Response {
+		let response = http::Response::builder()
+			.status(200)
+			.body(body.as_bytes().to_vec())
+			.expect("valid http response");
+
+		get_server_version::v1::Response::try_from_http_response(response)
+			.expect("valid version response")
+	}
+
+	fn keys(body: &str) -> ServerSigningKeys {
+		serde_json::from_str(body).expect("valid server keys")
+	}
+
+	#[test]
+	fn shows_version_and_key_ids() {
+		let version = version(r#"{"server":{"name":"Synapse","version":"1.99.0"}}"#);
+		let keys = keys(
+			r#"{
+				"server_name": "remote.example",
+				"verify_keys": {"ed25519:current": {"key": "VGVzdEtleQ"}},
+				"old_verify_keys": {
+					"ed25519:retired": {"key": "T2xkS2V5", "expired_ts": 1700000000000}
+				},
+				"signatures": {},
+				"valid_until_ts": 1800000000000
+			}"#,
+		);
+
+		let out =
+			format_info(server_name!("remote.example"), &version, &keys).expect("formatted");
+
+		assert!(out.contains("remote.example runs Synapse 1.99.0."), "{out}");
+		assert!(out.contains("valid until 2027-01-15T08:00:00+00:00"), "{out}");
+		assert!(out.contains("- ed25519:current\n"), "{out}");
+		assert!(out.contains("- ed25519:retired, expired 2023-11-14T22:13:20+00:00"), "{out}");
+	}
+
+	#[test]
+	fn tolerates_undisclosed_version() {
+		let version = version("{}");
+		let keys = keys(
+			r#"{
+				"server_name": "remote.example",
+				"verify_keys": {"ed25519:current": {"key": "VGVzdEtleQ"}},
+				"old_verify_keys": {},
+				"signatures": {},
+				"valid_until_ts": 1800000000000
+			}"#,
+		);
+
+		let out =
+			format_info(server_name!("remote.example"), &version, &keys).expect("formatted");
+
+		assert!(out.contains("runs unknown software of unknown version."), "{out}");
+		assert!(out.contains("- ed25519:current"), "{out}");
+		assert!(!out.contains("Old keys"), "{out}");
+	}
+}
@@ -0,0 +1,15 @@
+use ruma::OwnedServerName;
+use tuwunel_core::Result;
+
+use crate::admin_command;
+
+#[admin_command]
+pub(super) async fn kick(&self, server_name: OwnedServerName) -> Result {
+	let count = self
+		.services
+		.sending
+		.kick_server(&server_name)
+		.await?;
+
+	write!(self, "Dispatched {count} queued event(s) to {server_name}.").await
+}
@@ -2,10 +2,14 @@
 mod enable_room;
 mod fetch_support_well_known;
 mod incoming_federation;
+mod info;
+mod kick;
+mod queue_show;
 mod remote_user_in_rooms;
+mod room_version;
 
 use clap::Subcommand;
-use ruma::{OwnedRoomId, OwnedServerName, OwnedUserId};
+use ruma::{OwnedEventId, OwnedRoomId, OwnedServerName, OwnedUserId};
 use tuwunel_core::Result;
 
 use crate::admin_command_dispatch;
@@ -39,8 +43,41 @@ pub(super) enum FederationCommand {
 		server_name: OwnedServerName,
 	},
 
+	/// - Probe a remote server's software version and signing keys
+	///
+	/// Prints the implementation name and version it reports along with its
+	/// active and old key IDs and their validity.
+	Info {
+		server: OwnedServerName,
+	},
+
+	/// - Send everything queued for a server now instead of waiting for the
+	///   next retry
+	///
+	/// Clears the server's backoff once, e.g. after it came back online.
+	Kick {
+		server_name: OwnedServerName,
+	},
+
+	/// - Show the queue entry of an event awaiting delivery to a server
+	///
+	/// Prints its queue ID, the number of failed attempts to reach the server
+	/// and when the next attempt may be made.
+	QueueShow {
+		server: OwnedServerName,
+		event_id: OwnedEventId,
+	},
+
 	/// - Lists all the rooms we share/track with the specified *remote* user
 	RemoteUserInRooms {
 		user_id: OwnedUserId,
 	},
+
+	/// - Ask a remote server for the version of a room without joining it
+	///
+	/// The server defaults to the one in the room ID
+	RoomVersion {
+		room_id: OwnedRoomId,
+		server: Option<OwnedServerName>,
+	},
 }
@@ -0,0 +1,27 @@
+use ruma::{OwnedEventId, OwnedServerName};
+use tuwunel_core::{Result, utils::time};
+
+use crate::admin_command;
+
+#[admin_command]
+pub(super) async fn queue_show(&self, server: OwnedServerName, event_id: OwnedEventId) -> Result {
+	let queued = self
+		.services
+		.sending
+		.queued_event(&server, &event_id)
+		.await?;
+
+	let state = if queued.active { "in flight" } else { "queued" };
+	let next_attempt = queued
+		.next_attempt
+		.map_or_else(|| "now".to_owned(), |ts| time::format(ts, "%+"));
+
+	write!(
+		self,
+		"Event {event_id} is {state} for {server}.\n- queue id: {}\n- retries: {}\n- next \
+		 attempt: {next_attempt}",
+		queued.queue_id.escape_ascii(),
+		queued.retries,
+	)
+	.await
+}
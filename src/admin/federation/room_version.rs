@@ -0,0 +1,132 @@
+use ruma::{
+	OwnedRoomId, OwnedServerName, RoomVersionId,
+	api::{error::ErrorKind, federation::membership::prepare_join_event},
+};
+use tuwunel_core::{Err, Result, err};
+
+use crate::admin_command;
+
+#[admin_command]
+pub(super) async fn room_version(
+	&self,
+	room_id: OwnedRoomId,
+	server: Option<OwnedServerName>,
+) -> Result {
+	let Some(server) = server.or_else(|| room_id.server_name().map(ToOwned::to_owned)) else {
+		return Err!("This room ID has no server name; specify a server to ask.");
+	};
+
+	if self.services.globals.server_is_ours(&server) {
+		return Err!("Use `room info` for rooms on this server.");
+	}
+
+	// Probe with a make_join on behalf of the server user; the server reports the
+	// room version without anyone joining.
+	let response = self
+		.services
+		.federation
+		.execute(&server, prepare_join_event::v1::Request {
+			room_id: room_id.clone(),
+			user_id: self.services.globals.server_user.clone(),
+			ver: self
+				.services
+				.config
+				.supported_room_versions()
+				.map(|(version, _)| version)
+				.collect(),
+		})
+		.await;
+
+	let room_version = remote_room_version(response).map_err(|e| {
+		err!(
+			"{server} did not disclose the version of {room_id}; it may not be in the room or \
+			 may refuse to let us join:\n\n{e}"
+		)
+	})?;
+
+	let supported = if self
+		.services
+		.config
+		.supported_room_version(&room_version)
+	{
+		"supported"
+	} else {
+		"not supported"
+	};
+
+	write!(self, "{room_id} on {server} is room version {room_version} ({supported} here).").await
+}
+
+/// Room version disclosed by a make_join response. A server refusing us for
+/// an incompatible version still names it; a response without a version is
+/// for a version 1 or 2 room, reported as 1.
+fn remote_room_version(
+	response: Result<prepare_join_event::v1::Response>,
+) -> Result<RoomVersionId> {
+	match response {
+		| Ok(response) => Ok(response.room_version.unwrap_or(RoomVersionId::V1)),
+		| Err(e) => match e.kind() {
+			| ErrorKind::IncompatibleRoomVersion(data) => Ok(data.room_version),
+			| _ => Err(e),
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use ruma::{
+		RoomVersionId,
+		api::{
+			IncomingResponse,
+			error::{ErrorKind, IncompatibleRoomVersionErrorData},
+			federation::membership::prepare_join_event,
+		},
+	};
+	use tuwunel_core::{Error, err, http};
+
+	use super::remote_room_version;
+
+	fn response(body: &str) -> prepare_join_event::v1::Response {
+		let response = http::Response::builder()
+			.status(200)
+			.body(body.as_bytes().to_vec())
+			.expect("valid http response");
+
+		prepare_join_event::v1::Response::try_from_http_response(response)
+			.expect("valid make_join response")
+	}
+
+	#[test]
+	fn reports_version_from_make_join() {
+		let body = r#"{"room_version":"10","event":{"type":"m.room.member"}}"#;
+		let version = remote_room_version(Ok(response(body))).expect("version reported");
+
+		assert_eq!(version, RoomVersionId::V10, "version taken from the response");
+	}
+
+	#[test]
+	fn missing_version_is_v1() {
+		let body = r#"{"event":{"type":"m.room.member"}}"#;
+		let version = remote_room_version(Ok(response(body))).expect("version reported");
+
+		assert_eq!(version, RoomVersionId::V1, "absent version means v1 or v2");
+	}
+
+	#[test]
+	fn incompatible_version_is_reported() {
+		let kind = ErrorKind::IncompatibleRoomVersion(IncompatibleRoomVersionErrorData::new(
+			RoomVersionId::V11,
+		));
+		let error = Error::BadRequest(kind, "Room version not supported.");
+		let version = remote_room_version(Err(error)).expect("version reported");
+
+		assert_eq!(version, RoomVersionId::V11, "version taken from the error");
+	}
+
+	#[test]
+	fn refusal_is_an_error() {
+		let error = err!(Request(Forbidden("You are not invited to this room.")));
+
+		assert!(remote_room_version(Err(error)).is_err(), "refusal does not disclose a version");
+	}
+}
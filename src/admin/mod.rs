@@ -7,6 +7,7 @@
 pub(crate) mod utils;
 
 pub(crate) mod appservice;
+pub(crate) mod check;
 pub(crate) mod debug;
 pub(crate) mod federation;
 pub(crate) mod media;
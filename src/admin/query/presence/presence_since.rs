@@ -9,7 +9,6 @@ pub(super) async fn presence_presence_since(&self, since: u64, to: Option<u64>)
 		.services
 		.presence
 		.presence_since(since, to)
-		.map(|(user_id, count, bytes)| (user_id.to_owned(), count, bytes.to_vec()))
 		.collect::<Vec<(_, _, _)>>();
 
 	self.write_timed_query(query).await
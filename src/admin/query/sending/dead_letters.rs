@@ -0,0 +1,15 @@
+use futures::StreamExt;
+use tuwunel_core::Result;
+
+use crate::admin_command;
+
+#[admin_command]
+pub(super) async fn sending_dead_letters(&self) -> Result {
+	let query = self
+		.services
+		.sending
+		.list_dead_letters()
+		.collect::<Vec<_>>();
+
+	self.write_timed_query(query).await
+}
@@ -1,7 +1,9 @@
 mod active_requests;
 mod active_requests_for;
+mod dead_letters;
 mod get_latest_edu_count;
 mod queued_requests;
+mod requeue_dead_letter;
 
 use clap::Subcommand;
 use ruma::{OwnedServerName, OwnedUserId};
@@ -64,6 +66,15 @@ pub(crate) enum SendingCommand {
 	GetLatestEduCount {
 		server_name: OwnedServerName,
 	},
+
+	/// - Queries database for `deadletterid_data`, the events given up on after
+	///   exhausting their retries
+	DeadLetters,
+
+	/// - Moves a dead letter back into the queue of its destination
+	RequeueDeadLetter {
+		id: u64,
+	},
 }
 
 fn parse_destination(
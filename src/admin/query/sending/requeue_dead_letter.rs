@@ -0,0 +1,13 @@
+use tuwunel_core::Result;
+
+use crate::admin_command;
+
+#[admin_command]
+pub(super) async fn sending_requeue_dead_letter(&self, id: u64) -> Result {
+	self.services
+		.sending
+		.requeue_dead_letter(id)
+		.await?;
+
+	write!(self, "Requeued dead letter {id}.").await
+}
@@ -50,7 +50,5 @@ fn parse_alias_from_localpart(
 	services: &Services,
 	room_alias_localpart: &String,
 ) -> Result<OwnedRoomAliasId> {
-	let room_alias_str = format!("#{}:{}", room_alias_localpart, services.globals.server_name());
-
-	Ok(OwnedRoomAliasId::try_from(room_alias_str)?)
+	services.globals.local_alias(room_alias_localpart)
 }
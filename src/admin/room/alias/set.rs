@@ -1,7 +1,6 @@
 use ruma::OwnedRoomId;
 use tuwunel_core::{Err, Result, err};
 
-use super::parse_alias_from_localpart;
 use crate::admin_command;
 
 #[admin_command]
@@ -11,7 +10,10 @@ pub(super) async fn alias_set(
 	room_id: OwnedRoomId,
 	room_alias_localpart: String,
 ) -> Result {
-	let room_alias = parse_alias_from_localpart(self.services, &room_alias_localpart)?;
+	let room_alias = self
+		.services
+		.globals
+		.local_alias_checked(&room_alias_localpart)?;
 
 	match self
 		.services
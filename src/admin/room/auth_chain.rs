@@ -0,0 +1,141 @@
+use std::{
+	collections::{HashMap, HashSet},
+	time::Instant,
+};
+
+use futures::StreamExt;
+use ruma::{EventId, OwnedEventId, OwnedRoomId};
+use tuwunel_core::{
+	Err, Result,
+	matrix::Event,
+	utils::stream::{IterStream, ReadyExt, WidebandExt},
+};
+
+use crate::admin_command;
+
+#[admin_command]
+pub(super) async fn room_auth_chain(
+	&self,
+	room_id: OwnedRoomId,
+	event_id: Option<OwnedEventId>,
+) -> Result {
+	let starting_events: Vec<OwnedEventId> = match event_id {
+		| Some(event_id) => {
+			let Ok(pdu) = self.services.timeline.get_pdu(&event_id).await else {
+				return Err!("Event {event_id} not found.");
+			};
+
+			if *pdu.room_id() != *room_id {
+				return Err!("Event {event_id} is not in {room_id}.");
+			}
+
+			vec![event_id]
+		},
+		| None => {
+			let Ok(shortstatehash) = self
+				.services
+				.state
+				.get_room_shortstatehash(&room_id)
+				.await
+			else {
+				return Err!("No current state for {room_id}.");
+			};
+
+			self.services
+				.state_accessor
+				.state_full_ids(shortstatehash)
+				.map(|(_, event_id)| event_id)
+				.collect()
+				.await
+		},
+	};
+
+	let room_version = self
+		.services
+		.state
+		.get_room_version(&room_id)
+		.await?;
+
+	let start = Instant::now();
+	let auth_events: HashMap<_, Vec<_>> = self
+		.services
+		.auth_chain
+		.event_ids_iter(
+			&room_id,
+			&room_version,
+			starting_events.iter().map(|event_id| &**event_id),
+		)
+		.ready_filter_map(Result::ok)
+		.collect::<Vec<_>>()
+		.await
+		.into_iter()
+		.stream()
+		.wide_filter_map(async |event_id| {
+			let pdu = self
+				.services
+				.timeline
+				.get_pdu(&event_id)
+				.await
+				.ok()?;
+			let auth_events = pdu.auth_events().map(ToOwned::to_owned).collect();
+
+			Some((event_id, auth_events))
+		})
+		.collect()
+		.await;
+
+	let depth = max_depth(&auth_events);
+	let elapsed = start.elapsed();
+
+	write!(
+		self,
+		"Auth chain of {} starting event(s) has {} events with a maximum depth of {depth}, \
+		 computed in {elapsed:?}",
+		starting_events.len(),
+		auth_events.len(),
+	)
+	.await
+}
+
+/// Length of the longest path through `auth_events` within the chain.
+fn max_depth(auth_events: &HashMap<OwnedEventId, Vec<OwnedEventId>>) -> usize {
+	let mut depths: HashMap<&EventId, usize> = HashMap::new();
+	let mut visiting: HashSet<&EventId> = HashSet::new();
+	let in_chain = |event_id: &&OwnedEventId| auth_events.contains_key(*event_id);
+
+	for root in auth_events.keys() {
+		let mut stack = vec![(&**root, false)];
+		while let Some((event_id, expanded)) = stack.pop() {
+			if depths.contains_key(event_id) {
+				continue;
+			}
+
+			let parents = auth_events
+				.get(event_id)
+				.into_iter()
+				.flatten()
+				.filter(in_chain);
+
+			if expanded {
+				let depth = parents
+					.filter_map(|parent| depths.get(&**parent))
+					.max()
+					.copied()
+					.unwrap_or(0)
+					.saturating_add(1);
+
+				depths.insert(event_id, depth);
+			} else if visiting.insert(event_id) {
+				// A cycle is malformed; its back edge is simply not followed.
+				stack.push((event_id, true));
+				stack.extend(
+					parents
+						.filter(|parent| !visiting.contains(&**parent))
+						.map(|parent| (&**parent, false)),
+				);
+			}
+		}
+	}
+
+	depths.into_values().max().unwrap_or(0)
+}
@@ -0,0 +1,56 @@
+use ruma::OwnedRoomId;
+use tuwunel_core::{Err, Result};
+
+use super::moderation::do_ban_room;
+use crate::admin_command;
+
+#[admin_command]
+pub(super) async fn room_ban(
+	&self,
+	room_id: OwnedRoomId,
+	reason: Vec<String>,
+	evacuate: bool,
+) -> Result {
+	if let Ok(admin_room_id) = self.services.admin.get_admin_room().await
+		&& admin_room_id == room_id
+	{
+		return Err!("Not allowed to ban the admin room.");
+	}
+
+	let reason = reason.join(" ");
+	if evacuate {
+		do_ban_room(self.services, &room_id, Some(&reason)).await;
+
+		return self
+			.write_str("Room banned, removed all our local users, and disabled federation.")
+			.await;
+	}
+
+	self.services
+		.metadata
+		.ban_room(&room_id, Some(&reason));
+
+	self.write_str("Room banned; local users can no longer join it.")
+		.await
+}
+
+#[admin_command]
+pub(super) async fn room_unban(&self, room_id: OwnedRoomId) -> Result {
+	let metadata = &self.services.metadata;
+	if !metadata.is_banned(&room_id).await {
+		return Err!("Room {room_id} is not banned.");
+	}
+
+	let reason = metadata.ban_reason(&room_id).await;
+	metadata.unban_room(&room_id);
+
+	// A room disabled before it was banned stays disabled.
+	if metadata.is_disabled_by_ban(&room_id).await {
+		metadata.enable_room(&room_id);
+	}
+
+	match reason {
+		| Some(reason) => write!(self, "Room unbanned; it was banned for: {reason}").await,
+		| None => self.write_str("Room unbanned.").await,
+	}
+}
@@ -0,0 +1,41 @@
+use futures::StreamExt;
+use ruma::OwnedServerName;
+use tuwunel_core::{Err, Result};
+
+use crate::{PAGE_SIZE, admin_command, get_room_info};
+
+#[admin_command]
+pub(super) async fn room_by_server(
+	&self,
+	server_name: OwnedServerName,
+	page: Option<usize>,
+) -> Result {
+	let page = page.unwrap_or(1);
+	let mut rooms: Vec<_> = self
+		.services
+		.state_cache
+		.server_rooms(&server_name)
+		.then(|room_id| get_room_info(self.services, room_id))
+		.collect()
+		.await;
+
+	rooms.sort_by_key(|r| r.1);
+	rooms.reverse();
+
+	let total = rooms.len();
+	let rooms: Vec<_> = rooms
+		.into_iter()
+		.skip(page.saturating_sub(1).saturating_mul(PAGE_SIZE))
+		.take(PAGE_SIZE)
+		.collect();
+
+	if rooms.is_empty() {
+		return Err!("No more rooms shared with {server_name}.");
+	}
+
+	write!(self, "Rooms shared with {server_name} ({total}, page {page}):\n```\n").await?;
+	for (id, members, name) in &rooms {
+		writeln!(self, "{id}\tMembers: {members}\tName: {name}").await?;
+	}
+	write!(self, "```").await
+}
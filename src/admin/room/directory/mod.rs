@@ -17,7 +17,7 @@ pub(crate) enum RoomDirectoryCommand {
 		room_id: OwnedRoomId,
 	},
 
-	/// - Unpublish a room to the room directory
+	/// - Unpublish a room from the room directory
 	Unpublish {
 		/// The room id of the room to unpublish
 		room_id: OwnedRoomId,
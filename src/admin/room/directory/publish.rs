@@ -1,10 +1,32 @@
 use ruma::OwnedRoomId;
-use tuwunel_core::Result;
+use tuwunel_core::{Err, Result, info};
 
 use crate::admin_command;
 
 #[admin_command]
 pub(super) async fn directory_publish(&self, room_id: OwnedRoomId) -> Result {
+	if !self.services.metadata.exists(&room_id).await {
+		return Err!("Room {room_id} is not known to this server.");
+	}
+
+	if self
+		.services
+		.directory
+		.is_public_room(&room_id)
+		.await
+	{
+		return write!(self, "Room {room_id} is already published to the room directory.").await;
+	}
+
 	self.services.directory.set_public(&room_id);
-	self.write_str("Room published").await
+
+	if self.services.server.config.admin_room_notices {
+		self.services
+			.admin
+			.send_text(&format!("An admin made {room_id} public to the room directory"))
+			.await;
+	}
+	info!("An admin made {room_id} public to the room directory");
+
+	write!(self, "Room {room_id} is now published to the room directory.").await
 }
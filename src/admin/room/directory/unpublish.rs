@@ -1,10 +1,28 @@
 use ruma::OwnedRoomId;
-use tuwunel_core::Result;
+use tuwunel_core::{Result, info};
 
 use crate::admin_command;
 
 #[admin_command]
 pub(super) async fn directory_unpublish(&self, room_id: OwnedRoomId) -> Result {
+	if !self
+		.services
+		.directory
+		.is_public_room(&room_id)
+		.await
+	{
+		return write!(self, "Room {room_id} is not published to the room directory.").await;
+	}
+
 	self.services.directory.set_not_public(&room_id);
-	self.write_str("Room unpublished").await
+
+	if self.services.server.config.admin_room_notices {
+		self.services
+			.admin
+			.send_text(&format!("An admin removed {room_id} from the room directory"))
+			.await;
+	}
+	info!("An admin removed {room_id} from the room directory");
+
+	write!(self, "Room {room_id} is no longer published to the room directory.").await
 }
@@ -0,0 +1,31 @@
+use ruma::OwnedRoomId;
+use tuwunel_core::{Err, Result};
+
+use crate::admin_command;
+
+#[admin_command]
+pub(super) async fn event_type_counts(&self, room_id: OwnedRoomId) -> Result {
+	if !self.services.metadata.exists(&room_id).await {
+		return Err!("Room does not exist in the database.");
+	}
+
+	let counts = self
+		.services
+		.timeline
+		.event_type_counts(&room_id)
+		.await;
+
+	let total: u64 = counts
+		.values()
+		.copied()
+		.fold(0, u64::saturating_add);
+
+	writeln!(self, "{total} events in {room_id}:\n").await?;
+	writeln!(self, "| type | count |").await?;
+	writeln!(self, "| ---- | ----- |").await?;
+	for (kind, count) in counts {
+		writeln!(self, "| {kind} | {count} |").await?;
+	}
+
+	Ok(())
+}
@@ -1,3 +1,4 @@
+mod event_type_counts;
 mod list_joined_members;
 mod view_room_topic;
 
@@ -19,6 +20,14 @@ pub(crate) enum RoomInfoCommand {
 		local_only: bool,
 	},
 
+	/// - Tally the events in a room by type
+	///
+	/// This reads the room's entire timeline, which can take a while for
+	/// large rooms.
+	EventTypeCounts {
+		room_id: OwnedRoomId,
+	},
+
 	/// - Displays room topic
 	///
 	/// Room topics can be huge, so this is in its
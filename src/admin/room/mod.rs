@@ -1,4 +1,7 @@
 mod alias;
+mod auth_chain;
+mod ban;
+mod by_server;
 mod delete;
 mod directory;
 mod exists;
@@ -9,7 +12,7 @@
 mod purge_user;
 
 use clap::Subcommand;
-use ruma::OwnedRoomId;
+use ruma::{OwnedEventId, OwnedRoomId, OwnedServerName};
 use tuwunel_core::Result;
 
 use self::{
@@ -39,6 +42,16 @@ pub(super) enum RoomCommand {
 		no_details: bool,
 	},
 
+	/// - List the rooms a server participates in
+	///
+	/// Useful for planning defederation: shows every room we share with the
+	/// server, most populous first.
+	ByServer {
+		server_name: OwnedServerName,
+
+		page: Option<usize>,
+	},
+
 	#[command(subcommand)]
 	/// - View information about a room we know about
 	Info(RoomInfoCommand),
@@ -55,6 +68,40 @@ pub(super) enum RoomCommand {
 	/// - Manage the room directory
 	Directory(RoomDirectoryCommand),
 
+	/// - Show the size and depth of a room's auth chain
+	///
+	/// The chain is computed for the room's current state, or for a single
+	/// event when one is given. Useful when investigating slow state
+	/// resolution.
+	AuthChain {
+		room_id: OwnedRoomId,
+
+		event_id: Option<OwnedEventId>,
+	},
+
+	/// - Ban a room, preventing local users from joining it
+	///
+	/// The reason is recorded and shown by `rooms moderation
+	/// list-banned-rooms`. Local users already in the room stay unless
+	/// --evacuate is given, which also removes its local aliases and disables
+	/// federation with it like `rooms moderation ban-room`.
+	Ban {
+		room_id: OwnedRoomId,
+
+		/// Why the room is banned
+		#[arg(required = true, trailing_var_arg = true)]
+		reason: Vec<String>,
+
+		/// Make all local users leave the room
+		#[arg(long)]
+		evacuate: bool,
+	},
+
+	/// - Lift a ban placed with `rooms ban`
+	Unban {
+		room_id: OwnedRoomId,
+	},
+
 	/// - Check if we know about a room
 	Exists {
 		room_id: OwnedRoomId,
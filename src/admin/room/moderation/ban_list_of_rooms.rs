@@ -59,7 +59,7 @@ pub(super) async fn ban_list_of_rooms(&self) -> Result {
 	let rooms_len = room_ids.len();
 
 	for room_id in room_ids {
-		do_ban_room(self.services, &room_id).await;
+		do_ban_room(self.services, &room_id, None).await;
 	}
 
 	write!(
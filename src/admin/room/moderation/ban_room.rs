@@ -18,7 +18,7 @@ pub(super) async fn ban_room(&self, room: OwnedRoomOrAliasId) -> Result {
 
 	let room_id = self.services.alias.maybe_resolve(&room).await?;
 
-	do_ban_room(self.services, &room_id).await;
+	do_ban_room(self.services, &room_id, None).await;
 
 	self.write_str(
 		"Room banned, removed all our local users, and disabled incoming federation with room.",
@@ -35,7 +35,14 @@ pub(super) async fn list_banned_rooms(&self, no_details: bool) -> Result {
 		if no_details {
 			writeln!(self, "{id}").await?;
 		} else {
-			writeln!(self, "{id}\tMembers: {members}\tName: {name}").await?;
+			let reason = self
+				.services
+				.metadata
+				.ban_reason(id)
+				.await
+				.unwrap_or_default();
+
+			writeln!(self, "{id}\tMembers: {members}\tName: {name}\tReason: {reason}").await?;
 		}
 	}
 	write!(self, "```").await
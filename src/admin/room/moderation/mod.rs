@@ -46,8 +46,8 @@ pub(crate) enum RoomModerationCommand {
 	},
 }
 
-async fn do_ban_room(services: &Services, room_id: &RoomId) {
-	services.metadata.ban_room(room_id);
+pub(super) async fn do_ban_room(services: &Services, room_id: &RoomId, reason: Option<&str>) {
+	services.metadata.ban_room(room_id, reason);
 
 	debug!("Banned {room_id} successfully");
 
@@ -96,5 +96,8 @@ async fn do_ban_room(services: &Services, room_id: &RoomId) {
 	// unpublish from room directory, ignore errors
 	services.directory.set_not_public(room_id);
 
-	services.metadata.disable_room(room_id);
+	services
+		.metadata
+		.disable_banned_room(room_id)
+		.await;
 }
@@ -22,12 +22,13 @@ pub(super) async fn room_purge_user(
 
 	let count = if regex {
 		let pattern = &Regex::new(&user_id)?;
+		// Only rooms with tracked membership can have a matching member.
 		let rooms = services
-			.metadata
-			.iter_ids()
+			.state_cache
+			.all_rooms()
 			.map(ToOwned::to_owned)
 			.filter_map(async |room_id| {
-				(!services.admin.is_admin_room(&room_id).await
+				(!services.globals.is_system_room(&room_id).await
 					&& room_has_matching_member(services, &room_id, pattern, sole_member).await)
 					.then_some(room_id)
 			});
@@ -40,7 +41,7 @@ pub(super) async fn room_purge_user(
 			.rooms_joined(&user_id)
 			.map(ToOwned::to_owned)
 			.filter_map(async |room_id| {
-				(!services.admin.is_admin_room(&room_id).await
+				(!services.globals.is_system_room(&room_id).await
 					&& (!sole_member || is_sole_joined_member(services, &room_id).await))
 					.then_some(room_id)
 			});
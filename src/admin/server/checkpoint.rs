@@ -0,0 +1,18 @@
+use std::{path::PathBuf, sync::Arc};
+
+use tuwunel_core::Result;
+
+use crate::admin_command;
+
+#[admin_command]
+pub(super) async fn checkpoint(&self, path: PathBuf) -> Result {
+	let db = Arc::clone(&self.services.db);
+	let target = path.clone();
+	self.services
+		.server
+		.runtime()
+		.spawn_blocking(move || db.checkpoint(&target))
+		.await??;
+
+	write!(self, "Created database checkpoint at {}.", path.display()).await
+}
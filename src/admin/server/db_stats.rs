@@ -0,0 +1,28 @@
+use tuwunel_core::{Result, utils::bytes::pretty};
+
+use crate::admin_command;
+
+#[admin_command]
+pub(super) async fn db_stats(&self) -> Result {
+	let mut stats: Vec<_> = self
+		.services
+		.db
+		.iter()
+		.map(|(&name, map)| {
+			let size = map.approximate_size().unwrap_or(0);
+			let keys = map.estimate_num_keys().unwrap_or(0);
+			(name, size, keys)
+		})
+		.collect();
+
+	stats.sort_unstable_by_key(|&(_, size, _)| std::cmp::Reverse(size));
+
+	writeln!(self, "| map | approximate size | estimated keys |").await?;
+	writeln!(self, "| --- | ---------------- | -------------- |").await?;
+	for (name, size, keys) in stats {
+		let size = pretty(usize::try_from(size).unwrap_or(usize::MAX));
+		writeln!(self, "| {name} | {size} | {keys} |").await?;
+	}
+
+	Ok(())
+}
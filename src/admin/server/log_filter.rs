@@ -0,0 +1,18 @@
+use tuwunel_core::Result;
+
+use crate::{admin_command, utils::reload_log_filter};
+
+#[admin_command]
+pub(super) async fn log_filter(&self, filter: Option<String>) -> Result {
+	let Some(filter) = filter else {
+		let Some(current) = self.services.server.log.reload.current("console") else {
+			return write!(self, "No reloadable log filter is installed.").await;
+		};
+
+		return write!(self, "Current log filter: {current}").await;
+	};
+
+	let filter_layer = reload_log_filter(self.services, &filter)?;
+
+	write!(self, "Applied log filter: {filter_layer}").await
+}
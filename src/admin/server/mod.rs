@@ -1,9 +1,14 @@
 mod admin_notice;
 mod backup_database;
+mod checkpoint;
 mod clear_caches;
+mod db_stats;
 mod list_backups;
 mod list_features;
+mod log_filter;
 mod memory_usage;
+mod presence_reset;
+mod read_only;
 mod rebuild_relation_index;
 mod reload_config;
 mod reload_mods;
@@ -15,7 +20,7 @@
 
 use std::path::PathBuf;
 
-use clap::Subcommand;
+use clap::{ArgAction, Subcommand, builder::BoolishValueParser};
 use tuwunel_core::Result;
 
 use crate::admin_command_dispatch;
@@ -30,6 +35,9 @@ pub(super) enum ServerCommand {
 	ShowConfig,
 
 	/// - Reload configuration values
+	///
+	/// Only the options which can change while running are applied; the others
+	/// are listed as needing a restart.
 	ReloadConfig {
 		path: Option<PathBuf>,
 	},
@@ -46,15 +54,46 @@ pub(super) enum ServerCommand {
 		comma: bool,
 	},
 
+	/// - Show the current log filter, or apply a new one without restarting
+	///
+	/// This accepts the same format as the `log` config option.
+	LogFilter {
+		filter: Option<String>,
+	},
+
 	/// - Print database memory usage statistics
 	MemoryUsage,
 
+	/// - List the approximate size and key count of every database map
+	DbStats,
+
 	/// - Clears all of Tuwunel's caches
 	ClearCaches,
 
 	/// - Rebuild the typed relation index (relatesto_typed) from all PDUs
 	RebuildRelationIndex,
 
+	/// - Reset the presence of every local user to offline, e.g. after a
+	///   presence incident left stale online states behind.
+	///
+	/// Requires the `--yes-i-want-to-do-this` flag.
+	PresenceReset {
+		#[arg(long)]
+		yes_i_want_to_do_this: bool,
+	},
+
+	/// - Pause or resume writes from clients and federation while the database
+	///   stays open, e.g. before maintenance. Server admins may still write.
+	ReadOnly {
+		/// on or off
+		#[arg(action = ArgAction::Set, value_parser = BoolishValueParser::new())]
+		enabled: bool,
+
+		/// Keep read-only mode enabled after a restart
+		#[arg(long)]
+		persist: bool,
+	},
+
 	/// - Performs an online backup of the database (only available for RocksDB
 	///   at the moment)
 	BackupDatabase,
@@ -62,6 +101,16 @@ pub(super) enum ServerCommand {
 	/// - List database backups
 	ListBackups,
 
+	/// - Create a consistent on-disk snapshot of the database at the given
+	///   path, which must not exist yet
+	///
+	/// Files are hardlinked where possible, so placing the path on the same
+	/// filesystem as the database is cheap. The server continues running while
+	/// the checkpoint is taken; copy the directory away for a backup.
+	Checkpoint {
+		path: PathBuf,
+	},
+
 	/// - Send a message to the admin room.
 	AdminNotice {
 		message: Vec<String>,
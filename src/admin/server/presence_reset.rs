@@ -0,0 +1,17 @@
+use tuwunel_core::{Err, Result};
+
+use crate::admin_command;
+
+#[admin_command]
+pub(super) async fn presence_reset(&self, yes_i_want_to_do_this: bool) -> Result {
+	if !yes_i_want_to_do_this {
+		return Err!(
+			"You must pass the --yes-i-want-to-do-this flag to ensure you really want to reset \
+			 the presence of all local users to offline.",
+		);
+	}
+
+	let reset = self.services.presence.unset_all_presence().await;
+
+	write!(self, "Reset the presence of {reset} local user(s) to offline.").await
+}
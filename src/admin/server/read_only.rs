@@ -0,0 +1,19 @@
+use tuwunel_core::Result;
+
+use crate::admin_command;
+
+#[admin_command]
+pub(super) async fn read_only(&self, enabled: bool, persist: bool) -> Result {
+	self.services
+		.globals
+		.set_read_only_mode(enabled, persist);
+
+	let state = if enabled { "enabled" } else { "disabled" };
+	let persisted = if persist && enabled {
+		" and will persist across restarts"
+	} else {
+		""
+	};
+
+	write!(self, "Read-only mode {state}{persisted}.").await
+}
@@ -7,7 +7,24 @@
 #[admin_command]
 pub(super) async fn reload_config(&self, path: Option<PathBuf>) -> Result {
 	let path = path.as_deref().into_iter();
-	self.services.config.reload(path)?;
+	let (applied, ignored) = self.services.config.reload(path)?;
+	if applied.is_empty() && ignored.is_empty() {
+		return self
+			.write_str("No configuration changes found.")
+			.await;
+	}
 
-	self.write_str("Successfully reconfigured.").await
+	let applied = applied
+		.into_iter()
+		.map(|name| format!("{name}: applied"));
+
+	let ignored = ignored
+		.into_iter()
+		.map(|name| format!("{name}: ignored, requires a restart"));
+
+	let changes = applied
+		.chain(ignored)
+		.collect::<Vec<_>>()
+		.join("\n- ");
+	write!(self, "Reloaded configuration:\n- {changes}").await
 }
@@ -0,0 +1,102 @@
+use std::{path::PathBuf, pin::pin};
+
+use futures::{Stream, StreamExt};
+use ruma::events::AnyRawAccountDataEvent;
+use tokio::{
+	fs::OpenOptions,
+	io::{AsyncWrite, AsyncWriteExt, BufWriter},
+};
+use tuwunel_core::Result;
+
+use crate::{admin_command, utils::parse_local_user_id};
+
+#[admin_command]
+pub(super) async fn export_account_data(&self, user_id: String, path: PathBuf) -> Result {
+	let user_id = parse_local_user_id(self.services, &user_id)?;
+	let account_data = &self.services.account_data;
+
+	let mut rooms: Vec<_> = account_data
+		.rooms_with_data(&user_id)
+		.map(ToOwned::to_owned)
+		.collect()
+		.await;
+
+	rooms.sort_unstable();
+	rooms.dedup();
+
+	// Refuse to overwrite an existing file. Events are written as they are
+	// read so the export is never held whole.
+	let file = OpenOptions::new()
+		.write(true)
+		.create_new(true)
+		.open(&path)
+		.await?;
+
+	let mut out = BufWriter::new(file);
+	out.write_all(br#"{"user_id":"#).await?;
+	out.write_all(serde_json::to_string(&user_id)?.as_bytes())
+		.await?;
+
+	out.write_all(br#","global":["#).await?;
+	let global = account_data.changes_since(None, &user_id, 0, None);
+	let mut count = write_events(&mut out, global).await?;
+
+	out.write_all(br#"],"rooms":{"#).await?;
+	let mut first = true;
+	for room_id in &rooms {
+		let mut events = pin!(
+			account_data
+				.changes_since(Some(room_id), &user_id, 0, None)
+				.peekable()
+		);
+
+		if events.as_mut().peek().await.is_none() {
+			continue;
+		}
+
+		if !first {
+			out.write_all(b",").await?;
+		}
+
+		first = false;
+		out.write_all(serde_json::to_string(room_id)?.as_bytes())
+			.await?;
+		out.write_all(b":[").await?;
+		count = count.saturating_add(write_events(&mut out, events).await?);
+		out.write_all(b"]").await?;
+	}
+
+	out.write_all(b"}}").await?;
+	out.flush().await?;
+
+	write!(
+		self,
+		"Exported {count} account data event(s) for {user_id} to {}.",
+		path.display()
+	)
+	.await
+}
+
+async fn write_events<W, S>(out: &mut W, events: S) -> Result<usize>
+where
+	W: AsyncWrite + Unpin,
+	S: Stream<Item = AnyRawAccountDataEvent>,
+{
+	let mut events = pin!(events);
+	let mut count: usize = 0;
+	while let Some(event) = events.next().await {
+		if count > 0 {
+			out.write_all(b",").await?;
+		}
+
+		let json = match &event {
+			| AnyRawAccountDataEvent::Global(raw) => raw.json(),
+			| AnyRawAccountDataEvent::Room(raw) => raw.json(),
+		};
+
+		out.write_all(json.get().as_bytes()).await?;
+		count = count.saturating_add(1);
+	}
+
+	Ok(count)
+}
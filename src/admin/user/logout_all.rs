@@ -0,0 +1,16 @@
+use tuwunel_core::Result;
+
+use crate::{admin_command, utils::parse_local_user_id};
+
+#[admin_command]
+pub(super) async fn logout_all(&self, user_id: String) -> Result {
+	let user_id = parse_local_user_id(self.services, &user_id)?;
+
+	let count = self
+		.services
+		.users
+		.remove_all_tokens(&user_id)
+		.await;
+
+	write!(self, "Revoked access tokens for {count} device(s) of {user_id}.").await
+}
@@ -5,6 +5,7 @@
 mod del_email;
 mod delete_device;
 mod delete_room_tag;
+mod export_account_data;
 mod force_demote;
 mod force_join_all_local_users;
 mod force_join_list_of_local_users;
@@ -15,12 +16,19 @@
 mod last_active;
 mod list_joined_rooms;
 mod list_users;
+mod logout_all;
 mod make_user_admin;
+mod oauth_list;
+mod oauth_unbind;
+mod pusher_remove;
+mod pushers;
 mod put_room_tag;
 mod redact_event;
 mod reject_invites;
 mod reset_password;
 
+use std::path::PathBuf;
+
 use clap::Subcommand;
 use futures::FutureExt;
 use ruma::{OwnedDeviceId, OwnedEventId, OwnedRoomId, OwnedRoomOrAliasId, OwnedUserId, UserId};
@@ -107,6 +115,38 @@ pub(super) enum UserCommand {
 		device_id: OwnedDeviceId,
 	},
 
+	/// - Revoke the access and refresh tokens of all of a local user's devices,
+	///   logging every session out without deleting the devices.
+	LogoutAll {
+		user_id: String,
+	},
+
+	/// - List the OAuth session bindings of a local user.
+	OauthList {
+		user_id: String,
+	},
+
+	/// - Remove every OAuth session binding of a local user, revoking the
+	///   provider token where a revocation URL is configured.
+	///
+	/// Useful for clearing stale bindings left behind by provider migrations.
+	OauthUnbind {
+		user_id: String,
+	},
+
+	/// - List the pushers registered by a local user.
+	Pushers {
+		user_id: String,
+	},
+
+	/// - Remove one of a local user's pushers, dropping any pushes still queued
+	///   for it.
+	PusherRemove {
+		user_id: String,
+		app_id: String,
+		pushkey: String,
+	},
+
 	/// - List local users by recent activity.
 	LastActive {
 		#[arg(short, long)]
@@ -188,6 +228,13 @@ pub(super) enum UserCommand {
 		room_id: OwnedRoomId,
 	},
 
+	/// - Export a local user's global account data, and that of every room they
+	///   have not forgotten, as JSON to a file
+	ExportAccountData {
+		user_id: String,
+		path: PathBuf,
+	},
+
 	/// - Attempts to forcefully redact the specified event ID from the sender
 	///   user
 	///
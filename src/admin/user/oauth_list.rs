@@ -0,0 +1,44 @@
+use futures::StreamExt;
+use tuwunel_core::{Result, utils::stream::ReadyExt};
+
+use crate::{admin_command, utils::parse_local_user_id};
+
+#[admin_command]
+pub(super) async fn oauth_list(&self, user_id: String) -> Result {
+	let user_id = parse_local_user_id(self.services, &user_id)?;
+
+	let sessions: Vec<_> = self
+		.services
+		.oauth
+		.sessions
+		.get_by_user(&user_id)
+		.ready_filter_map(Result::ok)
+		.collect()
+		.await;
+
+	if sessions.is_empty() {
+		return write!(self, "{user_id} has no OAuth bindings.").await;
+	}
+
+	writeln!(self, "{user_id} has {} OAuth binding(s):", sessions.len()).await?;
+	for session in &sessions {
+		let sess_id = session.sess_id.as_deref().unwrap_or("?");
+		let provider = session.idp_id.as_deref().unwrap_or("?");
+		let configured = if self
+			.services
+			.oauth
+			.sessions
+			.provider(session)
+			.await
+			.is_ok()
+		{
+			""
+		} else {
+			" (provider not configured)"
+		};
+
+		writeln!(self, "- {sess_id} via {provider}{configured}").await?;
+	}
+
+	Ok(())
+}
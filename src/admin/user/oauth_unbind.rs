@@ -0,0 +1,53 @@
+use futures::StreamExt;
+use tuwunel_core::{Result, utils::stream::ReadyExt};
+
+use crate::{admin_command, utils::parse_local_user_id};
+
+#[admin_command]
+pub(super) async fn oauth_unbind(&self, user_id: String) -> Result {
+	let user_id = parse_local_user_id(self.services, &user_id)?;
+
+	let sessions: Vec<_> = self
+		.services
+		.oauth
+		.sessions
+		.get_by_user(&user_id)
+		.ready_filter_map(Result::ok)
+		.collect()
+		.await;
+
+	let mut revoked: usize = 0;
+	for session in &sessions {
+		// Stale bindings may name a provider which is no longer configured;
+		// those are removed without revocation.
+		if let Ok(provider) = self
+			.services
+			.oauth
+			.sessions
+			.provider(session)
+			.await && provider.revocation_url.is_some()
+		{
+			// Failures are logged by the service; the binding is removed regardless.
+			if self
+				.services
+				.oauth
+				.revoke_token((&provider, session))
+				.await
+				.is_ok()
+			{
+				revoked = revoked.saturating_add(1);
+			}
+		}
+
+		if let Some(sess_id) = session.sess_id.as_deref() {
+			self.services.oauth.sessions.delete(sess_id).await;
+		}
+	}
+
+	write!(
+		self,
+		"Removed {} OAuth binding(s) of {user_id}, revoking {revoked} provider token(s).",
+		sessions.len()
+	)
+	.await
+}
@@ -0,0 +1,36 @@
+use tuwunel_core::{Err, Result};
+
+use crate::{admin_command, utils::parse_local_user_id};
+
+#[admin_command]
+pub(super) async fn pusher_remove(
+	&self,
+	user_id: String,
+	app_id: String,
+	pushkey: String,
+) -> Result {
+	let user_id = parse_local_user_id(self.services, &user_id)?;
+	let Ok(pusher) = self
+		.services
+		.pusher
+		.get_pusher(&user_id, &pushkey)
+		.await
+	else {
+		return Err!("{user_id} has no pusher with push key {pushkey:?}.");
+	};
+
+	if pusher.ids.app_id != app_id {
+		return Err!(
+			"Pusher with push key {pushkey:?} belongs to app {:?}, not {app_id:?}.",
+			pusher.ids.app_id
+		);
+	}
+
+	// Also drops any pushes still queued for this pusher.
+	self.services
+		.pusher
+		.delete_pusher(&user_id, &pushkey)
+		.await;
+
+	write!(self, "Removed pusher {app_id} {pushkey} of {user_id}.").await
+}
@@ -0,0 +1,29 @@
+use ruma::api::client::push::PusherKind;
+use tuwunel_core::Result;
+
+use crate::{admin_command, utils::parse_local_user_id};
+
+#[admin_command]
+pub(super) async fn pushers(&self, user_id: String) -> Result {
+	let user_id = parse_local_user_id(self.services, &user_id)?;
+	let pushers = self.services.pusher.get_pushers(&user_id).await;
+
+	if pushers.is_empty() {
+		return write!(self, "{user_id} has no pushers.").await;
+	}
+
+	writeln!(self, "{user_id} has {} pusher(s):", pushers.len()).await?;
+	for pusher in &pushers {
+		let app_id = &pusher.ids.app_id;
+		let pushkey = &pusher.ids.pushkey;
+		let kind = match &pusher.kind {
+			| PusherKind::Http(http) => format!("http {}", http.url),
+			| PusherKind::Email(_) => "email".to_owned(),
+			| _ => "custom".to_owned(),
+		};
+
+		writeln!(self, "- {app_id} {pushkey} ({kind})").await?;
+	}
+
+	Ok(())
+}
@@ -1,5 +1,6 @@
 use futures::StreamExt;
 use ruma::{OwnedRoomId, OwnedUserId, RoomId, UserId};
+use tracing_subscriber::EnvFilter;
 use tuwunel_core::{Err, Result, err};
 use tuwunel_service::Services;
 
@@ -62,3 +63,19 @@ pub(crate) async fn parse_active_local_user_id(
 
 	Ok(user_id)
 }
+
+/// Parse `filter` in the format of the `log` config option and apply it to
+/// the console log in place of the current one.
+pub(crate) fn reload_log_filter(services: &Services, filter: &str) -> Result<EnvFilter> {
+	let filter_layer = EnvFilter::builder()
+		.with_regex(services.config.log_filter_regex)
+		.parse(filter)?;
+
+	services
+		.server
+		.log
+		.reload
+		.reload(&filter_layer, Some(&["console"]))?;
+
+	Ok(filter_layer)
+}
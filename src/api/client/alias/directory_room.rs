@@ -33,6 +33,11 @@ pub(crate) async fn create_alias_route(
 		return Err!(Request(Forbidden("Room alias is forbidden.")));
 	}
 
+	// Aliases reserved for the server's own rooms, such as the admin room.
+	services
+		.globals
+		.local_alias_checked(body.room_alias.alias())?;
+
 	if services
 		.alias
 		.resolve_local_alias(&body.room_alias)
@@ -1,6 +1,6 @@
 use axum::extract::State;
 use ruma::api::client::backup::{delete_backup_version, get_backup_info, update_backup_version};
-use tuwunel_core::{Result, err};
+use tuwunel_core::{Err, Result, err};
 
 use super::{get_count_etag, validate_algorithm_shape};
 use crate::Ruma;
@@ -66,10 +66,16 @@ pub(crate) async fn delete_backup_version_route(
 	State(services): State<crate::State>,
 	body: Ruma<delete_backup_version::v3::Request>,
 ) -> Result<delete_backup_version::v3::Response> {
-	services
+	if !services
 		.key_backups
 		.delete_backup(body.sender_user(), &body.version)
-		.await;
+		.await?
+	{
+		return Err!(Request(NotFound(
+			"Key backup does not exist at version {:?}",
+			body.version
+		)));
+	}
 
 	Ok(delete_backup_version::v3::Response {})
 }
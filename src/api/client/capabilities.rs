@@ -88,6 +88,9 @@ pub(crate) async fn get_capabilities_route(
 	capabilities
 		.set("io.element.msc4452.preview_url", json!({"enabled": preview_url_enabled}))?;
 
+	capabilities
+		.set("chat.tuwunel.features", serde_json::to_value(services.globals.capabilities())?)?;
+
 	// MSC4323: advertise admin moderation only to admins; absence implies
 	// neither suspend nor lock is available to the caller.
 	if services
@@ -1,3 +1,5 @@
+use std::any::type_name;
+
 use axum::extract::State;
 use ruma::{
 	UserId,
@@ -146,6 +148,7 @@ fn create_oauth_uiaa(
 		body.json_body
 			.as_ref()
 			.ok_or_else(|| err!(Request(NotJson("JSON body is not valid"))))?,
+		Some(type_name::<upload_signing_keys::v3::Request>()),
 	);
 
 	Ok(uiaainfo)
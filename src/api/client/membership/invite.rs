@@ -9,7 +9,6 @@
 };
 use tuwunel_core::{Err, Result};
 
-use super::banned_room_check;
 use crate::{ClientIp, Ruma, client::utils::invite_check};
 
 /// # `POST /_matrix/client/r0/rooms/{roomId}/invite`
@@ -27,7 +26,10 @@ pub(crate) async fn invite_user_route(
 
 	invite_check(&services, sender_user, room_id).await?;
 
-	banned_room_check(&services, sender_user, room_id, None, client).await?;
+	services
+		.membership
+		.banned_room_check(sender_user, room_id, None, client)
+		.await?;
 
 	let InvitationRecipient::UserId(InviteUserId { user_id, reason }) = &body.recipient else {
 		return Err!(Request(ThreepidDenied("Third party identifiers are not implemented")));
@@ -6,7 +6,6 @@
 };
 use tuwunel_core::{Result, warn};
 
-use super::banned_room_check;
 use crate::{ClientIp, Ruma};
 
 /// # `POST /_matrix/client/r0/rooms/{roomId}/join`
@@ -27,7 +26,10 @@ pub(crate) async fn join_room_by_id_route(
 
 	let room_id: &RoomId = &body.room_id;
 
-	banned_room_check(&services, sender_user, room_id, None, client).await?;
+	services
+		.membership
+		.banned_room_check(sender_user, room_id, None, client)
+		.await?;
 
 	let extra_content = extra_member_content(body.json_body.as_ref());
 
@@ -82,7 +84,9 @@ pub(crate) async fn join_room_by_id_or_alias_route(
 		.maybe_resolve_with_servers(&body.room_id_or_alias, Some(&body.via))
 		.await?;
 
-	banned_room_check(&services, sender_user, &room_id, Some(&body.room_id_or_alias), client)
+	services
+		.membership
+		.banned_room_check(sender_user, &room_id, Some(&body.room_id_or_alias), client)
 		.await?;
 
 	let extra_content = extra_member_content(body.json_body.as_ref());
@@ -2,7 +2,6 @@
 use ruma::api::client::knock::knock_room;
 use tuwunel_core::Result;
 
-use super::banned_room_check;
 use crate::{ClientIp, Ruma};
 
 /// # `POST /_matrix/client/*/knock/{roomIdOrAlias}`
@@ -21,7 +20,9 @@ pub(crate) async fn knock_room_route(
 		.maybe_resolve_with_servers(&body.room_id_or_alias, Some(&body.via))
 		.await?;
 
-	banned_room_check(&services, sender_user, &room_id, Some(&body.room_id_or_alias), client)
+	services
+		.membership
+		.banned_room_check(sender_user, &room_id, Some(&body.room_id_or_alias), client)
 		.await?;
 
 	let state_lock = services.state.mutex.lock(&room_id).await;
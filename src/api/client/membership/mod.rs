@@ -8,13 +8,10 @@
 mod members;
 mod unban;
 
-use std::net::IpAddr;
-
 use axum::extract::State;
-use futures::{FutureExt, StreamExt};
-use ruma::{RoomId, RoomOrAliasId, UserId, api::client::membership::joined_rooms};
-use tuwunel_core::{Err, Result, result::LogErr, warn};
-use tuwunel_service::Services;
+use futures::StreamExt;
+use ruma::api::client::membership::joined_rooms;
+use tuwunel_core::Result;
 
 pub(crate) use self::{
 	ban::ban_user_route,
@@ -45,79 +42,3 @@ pub(crate) async fn joined_rooms_route(
 			.await,
 	})
 }
-
-/// Checks if the room is banned in any way possible and the sender user is not
-/// an admin.
-///
-/// Performs automatic deactivation if `auto_deactivate_banned_room_attempts` is
-/// enabled
-#[tracing::instrument(skip(services))]
-pub(crate) async fn banned_room_check(
-	services: &Services,
-	user_id: &UserId,
-	room_id: &RoomId,
-	orig_room_id: Option<&RoomOrAliasId>,
-	client_ip: IpAddr,
-) -> Result {
-	if services.admin.user_is_admin(user_id).await {
-		return Ok(());
-	}
-
-	// room id is banned ...
-	if services.metadata.is_banned(room_id).await
-		// ... or legacy room id server is banned ...
-		|| room_id.server_name().is_some_and(|server_name| {
-			services
-				.config
-				.is_forbidden_remote_server_name(server_name)
-		})
-		// ... or alias server is banned
-		|| orig_room_id.is_some_and(|orig_room_id| {
-			orig_room_id.server_name().is_some_and(|orig_server_name| {
-			services
-				.config
-				.is_forbidden_remote_server_name(orig_server_name)
-		})
-	}) {
-		warn!(
-			"User {user_id} who is not an admin attempted to send an invite for or attempted to \
-			 join a banned room or banned room server name: {room_id}"
-		);
-
-		maybe_deactivate(services, user_id, client_ip)
-			.await
-			.log_err()
-			.ok();
-
-		return Err!(Request(Forbidden("This room is banned on this homeserver.")));
-	}
-
-	Ok(())
-}
-
-async fn maybe_deactivate(services: &Services, user_id: &UserId, client_ip: IpAddr) -> Result {
-	if services
-		.server
-		.config
-		.auto_deactivate_banned_room_attempts
-	{
-		let notice = format!(
-			"Automatically deactivating user {user_id} due to attempted banned room join from \
-			 IP {client_ip}"
-		);
-
-		warn!("{notice}");
-
-		if services.server.config.admin_room_notices {
-			services.admin.send_text(&notice).await;
-		}
-
-		services
-			.deactivate
-			.full_deactivate(user_id, false)
-			.boxed()
-			.await?;
-	}
-
-	Ok(())
-}
@@ -320,7 +320,7 @@ async fn enforce_uiaa(
 		| Some(auth) => {
 			let (worked, uiaainfo) = services
 				.uiaa
-				.try_auth(&server_user, "".into(), auth, &uiaainfo)
+				.try_auth(&server_user, "".into(), auth, &uiaainfo, None, None)
 				.await?;
 
 			if !worked {
@@ -346,7 +346,7 @@ async fn enforce_uiaa(
 				uiaainfo.session = Some(utils::random_string(SESSION_ID_LENGTH));
 				services
 					.uiaa
-					.create(&server_user, "".into(), &uiaainfo, json);
+					.create(&server_user, "".into(), &uiaainfo, json, None);
 
 				Err(Error::Uiaa(uiaainfo))
 			},
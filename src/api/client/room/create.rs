@@ -600,10 +600,11 @@ async fn create_create_event(
 		let mut additional_creators = body
 			.creation_content
 			.as_ref()
-			.and_then(|c| {
+			.map(|c| {
 				c.deserialize_as_unchecked::<CreationContent>()
-					.ok()
+					.map_err(|e| err!(Request(InvalidParam("Invalid creation content: {e}"))))
 			})
+			.transpose()?
 			.unwrap_or_default()
 			.additional_creators;
 
@@ -613,6 +614,11 @@ async fn create_create_event(
 
 		additional_creators.sort();
 		additional_creators.dedup();
+		services
+			.users
+			.check_additional_creators(&additional_creators)
+			.await?;
+
 		if !additional_creators.is_empty() {
 			create_content
 				.insert("additional_creators".into(), json!(additional_creators).try_into()?);
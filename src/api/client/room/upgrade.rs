@@ -226,6 +226,11 @@ async fn upgrade_room_create(
 	{
 		additional_creators.sort();
 		additional_creators.dedup();
+		services
+			.users
+			.check_additional_creators(&additional_creators)
+			.await?;
+
 		content.remove("additional_creators");
 		if !additional_creators.is_empty() {
 			content.insert("additional_creators".into(), json!(additional_creators).try_into()?);
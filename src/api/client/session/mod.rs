@@ -21,7 +21,10 @@
 		v3::{DiscoveryInfo, HomeserverInfo, LoginInfo},
 	},
 };
-use tuwunel_core::{Err, Result, info, utils::stream::ReadyExt};
+use tuwunel_core::{
+	Err, Result, config::IdentityProvider as IdentityProviderConfig, info,
+	utils::stream::ReadyExt,
+};
 use tuwunel_service::users::device::generate_refresh_token;
 
 use self::{ldap::ldap_login, password::password_login};
@@ -50,18 +53,12 @@ pub(crate) async fn get_login_types_route(
 
 	let list_idps = !services.config.sso_custom_providers_page && !services.config.single_sso;
 
-	let identity_providers: Vec<_> = services
+	let identity_providers = services
 		.config
 		.identity_provider
 		.values()
 		.filter(|_| list_idps)
-		.cloned()
-		.map(|config| IdentityProvider {
-			id: config.id().to_owned(),
-			brand: Some(config.brand.clone().into()),
-			icon: config.icon,
-			name: config.name.unwrap_or(config.brand),
-		})
+		.map(identity_provider)
 		.collect();
 
 	let flows = [
@@ -90,6 +87,20 @@ pub(crate) async fn get_login_types_route(
 	})
 }
 
+/// Advertise a configured provider so clients can render a button for it; the
+/// id is what `sso_login_with_provider_route` takes as `idp_id`.
+fn identity_provider(config: &IdentityProviderConfig) -> IdentityProvider {
+	IdentityProvider {
+		id: config.id().to_owned(),
+		brand: Some(config.brand.clone().into()),
+		icon: config.icon.clone(),
+		name: config
+			.name
+			.clone()
+			.unwrap_or_else(|| config.brand.clone()),
+	}
+}
+
 /// # `POST /_matrix/client/v3/login`
 ///
 /// Authenticates the user and returns an access token it can use in subsequent
@@ -193,3 +204,56 @@ pub(crate) async fn login_route(
 		refresh_token,
 	})
 }
+
+#[cfg(test)]
+mod tests {
+	use ruma::api::client::session::get_login_types::v3::IdentityProviderBrand;
+	use serde_json::json;
+
+	use super::*;
+
+	fn provider_config(config: &serde_json::Value) -> IdentityProviderConfig {
+		serde_json::from_value(config.clone()).expect("valid identity provider config")
+	}
+
+	#[test]
+	fn identity_providers_carry_their_metadata() {
+		let configs = [
+			provider_config(&json!({
+				"brand": "GitHub",
+				"client_id": "github-client",
+				"name": "GitHub Enterprise",
+				"icon": "mxc://example.com/github",
+			})),
+			provider_config(&json!({
+				"brand": "Keycloak",
+				"client_id": "keycloak-client",
+			})),
+		];
+
+		let providers: Vec<_> = configs.iter().map(identity_provider).collect();
+
+		assert_eq!(providers.len(), 2, "one entry per configured provider");
+
+		assert_eq!(providers[0].id, "github-client", "id selects the provider");
+		assert_eq!(providers[0].name, "GitHub Enterprise", "configured name is shown");
+		assert_eq!(providers[0].brand, Some(IdentityProviderBrand::GitHub), "brand is known");
+		assert_eq!(
+			providers[0]
+				.icon
+				.as_ref()
+				.map(|icon| icon.as_str()),
+			Some("mxc://example.com/github"),
+			"icon is passed through"
+		);
+
+		assert_eq!(providers[1].id, "keycloak-client", "id selects the provider");
+		assert_eq!(providers[1].name, "keycloak", "name falls back to the brand");
+		assert_eq!(
+			providers[1].brand,
+			Some(IdentityProviderBrand::from("keycloak")),
+			"unrecognized brand is kept"
+		);
+		assert_eq!(providers[1].icon, None, "no icon configured");
+	}
+}
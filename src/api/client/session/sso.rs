@@ -38,8 +38,7 @@
 	client::read_response_capped,
 	media::MXC_LENGTH,
 	oauth::{
-		CODE_VERIFIER_LENGTH, Provider, SESSION_ID_LENGTH, Session, TokenResponse, UserInfo,
-		unique_id_sub,
+		CODE_VERIFIER_LENGTH, Provider, SESSION_ID_LENGTH, Session, UserInfo, unique_id_sub,
 	},
 	users::{PASSWORD_SENTINEL, Register},
 };
@@ -398,7 +397,7 @@ pub(crate) async fn sso_callback_route(
 		.request_token((&provider, &session), code)
 		.await?;
 
-	let session = apply_token_response(session, token_response)?;
+	let session = token_response.into_session(session)?;
 
 	let userinfo = services
 		.oauth
@@ -515,31 +514,6 @@ fn validate_session_cookie(
 	Ok(())
 }
 
-fn apply_token_response(session: Session, token: TokenResponse) -> Result<Session> {
-	let expires_at = token
-		.expires_in
-		.map(Duration::from_secs)
-		.map(timepoint_from_now)
-		.transpose()?;
-
-	let refresh_token_expires_at = token
-		.refresh_token_expires_in
-		.map(Duration::from_secs)
-		.map(timepoint_from_now)
-		.transpose()?;
-
-	Ok(Session {
-		scope: token.scope,
-		token_type: token.token_type,
-		access_token: token.access_token,
-		id_token: token.id_token,
-		expires_at,
-		refresh_token: token.refresh_token,
-		refresh_token_expires_at,
-		..session
-	})
-}
-
 /// Locate any prior session bound to the same upstream identity, to preserve
 /// one session and its `user_id` association per identity.
 async fn existing_identity_session(
@@ -12,7 +12,7 @@
 use ruma::{
 	DeviceId, EventId, OwnedEventId, OwnedRoomId, OwnedUserId, RoomId, UInt, UserId,
 	api::client::{
-		filter::FilterDefinition,
+		filter::{FilterDefinition, RoomEventFilter},
 		sync::sync_events::{
 			self, DeviceLists, UnreadNotificationsCount,
 			v3::{
@@ -59,6 +59,7 @@
 };
 use tuwunel_service::{
 	Services,
+	pusher::NotificationCounts,
 	rooms::{
 		lazy_loading,
 		lazy_loading::{Options, Witness},
@@ -282,7 +283,14 @@ pub(crate) async fn sync_events_route(
 			since = next_batch;
 		}
 
-		if since < next_batch || full_state {
+		// Rooms deferred from an initial sync are sent without waiting.
+		let deferred_pending = !services
+			.sync
+			.deferred_rooms(sender_user, sender_device, since)
+			.await
+			.is_empty();
+
+		if since < next_batch || full_state || deferred_pending {
 			let response = build_sync_events(
 				&services,
 				sender_user,
@@ -301,7 +309,7 @@ pub(crate) async fn sync_events_route(
 				&& response.device_lists.is_empty()
 				&& response.to_device.is_empty();
 
-			if !empty || full_state {
+			if !empty || full_state || deferred_pending {
 				return Ok(response);
 			}
 		}
@@ -381,11 +389,15 @@ async fn build_sync_events(
 	// invite events from /sync entirely; a later unblock re-exposes them.
 	let invites_blocked = services.users.invites_blocked(sender_user).await;
 
+	let joined_room_ids =
+		select_joined_rooms(services, sender_user, sender_device, since, next_batch, filter)
+			.await;
+
 	let joined_rooms = collect_joined_rooms(
 		services,
 		sender_user,
 		sender_device,
-		since,
+		joined_room_ids,
 		next_batch,
 		full_state,
 		state_after,
@@ -505,12 +517,60 @@ async fn build_sync_events(
 	})
 }
 
+/// Joined rooms to load, each with the `since` it is loaded from. An initial
+/// sync is held to `initial_sync_room_limit`; the syncs after it load a batch
+/// of the deferred rooms as though initial and withhold the remainder, so no
+/// room is ever sent incrementally before the client has seen it in full.
+async fn select_joined_rooms(
+	services: &Services,
+	sender_user: &UserId,
+	sender_device: Option<&DeviceId>,
+	since: u64,
+	next_batch: u64,
+	filter: &FilterDefinition,
+) -> Vec<(OwnedRoomId, u64)> {
+	let joined: Vec<_> = services
+		.state_cache
+		.rooms_joined(sender_user)
+		.ready_filter(|&room_id| filter.room.matches(room_id))
+		.map(ToOwned::to_owned)
+		.collect()
+		.await;
+
+	if since == 0 {
+		return services
+			.sync
+			.limit_initial_rooms(sender_user, sender_device, joined, next_batch)
+			.await
+			.into_iter()
+			.map(|room_id| (room_id, since))
+			.collect();
+	}
+
+	let (batch, withheld) = services
+		.sync
+		.take_deferred_rooms(sender_user, sender_device, since, next_batch)
+		.await;
+
+	let batch: HashSet<_> = batch.into_iter().collect();
+	let withheld: HashSet<_> = withheld.into_iter().collect();
+
+	joined
+		.into_iter()
+		.filter(|room_id| !withheld.contains(room_id))
+		.map(|room_id| {
+			let since = if batch.contains(&room_id) { 0 } else { since };
+			(room_id, since)
+		})
+		.collect()
+}
+
 #[expect(clippy::too_many_arguments)]
 fn collect_joined_rooms<'a>(
 	services: &'a Services,
 	sender_user: &'a UserId,
 	sender_device: Option<&'a DeviceId>,
-	since: u64,
+	rooms: Vec<(OwnedRoomId, u64)>,
 	next_batch: u64,
 	full_state: bool,
 	state_after: StateAfter,
@@ -519,12 +579,10 @@ fn collect_joined_rooms<'a>(
 	Output = (BTreeMap<OwnedRoomId, JoinedRoom>, HashSet<OwnedUserId>, HashSet<OwnedUserId>),
 > + Send
 + 'a {
-	services
-		.state_cache
-		.rooms_joined(sender_user)
-		.ready_filter(|&room_id| filter.room.matches(room_id))
-		.map(ToOwned::to_owned)
-		.broad_filter_map(move |room_id| {
+	rooms
+		.into_iter()
+		.stream()
+		.broad_filter_map(move |(room_id, since)| {
 			load_joined_room(
 				services,
 				sender_user,
@@ -593,30 +651,22 @@ async fn collect_invited_rooms<'a>(
 	filter: &'a FilterDefinition,
 	invites_blocked: bool,
 ) -> BTreeMap<OwnedRoomId, InvitedRoom> {
+	if invites_blocked {
+		return BTreeMap::new();
+	}
+
 	services
 		.state_cache
-		.rooms_invited_state(sender_user)
-		.ready_filter(move |_| !invites_blocked)
+		.rooms_invited_changed(sender_user, since, next_batch)
 		.ready_filter(|(room_id, _)| filter.room.matches(room_id))
-		.fold_default(async |mut invited_rooms: BTreeMap<_, _>, (room_id, invite_state)| {
-			let invite_count = services
-				.state_cache
-				.get_invite_count(&room_id, sender_user)
-				.await
-				.ok();
-
-			// Invited before last sync
-			if Some(since) >= invite_count || Some(next_batch) < invite_count {
-				return invited_rooms;
-			}
-
+		.map(|(room_id, invite_state)| {
 			let invited_room = InvitedRoom {
 				invite_state: InviteState { events: invite_state },
 			};
 
-			invited_rooms.insert(room_id, invited_room);
-			invited_rooms
+			(room_id, invited_room)
 		})
+		.collect()
 		.await
 }
 
@@ -629,27 +679,16 @@ async fn collect_knocked_rooms<'a>(
 ) -> BTreeMap<OwnedRoomId, KnockedRoom> {
 	services
 		.state_cache
-		.rooms_knocked_state(sender_user)
+		.rooms_knocked_since(sender_user, since, next_batch)
 		.ready_filter(|(room_id, _)| filter.room.matches(room_id))
-		.fold_default(async |mut knocked_rooms: BTreeMap<_, _>, (room_id, knock_state)| {
-			let knock_count = services
-				.state_cache
-				.get_knock_count(&room_id, sender_user)
-				.await
-				.ok();
-
-			// Knocked before last sync; or after the cutoff for this sync
-			if Some(since) >= knock_count || Some(next_batch) < knock_count {
-				return knocked_rooms;
-			}
-
+		.map(|(room_id, knock_state)| {
 			let knocked_room = KnockedRoom {
 				knock_state: KnockState { events: knock_state },
 			};
 
-			knocked_rooms.insert(room_id, knocked_room);
-			knocked_rooms
+			(room_id, knocked_room)
 		})
+		.collect()
 		.await
 }
 
@@ -706,19 +745,22 @@ async fn process_presence_updates(
 		.presence
 		.presence_since(since, Some(next_batch))
 		.ready_filter(|(user_id, ..)| filter.presence.matches(user_id))
-		.filter(|(user_id, ..)| {
-			services
+		.filter_map(async |(user_id, _, presence_bytes)| {
+			if !services
 				.state_cache
-				.user_sees_user(syncing_user, user_id)
-		})
-		.filter_map(|(user_id, _, presence_bytes)| {
+				.user_sees_user(syncing_user, &user_id)
+				.await
+			{
+				return None;
+			}
+
 			services
 				.presence
-				.from_json_bytes_to_event(presence_bytes, user_id)
-				.map_ok(move |event| (user_id, event))
+				.from_json_bytes_to_event(&presence_bytes, &user_id)
+				.await
 				.ok()
+				.map(|event| (user_id, event.content))
 		})
-		.map(|(user_id, event)| (user_id.to_owned(), event.content))
 		.collect()
 		.boxed()
 		.await
@@ -1280,9 +1322,7 @@ struct JoinAggregates {
 	account_data_events: Vec<Raw<AnyRoomAccountDataEvent>>,
 	typing_events: Vec<Raw<AnySyncEphemeralRoomEvent>>,
 	private_read_events: Option<PrivateReadEvents>,
-	notification_count: Option<UInt>,
-	highlight_count: Option<UInt>,
-	thread_counts: Option<BTreeMap<OwnedEventId, (u64, u64)>>,
+	notification_counts: Option<NotificationCounts>,
 	device_list_updates: HashSet<OwnedUserId>,
 	left_encrypted_users: HashSet<OwnedUserId>,
 }
@@ -1303,8 +1343,11 @@ async fn await_join_aggregates(
 	send_notification_counts: bool,
 	filter: &FilterDefinition,
 ) -> JoinAggregates {
-	let (notification_count, highlight_count, thread_counts) =
-		notification_count_futures(services, sender_user, room_id, send_notification_counts);
+	let notification_counts = send_notification_counts.then_async(|| {
+		services
+			.pusher
+			.notification_counts(sender_user, room_id)
+	});
 
 	let private_read_events = last_privateread_update.gt(&since).then_async(|| {
 		services
@@ -1340,12 +1383,12 @@ async fn await_join_aggregates(
 	let (
 		(room_events, account_data_events),
 		(typing_events, private_read_events),
-		(notification_count, highlight_count, thread_counts),
+		notification_counts,
 		(device_list_updates, left_encrypted_users),
 	) = join4(
 		join(room_events, account_data_events),
 		join(typing_events, private_read_events),
-		join3(notification_count, highlight_count, thread_counts),
+		notification_counts,
 		device_list_updates,
 	)
 	.boxed()
@@ -1356,9 +1399,7 @@ async fn await_join_aggregates(
 		account_data_events,
 		typing_events,
 		private_read_events,
-		notification_count,
-		highlight_count,
-		thread_counts,
+		notification_counts,
 		device_list_updates,
 		left_encrypted_users,
 	}
@@ -1395,9 +1436,7 @@ async fn finalize_joined_room(
 		account_data_events,
 		typing_events,
 		private_read_events,
-		notification_count,
-		highlight_count,
-		thread_counts,
+		notification_counts,
 		device_list_updates,
 		left_encrypted_users,
 	} = aggregates;
@@ -1424,9 +1463,7 @@ async fn finalize_joined_room(
 	.await;
 
 	let (unread_notifications, unread_thread_notifications) = assemble_unread_notifications(
-		notification_count,
-		highlight_count,
-		thread_counts,
+		notification_counts,
 		thread_last_reads,
 		send_notification_count_filter,
 		filter.room.timeline.unread_thread_notifications,
@@ -1452,13 +1489,18 @@ async fn finalize_joined_room(
 			joined_since_last_sync,
 			prev_batch,
 		},
+		&filter.room.ephemeral,
 		filter.event_fields.as_deref(),
 	);
 
 	(joined_room, device_list_updates, left_encrypted_users)
 }
 
-fn build_joined_room(args: BuildJoinedRoom, event_fields: Option<&[String]>) -> JoinedRoom {
+fn build_joined_room(
+	args: BuildJoinedRoom,
+	ephemeral_filter: &RoomEventFilter,
+	event_fields: Option<&[String]>,
+) -> JoinedRoom {
 	let BuildJoinedRoom {
 		receipt_events,
 		typing_events,
@@ -1477,12 +1519,13 @@ fn build_joined_room(args: BuildJoinedRoom, event_fields: Option<&[String]>) ->
 		prev_batch,
 	} = args;
 
-	let edus: Vec<Raw<AnySyncEphemeralRoomEvent>> = receipt_events
+	let edus = receipt_events
 		.into_iter()
 		.map(at!(1))
 		.chain(typing_events)
-		.chain(private_read_events.into_iter().flatten())
-		.collect();
+		.chain(private_read_events.into_iter().flatten());
+
+	let edus = filter_ephemeral(edus, ephemeral_filter);
 
 	let state = state_after.wrap(StateEvents { events: state_events });
 
@@ -1651,44 +1694,6 @@ fn collect_room_account_data<'a>(
 		.collect()
 }
 
-#[expect(clippy::type_complexity)]
-fn notification_count_futures<'a>(
-	services: &'a Services,
-	sender_user: &'a UserId,
-	room_id: &'a RoomId,
-	send: bool,
-) -> (
-	impl Future<Output = Option<UInt>> + Send + 'a,
-	impl Future<Output = Option<UInt>> + Send + 'a,
-	impl Future<Output = Option<BTreeMap<OwnedEventId, (u64, u64)>>> + Send + 'a,
-) {
-	let notification_count = send.then_async(move || {
-		services
-			.pusher
-			.notification_count(sender_user, room_id)
-			.map(TryInto::try_into)
-			.unwrap_or(uint!(0))
-	});
-
-	let highlight_count = send.then_async(move || {
-		services
-			.pusher
-			.highlight_count(sender_user, room_id)
-			.map(TryInto::try_into)
-			.unwrap_or(uint!(0))
-	});
-
-	// MSC3773: per-thread counts. Filtered downstream by per-thread last-read
-	// so quiet threads are omitted on rounds where the main cursor advanced.
-	let thread_counts = send.then_async(move || {
-		services
-			.pusher
-			.thread_notification_counts(sender_user, room_id)
-	});
-
-	(notification_count, highlight_count, thread_counts)
-}
-
 fn take_sender_membership_for_join(
 	state_events: &mut Vec<PduEvent>,
 	sender_user: &UserId,
@@ -1959,43 +1964,38 @@ async fn assemble_state_events(
 
 #[expect(clippy::too_many_arguments)]
 fn assemble_unread_notifications(
-	notification_count: Option<UInt>,
-	highlight_count: Option<UInt>,
-	thread_counts: Option<BTreeMap<OwnedEventId, (u64, u64)>>,
+	notification_counts: Option<NotificationCounts>,
 	thread_last_reads: Option<&BTreeMap<OwnedEventId, u64>>,
 	send_notification_count_filter: impl Fn(&UInt) -> bool,
 	want_thread_unread: bool,
 	initial: bool,
 	in_window: impl Fn(u64) -> bool,
 ) -> (UnreadNotificationsCount, BTreeMap<OwnedEventId, UnreadNotificationsCount>) {
-	let thread_counts = thread_counts.unwrap_or_default();
-
-	let (thread_total_notifications, thread_total_highlights) = thread_counts
-		.values()
-		.fold((0_u64, 0_u64), |(n, h), &(notifs, hl)| {
-			(n.saturating_add(notifs), h.saturating_add(hl))
-		});
-
 	// MSC3773: when the client opts in via the timeline filter, partition
-	// notification counts per thread. Otherwise sum into the room total.
-	let merge_total = |total: u64| {
-		move |count: UInt| {
-			want_thread_unread
-				.is_false()
-				.then(|| count.saturating_add(UInt::try_from(total).unwrap_or_default()))
-				.unwrap_or(count)
+	// notification counts per thread. Otherwise the room reports the total
+	// over the main timeline and every thread, derived from the same counts
+	// as the threads so the two never disagree.
+	let room_counts = notification_counts.as_ref().map(|counts| {
+		if want_thread_unread {
+			counts.main
+		} else {
+			counts.total()
 		}
-	};
+	});
 
 	let unread_notifications = UnreadNotificationsCount {
-		highlight_count: highlight_count
-			.map(merge_total(thread_total_highlights))
+		highlight_count: room_counts
+			.map(|(_, highlights)| UInt::new_saturating(highlights))
 			.filter(&send_notification_count_filter),
-		notification_count: notification_count
-			.map(merge_total(thread_total_notifications))
+		notification_count: room_counts
+			.map(|(notifications, _)| UInt::new_saturating(notifications))
 			.filter(&send_notification_count_filter),
 	};
 
+	let thread_counts = notification_counts
+		.map(|counts| counts.threads)
+		.unwrap_or_default();
+
 	// On quiet rounds (timeline empty) `thread_last_reads` is `Some`; emit
 	// only threads whose read cursor advanced within the window. When the
 	// timeline carried events `thread_last_reads` is `None`; emit all.
@@ -2013,8 +2013,8 @@ fn assemble_unread_notifications(
 		.filter(|(root, _)| advanced_in_window(root))
 		.map(|(root, (notifications, highlights))| {
 			let counts = UnreadNotificationsCount {
-				notification_count: UInt::try_from(notifications).ok(),
-				highlight_count: UInt::try_from(highlights).ok(),
+				notification_count: Some(UInt::new_saturating(notifications)),
+				highlight_count: Some(UInt::new_saturating(highlights)),
 			};
 
 			(root, counts)
@@ -2265,6 +2265,23 @@ async fn typings_event_for_user(
 	})
 }
 
+/// Apply the room ephemeral filter's `types`, `not_types` and `limit` to the
+/// receipt and typing EDUs about to be sent.
+fn filter_ephemeral<I>(edus: I, filter: &RoomEventFilter) -> Vec<Raw<AnySyncEphemeralRoomEvent>>
+where
+	I: Iterator<Item = Raw<AnySyncEphemeralRoomEvent>>,
+{
+	let limit: usize = filter
+		.limit
+		.map(TryInto::try_into)
+		.map_expect("UInt to usize")
+		.unwrap_or(usize::MAX);
+
+	edus.filter(|edu| filter.matches(edu))
+		.take(limit)
+		.collect()
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -2282,6 +2299,45 @@ fn state_after_wraps_into_named_variant() {
 		assert!(StateAfter::Unstable.requested());
 	}
 
+	fn ephemeral(kind: &str) -> Raw<AnySyncEphemeralRoomEvent> {
+		serde_json::from_str(&format!(r#"{{"type":"{kind}","content":{{}}}}"#))
+			.expect("valid ephemeral event")
+	}
+
+	fn kinds(edus: &[Raw<AnySyncEphemeralRoomEvent>]) -> Vec<String> {
+		edus.iter()
+			.filter_map(|edu| edu.get_field::<String>("type").ok().flatten())
+			.collect()
+	}
+
+	#[test]
+	fn ephemeral_filter_withholds_excluded_receipts() {
+		let mut filter = RoomEventFilter::default();
+		filter.not_types = vec!["m.receipt".to_owned()];
+
+		let edus = [ephemeral("m.receipt"), ephemeral("m.typing"), ephemeral("m.receipt")];
+		let edus = filter_ephemeral(edus.into_iter(), &filter);
+
+		assert_eq!(kinds(&edus), ["m.typing"], "receipts must be withheld");
+	}
+
+	#[test]
+	fn ephemeral_filter_applies_types_and_limit() {
+		let mut filter = RoomEventFilter::default();
+		filter.types = Some(vec!["m.receipt".to_owned()]);
+		filter.limit = Some(uint!(1));
+
+		let edus = [ephemeral("m.typing"), ephemeral("m.receipt"), ephemeral("m.receipt")];
+		let edus = filter_ephemeral(edus.into_iter(), &filter);
+
+		assert_eq!(kinds(&edus), ["m.receipt"], "only one receipt within the limit");
+
+		let edus = [ephemeral("m.receipt"), ephemeral("m.typing")];
+		let edus = filter_ephemeral(edus.into_iter(), &RoomEventFilter::default());
+
+		assert_eq!(kinds(&edus), ["m.receipt", "m.typing"], "no filter passes everything");
+	}
+
 	#[test]
 	fn state_after_selects_unstable_when_both_opted_in() {
 		// (use_state_after, use_state_after_unstable)
@@ -2290,4 +2346,35 @@ fn state_after_selects_unstable_when_both_opted_in() {
 		assert!(matches!(StateAfter::from((false, true)), StateAfter::Unstable));
 		assert!(matches!(StateAfter::from((true, true)), StateAfter::Unstable));
 	}
+
+	#[test]
+	fn unread_notifications_total_main_and_threads() {
+		let root = ruma::owned_event_id!("$root");
+		let counts = NotificationCounts {
+			main: (1, 0),
+			threads: [(root.clone(), (2, 1))].into(),
+		};
+
+		let assemble = |want_thread_unread| {
+			assemble_unread_notifications(
+				Some(counts.clone()),
+				None,
+				|_| true,
+				want_thread_unread,
+				true,
+				|_| true,
+			)
+		};
+
+		let (room, threads) = assemble(false);
+		assert_eq!(room.notification_count, Some(uint!(3)), "room total includes threads");
+		assert_eq!(room.highlight_count, Some(uint!(1)));
+		assert!(threads.is_empty(), "threads are not split out unless requested");
+
+		let (room, threads) = assemble(true);
+		assert_eq!(room.notification_count, Some(uint!(1)), "room reports the main timeline");
+		assert_eq!(room.highlight_count, Some(uint!(0)));
+		assert_eq!(threads[&root].notification_count, Some(uint!(2)));
+		assert_eq!(threads[&root].highlight_count, Some(uint!(1)));
+	}
 }
@@ -18,19 +18,22 @@ pub(super) async fn calculate_heroes(
 	room_name: Option<&DisplayName>,
 	room_avatar: Option<&MxcUri>,
 ) -> (Option<Heroes>, Option<DisplayName>, Option<OwnedMxcUri>) {
+	// Member events are loaded a batch at a time until enough heroes are found,
+	// rather than for every member of the room.
 	let heroes: Heroes = services
 		.state_cache
 		.room_members(room_id)
 		.ready_filter(|&member| member != sender_user)
 		.ready_filter_map(|member| room_name.is_none().then_some(member))
-		.map(ToOwned::to_owned)
-		.broadn_filter_map(MAX_HEROES, async |user_id| {
-			let content = services
+		.ready_chunks(MAX_HEROES)
+		.flat_map(|members| {
+			services
 				.state_accessor
-				.get_member(room_id, &user_id)
-				.await
-				.ok()?;
-
+				.get_members(room_id, members.into_iter())
+		})
+		.ready_filter_map(|(user_id, content)| Some((user_id, content.ok()?)))
+		.take(MAX_HEROES)
+		.broadn_then(MAX_HEROES, async |(user_id, content)| {
 			let name = content
 				.displayname
 				.is_none()
@@ -42,17 +45,15 @@ pub(super) async fn calculate_heroes(
 				.then_async(|| services.profile.avatar_url(&user_id).ok());
 
 			let (name, avatar) = join(name, avatar).await;
-			let hero = response::Hero {
+
+			response::Hero {
 				user_id,
 				avatar: avatar.unwrap_or(content.avatar_url),
 				name: name
 					.unwrap_or(content.displayname)
 					.map(Into::into),
-			};
-
-			Some(hero)
+			}
 		})
-		.take(MAX_HEROES)
 		.collect()
 		.await;
 
@@ -5,6 +5,7 @@
 mod request;
 mod response;
 pub mod state;
+mod writable;
 
 use axum::{
 	Router,
@@ -330,6 +331,7 @@ fn register_federation_routes(router: Router<State>, allow_federation: bool) ->
 			.ruma_route(&server::get_public_rooms_route)
 			.ruma_route(&server::get_public_rooms_filtered_route)
 			.ruma_route(&server::send_transaction_message_route)
+			.ruma_route(&server::relay_transaction_route)
 			.ruma_route(&server::get_event_route)
 			.ruma_route(&server::get_event_by_timestamp_route)
 			.ruma_route(&server::get_backfill_route)
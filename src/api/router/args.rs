@@ -16,6 +16,7 @@
 	auth::{Auth, AuthDispatch},
 	request,
 	request::Request,
+	writable::check_writable,
 };
 use crate::State;
 
@@ -130,6 +131,8 @@ async fn from_request(
 		)
 		.await?;
 
+		check_writable::<T>(services, auth.sender_user.as_deref()).await?;
+
 		Ok(Self {
 			body: make_body::<T>(services, &mut request, json_body.as_mut(), &auth)?,
 			cookie: request.cookie,
@@ -1,8 +1,14 @@
+use std::any::{TypeId, type_name};
+
 use ruma::{
 	CanonicalJsonValue, OwnedUserId,
 	api::{
 		IncomingRequest,
-		client::uiaa::{AuthData, AuthFlow, AuthType, Jwt, UiaaInfo},
+		client::{
+			device::{delete_device, delete_devices},
+			keys::upload_signing_keys,
+			uiaa::{AuthData, AuthFlow, AuthType, Jwt, UiaaInfo},
+		},
 	},
 };
 use serde_json::{json, value::to_raw_value};
@@ -13,13 +19,16 @@
 		future::{OptionFutureExt, TryExtExt},
 	},
 };
-use tuwunel_service::{Services, uiaa::SESSION_ID_LENGTH};
+use tuwunel_service::{
+	Services,
+	uiaa::{APPSERVICE_AUTH_TYPE, SESSION_ID_LENGTH},
+};
 
 use crate::{Ruma, client::jwt};
 
 pub(crate) async fn auth_uiaa<T>(services: &Services, body: &Ruma<T>) -> Result<OwnedUserId>
 where
-	T: IncomingRequest + Send + Sync,
+	T: IncomingRequest + Send + Sync + 'static,
 {
 	let sender_user = body.sender_user.as_deref();
 
@@ -83,12 +92,18 @@ pub(crate) async fn auth_uiaa<T>(services: &Services, body: &Ruma<T>) -> Result<
 	let jwt_flow = [AuthType::Jwt];
 	let has_jwt = services.config.jwt.enable;
 
+	// Bridges manage the devices and cross-signing keys of their users, who
+	// usually have no password to re-authenticate with.
+	let appservice_flow = [AuthType::from(APPSERVICE_AUTH_TYPE)];
+	let has_appservice = body.appservice_info.is_some() && is_bridge_route::<T>();
+
 	let mut uiaainfo = UiaaInfo {
 		flows: has_password
 			.then_some(password_flow)
 			.into_iter()
 			.chain(has_sso.then_some(sso_flow))
 			.chain(has_jwt.then_some(jwt_flow))
+			.chain(has_appservice.then_some(appservice_flow))
 			.map(Vec::from)
 			.map(AuthFlow::new)
 			.collect(),
@@ -125,7 +140,14 @@ pub(crate) async fn auth_uiaa<T>(services: &Services, body: &Ruma<T>) -> Result<
 			let sender_device = body.sender_device()?;
 			let (worked, uiaainfo) = services
 				.uiaa
-				.try_auth(sender_user, sender_device, auth, &uiaainfo)
+				.try_auth(
+					sender_user,
+					sender_device,
+					auth,
+					&uiaainfo,
+					body.appservice_info.as_ref(),
+					Some(type_name::<T>()),
+				)
 				.await?;
 
 			if !worked {
@@ -157,9 +179,13 @@ pub(crate) async fn auth_uiaa<T>(services: &Services, body: &Ruma<T>) -> Result<
 					.ok();
 				}
 
-				services
-					.uiaa
-					.create(sender_user, sender_device, &uiaainfo, json);
+				services.uiaa.create(
+					sender_user,
+					sender_device,
+					&uiaainfo,
+					json,
+					is_reusable_route::<T>().then(type_name::<T>),
+				);
 
 				Err(Error::Uiaa(uiaainfo))
 			},
@@ -167,3 +193,17 @@ pub(crate) async fn auth_uiaa<T>(services: &Services, body: &Ruma<T>) -> Result<
 		},
 	}
 }
+
+fn is_bridge_route<T: 'static>() -> bool {
+	let route = TypeId::of::<T>();
+
+	route == TypeId::of::<delete_device::v3::Request>()
+		|| route == TypeId::of::<delete_devices::v3::Request>()
+		|| route == TypeId::of::<upload_signing_keys::v3::Request>()
+}
+
+/// Routes clients call again straight after a first success, such as setting
+/// up cross-signing during bootstrap, which may reuse a completed session.
+fn is_reusable_route<T: 'static>() -> bool {
+	TypeId::of::<T>() == TypeId::of::<upload_signing_keys::v3::Request>()
+}
@@ -0,0 +1,115 @@
+use std::any::TypeId;
+
+use http::Method;
+use ruma::{
+	UserId,
+	api::{
+		IncomingRequest,
+		client::{
+			directory::get_public_rooms_filtered, keys::get_keys, search::search_events,
+			user_directory::search_users,
+		},
+		federation::{
+			directory::get_public_rooms_filtered as federation_get_public_rooms_filtered,
+			event::get_missing_events, keys::get_keys as federation_get_keys,
+		},
+	},
+};
+use tuwunel_core::Result;
+use tuwunel_service::Services;
+
+/// Writes are refused while the server is in read-only mode, except from
+/// server admins so the mode can still be lifted from the admin room. Whether
+/// a route writes is decided by `is_write_route`.
+pub(super) async fn check_writable<T>(services: &Services, sender_user: Option<&UserId>) -> Result
+where
+	T: IncomingRequest + 'static,
+{
+	if !services.globals.read_only_mode() || !is_write_route::<T>() {
+		return Ok(());
+	}
+
+	if let Some(sender_user) = sender_user
+		&& services.admin.user_is_admin(sender_user).await
+	{
+		return Ok(());
+	}
+
+	services.globals.check_writable()
+}
+
+/// Every route writes unless it is fetched with GET or listed in `reads`, so
+/// a route added without a thought for read-only mode is refused rather than
+/// served.
+fn is_write_route<T>() -> bool
+where
+	T: IncomingRequest + 'static,
+{
+	let read = matches!(T::METHOD, Method::GET | Method::HEAD | Method::OPTIONS)
+		|| reads().contains(&TypeId::of::<T>());
+
+	!read
+}
+
+/// Routes sent as POST which only look things up.
+fn reads() -> [TypeId; 7] {
+	[
+		TypeId::of::<get_keys::v3::Request>(),
+		TypeId::of::<search_events::v3::Request>(),
+		TypeId::of::<search_users::v3::Request>(),
+		TypeId::of::<get_public_rooms_filtered::v3::Request>(),
+		TypeId::of::<federation_get_keys::v1::Request>(),
+		TypeId::of::<federation_get_public_rooms_filtered::v1::Request>(),
+		TypeId::of::<get_missing_events::v1::Request>(),
+	]
+}
+
+#[cfg(test)]
+mod tests {
+	use ruma::api::{
+		client::{
+			keys::{claim_keys, get_keys},
+			message::send_message_event,
+			search::search_events,
+			session::{login, logout, logout_all, refresh_token},
+			sync::sync_events,
+			typing::create_typing_event,
+			user_directory::search_users,
+		},
+		federation::event::get_missing_events,
+	};
+
+	use super::is_write_route;
+
+	#[test]
+	fn reads_sent_as_post_are_not_writes() {
+		assert!(!is_write_route::<get_keys::v3::Request>());
+		assert!(!is_write_route::<search_events::v3::Request>());
+		assert!(!is_write_route::<search_users::v3::Request>());
+		assert!(!is_write_route::<get_missing_events::v1::Request>());
+	}
+
+	#[test]
+	fn fetches_are_not_writes() {
+		assert!(!is_write_route::<sync_events::v3::Request>());
+	}
+
+	#[test]
+	fn sending_an_event_is_a_write() {
+		assert!(is_write_route::<send_message_event::v3::Request>());
+	}
+
+	#[test]
+	fn sessions_and_one_time_keys_are_writes() {
+		assert!(is_write_route::<login::v3::Request>());
+		assert!(is_write_route::<refresh_token::v3::Request>());
+		assert!(is_write_route::<logout::v3::Request>());
+		assert!(is_write_route::<logout_all::v3::Request>());
+		assert!(is_write_route::<claim_keys::v3::Request>());
+	}
+
+	#[test]
+	fn unlisted_routes_are_writes() {
+		assert!(is_write_route::<create_typing_event::v3::Request>());
+	}
+}
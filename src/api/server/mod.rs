@@ -13,6 +13,7 @@
 pub(super) mod openid;
 pub(super) mod publicrooms;
 pub(super) mod query;
+pub(super) mod relay;
 pub(super) mod send;
 pub(super) mod send_join;
 pub(super) mod send_knock;
@@ -39,6 +40,7 @@
 pub(super) use openid::*;
 pub(super) use publicrooms::*;
 pub(super) use query::*;
+pub(super) use relay::*;
 pub(super) use send::*;
 pub(super) use send_join::*;
 pub(super) use send_knock::*;
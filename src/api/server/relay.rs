@@ -0,0 +1,33 @@
+use axum::extract::State;
+use tuwunel_core::{Err, Result};
+use tuwunel_service::sending::{EDU_LIMIT, PDU_LIMIT, relay};
+
+use crate::Ruma;
+
+/// # `PUT /_matrix/federation/unstable/net.tuwunel.relay/send/{destination}/{txnId}`
+///
+/// Forward a transaction on to `destination` for a server configured to relay
+/// its federation through this one.
+pub(crate) async fn relay_transaction_route(
+	State(services): State<crate::State>,
+	body: Ruma<relay::v1::Request>,
+) -> Result<relay::v1::Response> {
+	if body.origin() != body.body.origin {
+		return Err!(Request(Forbidden(
+			"Not allowed to relay transactions on behalf of other servers"
+		)));
+	}
+
+	if body.pdus.len() > PDU_LIMIT || body.edus.len() > EDU_LIMIT {
+		return Err!(Request(Forbidden("Transaction exceeds the PDU or EDU limit")));
+	}
+
+	let origin = body.origin().to_owned();
+	let body = body.body;
+	services
+		.sending
+		.forward_relayed(&origin, &body.destination, &body.transaction_id, body.pdus, body.edus)
+		.await?;
+
+	Ok(relay::v1::Response {})
+}
@@ -21,7 +21,10 @@
 			send_transaction_message,
 		},
 	},
-	events::receipt::{ReceiptEvent, ReceiptEventContent, ReceiptType},
+	events::{
+		presence::PresenceEventContent,
+		receipt::{ReceiptEvent, ReceiptEventContent, ReceiptType},
+	},
 	int,
 	serde::Raw,
 	to_device::DeviceIdOrAllDevices,
@@ -454,15 +457,14 @@ async fn handle_edu_presence_update(
 		return;
 	}
 
+	let mut content = PresenceEventContent::new(update.presence);
+	content.currently_active = Some(update.currently_active);
+	content.last_active_ago = Some(update.last_active_ago);
+	content.status_msg = update.status_msg;
+
 	services
 		.presence
-		.set_presence_from_federation(
-			&update.user_id,
-			&update.presence,
-			update.currently_active,
-			update.last_active_ago,
-			update.status_msg.clone(),
-		)
+		.ingest_remote_presence(&update.user_id, &content, millis_since_unix_epoch())
 		.await
 		.log_err()
 		.ok();
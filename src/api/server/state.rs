@@ -3,10 +3,7 @@
 use axum::extract::State;
 use futures::{FutureExt, StreamExt, TryFutureExt, TryStreamExt, future::try_join};
 use ruma::{OwnedEventId, api::federation::event::get_room_state};
-use tuwunel_core::{
-	Result, at, err,
-	utils::stream::{IterStream, TryBroadbandExt},
-};
+use tuwunel_core::{Result, at, err, utils::stream::TryBroadbandExt};
 
 use super::AccessCheck;
 use crate::Ruma;
@@ -63,15 +60,11 @@ pub(crate) async fn get_room_state_route(
 		})
 		.try_collect();
 
-	let pdus = state_ids
-		.iter()
-		.try_stream()
-		.broad_and_then(|id| {
-			services
-				.timeline
-				.get_pdu_json(id)
-				.and_then(into_federation_format)
-		})
+	let pdus = services
+		.timeline
+		.get_pdus_json(state_ids.iter().map(|id| &**id))
+		.map(at!(1))
+		.broad_and_then(into_federation_format)
 		.try_collect();
 
 	let (auth_chain, pdus) = try_join(auth_chain, pdus).await?;
@@ -91,6 +91,21 @@ fn check_network(config: &Config) -> Result {
 		return Err!(Config("tls", "tls.certs and tls.key must either both be set or unset"));
 	}
 
+	if config.federation_max_inflight_per_dest == 0 {
+		return Err!(Config("federation_max_inflight_per_dest", "must be at least 1"));
+	}
+
+	if config
+		.federation_relay_server
+		.as_ref()
+		.is_some_and(|relay| *relay == config.server_name)
+	{
+		return Err!(Config(
+			"federation_relay_server",
+			"The federation relay must be another Matrix server, not this one."
+		));
+	}
+
 	// A non-zero depth shards the 64-char SHA-256 hex digest into `depth`
 	// segments of `length` plus a remainder, so the product must stay below 64.
 	let depth = config.conduit_media_directory_depth;
@@ -180,6 +180,20 @@ pub struct Config {
 	#[serde(default = "default_database_backups_to_keep")]
 	pub database_backups_to_keep: i16,
 
+	/// Number of sequence numbers reserved in the database each time the global
+	/// counter is persisted.
+	///
+	/// Every event, receipt and other update draws a number from this counter.
+	/// With the default of 1 the counter is written to the database for every
+	/// number. Larger values write less often, but each restart skips the
+	/// unused remainder of the last reserved batch, leaving a gap of up to
+	/// this many numbers. Numbers are never reused after a crash regardless of
+	/// this value.
+	///
+	/// default: 1
+	#[serde(default = "default_counter_checkpoint_batch")]
+	pub counter_checkpoint_batch: u64,
+
 	/// Set this to any float value to multiply tuwunel's in-memory LRU caches
 	/// with such as "auth_chain_cache_capacity".
 	///
@@ -266,6 +280,21 @@ pub struct Config {
 	#[serde(default = "default_stateinfo_cache_capacity")]
 	pub stateinfo_cache_capacity: u32,
 
+	/// Number of rooms' effective power levels kept cached, each valid until
+	/// the room's state changes.
+	///
+	/// default: varies by system
+	#[serde(default = "default_power_levels_cache_capacity")]
+	pub power_levels_cache_capacity: u32,
+
+	/// Number of rooms whose joined active local users are kept cached for
+	/// notifying them of new events, each valid until the room's membership
+	/// changes.
+	///
+	/// default: varies by system
+	#[serde(default = "default_our_real_users_cache_capacity")]
+	pub our_real_users_cache_capacity: u32,
+
 	/// Minimum time-to-live in seconds for room summary entries in the spaces
 	/// cache.
 	///
@@ -282,6 +311,15 @@ pub struct Config {
 	#[serde(default = "default_spacehierarchy_cache_ttl_max")]
 	pub spacehierarchy_cache_ttl_max: u64,
 
+	/// Maximum number of `via` servers asked at once for a space child's
+	/// hierarchy over federation. The first successful answer is used; the
+	/// remaining servers are only asked as earlier ones fail.
+	///
+	/// reloadable: yes
+	/// default: 3
+	#[serde(default = "default_space_hierarchy_federation_concurrency")]
+	pub space_hierarchy_federation_concurrency: usize,
+
 	/// Minimum timeout a client can request for long-polling sync. Requests
 	/// will be clamped up to this value if smaller.
 	///
@@ -306,6 +344,16 @@ pub struct Config {
 	#[serde(default = "default_client_sync_timeout_max")]
 	pub client_sync_timeout_max: u64,
 
+	/// Maximum number of joined rooms delivered in a single initial sync. When
+	/// a user is in more rooms, the most recently active are sent first and
+	/// the rest follow in batches of the same size on subsequent syncs, which
+	/// return immediately until none remain. 0 sends every room at once.
+	///
+	/// reloadable: yes
+	/// default: 0
+	#[serde(default)]
+	pub initial_sync_room_limit: usize,
+
 	/// Maximum entries stored in DNS memory-cache. The size of an entry may
 	/// vary so please take care if raising this value excessively. Only
 	/// decrease this when using an external DNS cache. Please note that
@@ -504,6 +552,16 @@ pub struct Config {
 	#[serde(default = "default_fetch_prev_wait_ms")]
 	pub fetch_prev_wait_ms: u64,
 
+	/// Maximum number of prev_event generations a single backfill request
+	/// walks back from the event it starts at. Events the remote returns from
+	/// further back are dropped and the backfill completes with what was
+	/// reached. The value 0 leaves backfill depth uncapped.
+	///
+	/// reloadable: yes
+	/// default: 0
+	#[serde(default)]
+	pub backfill_max_depth: usize,
+
 	/// Default/base connection timeout (seconds). This is used only by URL
 	/// previews and update/news endpoint checks.
 	///
@@ -614,6 +672,15 @@ pub struct Config {
 	#[serde(default = "default_federation_keys_timeout")]
 	pub federation_keys_timeout: u64,
 
+	/// Timeout (seconds) for the few federation requests sent with a very
+	/// large timeout for compatibility with Synapse, which can take minutes
+	/// to answer large state and media requests. Bounds the wait for more data;
+	/// the whole request is still bounded by `request_total_timeout`.
+	///
+	/// default: 305
+	#[serde(default = "default_federation_synapse_timeout_s")]
+	pub federation_synapse_timeout_s: u64,
+
 	/// Federation client idle connection pool timeout (seconds).
 	///
 	/// default: 25
@@ -647,6 +714,76 @@ pub struct Config {
 	#[serde(default = "default_sender_retry_backoff_limit")]
 	pub sender_retry_backoff_limit: u64,
 
+	/// Number of consecutive failed attempts after which the federation sender
+	/// gives up on a transaction to a server and drops its events. The value 0
+	/// retries forever.
+	///
+	/// default: 0
+	#[serde(default)]
+	pub sender_max_retries: u32,
+
+	/// Number of consecutive failed transactions after which the sender opens
+	/// the circuit to a destination and stops attempting it altogether for
+	/// `sender_circuit_cooldown` seconds. Once the cooldown passes a single
+	/// probe transaction is let through; its success closes the circuit again
+	/// and its failure re-opens it. The value 0 disables the circuit breaker.
+	///
+	/// reloadable: yes
+	/// default: 0
+	#[serde(default)]
+	pub sender_circuit_failure_threshold: u32,
+
+	/// Time in seconds an open circuit refuses transactions to its destination
+	/// before admitting a probe (see `sender_circuit_failure_threshold`).
+	///
+	/// reloadable: yes
+	/// default: 600
+	#[serde(default = "default_sender_circuit_cooldown")]
+	pub sender_circuit_cooldown: u64,
+
+	/// Maximum number of transactions the federation sender keeps in flight to
+	/// a single server at once. The default of 1 sends a server's transactions
+	/// one after another; raising it lets a large backlog drain sooner, at the
+	/// cost of the server receiving them out of order. Appservices and push
+	/// gateways are always sent one transaction at a time.
+	///
+	/// reloadable: yes
+	/// default: 1
+	#[serde(default = "default_federation_max_inflight_per_dest")]
+	pub federation_max_inflight_per_dest: usize,
+
+	/// URL to POST a JSON notification to whenever the federation sender gives
+	/// up delivering a transaction to a server (see `sender_max_retries`). The
+	/// payload carries the destination, the abandoned event IDs and the last
+	/// error. Delivery is best-effort and never delays the sender.
+	///
+	/// example: "https://alerts.example.com/tuwunel/federation"
+	pub federation_failure_webhook_url: Option<Url>,
+
+	/// Hand all outgoing federation to this server instead of delivering it to
+	/// each destination directly, for deployments which may only reach the
+	/// federation through a trusted relay. Events are still queued, retried and
+	/// backed off per destination; each transaction is then wrapped in an
+	/// envelope naming its real destination and put to the relay's
+	/// `/_matrix/federation/unstable/net.tuwunel.relay/send/{destination}/
+	/// {txnId}` endpoint.
+	///
+	/// The relay must be another tuwunel server listing this one in its
+	/// `federation_relay_clients`. It forwards the PDUs under its own name;
+	/// EDUs are forwarded too, but destinations which check them against the
+	/// sending server will drop them.
+	///
+	/// example: "relay.example.com"
+	pub federation_relay_server: Option<OwnedServerName>,
+
+	/// Servers allowed to hand their outgoing federation to this server for
+	/// forwarding (see `federation_relay_server`). Relay requests from any
+	/// other server are refused.
+	///
+	/// default: []
+	#[serde(default)]
+	pub federation_relay_clients: Vec<OwnedServerName>,
+
 	/// Appservice URL request connection timeout. Defaults to 35 seconds as
 	/// generally appservices are hosted within the same network.
 	///
@@ -1604,6 +1741,18 @@ pub struct Config {
 	#[serde(default)]
 	pub oidc_rc_burst_count: u32,
 
+	/// Lead time in seconds for refreshing identity provider access tokens.
+	///
+	/// Sessions are checked every minute; an access token due to expire within
+	/// this many seconds is refreshed ahead of time using its refresh token, so
+	/// userinfo requests do not start failing when it lapses. Sessions without
+	/// a refresh token are left alone. `0` disables the proactive refresh.
+	///
+	/// reloadable: yes
+	/// default: 300
+	#[serde(default = "default_oauth_token_refresh_lead_seconds")]
+	pub oauth_token_refresh_lead_seconds: u64,
+
 	/// Static TURN username to provide the client if not using a shared secret
 	/// ("turn_secret"), It is recommended to use a shared secret over static
 	/// credentials.
@@ -2069,6 +2218,38 @@ pub struct Config {
 	#[serde(default = "true_fn")]
 	pub presence_timeout_remote_users: bool,
 
+	/// Number of recent presence updates kept in memory to answer incremental
+	/// syncs and federation presence queries without scanning the database.
+	///
+	/// Requests for updates older than the cached window fall back to the
+	/// database. Busy servers with frequent presence churn benefit the most.
+	/// Set to 0 to disable the cache.
+	///
+	/// default: 0
+	#[serde(default)]
+	pub presence_cache_size: usize,
+
+	/// Maximum length in characters of a presence status message. Longer
+	/// messages, local or received over federation, are truncated before they
+	/// are stored. Control characters are always stripped. Set to 0 to not
+	/// limit the length.
+	///
+	/// default: 256
+	#[serde(default = "default_presence_status_msg_max_len")]
+	pub presence_status_msg_max_len: usize,
+
+	/// Maximum number of servers a local user's presence is federated to.
+	///
+	/// By default presence is sent to every server sharing a room with the
+	/// user, which for users in large federated rooms can mean thousands of
+	/// destinations per update. When set, only the servers sharing the most
+	/// rooms with the user receive it. Set to 0 to not limit the fan-out.
+	///
+	/// reloadable: yes
+	/// default: 0
+	#[serde(default)]
+	pub presence_federation_server_limit: usize,
+
 	/// Suppresses push notifications for users marked as active. (Experimental)
 	///
 	/// When enabled, users with `Online` presence and recent activity
@@ -2505,6 +2686,20 @@ pub struct Config {
 	#[serde(default, with = "serde_regex")]
 	pub forbidden_usernames: RegexSet,
 
+	/// List of room alias localpart patterns reserved for the server's own
+	/// use, as strings of regex patterns.
+	///
+	/// Server code creating aliases on behalf of users refuses localparts
+	/// matching any of these. The server itself may still create them, such as
+	/// the admin room alias.
+	///
+	/// default: ["^admins$", "-userroom$"]
+	#[serde(
+		default = "default_reserved_alias_patterns",
+		with = "serde_regex"
+	)]
+	pub reserved_alias_patterns: RegexSet,
+
 	/// List of server names to deprioritize joining through.
 	///
 	/// If a client requests a join through one of these servers,
@@ -2797,6 +2992,33 @@ pub struct Config {
 	#[serde(default = "default_redaction_retention_seconds")]
 	pub redaction_retention_seconds: u64,
 
+	/// Outlier retention period in seconds.
+	///
+	/// Outliers are events fetched over federation to check other events
+	/// against, but which are not part of a room's timeline. When non-zero,
+	/// outliers older than this which no accepted event depends on are pruned
+	/// hourly. They can also be pruned with `admin debug prune-outliers`.
+	///
+	/// reloadable: yes
+	/// default: 0
+	#[serde(default)]
+	pub outlier_retention_seconds: u64,
+
+	/// Index the body of each message for full-text `/search`.
+	///
+	/// Every word of every message is stored once more in the search index,
+	/// keyed by room, so the index grows roughly as large as the message text
+	/// itself. Disabling it saves that space, but messages received while it is
+	/// disabled are never indexed and cannot be found later.
+	///
+	/// It stays enabled by default so that servers which already relied on
+	/// `/search` keep indexing new messages on upgrade.
+	///
+	/// reloadable: yes
+	/// default: true
+	#[serde(default = "true_fn")]
+	pub enable_message_search: bool,
+
 	/// Allows users with `redact` power level to request unredacted events with
 	/// MSC2815.
 	///
@@ -2982,6 +3204,9 @@ pub struct Config {
 	/// default but can be enabled for deployments interested in conserving
 	/// space. It may eventually default to true in a future release.
 	///
+	/// Either way the server stops participating in such a room; this option
+	/// additionally purges its events and state.
+	///
 	/// Note that not all pathways which can remove the last local user
 	/// currently invoke this operation, so in some cases you may find the room
 	/// still exists.
@@ -3165,7 +3390,7 @@ pub struct Config {
 	catchall: BTreeMap<String, IgnoredAny>,
 }
 
-#[derive(Clone, Debug, Deserialize, Default)]
+#[derive(Clone, Debug, Deserialize, Default, PartialEq)]
 #[config_example_generator(filename = "tuwunel-example.toml", section = "global.tls")]
 pub struct TlsConfig {
 	/// Path to a valid TLS certificate file.
@@ -3184,7 +3409,7 @@ pub struct TlsConfig {
 }
 
 #[expect(rustdoc::bare_urls)]
-#[derive(Clone, Debug, Deserialize, Default)]
+#[derive(Clone, Debug, Deserialize, Default, PartialEq)]
 #[config_example_generator(
 	filename = "tuwunel-example.toml",
 	section = "global.well_known",
@@ -3299,7 +3524,7 @@ pub struct WellKnownConfig {
 	pub rtc_transports: Vec<serde_json::Value>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 #[config_example_generator(
 	filename = "tuwunel-example.toml",
 	section = "global.well_known.support_policy.<ID>",
@@ -3316,7 +3541,7 @@ pub struct SupportPolicy {
 	pub policy_translation: BTreeMap<String, SupportPolicyTranslation>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 #[config_example_generator(
 	filename = "tuwunel-example.toml",
 	section = "global.well_known.support_policy.<ID>.policy_translation.<LANG>"
@@ -3335,7 +3560,7 @@ pub struct SupportPolicyTranslation {
 	pub url: Url,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 #[config_example_generator(
 	filename = "tuwunel-example.toml",
 	section = "global.registration_terms.<ID>",
@@ -3355,7 +3580,7 @@ pub struct TermsPolicy {
 	pub translations: BTreeMap<String, TermsPolicyTranslation>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 #[config_example_generator(
 	filename = "tuwunel-example.toml",
 	section = "global.registration_terms.<ID>.translations.<LANG>"
@@ -3385,7 +3610,7 @@ fn from(conf: SupportPolicyTranslation) -> Self {
 	}
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 #[config_example_generator(
 	filename = "tuwunel-example.toml",
 	section = "global.well_known.support_contact.<ID>"
@@ -3431,7 +3656,7 @@ fn from(conf: SupportContact) -> Self {
 	}
 }
 
-#[derive(Clone, Debug, Default, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
 #[config_example_generator(filename = "tuwunel-example.toml", section = "global.ldap")]
 pub struct LdapConfig {
 	/// Whether to enable LDAP login.
@@ -3539,7 +3764,7 @@ pub struct LdapConfig {
 	pub admin_filter: String,
 }
 
-#[derive(Clone, Debug, Default, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
 #[config_example_generator(filename = "tuwunel-example.toml", section = "global.jwt")]
 pub struct JwtConfig {
 	/// Enable JWT logins
@@ -3645,7 +3870,7 @@ pub struct JwtConfig {
 	pub validate_signature: bool,
 }
 
-#[derive(Clone, Debug, Default, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
 #[config_example_generator(filename = "tuwunel-example.toml", section = "global.smtp")]
 pub struct SmtpConfig {
 	/// Connection URL for the outbound SMTP relay used to send email
@@ -3689,7 +3914,7 @@ pub struct SmtpConfig {
 	pub require_email_for_token_registration: bool,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 #[config_example_generator(
 	filename = "tuwunel-example.toml",
 	section = "[global.identity_provider]"
@@ -3980,7 +4205,7 @@ pub async fn get_client_secret(&self) -> Result<String> {
 	}
 }
 
-#[derive(Clone, Debug, Default, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
 pub enum StorageProvider {
 	#[expect(non_camel_case_types)]
 	local(StorageProviderLocal),
@@ -3991,7 +4216,7 @@ pub enum StorageProvider {
 	None,
 }
 
-#[derive(Clone, Debug, Default, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
 #[config_example_generator(
 	filename = "tuwunel-example.toml",
 	section = "global.storage_provider.<ID>.local"
@@ -4022,7 +4247,7 @@ pub struct StorageProviderLocal {
 	pub startup_check: bool,
 }
 
-#[derive(Clone, Debug, Default, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
 #[config_example_generator(
 	filename = "tuwunel-example.toml",
 	section = "global.storage_provider.<ID>.s3"
@@ -4140,7 +4365,7 @@ pub struct StorageProviderS3 {
 	pub startup_check: bool,
 }
 
-#[derive(Clone, Debug, Default, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
 #[config_example_generator(
 	filename = "tuwunel-example.toml",
 	section = "global.appservice.<ID>",
@@ -4238,7 +4463,7 @@ fn from(conf: AppService) -> Self {
 	}
 }
 
-#[derive(Clone, Debug, Default, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
 #[config_example_generator(
 	filename = "tuwunel-example.toml",
 	section = "[global.appservice.<ID>.<users|rooms|aliases>]"
@@ -4373,6 +4598,8 @@ fn default_unix_socket_perms() -> u32 { 660 }
 
 fn default_database_backups_to_keep() -> i16 { 1 }
 
+fn default_counter_checkpoint_batch() -> u64 { 1 }
+
 fn default_db_write_buffer_capacity_mb() -> f64 { 48.0 + parallelism_scaled_f64(4.0) }
 
 fn default_db_cache_capacity_mb() -> f64 { 128.0 + parallelism_scaled_f64(64.0) }
@@ -4411,10 +4638,16 @@ fn default_servernameevent_data_cache_capacity() -> u32 {
 
 fn default_stateinfo_cache_capacity() -> u32 { parallelism_scaled_u32(100) }
 
+fn default_power_levels_cache_capacity() -> u32 { parallelism_scaled_u32(500) }
+
+fn default_our_real_users_cache_capacity() -> u32 { parallelism_scaled_u32(500) }
+
 fn default_spacehierarchy_cache_ttl_min() -> u64 { 60 * 60 * 3 }
 
 fn default_spacehierarchy_cache_ttl_max() -> u64 { 60 * 60 * 18 }
 
+fn default_space_hierarchy_federation_concurrency() -> usize { 3 }
+
 fn default_dns_cache_entries() -> u32 { 32768 }
 
 fn default_dns_min_ttl() -> u64 { 60 * 180 }
@@ -4457,6 +4690,10 @@ fn default_federation_timeout() -> u64 { 25 }
 
 fn default_federation_keys_timeout() -> u64 { 8 }
 
+fn default_federation_synapse_timeout_s() -> u64 { 305 }
+
+fn default_oauth_token_refresh_lead_seconds() -> u64 { 300 }
+
 fn default_federation_idle_timeout() -> u64 { 25 }
 
 fn default_federation_idle_per_host() -> u16 { 1 }
@@ -4467,6 +4704,10 @@ fn default_sender_idle_timeout() -> u64 { 180 }
 
 fn default_sender_retry_backoff_limit() -> u64 { 86400 }
 
+fn default_sender_circuit_cooldown() -> u64 { 600 }
+
+fn default_federation_max_inflight_per_dest() -> usize { 1 }
+
 fn default_appservice_timeout() -> u64 { 35 }
 
 fn default_appservice_idle_timeout() -> u64 { 300 }
@@ -4521,6 +4762,8 @@ fn default_presence_idle_timeout_s() -> u64 { 5 * 60 }
 
 fn default_presence_offline_timeout_s() -> u64 { 30 * 60 }
 
+fn default_presence_status_msg_max_len() -> usize { 256 }
+
 fn default_typing_federation_timeout_s() -> u64 { 30 }
 
 fn default_typing_client_timeout_min_s() -> u64 { 15 }
@@ -4687,6 +4930,10 @@ fn default_access_token_ttl() -> u64 { 604_800 }
 
 fn default_refresh_token_reuse_grace() -> u64 { 15 }
 
+fn default_reserved_alias_patterns() -> RegexSet {
+	RegexSet::new(["^admins$", "-userroom$"]).expect("valid set of regular expressions")
+}
+
 fn default_deprioritize_joins_through_servers() -> RegexSet {
 	RegexSet::new([r"matrix\.org"]).expect("valid set of regular expressions")
 }
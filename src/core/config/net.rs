@@ -9,14 +9,14 @@
 
 use crate::{Result, err, implement, utils::BoolExt};
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Clone, Debug, PartialEq)]
 #[serde(transparent)]
 pub(super) struct ListeningPort {
 	#[serde(with = "either::serde_untagged")]
 	pub(super) ports: Either<u16, Vec<u16>>,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Clone, Debug, PartialEq)]
 #[serde(transparent)]
 pub(super) struct ListeningAddr {
 	#[serde(with = "either::serde_untagged")]
@@ -28,7 +28,7 @@
 /// be used if it was included because of a more specific rule than it was
 /// excluded. In the above example, the proxy would be used for
 /// `ordinary.onion`, `matrix.myspecial.onion`, but not `hello.myspecial.onion`.
-#[derive(Clone, Default, Debug, Deserialize)]
+#[derive(Clone, Default, Debug, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum ProxyConfig {
 	#[default]
@@ -55,7 +55,7 @@ pub fn to_proxy(&self) -> Result<Option<Proxy>> {
 	}
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct PartialProxyConfig {
 	#[serde(deserialize_with = "crate::utils::deserialize_from_str")]
 	url: Url,
@@ -99,7 +99,7 @@ pub fn for_url(&self, url: &Url) -> Option<&Url> {
 }
 
 /// A domain name, that optionally allows a * as its first subdomain.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 enum WildCardedDomain {
 	WildCard,
 	WildCarded(String),
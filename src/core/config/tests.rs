@@ -272,6 +272,29 @@ fn reload_accepts_unchanged_none_and_unchanged_some() {
 	check::reload(&some, &some).expect("unchanged some config should reload");
 }
 
+#[test]
+fn reload_from_reports_changed_sections_and_compares_regex_patterns() {
+	let old = config_from_toml("[global]\n").unwrap();
+	let new = config_from_toml(
+		r#"[global]
+forbidden_remote_server_names = ["evil\\.example"]
+
+[global.ldap]
+uri = "ldap://ldap.example.com"
+"#,
+	)
+	.unwrap();
+
+	let mut config = old.clone();
+	let (applied, ignored) = config.reload_from(&new);
+	assert_eq!(applied, ["forbidden_remote_server_names"], "{applied:?}");
+	assert_eq!(ignored, ["ldap"], "a changed section must be reported: {ignored:?}");
+	assert!(config.ldap.uri.is_none(), "a section needing a restart was applied");
+
+	let (applied, ignored) = config.clone().reload_from(&config);
+	assert!(applied.is_empty() && ignored.is_empty(), "{applied:?} {ignored:?}");
+}
+
 fn check_support_pgp_key(value: &str) -> Result {
 	let toml = format!(
 		"[global.well_known.support_contact.admin]\nrole = \"m.role.admin\"\nemail_address = \
@@ -304,3 +327,12 @@ fn pgp_key_rejects_raw_material_and_bare_fingerprints() {
 	let err = check_support_pgp_key("openpgp4fpr:nothex").unwrap_err();
 	assert!(err.to_string().contains("hex fingerprint"), "{err}");
 }
+
+#[test]
+fn federation_synapse_timeout_defaults_and_overrides() {
+	let config = config_from_toml("[global]\n").unwrap();
+	assert_eq!(config.federation_synapse_timeout_s, 305);
+
+	let config = config_from_toml("[global]\nfederation_synapse_timeout_s = 900\n").unwrap();
+	assert_eq!(config.federation_synapse_timeout_s, 900);
+}
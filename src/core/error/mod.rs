@@ -3,6 +3,8 @@
 mod panic;
 mod response;
 mod serde;
+#[cfg(test)]
+mod tests;
 
 use std::{
 	any::Any,
@@ -11,7 +13,7 @@
 	sync::{Mutex, PoisonError},
 };
 
-pub use self::{err::visit, log::*};
+pub use self::{err::visit, log::*, response::EXTENDED_ERRCODE_FIELD};
 use crate::utils::{assert_ref_unwind_safe, assert_send, assert_sync, assert_unwind_safe};
 
 #[derive(thiserror::Error)]
@@ -157,6 +159,14 @@ pub enum Error {
 	Signatures(#[from] ruma::signatures::VerificationError),
 	#[error(transparent)]
 	SignaturesJson(#[from] ruma::signatures::JsonError),
+
+	/// Event was persisted but soft-failed against the current room state.
+	///
+	/// Surfaces to the wire as 400 / M_INVALID_PARAM. Exists so callers of
+	/// the event handler can tell soft-failure apart from a hard rejection
+	/// and recover the reason without grepping the message text.
+	#[error("Event {0} has been soft failed: {1}")]
+	SoftFailed(ruma::OwnedEventId, Cow<'static, str>),
 	#[error("uiaa")]
 	Uiaa(ruma::api::client::uiaa::UiaaInfo),
 
@@ -203,13 +213,14 @@ pub fn message(&self) -> String {
 	pub fn kind(&self) -> ruma::api::error::ErrorKind {
 		use ruma::api::error::{
 			ErrorKind,
-			ErrorKind::{FeatureDisabled, NotJson, Unknown},
+			ErrorKind::{FeatureDisabled, InvalidParam, NotJson, Unknown},
 		};
 
 		match self {
 			| Self::FeatureDisabled(..) => FeatureDisabled,
 			| Self::CanonicalJson(..) | Self::Json(..) => NotJson,
 			| Self::AuthCheck(..) => ErrorKind::forbidden(),
+			| Self::SoftFailed(..) => InvalidParam,
 			| Self::BadRequest(kind, ..) | Self::Request(kind, ..) => kind.clone(),
 			| Self::Federation(_, error) | Self::Ruma(error) =>
 				response::ruma_error_kind(error).clone(),
@@ -217,6 +228,28 @@ pub fn kind(&self) -> ruma::api::error::ErrorKind {
 		}
 	}
 
+	/// Returns a stable tuwunel-specific error code for variants which would
+	/// otherwise be indistinguishable behind a generic Matrix error kind. It is
+	/// sent to clients alongside `errcode` in the error response.
+	#[must_use]
+	pub fn errcode_extended(&self) -> Option<&'static str> {
+		match self {
+			| Self::Arithmetic(..) => Some("TUWUNEL_ARITHMETIC"),
+			| Self::AuthCheck(..) => Some("TUWUNEL_AUTH_CHECK"),
+			| Self::BadServerResponse(..) => Some("TUWUNEL_BAD_SERVER_RESPONSE"),
+			| Self::Config(..) => Some("TUWUNEL_CONFIG"),
+			| Self::Conflict(..) => Some("TUWUNEL_CONFLICT"),
+			| Self::Database(..) => Some("TUWUNEL_DATABASE"),
+			| Self::FeatureDisabled(..) => Some("TUWUNEL_FEATURE_DISABLED"),
+			| Self::Federation(..) => Some("TUWUNEL_FEDERATION"),
+			| Self::InconsistentRoomState(..) => Some("TUWUNEL_INCONSISTENT_ROOM_STATE"),
+			| Self::Ldap(..) => Some("TUWUNEL_LDAP"),
+			| Self::Redaction(..) => Some("TUWUNEL_REDACTION"),
+			| Self::SoftFailed(..) => Some("TUWUNEL_SOFT_FAILED"),
+			| _ => None,
+		}
+	}
+
 	/// Returns the HTTP error code or closest approximation based on error
 	/// variant.
 	pub fn status_code(&self) -> http::StatusCode {
@@ -230,7 +263,8 @@ pub fn status_code(&self) -> http::StatusCode {
 			| Self::CanonicalJson(..)
 			| Self::Json(..)
 			| Self::JsParseInt(..)
-			| Self::JsTryFromInt(..) => response::bad_request_code(&self.kind()),
+			| Self::JsTryFromInt(..)
+			| Self::SoftFailed(..) => response::bad_request_code(&self.kind()),
 			| Self::BadRequest(kind, ..) => response::bad_request_code(kind),
 			| Self::Request(kind, _, code) => response::status_code(kind, *code),
 			| Self::Io(error) => response::io_error_code(error.kind()),
@@ -248,6 +282,23 @@ pub fn status_code(&self) -> http::StatusCode {
 	/// Result where Ok(None) is instead Err(e) if e.is_not_found().
 	#[inline]
 	pub fn is_not_found(&self) -> bool { self.status_code() == http::StatusCode::NOT_FOUND }
+
+	/// Returns true if the event was soft-failed rather than rejected.
+	#[inline]
+	pub fn is_soft_failed(&self) -> bool { matches!(self, Self::SoftFailed(..)) }
+
+	/// Converts a user-interactive authentication challenge into the response
+	/// continuing the flow; `None` for every other error.
+	#[inline]
+	#[must_use]
+	pub fn into_uiaa_response(self) -> Option<ruma::api::client::uiaa::UiaaResponse> {
+		use ruma::api::client::uiaa::UiaaResponse;
+
+		match self {
+			| Self::Uiaa(uiaainfo) => Some(UiaaResponse::AuthResponse(uiaainfo)),
+			| _ => None,
+		}
+	}
 }
 
 impl std::fmt::Debug for Error {
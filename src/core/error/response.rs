@@ -4,12 +4,16 @@
 use ruma::api::{
 	OutgoingResponse,
 	client::uiaa::UiaaResponse,
-	error::{ErrorBody, ErrorKind},
+	error::{ErrorBody, ErrorKind, StandardErrorBody},
 };
+use serde_json::Value as JsonValue;
 
 use super::Error;
 use crate::error;
 
+/// Response field carrying [`Error::errcode_extended`].
+pub const EXTENDED_ERRCODE_FIELD: &str = "tuwunel.errcode";
+
 impl axum::response::IntoResponse for Error {
 	fn into_response(self) -> axum::response::Response {
 		let response: UiaaResponse = self.into();
@@ -34,15 +38,32 @@ fn from(error: Error) -> Self {
 			return Self::AuthResponse(uiaainfo);
 		}
 
-		let body = ErrorBody::Standard(ruma::api::error::StandardErrorBody {
+		let body = StandardErrorBody {
 			kind: error.kind(),
 			message: error.message(),
-		});
+		};
+
+		let body = match error.errcode_extended() {
+			| Some(errcode) => extended_body(body, errcode),
+			| None => ErrorBody::Standard(body),
+		};
 
 		Self::MatrixError(ruma::api::error::Error::new(error.status_code(), body))
 	}
 }
 
+/// Adds the tuwunel-specific error code to a standard error body, which is
+/// left as it is should it not serialize to an object.
+fn extended_body(body: StandardErrorBody, errcode: &'static str) -> ErrorBody {
+	match serde_json::to_value(&body) {
+		| Ok(JsonValue::Object(mut object)) => {
+			object.insert(EXTENDED_ERRCODE_FIELD.into(), errcode.into());
+			ErrorBody::Json(object.into())
+		},
+		| _ => ErrorBody::Standard(body),
+	}
+}
+
 pub(super) fn status_code(kind: &ErrorKind, hint: StatusCode) -> StatusCode {
 	if hint == StatusCode::BAD_REQUEST {
 		bad_request_code(kind)
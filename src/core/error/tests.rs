@@ -0,0 +1,113 @@
+use http::StatusCode;
+use ruma::{
+	api::{
+		OutgoingResponse,
+		client::uiaa::{AuthFlow, AuthType, UiaaInfo, UiaaResponse},
+		error::ErrorKind,
+	},
+	owned_event_id,
+};
+
+use super::{EXTENDED_ERRCODE_FIELD, Error};
+
+#[test]
+fn soft_failed_carries_event_id_and_reason() {
+	let event_id = owned_event_id!("$soft_failed:example.com");
+	let error = Error::SoftFailed(event_id.clone(), "failed auth at current state".into());
+
+	let Error::SoftFailed(ref id, ref reason) = error else {
+		panic!("expected SoftFailed variant");
+	};
+
+	assert_eq!(*id, event_id);
+	assert_eq!(reason, "failed auth at current state");
+	assert!(error.is_soft_failed());
+	assert!(error.message().contains(event_id.as_str()));
+}
+
+#[test]
+fn soft_failed_maps_to_invalid_param() {
+	let error = Error::SoftFailed(owned_event_id!("$event:example.com"), "reason".into());
+
+	assert!(matches!(error.kind(), ErrorKind::InvalidParam));
+	assert_eq!(error.status_code(), StatusCode::BAD_REQUEST);
+	assert!(!error.is_not_found());
+}
+
+#[test]
+fn hard_rejection_is_not_soft_failed() {
+	let error = Error::AuthCheck(Box::new(Error::Err("rejected".into())));
+
+	assert!(!error.is_soft_failed());
+	assert_eq!(error.status_code(), StatusCode::FORBIDDEN);
+}
+
+#[test]
+fn uiaa_converts_to_auth_response() {
+	let uiaainfo = UiaaInfo {
+		flows: vec![AuthFlow::new(vec![AuthType::Password])],
+		session: Some("session".into()),
+		..Default::default()
+	};
+
+	let Some(UiaaResponse::AuthResponse(converted)) = Error::Uiaa(uiaainfo).into_uiaa_response()
+	else {
+		panic!("expected an auth response");
+	};
+
+	assert_eq!(converted.session.as_deref(), Some("session"));
+	assert_eq!(converted.flows.len(), 1);
+	assert_eq!(converted.flows[0].stages, [AuthType::Password]);
+	assert!(converted.completed.is_empty());
+}
+
+#[test]
+fn non_uiaa_has_no_uiaa_response() {
+	let error = Error::Err("plain".into());
+
+	assert!(error.into_uiaa_response().is_none());
+}
+
+#[test]
+fn custom_variants_have_extended_errcodes() {
+	let cases = [
+		(Error::FeatureDisabled("search".into()), "TUWUNEL_FEATURE_DISABLED"),
+		(Error::Ldap("bind failed".into()), "TUWUNEL_LDAP"),
+		(Error::Conflict("alias exists".into()), "TUWUNEL_CONFLICT"),
+		(
+			Error::SoftFailed(owned_event_id!("$event:example.com"), "reason".into()),
+			"TUWUNEL_SOFT_FAILED",
+		),
+	];
+
+	for (error, errcode) in cases {
+		assert_eq!(error.errcode_extended(), Some(errcode), "{error:?}");
+	}
+
+	let error = Error::Request(ErrorKind::NotFound, "missing".into(), StatusCode::NOT_FOUND);
+	assert_eq!(error.errcode_extended(), None, "Matrix kinds need no extension");
+	assert_eq!(Error::Err("plain".into()).errcode_extended(), None);
+}
+
+#[test]
+fn extended_errcode_is_sent_in_response() {
+	let response: UiaaResponse = Error::FeatureDisabled("search".into()).into();
+	let body = response
+		.try_into_http_response::<Vec<u8>>()
+		.expect("error response")
+		.into_body();
+
+	let body: serde_json::Value = serde_json::from_slice(&body).expect("json body");
+	assert_eq!(body["errcode"], "M_FEATURE_DISABLED");
+	assert_eq!(body[EXTENDED_ERRCODE_FIELD], "TUWUNEL_FEATURE_DISABLED");
+
+	let response: UiaaResponse = Error::Err("plain".into()).into();
+	let body = response
+		.try_into_http_response::<Vec<u8>>()
+		.expect("error response")
+		.into_body();
+
+	let body: serde_json::Value = serde_json::from_slice(&body).expect("json body");
+	assert_eq!(body["errcode"], "M_UNKNOWN");
+	assert!(body.get(EXTENDED_ERRCODE_FIELD).is_none(), "no extension for generic errors");
+}
@@ -3,6 +3,7 @@
 use ruma::{
 	RoomId, UserId,
 	api::client::filter::{Filter, RoomEventFilter, RoomFilter, UrlFilter},
+	events::AnySyncEphemeralRoomEvent,
 	serde::Raw,
 };
 use serde_json::{Map, Value};
@@ -41,6 +42,17 @@ fn matches(&self, event: &E) -> bool {
 	}
 }
 
+impl Matches<&Raw<AnySyncEphemeralRoomEvent>> for RoomEventFilter {
+	#[inline]
+	fn matches(&self, event: &Raw<AnySyncEphemeralRoomEvent>) -> bool {
+		let Ok(Some(kind)) = event.get_field::<String>("type") else {
+			return false;
+		};
+
+		matches_kind(&kind, self)
+	}
+}
+
 impl Matches<&RoomId> for RoomFilter {
 	#[inline]
 	fn matches(&self, room_id: &RoomId) -> bool {
@@ -132,14 +144,16 @@ fn matches_sender<E: Event>(event: &E, filter: &RoomEventFilter) -> bool {
 }
 
 fn matches_type<E: Event>(event: &E, filter: &RoomEventFilter) -> bool {
-	let kind = event.kind().to_cow_str();
+	matches_kind(&event.kind().to_cow_str(), filter)
+}
 
-	if filter.not_types.iter().any(is_equal_to!(&kind)) {
+fn matches_kind(kind: &str, filter: &RoomEventFilter) -> bool {
+	if filter.not_types.iter().any(is_equal_to!(kind)) {
 		return false;
 	}
 
 	if let Some(types) = filter.types.as_ref()
-		&& !types.iter().any(is_equal_to!(&kind))
+		&& !types.iter().any(is_equal_to!(kind))
 	{
 		return false;
 	}
@@ -0,0 +1,58 @@
+//! Lock-free latency histogram with fixed millisecond buckets.
+
+use std::{
+	sync::atomic::{AtomicU64, Ordering},
+	time::Duration,
+};
+
+const BUCKETS: usize = 13;
+
+/// Upper bounds of each bucket in milliseconds; a final bucket catches the
+/// remainder.
+pub const BOUNDS_MS: [u64; BUCKETS - 1] =
+	[1, 5, 10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000];
+
+#[derive(Debug, Default)]
+pub struct Histogram {
+	buckets: [AtomicU64; BUCKETS],
+	count: AtomicU64,
+	sum_micros: AtomicU64,
+}
+
+impl Histogram {
+	pub fn record(&self, elapsed: Duration) {
+		let millis = u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX);
+		let micros = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+		let bucket = BOUNDS_MS
+			.iter()
+			.position(|&bound| millis < bound)
+			.unwrap_or(BOUNDS_MS.len());
+
+		if let Some(counter) = self.buckets.get(bucket) {
+			counter.fetch_add(1, Ordering::Relaxed);
+		}
+
+		self.count.fetch_add(1, Ordering::Relaxed);
+		self.sum_micros
+			.fetch_add(micros, Ordering::Relaxed);
+	}
+
+	#[inline]
+	#[must_use]
+	pub fn count(&self) -> u64 { self.count.load(Ordering::Relaxed) }
+
+	#[inline]
+	#[must_use]
+	pub fn sum(&self) -> Duration {
+		Duration::from_micros(self.sum_micros.load(Ordering::Relaxed))
+	}
+
+	/// Bucket counts paired with their upper bound in milliseconds; `None`
+	/// for the unbounded last bucket.
+	pub fn buckets(&self) -> impl Iterator<Item = (Option<u64>, u64)> + '_ {
+		self.buckets
+			.iter()
+			.enumerate()
+			.map(|(i, counter)| (BOUNDS_MS.get(i).copied(), counter.load(Ordering::Relaxed)))
+	}
+}
@@ -0,0 +1,64 @@
+//! Processing latency of PDUs received over federation.
+
+use std::{fmt, time::Duration};
+
+use super::histogram::Histogram;
+
+/// How the handling of an incoming PDU concluded.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PduOutcome {
+	/// Appended to the timeline.
+	Accepted,
+
+	/// Persisted as an outlier only, or skipped without error.
+	Outlier,
+
+	/// Persisted but excluded from the current state.
+	SoftFailed,
+
+	/// Failed validation or could not be processed.
+	Rejected,
+}
+
+#[derive(Debug, Default)]
+pub struct IncomingPdu {
+	accepted: Histogram,
+	outlier: Histogram,
+	soft_failed: Histogram,
+	rejected: Histogram,
+}
+
+impl PduOutcome {
+	pub const ALL: [Self; 4] = [Self::Accepted, Self::Outlier, Self::SoftFailed, Self::Rejected];
+
+	#[must_use]
+	pub fn as_str(self) -> &'static str {
+		match self {
+			| Self::Accepted => "accepted",
+			| Self::Outlier => "outlier",
+			| Self::SoftFailed => "soft-failed",
+			| Self::Rejected => "rejected",
+		}
+	}
+}
+
+impl fmt::Display for PduOutcome {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(self.as_str()) }
+}
+
+impl IncomingPdu {
+	#[inline]
+	pub fn record(&self, outcome: PduOutcome, elapsed: Duration) {
+		self.get(outcome).record(elapsed);
+	}
+
+	#[must_use]
+	pub fn get(&self, outcome: PduOutcome) -> &Histogram {
+		match outcome {
+			| PduOutcome::Accepted => &self.accepted,
+			| PduOutcome::Outlier => &self.outlier,
+			| PduOutcome::SoftFailed => &self.soft_failed,
+			| PduOutcome::Rejected => &self.rejected,
+		}
+	}
+}
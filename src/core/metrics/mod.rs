@@ -1,4 +1,7 @@
 pub mod dump;
+pub mod histogram;
+pub mod incoming_pdu;
+pub mod outgoing;
 
 use std::sync::{
 	Arc,
@@ -10,6 +13,11 @@
 use tokio_metrics::{RuntimeIntervals, RuntimeMonitor};
 use tokio_metrics::{TaskMetrics, TaskMonitor};
 
+pub use self::{
+	incoming_pdu::{IncomingPdu, PduOutcome},
+	outgoing::{Outgoing, OutgoingCounts, OutgoingKind},
+};
+
 pub struct Metrics {
 	_runtime: Option<runtime::Handle>,
 
@@ -30,6 +38,10 @@ pub struct Metrics {
 	pub requests_handle_finished: AtomicU64,
 	pub requests_handle_active: AtomicU32,
 	pub requests_panic: AtomicU32,
+
+	pub incoming_pdu: IncomingPdu,
+
+	pub outgoing: Outgoing,
 }
 
 impl Metrics {
@@ -76,6 +88,10 @@ pub fn new(runtime: Option<&runtime::Handle>) -> Arc<Self> {
 			requests_handle_finished: AtomicU64::new(0),
 			requests_handle_active: AtomicU32::new(0),
 			requests_panic: AtomicU32::new(0),
+
+			incoming_pdu: IncomingPdu::default(),
+
+			outgoing: Outgoing::default(),
 		})
 	}
 
@@ -0,0 +1,127 @@
+//! Events delivered to other servers over federation.
+
+use std::{
+	collections::HashMap,
+	fmt,
+	sync::{
+		Mutex,
+		atomic::{AtomicU64, Ordering},
+	},
+};
+
+use ruma::{OwnedServerName, ServerName};
+
+/// What was delivered in a federation transaction. EDUs are told apart by
+/// their `edu_type`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OutgoingKind {
+	Pdu,
+	Typing,
+	Receipt,
+	Presence,
+
+	/// Any other EDU, e.g. device list updates and to-device messages.
+	Edu,
+}
+
+/// Delivered counts indexed like [`OutgoingKind::ALL`].
+pub type OutgoingCounts = [u64; KINDS];
+
+const KINDS: usize = 5;
+
+#[derive(Debug, Default)]
+pub struct Outgoing {
+	total: [AtomicU64; KINDS],
+	destinations: Mutex<HashMap<OwnedServerName, OutgoingCounts>>,
+}
+
+impl OutgoingKind {
+	pub const ALL: [Self; KINDS] =
+		[Self::Pdu, Self::Typing, Self::Receipt, Self::Presence, Self::Edu];
+
+	#[must_use]
+	pub fn of_edu(edu_type: &str) -> Self {
+		match edu_type {
+			| "m.typing" => Self::Typing,
+			| "m.receipt" => Self::Receipt,
+			| "m.presence" => Self::Presence,
+			| _ => Self::Edu,
+		}
+	}
+
+	#[must_use]
+	pub fn as_str(self) -> &'static str {
+		match self {
+			| Self::Pdu => "pdu",
+			| Self::Typing => "typing",
+			| Self::Receipt => "receipt",
+			| Self::Presence => "presence",
+			| Self::Edu => "other-edu",
+		}
+	}
+
+	#[must_use]
+	pub fn index(self) -> usize {
+		match self {
+			| Self::Pdu => 0,
+			| Self::Typing => 1,
+			| Self::Receipt => 2,
+			| Self::Presence => 3,
+			| Self::Edu => 4,
+		}
+	}
+}
+
+impl fmt::Display for OutgoingKind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(self.as_str()) }
+}
+
+impl Outgoing {
+	/// Record the events of one transaction delivered to `dest`.
+	pub fn record(&self, dest: &ServerName, counts: &OutgoingCounts) {
+		for (total, count) in self.total.iter().zip(counts) {
+			total.fetch_add(*count, Ordering::Relaxed);
+		}
+
+		let mut destinations = self.destinations.lock().expect("locked");
+		let sums = destinations.entry(dest.to_owned()).or_default();
+		for (sum, count) in sums.iter_mut().zip(counts) {
+			*sum = sum.saturating_add(*count);
+		}
+	}
+
+	/// Events of `kind` delivered to all destinations.
+	#[must_use]
+	pub fn total(&self, kind: OutgoingKind) -> u64 {
+		self.total
+			.get(kind.index())
+			.map_or(0, |total| total.load(Ordering::Relaxed))
+	}
+
+	/// Events delivered to `dest`.
+	#[must_use]
+	pub fn destination(&self, dest: &ServerName) -> OutgoingCounts {
+		self.destinations
+			.lock()
+			.expect("locked")
+			.get(dest)
+			.copied()
+			.unwrap_or_default()
+	}
+
+	/// Events delivered to each destination, busiest first.
+	#[must_use]
+	pub fn destinations(&self) -> Vec<(OwnedServerName, OutgoingCounts)> {
+		let mut destinations: Vec<_> = self
+			.destinations
+			.lock()
+			.expect("locked")
+			.iter()
+			.map(|(dest, counts)| (dest.clone(), *counts))
+			.collect();
+
+		destinations.sort_by_key(|(_, counts)| std::cmp::Reverse(counts.iter().sum::<u64>()));
+
+		destinations
+	}
+}
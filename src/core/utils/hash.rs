@@ -8,3 +8,29 @@ pub fn verify_password(password: &str, password_hash: &str) -> Result {
 }
 
 pub fn password(password: &str) -> Result<String> { argon::password(password) }
+
+/// Whether `password_hash` is in a recognized format: an Argon2 PHC string
+/// or a bcrypt hash as carried over from other servers. Only the format is
+/// inspected; nothing is verified.
+#[must_use]
+pub fn is_password_hash(password_hash: &str) -> bool {
+	argon::is_password_hash(password_hash) || is_bcrypt(password_hash)
+}
+
+fn is_bcrypt(password_hash: &str) -> bool {
+	let Some((cost, hash)) = ["$2a$", "$2b$", "$2y$"]
+		.iter()
+		.find_map(|prefix| password_hash.strip_prefix(prefix))
+		.and_then(|rest| rest.split_once('$'))
+	else {
+		return false;
+	};
+
+	// Two digit cost, then 22 characters of salt and 31 of hash.
+	cost.len() == 2
+		&& cost.bytes().all(|b| b.is_ascii_digit())
+		&& hash.len() == 53
+		&& hash
+			.bytes()
+			.all(|b| b.is_ascii_alphanumeric() || b == b'.' || b == b'/')
+}
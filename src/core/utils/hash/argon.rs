@@ -48,6 +48,11 @@ pub(super) fn verify_password(password: &str, password_hash: &str) -> Result {
 		.map_err(map_err)
 }
 
+pub(super) fn is_password_hash(password_hash: &str) -> bool {
+	PasswordHash::new(password_hash)
+		.is_ok_and(|hash| hash.algorithm.as_str().starts_with("argon2"))
+}
+
 fn map_err(e: password_hash::Error) -> Error { err!("{e}") }
 
 #[cfg(test)]
@@ -69,4 +74,19 @@ fn password_hash_and_verify_fail() {
 		let digest = hash::password(preimage).expect("digest");
 		hash::verify_password(fakeimage, &digest).expect("unverified");
 	}
+
+	#[test]
+	fn password_hash_formats() {
+		use crate::utils::hash;
+		let digest = hash::password("temp123").expect("digest");
+		assert!(hash::is_password_hash(&digest), "argon2 digest");
+		assert!(
+			hash::is_password_hash(
+				"$2b$12$R9h/cIPz0gi.URNNX3kh2OPST9/PgBkqquzi.Ss7KIUgO2t0jWMUW"
+			),
+			"bcrypt digest"
+		);
+		assert!(!hash::is_password_hash("temp123"), "plaintext");
+		assert!(!hash::is_password_hash("$2b$12$short"), "truncated bcrypt");
+	}
 }
@@ -0,0 +1,92 @@
+use std::{pin::Pin, task::ready};
+
+use futures::{
+	Stream, StreamExt,
+	stream::{Fuse, FusedStream},
+	task::{Context, Poll},
+};
+
+use super::poll_head;
+
+struct IntersectionSortedStreamN<S, Item> {
+	streams: Vec<Pin<Box<Fuse<S>>>>,
+	peeked: Vec<Option<Item>>,
+}
+
+/// Intersection of sets
+///
+/// Outputs the set of elements common to every stream. All streams must be
+/// ascending under a total order; multiplicity is preserved (a value appears
+/// as many times as its fewest copies in any one stream). No streams yields
+/// no elements.
+pub fn intersection_sorted_stream_n<S, Item>(streams: Vec<S>) -> impl Stream<Item = Item> + Send
+where
+	S: Stream<Item = Item> + Send,
+	Item: Ord + Send + Sync,
+{
+	IntersectionSortedStreamN {
+		peeked: streams.iter().map(|_| None).collect(),
+		streams: streams
+			.into_iter()
+			.map(|stream| Box::pin(stream.fuse()))
+			.collect(),
+	}
+}
+
+impl<S, Item> Stream for IntersectionSortedStreamN<S, Item>
+where
+	S: Stream<Item = Item>,
+	Item: Ord,
+{
+	type Item = Item;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		let this = self.get_mut();
+		loop {
+			for (stream, peeked) in this.streams.iter_mut().zip(&mut this.peeked) {
+				if ready!(poll_head(stream.as_mut(), peeked, cx)).is_none() {
+					return Poll::Ready(None);
+				}
+			}
+
+			let Some(max) =
+				(0..this.peeked.len()).max_by(|&i, &j| this.peeked[i].cmp(&this.peeked[j]))
+			else {
+				return Poll::Ready(None);
+			};
+
+			// Advance every stream behind the greatest head; once none are behind
+			// all heads are equal and one copy is output.
+			let mut behind = false;
+			for i in 0..this.peeked.len() {
+				if this.peeked[i] < this.peeked[max] {
+					this.peeked[i] = None;
+					behind = true;
+				}
+			}
+
+			if !behind {
+				let item = this.peeked[max].take();
+				this.peeked
+					.iter_mut()
+					.for_each(|peeked| *peeked = None);
+				return Poll::Ready(item);
+			}
+		}
+	}
+}
+
+impl<S, Item> FusedStream for IntersectionSortedStreamN<S, Item>
+where
+	S: Stream<Item = Item>,
+	Item: Ord,
+{
+	fn is_terminated(&self) -> bool {
+		self.streams.is_empty()
+			|| self
+				.streams
+				.iter()
+				.zip(&self.peeked)
+				.any(|(stream, peeked)| peeked.is_none() && stream.is_terminated())
+	}
+}
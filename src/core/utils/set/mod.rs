@@ -8,6 +8,7 @@
 mod intersection;
 mod intersection_sorted;
 mod intersection_sorted_stream2;
+mod intersection_sorted_stream_n;
 
 use std::{pin::Pin, task::ready};
 
@@ -19,6 +20,7 @@
 pub use self::{
 	difference_sorted_stream2::difference_sorted_stream2, intersection::intersection,
 	intersection_sorted::intersection_sorted,
+	intersection_sorted_stream_n::intersection_sorted_stream_n,
 	intersection_sorted_stream2::intersection_sorted_stream2,
 };
 
@@ -6,9 +6,11 @@
 mod iter_stream;
 mod ready;
 mod tools;
+mod try_broad_for_each;
 mod try_broadband;
 mod try_parallel;
 mod try_ready;
+mod try_ready_chunks;
 mod try_tools;
 mod try_wideband;
 mod wideband;
@@ -25,9 +27,11 @@
 	iter_stream::IterStream,
 	ready::ReadyExt,
 	tools::Tools,
+	try_broad_for_each::TryBroadForEach,
 	try_broadband::TryBroadbandExt,
 	try_parallel::TryParallelExt,
 	try_ready::TryReadyExt,
+	try_ready_chunks::{TryReadyChunks, TryReadyChunksExt},
 	try_tools::TryTools,
 	try_wideband::TryWidebandExt,
 	wideband::WidebandExt,
@@ -0,0 +1,48 @@
+//! Bounded-concurrency fallible for_each over futures::Stream
+
+use futures::{
+	Future, TryStreamExt,
+	stream::{Stream, StreamExt},
+};
+
+use super::automatic_width;
+
+/// Concurrent for_each() aborting at the first error. Up to `n` futures run at
+/// once (`None` for automatic_width()); once one fails no further items are
+/// pulled, the futures still running are dropped and the error is returned.
+pub trait TryBroadForEach<Item>
+where
+	Self: Stream<Item = Item> + Send + Sized,
+{
+	fn try_broad_for_each<F, Fut, E, N>(
+		self,
+		n: N,
+		f: F,
+	) -> impl Future<Output = Result<(), E>> + Send
+	where
+		N: Into<Option<usize>>,
+		F: FnMut(Item) -> Fut + Send,
+		Fut: Future<Output = Result<(), E>> + Send,
+		E: Send;
+}
+
+impl<Item, S> TryBroadForEach<Item> for S
+where
+	S: Stream<Item = Item> + Send + Sized,
+{
+	#[inline]
+	fn try_broad_for_each<F, Fut, E, N>(
+		self,
+		n: N,
+		f: F,
+	) -> impl Future<Output = Result<(), E>> + Send
+	where
+		N: Into<Option<usize>>,
+		F: FnMut(Item) -> Fut + Send,
+		Fut: Future<Output = Result<(), E>> + Send,
+		E: Send,
+	{
+		self.map(Ok)
+			.try_for_each_concurrent(n.into().unwrap_or_else(automatic_width), f)
+	}
+}
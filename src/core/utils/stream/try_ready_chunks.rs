@@ -0,0 +1,104 @@
+//! Bounded batching of fallible streams
+
+use std::{
+	mem::{replace, take},
+	pin::Pin,
+};
+
+use futures::{
+	Stream, StreamExt,
+	stream::{Fuse, FusedStream},
+	task::{Context, Poll},
+};
+use pin_project_lite::pin_project;
+
+use crate::Result;
+
+pin_project! {
+	/// Stream for [`TryReadyChunksExt::try_ready_chunks`].
+	#[must_use = "streams do nothing unless polled"]
+	pub struct TryReadyChunks<S, T, E> {
+		#[pin] stream: Fuse<S>,
+		items: Vec<T>,
+		error: Option<E>,
+		cap: usize,
+	}
+}
+
+/// Groups the values of a TryStream into vectors of at most `cap` items, e.g.
+/// to feed Map::insert_batch() without collecting the whole stream first.
+pub trait TryReadyChunksExt<T, E>
+where
+	Self: Stream<Item = Result<T, E>> + Sized,
+{
+	/// Chunks are yielded as soon as they fill; a final partial chunk is
+	/// yielded when the stream ends. An error is yielded as soon as it is
+	/// received, after any values buffered ahead of it are flushed as a
+	/// partial chunk, so nothing read before the error is lost.
+	///
+	/// # Panics
+	///
+	/// If `cap` is zero.
+	fn try_ready_chunks(self, cap: usize) -> TryReadyChunks<Self, T, E>;
+}
+
+impl<T, E, S> TryReadyChunksExt<T, E> for S
+where
+	S: Stream<Item = Result<T, E>> + Sized,
+{
+	#[inline]
+	fn try_ready_chunks(self, cap: usize) -> TryReadyChunks<Self, T, E> {
+		assert!(cap > 0, "chunk capacity must be greater than zero");
+
+		TryReadyChunks {
+			stream: self.fuse(),
+			items: Vec::with_capacity(cap),
+			error: None,
+			cap,
+		}
+	}
+}
+
+impl<T, E, S> Stream for TryReadyChunks<S, T, E>
+where
+	S: Stream<Item = Result<T, E>>,
+{
+	type Item = Result<Vec<T>, E>;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		let mut this = self.project();
+		if let Some(error) = this.error.take() {
+			return Poll::Ready(Some(Err(error)));
+		}
+
+		loop {
+			match this.stream.as_mut().poll_next(cx) {
+				| Poll::Pending => return Poll::Pending,
+				| Poll::Ready(Some(Ok(item))) => {
+					this.items.push(item);
+					if this.items.len() >= *this.cap {
+						let chunk = Vec::with_capacity(*this.cap);
+						return Poll::Ready(Some(Ok(replace(this.items, chunk))));
+					}
+				},
+				| Poll::Ready(Some(Err(error))) if this.items.is_empty() =>
+					return Poll::Ready(Some(Err(error))),
+				| Poll::Ready(Some(Err(error))) => {
+					*this.error = Some(error);
+					return Poll::Ready(Some(Ok(take(this.items))));
+				},
+				| Poll::Ready(None) if this.items.is_empty() => return Poll::Ready(None),
+				| Poll::Ready(None) => return Poll::Ready(Some(Ok(take(this.items)))),
+			}
+		}
+	}
+}
+
+impl<T, E, S> FusedStream for TryReadyChunks<S, T, E>
+where
+	S: Stream<Item = Result<T, E>>,
+{
+	fn is_terminated(&self) -> bool {
+		self.stream.is_terminated() && self.items.is_empty() && self.error.is_none()
+	}
+}
@@ -274,6 +274,48 @@ async fn set_intersection_sorted_stream2() {
 	assert_eq!(r, &["ccc", "ggg", "iii"]);
 }
 
+#[tokio::test]
+async fn set_intersection_sorted_stream_n() {
+	use futures::StreamExt;
+	use utils::{IterStream, set::intersection_sorted_stream_n};
+
+	let a = ["aaa", "bbb", "ccc", "eee", "ggg"];
+	let b = ["bbb", "ccc", "ddd", "ggg", "hhh"];
+	let c = ["aaa", "ccc", "fff", "ggg"];
+	let r = intersection_sorted_stream_n(vec![
+		a.iter().stream(),
+		b.iter().stream(),
+		c.iter().stream(),
+	])
+	.collect::<Vec<&str>>()
+	.await;
+	assert_eq!(r, &["ccc", "ggg"]);
+
+	let r = intersection_sorted_stream_n(vec![
+		c.iter().stream(),
+		a.iter().stream(),
+		b.iter().stream(),
+	])
+	.collect::<Vec<&str>>()
+	.await;
+	assert_eq!(r, &["ccc", "ggg"]);
+
+	let empty: [&str; 0] = [];
+	let r = intersection_sorted_stream_n(vec![
+		a.iter().stream(),
+		empty.iter().stream(),
+		b.iter().stream(),
+	])
+	.collect::<Vec<&str>>()
+	.await;
+	assert!(r.is_empty());
+
+	let r = intersection_sorted_stream_n(Vec::<futures::stream::Empty<&str>>::new())
+		.collect::<Vec<&str>>()
+		.await;
+	assert!(r.is_empty());
+}
+
 #[tokio::test]
 async fn set_difference_sorted_stream2() {
 	use futures::StreamExt;
@@ -322,3 +364,185 @@ fn page_size() {
 
 	assert!(val != 0, "page size was zero");
 }
+
+#[test]
+fn two_phase_counter_batch_recovery() {
+	use std::sync::{
+		Arc,
+		atomic::{AtomicU64, Ordering},
+	};
+
+	use crate::{Result, utils::two_phase_counter::Counter};
+
+	type Callback = Box<dyn Fn(u64) -> Result + Send + Sync>;
+
+	let counter = |init: u64, batch: u64, stored: &Arc<AtomicU64>, commits: &Arc<AtomicU64>| {
+		let (stored, commits) = (stored.clone(), commits.clone());
+		let commit: Callback = Box::new(move |count| {
+			stored.store(count, Ordering::SeqCst);
+			commits.fetch_add(1, Ordering::SeqCst);
+			Ok(())
+		});
+
+		let release: Callback = Box::new(|_| Ok(()));
+
+		Counter::with_batch(init, batch, commit, release)
+	};
+
+	for (batch, expected_commits) in [(0, 10), (1, 10), (4, 3), (7, 2), (16, 1)] {
+		let stored = Arc::new(AtomicU64::new(0));
+		let commits = Arc::new(AtomicU64::new(0));
+		let first = counter(0, batch, &stored, &commits);
+
+		// Half the permits are still pending when the server goes away.
+		let mut pending = Vec::new();
+		let mut issued = 0;
+		for i in 0..10 {
+			let permit = first.next().unwrap();
+			issued = issued.max(*permit);
+			if i % 2 == 0 {
+				pending.push(permit);
+			}
+		}
+
+		assert_eq!(commits.load(Ordering::SeqCst), expected_commits, "batch {batch}");
+		assert_eq!(first.current(), 0, "oldest permit still pending");
+
+		let recovered = counter(stored.load(Ordering::SeqCst), batch, &stored, &commits);
+		let next = recovered.next().unwrap();
+		assert!(*next > issued, "batch {batch}: reissued {} after {issued}", *next);
+
+		drop(pending);
+		assert_eq!(first.current(), issued, "retirement unaffected by batch");
+	}
+}
+
+#[tokio::test]
+async fn try_broad_for_each_completes() {
+	use std::sync::atomic::{AtomicUsize, Ordering};
+
+	use utils::stream::{IterStream, TryBroadForEach};
+
+	let visited = AtomicUsize::new(0);
+	let result: Result<(), ()> = (0..10)
+		.stream()
+		.try_broad_for_each(4, async |_| {
+			visited.fetch_add(1, Ordering::SeqCst);
+			Ok(())
+		})
+		.await;
+
+	assert_eq!(result, Ok(()));
+	assert_eq!(visited.load(Ordering::SeqCst), 10, "every item visited");
+}
+
+#[tokio::test]
+async fn try_broad_for_each_aborts_on_error() {
+	use std::sync::atomic::{AtomicUsize, Ordering};
+
+	use utils::stream::{IterStream, TryBroadForEach};
+
+	let visited = AtomicUsize::new(0);
+	let result = (0..100)
+		.stream()
+		.try_broad_for_each(1, async |i| {
+			visited.fetch_add(1, Ordering::SeqCst);
+			if i == 3 { Err(i) } else { Ok(()) }
+		})
+		.await;
+
+	assert_eq!(result, Err(3));
+	assert_eq!(visited.load(Ordering::SeqCst), 4, "no items pulled after the error");
+}
+
+#[tokio::test]
+async fn try_broad_for_each_respects_limit() {
+	use std::sync::atomic::{AtomicUsize, Ordering};
+
+	use utils::stream::{IterStream, TryBroadForEach};
+
+	let active = AtomicUsize::new(0);
+	let peak = AtomicUsize::new(0);
+	let result: Result<(), ()> = (0..16)
+		.stream()
+		.try_broad_for_each(3, async |_| {
+			let now = active
+				.fetch_add(1, Ordering::SeqCst)
+				.saturating_add(1);
+			peak.fetch_max(now, Ordering::SeqCst);
+			for _ in 0..4 {
+				tokio::task::yield_now().await;
+			}
+
+			active.fetch_sub(1, Ordering::SeqCst);
+			Ok(())
+		})
+		.await;
+
+	assert_eq!(result, Ok(()));
+	assert_eq!(peak.load(Ordering::SeqCst), 3, "concurrency capped at the limit");
+}
+
+#[tokio::test]
+async fn try_ready_chunks_exact() {
+	use futures::StreamExt;
+	use utils::stream::{IterStream, TryReadyChunksExt};
+
+	let chunks: Vec<Result<Vec<u32>, ()>> = (0..6)
+		.map(Ok)
+		.stream()
+		.try_ready_chunks(3)
+		.collect()
+		.await;
+
+	assert_eq!(chunks, [Ok(vec![0, 1, 2]), Ok(vec![3, 4, 5])]);
+}
+
+#[tokio::test]
+async fn try_ready_chunks_remainder() {
+	use futures::StreamExt;
+	use utils::stream::{IterStream, TryReadyChunksExt};
+
+	let chunks: Vec<Result<Vec<u32>, ()>> = (0..7)
+		.map(Ok)
+		.stream()
+		.try_ready_chunks(3)
+		.collect()
+		.await;
+
+	assert_eq!(chunks, [Ok(vec![0, 1, 2]), Ok(vec![3, 4, 5]), Ok(vec![6])]);
+
+	let chunks: Vec<Result<Vec<u32>, ()>> = std::iter::empty()
+		.stream()
+		.try_ready_chunks(3)
+		.collect()
+		.await;
+
+	assert!(chunks.is_empty(), "no empty chunk for an empty stream");
+}
+
+#[tokio::test]
+async fn try_ready_chunks_error() {
+	use futures::{StreamExt, TryStreamExt};
+	use utils::stream::{IterStream, TryReadyChunksExt};
+
+	let items = [Ok(0), Ok(1), Ok(2), Ok(3), Err("bad"), Ok(5)];
+	let chunks: Vec<_> = items
+		.into_iter()
+		.stream()
+		.try_ready_chunks(3)
+		.collect()
+		.await;
+
+	assert_eq!(chunks, [Ok(vec![0, 1, 2]), Ok(vec![3]), Err("bad"), Ok(vec![5])]);
+
+	let items = [Ok(0), Ok(1), Err("bad"), Ok(3), Ok(4)];
+	let result: Result<Vec<Vec<u32>>, _> = items
+		.into_iter()
+		.stream()
+		.try_ready_chunks(8)
+		.try_collect()
+		.await;
+
+	assert_eq!(result, Err("bad"), "error surfaces before the chunk fills");
+}
@@ -37,6 +37,15 @@ pub struct State<F: Fn(u64) -> Result + Send + Sync> {
 	/// This prevents pending numbers from being reused after server restart.
 	commit: F,
 
+	/// Highest sequence number persisted by `commit`. Numbers up to this value
+	/// may be dispatched without calling `commit` again.
+	committed: u64,
+
+	/// Number of sequence numbers reserved by each call to `commit`. Larger
+	/// batches persist less often at the cost of skipping up to `batch - 1`
+	/// unused numbers after a restart.
+	batch: u64,
+
 	/// List of pending sequence numbers. One less than the minimum value in
 	/// this list is the "retirement" sequence number where all writes have
 	/// completed and all reads are globally visible.
@@ -65,8 +74,17 @@ impl<F: Fn(u64) -> Result + Send + Sync> Counter<F> {
 	/// considered retired, and the next sequence number dispatched will be one
 	/// greater.
 	pub fn new(init: u64, commit: F, release: F) -> Arc<Self> {
+		Self::with_batch(init, 1, commit, release)
+	}
+
+	/// Construct a new Two-Phase counter which persists through `commit` once
+	/// per `batch` sequence numbers rather than for each one. The value given
+	/// to `commit` is the highest number of the batch, so restarting from the
+	/// persisted value never reissues a dispatched number. A `batch` of zero is
+	/// treated as one.
+	pub fn with_batch(init: u64, batch: u64, commit: F, release: F) -> Arc<Self> {
 		Arc::new(Self {
-			inner: State::new(init, commit, release).into(),
+			inner: State::new(init, batch.max(1), commit, release).into(),
 		})
 	}
 
@@ -112,10 +130,12 @@ pub fn dispatched(&self) -> u64 {
 impl<F: Fn(u64) -> Result + Send + Sync> State<F> {
 	/// Create new state, starting from `init`. The next sequence number
 	/// dispatched will be one greater than `init`.
-	fn new(dispatched: u64, commit: F, release: F) -> Self {
+	fn new(dispatched: u64, batch: u64, commit: F, release: F) -> Self {
 		Self {
 			dispatched,
 			commit,
+			committed: dispatched,
+			batch,
 			pending: VecDeque::new(),
 			release,
 		}
@@ -132,7 +152,12 @@ fn dispatch(&mut self) -> Result<(u64, u64)> {
 			"sequence number cannot already be pending",
 		);
 
-		(self.commit)(dispatched)?;
+		if dispatched > self.committed {
+			let committed = checked!(prev + self.batch)?;
+			(self.commit)(committed)?;
+			self.committed = committed;
+		}
+
 		self.dispatched = dispatched;
 		self.pending.push_back(self.dispatched);
 		Ok((retired, self.dispatched))
@@ -9,6 +9,7 @@
 
 mod backup;
 mod cf_opts;
+mod checkpoint;
 pub(crate) mod context;
 mod db_opts;
 pub(crate) mod descriptor;
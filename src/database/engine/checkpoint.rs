@@ -0,0 +1,31 @@
+use std::path::Path;
+
+use rocksdb::checkpoint::Checkpoint;
+use tuwunel_core::{Err, Result, implement, info};
+
+use super::Engine;
+use crate::util::map_err;
+
+/// Create a consistent snapshot of every column at `path`, which must not yet
+/// exist. Table files are hardlinked when `path` is on the same filesystem,
+/// otherwise copied. The server keeps serving while the checkpoint is taken.
+#[implement(Engine)]
+#[tracing::instrument(skip(self))]
+pub fn checkpoint(&self, path: &Path) -> Result {
+	if self.is_secondary() {
+		return Err!("Cannot create a checkpoint from a secondary instance.");
+	}
+
+	// Push out WAL records still buffered under a cork so they are included.
+	if !self.is_read_only() {
+		self.flush()?;
+	}
+
+	Checkpoint::new(&self.db)
+		.and_then(|checkpoint| checkpoint.create_checkpoint(path))
+		.map_err(map_err)?;
+
+	info!("Created database checkpoint at {path:?}");
+
+	Ok(())
+}
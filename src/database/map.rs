@@ -45,6 +45,10 @@
 pub use self::{get_batch::Get, qry_batch::Qry};
 use crate::Engine;
 
+const LIVE_DATA_SIZE_PROPERTY: &CStr = c"rocksdb.estimate-live-data-size";
+const MEMTABLE_SIZE_PROPERTY: &CStr = c"rocksdb.size-all-mem-tables";
+const NUM_KEYS_PROPERTY: &CStr = c"rocksdb.estimate-num-keys";
+
 pub struct Map {
 	name: &'static str,
 	watch: Watch,
@@ -78,6 +82,19 @@ pub fn property(&self, name: &str) -> Result<String> {
 		self.engine.property(&self.cf(), name)
 	}
 
+	/// Approximate bytes held by the map: live data in table files plus the
+	/// memtables not yet flushed.
+	pub fn approximate_size(&self) -> Result<u64> {
+		let live = self.property_integer(LIVE_DATA_SIZE_PROPERTY)?;
+		let memtables = self.property_integer(MEMTABLE_SIZE_PROPERTY)?;
+
+		Ok(live.saturating_add(memtables))
+	}
+
+	/// Estimated number of keys in the map, including unflushed memtables.
+	#[inline]
+	pub fn estimate_num_keys(&self) -> Result<u64> { self.property_integer(NUM_KEYS_PROPERTY) }
+
 	#[inline]
 	pub fn name(&self) -> &str { self.name }
 
@@ -19,8 +19,9 @@
 mod tests;
 pub(crate) mod util;
 
-use std::{ops::Index, sync::Arc};
+use std::{ops::Index, path::Path, sync::Arc};
 
+use futures::Stream;
 use log as _;
 use tuwunel_core::{Result, Server, err};
 
@@ -80,6 +81,22 @@ pub fn iter(&self) -> impl Iterator<Item = (&MapsKey, &MapsVal)> + Send + '_ {
 	#[inline]
 	pub fn keys(&self) -> impl Iterator<Item = &MapsKey> + Send + '_ { self.maps.keys() }
 
+	/// Stream every raw key-value of the map `name`, e.g. for backup or
+	/// migration tooling enumerating `keys()`. The iterator reads from the
+	/// point-in-time snapshot taken when it is created, so writers are never
+	/// blocked; data written while dumping may be missed.
+	pub fn dump_map<'a>(
+		&'a self,
+		name: &str,
+	) -> Result<impl Stream<Item = Result<KeyVal<'a>>> + Send + 'a> {
+		self.get(name).map(Map::raw_stream)
+	}
+
+	/// Snapshot the database into a new directory at `path`; see
+	/// `Engine::checkpoint`. Safe to call while the server is running.
+	#[inline]
+	pub fn checkpoint(&self, path: &Path) -> Result { self.engine.checkpoint(path) }
+
 	#[inline]
 	#[must_use]
 	pub fn is_read_only(&self) -> bool { self.engine.is_read_only() }
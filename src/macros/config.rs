@@ -82,13 +82,40 @@ fn generate_example(input: &ItemStruct, args: &[Meta], emit: bool) -> Result<Tok
 	}
 
 	let mut summary: Vec<TokenStream2> = Vec::new();
+	let mut reload: Vec<TokenStream2> = Vec::new();
+	let mut restart: Vec<TokenStream2> = Vec::new();
 	if let Fields::Named(FieldsNamed { named, .. }) = &input.fields {
 		for field in named {
 			let Some(ident) = &field.ident else {
 				continue;
 			};
 
-			if ignore.contains(ident.to_string().as_str()) {
+			// Separate sections are applied whole when they are reloadable, and
+			// otherwise reported whole when they changed.
+			let name = ident.to_string();
+			let changed = if get_type_name(field).as_deref() == Some("RegexSet") {
+				quote! { self.#ident.patterns() != new.#ident.patterns() }
+			} else {
+				quote! { self.#ident != new.#ident }
+			};
+
+			let reloadable = get_doc_comment_line(field, "reloadable").as_deref() == Some("yes");
+			if reloadable {
+				reload.push(quote! {
+					if #changed {
+						self.#ident = new.#ident.clone();
+						applied.push(#name);
+					}
+				});
+			} else if !is_flattened(field) {
+				restart.push(quote! {
+					if #changed {
+						ignored.push(#name);
+					}
+				});
+			}
+
+			if ignore.contains(name.as_str()) {
 				continue;
 			}
 
@@ -151,7 +178,6 @@ fn generate_example(input: &ItemStruct, args: &[Meta], emit: bool) -> Result<Tok
 					quote! { format_args!("{:?}", self.#ident) }
 				};
 
-				let name = ident.to_string();
 				summary.push(quote! {
 					writeln!(out, "| {} | {} |", #name, #value)?;
 				});
@@ -179,7 +205,23 @@ fn fmt(&self, out: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		}
 	};
 
-	Ok(display)
+	let reload = quote! {
+		impl #struct_name {
+			/// Applies the fields documented as `reloadable: yes` from `new`.
+			/// Returns the names of the fields applied, and of the changed fields
+			/// left as they were because they only take effect after a restart.
+			#[allow(unused_mut, unused_variables, clippy::float_cmp)]
+			pub fn reload_from(&mut self, new: &Self) -> (Vec<&'static str>, Vec<&'static str>) {
+				let mut applied = Vec::new();
+				let mut ignored = Vec::new();
+				#( #reload )*
+				#( #restart )*
+				(applied, ignored)
+			}
+		}
+	};
+
+	Ok([display, reload].into_iter().collect())
 }
 
 fn append_section(filename: &str, truncate: bool, content: &[u8]) {
@@ -330,6 +372,24 @@ fn get_doc_comment_full(field: &Field) -> Option<String> {
 	(!out.is_empty()).then_some(out)
 }
 
+/// Whether the field is `#[serde(flatten)]`, collecting whatever keys the
+/// others leave rather than holding an option of its own.
+fn is_flattened(field: &Field) -> bool {
+	field.attrs.iter().any(|attr| {
+		let Meta::List(MetaList { path, tokens, .. }) = &attr.meta else {
+			return false;
+		};
+
+		path.is_ident("serde")
+			&& Punctuated::<Meta, syn::Token![,]>::parse_terminated
+				.parse(tokens.clone().into())
+				.is_ok_and(|args| {
+					args.iter()
+						.any(|arg| arg.path().is_ident("flatten"))
+				})
+	})
+}
+
 fn get_type_name(field: &Field) -> Option<String> {
 	let Type::Path(TypePath { path, .. }) = &field.ty else {
 		return None;
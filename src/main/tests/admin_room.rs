@@ -0,0 +1,200 @@
+#![cfg(test)]
+
+mod common;
+
+use std::net::IpAddr;
+
+use tuwunel_core::{
+	Err, Result,
+	matrix::{PduCount, pdu::PduBuilder},
+	ruma::{
+		OwnedRoomId, RoomId, RoomVersionId, UserId,
+		events::{
+			StateEventType,
+			room::{
+				create::RoomCreateEventContent,
+				member::{MembershipState, RoomMemberEventContent},
+				name::RoomNameEventContent,
+			},
+		},
+	},
+};
+use tuwunel_service::Services;
+
+use self::common::{Options, run_admin_command, with_services};
+
+/// Every room shared with a server is listed along with its name.
+#[test]
+fn by_server_lists_shared_rooms() -> Result {
+	with_services("by-server", Options::default(), async |services| {
+		let carol = UserId::parse("@carol:remote.example")?;
+		let mut rooms = Vec::new();
+		for name in ["Planning", "Lounge"] {
+			let room_id = create_room(services, name).await?;
+			join_remote(services, &carol, &room_id).await?;
+			rooms.push((room_id, name));
+		}
+
+		let body = run_admin_command(services, "rooms by-server remote.example").await?;
+		for (room_id, name) in &rooms {
+			if !body
+				.lines()
+				.any(|line| line.starts_with(room_id.as_str()) && line.ends_with(name))
+			{
+				return Err!("room {room_id} named {name:?} is not listed: {body}");
+			}
+		}
+
+		Ok(())
+	})
+}
+
+/// A banned room refuses local joins and keeps the reason it was banned for.
+#[test]
+fn ban_rejects_local_joins() -> Result {
+	with_services("ban", Options::default(), async |services| {
+		let room_id = create_room(services, "Spam").await?;
+
+		run_admin_command(services, &format!("rooms ban {room_id} spam wave")).await?;
+
+		let reason = services.metadata.ban_reason(&room_id).await;
+		if reason.as_deref() != Some("spam wave") {
+			return Err!("ban reason was not recorded: {reason:?}");
+		}
+
+		let bob = UserId::parse_with_server_name("bob", services.globals.server_name())?;
+		let checked = services
+			.membership
+			.banned_room_check(&bob, &room_id, None, IpAddr::from([127, 0, 0, 1]))
+			.await;
+
+		match checked {
+			| Err(e) if e.to_string().contains("banned") => Ok(()),
+			| _ => Err!("join to a banned room was not rejected: {checked:?}"),
+		}
+	})
+}
+
+/// Lifting an evacuating ban re-enables federation only for a room the ban
+/// disabled.
+#[test]
+fn unban_enables_only_rooms_disabled_by_ban() -> Result {
+	with_services("unban", Options::default(), async |services| {
+		let banned = create_room(services, "Raid").await?;
+		let disabled = create_room(services, "Quarantine").await?;
+		services.metadata.disable_room(&disabled);
+
+		for room_id in [&banned, &disabled] {
+			for command in
+				[format!("rooms ban --evacuate {room_id} raid"), format!("rooms unban {room_id}")]
+			{
+				run_admin_command(services, &command).await?;
+			}
+		}
+
+		if services.metadata.is_disabled(&banned).await {
+			return Err!("room disabled by its ban was not re-enabled");
+		}
+
+		if !services.metadata.is_disabled(&disabled).await {
+			return Err!("room disabled before its ban was re-enabled");
+		}
+
+		Ok(())
+	})
+}
+
+/// The admin room's current state has a short but non-trivial auth chain; its
+/// create event has none.
+#[test]
+fn auth_chain_reports_size_and_depth() -> Result {
+	with_services("auth-chain", Options::default(), async |services| {
+		let room_id = services.admin.get_admin_room().await?;
+		let create_id = services
+			.state_accessor
+			.room_state_get_id(&room_id, &StateEventType::RoomCreate, "")
+			.await?;
+
+		let (events, depth) =
+			auth_chain(services, &format!("rooms auth-chain {room_id}")).await?;
+		if !(2..100).contains(&events) || depth < 2 || depth > events {
+			return Err!("implausible auth chain of {events} events with depth {depth}");
+		}
+
+		let command = format!("rooms auth-chain {room_id} {create_id}");
+		let (events, depth) = auth_chain(services, &command).await?;
+		if events != 0 || depth != 0 {
+			return Err!("create event has an auth chain of {events} events, depth {depth}");
+		}
+
+		Ok(())
+	})
+}
+
+/// Run an auth-chain command, returning the reported event count and depth.
+async fn auth_chain(services: &Services, command: &str) -> Result<(usize, usize)> {
+	let body = run_admin_command(services, command).await?;
+	let number_after = |prefix: &str| {
+		body.split_once(prefix)
+			.and_then(|(_, rest)| rest.split_whitespace().next())
+			.and_then(|number| number.trim_end_matches(',').parse().ok())
+	};
+
+	let (Some(events), Some(depth)) = (number_after(" has "), number_after(" depth of ")) else {
+		return Err!("unexpected auth-chain output: {body}");
+	};
+
+	Ok((events, depth))
+}
+
+/// Create a named local room owned by the server user.
+async fn create_room(services: &Services, name: &str) -> Result<OwnedRoomId> {
+	let room_id = RoomId::new_v1(services.globals.server_name());
+	let server_user = &services.globals.server_user;
+
+	services
+		.short
+		.get_or_create_shortroomid(&room_id)
+		.await;
+
+	let state_lock = services.state.mutex.lock(&room_id).await;
+	let events = [
+		PduBuilder::state(String::new(), &RoomCreateEventContent {
+			room_version: RoomVersionId::V11,
+			..RoomCreateEventContent::new_v11()
+		}),
+		PduBuilder::state(
+			server_user.to_string(),
+			&RoomMemberEventContent::new(MembershipState::Join),
+		),
+		PduBuilder::state(String::new(), &RoomNameEventContent::new(name.to_owned())),
+	];
+
+	for event in events {
+		services
+			.timeline
+			.build_and_append_pdu(event, server_user, &room_id, &state_lock)
+			.await?;
+	}
+
+	Ok(room_id)
+}
+
+/// Record a remote user as joined, placing their server in the room.
+async fn join_remote(services: &Services, user_id: &UserId, room_id: &RoomId) -> Result {
+	let count = PduCount::Normal(*services.globals.next_count());
+
+	services
+		.state_cache
+		.update_membership(
+			room_id,
+			user_id,
+			RoomMemberEventContent::new(MembershipState::Join),
+			user_id,
+			None,
+			None,
+			true,
+			count,
+		)
+		.await
+}
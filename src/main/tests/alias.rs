@@ -0,0 +1,56 @@
+#![cfg(test)]
+
+mod common;
+
+use tuwunel_core::{
+	Err, Result,
+	ruma::{room_alias_id, room_id},
+};
+use tuwunel_service::Services;
+
+use self::common::{Options, run_admin_command, with_services};
+
+/// A local alias is reported as resolved from the database.
+#[test]
+fn resolve_alias_reports_local_step() -> Result {
+	with_services("resolve-local", Options::default(), async |services| {
+		let alias = services.globals.local_alias("lobby")?;
+		let room_id = room_id!("!lobby:example.com");
+		services.alias.set_alias(&alias, room_id)?;
+
+		let body = resolve_alias(services, alias.as_str()).await?;
+		if !body.contains("is local") || !body.contains(&format!("Resolved locally to {room_id}"))
+		{
+			return Err!("unexpected resolve-alias output: {body}");
+		}
+
+		Ok(())
+	})
+}
+
+/// A remote alias names the server queried over federation, and the failure
+/// it returned, without touching the local alias tables.
+#[test]
+fn resolve_alias_reports_remote_step() -> Result {
+	let options = ["allow_federation=false".to_owned()];
+	with_services("resolve-remote", Options::config(&options), async |services| {
+		let body =
+			resolve_alias(services, room_alias_id!("#lobby:remote.example").as_str()).await?;
+
+		for expected in [
+			"is remote; querying remote.example",
+			"Remote resolution via remote.example failed",
+			"Federation is disabled",
+		] {
+			if !body.contains(expected) {
+				return Err!("resolve-alias output is missing {expected:?}: {body}");
+			}
+		}
+
+		Ok(())
+	})
+}
+
+async fn resolve_alias(services: &Services, alias: &str) -> Result<String> {
+	run_admin_command(services, &format!("debug resolve-alias {alias}")).await
+}
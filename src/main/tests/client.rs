@@ -0,0 +1,56 @@
+#![cfg(test)]
+
+mod common;
+
+use std::time::Instant;
+
+use tokio::{
+	net::TcpListener,
+	time::{Duration, sleep},
+};
+use tuwunel_core::{Err, Result};
+
+use self::common::{Options, with_services};
+
+/// The synapse client gives up on a response stalled for longer than
+/// `federation_synapse_timeout_s`, well before the default read timeout.
+#[test]
+fn synapse_client_times_out_at_configured_timeout() -> Result {
+	let options = ["federation_synapse_timeout_s=1".to_owned()];
+	with_services("synapse-timeout", Options::config(&options), async |services| {
+		let listener = TcpListener::bind("127.0.0.1:0").await?;
+		let addr = listener.local_addr()?;
+
+		// Take the request and never answer it.
+		let stall = tokio::spawn(async move {
+			let accepted = listener.accept().await;
+			sleep(Duration::from_secs(60)).await;
+			drop(accepted);
+		});
+
+		let started = Instant::now();
+		let result = services
+			.client
+			.synapse
+			.get(format!("http://{addr}/_matrix/federation/v1/version"))
+			.send()
+			.await;
+
+		let elapsed = started.elapsed();
+		stall.abort();
+
+		let Err(error) = result else {
+			return Err!("stalled response did not time out");
+		};
+
+		if !error.is_timeout() {
+			return Err!("expected a timeout, found: {error}");
+		}
+
+		if elapsed < Duration::from_secs(1) || elapsed >= Duration::from_secs(10) {
+			return Err!("timed out after {elapsed:?} rather than the configured second");
+		}
+
+		Ok(())
+	})
+}
@@ -0,0 +1,169 @@
+//! Harness shared by the integration tests. Each test file is its own crate
+//! and uses only part of it.
+
+#![allow(dead_code)]
+
+use std::{
+	fs::remove_dir_all,
+	io::{Read, Write},
+	net::TcpStream,
+	path::{Path, PathBuf},
+	process::id as process_id,
+	time::Duration,
+};
+
+use tuwunel::{Args, Runtime, Server};
+use tuwunel_core::{Err, Result};
+use tuwunel_service::Services;
+
+/// How `with_services` sets up the server.
+#[derive(Clone, Copy, Default)]
+pub struct Options<'a> {
+	/// Extra config options, each a `key=value` line.
+	pub config: &'a [String],
+
+	/// Config file loaded as though given with `--config`.
+	pub config_path: Option<&'a Path>,
+
+	/// Database opened instead of a scratch one; it is left in place.
+	pub database_path: Option<&'a str>,
+
+	/// Test directives for `Args::default_test`; `fresh` and `cleanup` when
+	/// empty.
+	pub directives: &'a [&'a str],
+}
+
+impl<'a> Options<'a> {
+	/// Options adding `config` to the defaults.
+	#[must_use]
+	pub fn config(config: &'a [String]) -> Self { Self { config, ..Self::default() } }
+}
+
+/// Boot the full service graph against a scratch database set up by
+/// `options`, run `test`, then shut everything down again.
+pub fn with_services<F>(name: &str, options: Options<'_>, test: F) -> Result
+where
+	F: AsyncFnOnce(&Services) -> Result,
+{
+	// Isolate the database under /tmp so parallel tests do not contend.
+	let scratch = options.database_path.is_none().then(|| {
+		PathBuf::from(format!(
+			"/tmp/tuwunel-test-{}-{name}-{}",
+			env!("CARGO_CRATE_NAME"),
+			process_id()
+		))
+	});
+
+	let db_path = options
+		.database_path
+		.map(PathBuf::from)
+		.or_else(|| scratch.clone())
+		.expect("a database path");
+
+	let directives: &[&str] = if options.directives.is_empty() {
+		&["fresh", "cleanup"]
+	} else {
+		options.directives
+	};
+
+	let mut args = Args::default_test(directives);
+	args.maintenance = true;
+	args.option
+		.push(format!("database_path=\"{}\"", db_path.display()));
+	args.option.extend_from_slice(options.config);
+	args.config = options
+		.config_path
+		.map(|path| vec![path.to_path_buf()]);
+
+	let runtime = Runtime::new(Some(&args))?;
+	let server = Server::new(Some(&args), Some(&runtime))?;
+
+	let result: Result = runtime.block_on(async {
+		let services = tuwunel::async_start(&server).await?;
+
+		let outcome = test(&services).await;
+
+		server.server.shutdown()?;
+		drop(services);
+
+		tuwunel::async_run(&server).await?;
+		tuwunel::async_stop(&server).await?;
+
+		outcome
+	});
+
+	drop(runtime);
+
+	if let Some(scratch) = scratch {
+		remove_dir_all(&scratch).ok();
+	}
+
+	result
+}
+
+/// Run an admin command in place, returning the body of its output. A command
+/// which fails has the body of its error output as the error.
+pub async fn run_admin_command(services: &Services, command: &str) -> Result<String> {
+	tuwunel_admin::init(&services.admin);
+	let output = services
+		.admin
+		.command_in_place(command.to_owned(), None)
+		.await;
+	tuwunel_admin::fini(&services.admin);
+
+	match output {
+		| Ok(Some(output)) => Ok(output.body().to_owned()),
+		| Ok(None) => Err!("{command:?} produced no output"),
+		| Err(output) => Err!("{}", output.body()),
+	}
+}
+
+/// Serve one HTTP request off `stream` as a stand-in for an outside endpoint:
+/// the request is read in full and answered with the raw response `answer`
+/// makes of it. Returns the request.
+pub fn respond<F>(mut stream: TcpStream, answer: F) -> Result<String>
+where
+	F: FnOnce(&str) -> String,
+{
+	stream.set_nonblocking(false)?;
+	stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+
+	let mut request = Vec::new();
+	let mut buf = [0_u8; 4096];
+	loop {
+		let len = stream.read(&mut buf)?;
+		if len == 0 {
+			break;
+		}
+
+		request.extend(buf.iter().take(len));
+		if let Some(expected) = request_len(&request)
+			&& request.len() >= expected
+		{
+			break;
+		}
+	}
+
+	let request = String::from_utf8_lossy(&request).into_owned();
+	stream.write_all(answer(&request).as_bytes())?;
+
+	Ok(request)
+}
+
+/// Total length of a request once its headers are complete.
+fn request_len(request: &[u8]) -> Option<usize> {
+	let request = std::str::from_utf8(request).ok()?;
+	let (head, _) = request.split_once("\r\n\r\n")?;
+	let content_length = head
+		.lines()
+		.filter_map(|line| line.split_once(':'))
+		.find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+		.and_then(|(_, value)| value.trim().parse::<usize>().ok())
+		.unwrap_or(0);
+
+	Some(
+		head.len()
+			.saturating_add("\r\n\r\n".len())
+			.saturating_add(content_length),
+	)
+}
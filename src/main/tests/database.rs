@@ -0,0 +1,104 @@
+#![cfg(test)]
+
+mod common;
+
+use std::{fs::remove_dir_all, path::Path, process::id as process_id};
+
+use tuwunel_core::{Err, Result, utils::stream::ReadyExt};
+
+use self::common::{Options, with_services};
+
+#[test]
+fn map_estimates_nonzero_after_insert() -> Result {
+	with_services("map-estimates", Options::default(), async |services| {
+		let map = &services.db["bannedroomids"];
+		for i in 0_u32..128 {
+			map.insert(&format!("!room{i}:example.com"), b"1");
+		}
+
+		let size = map.approximate_size()?;
+		if size == 0 {
+			return Err!("approximate size of {map} is zero after insert");
+		}
+
+		let keys = map.estimate_num_keys()?;
+		if keys == 0 {
+			return Err!("estimated key count of {map} is zero after insert");
+		}
+
+		Ok(())
+	})
+}
+
+#[test]
+fn checkpoint_opens_as_database() -> Result {
+	let checkpoint = format!("/tmp/tuwunel-test-database-checkpoint-{}", process_id());
+	remove_dir_all(&checkpoint).ok();
+
+	with_services("checkpoint-source", Options::default(), async |services| {
+		services.db["bannedroomids"].insert("!checkpointed:example.com", b"1");
+
+		services.db.checkpoint(Path::new(&checkpoint))
+	})?;
+
+	let options = Options {
+		database_path: Some(&checkpoint),
+		directives: &["cleanup"],
+		..Options::default()
+	};
+
+	let result = with_services("checkpoint", options, async |services| {
+		if services.db["bannedroomids"]
+			.get("!checkpointed:example.com")
+			.await
+			.is_err()
+		{
+			return Err!("checkpoint is missing data written before it was taken");
+		}
+
+		Ok(())
+	});
+
+	remove_dir_all(&checkpoint).ok();
+
+	result
+}
+
+/// Dumping a map streams back exactly the key-values written to it.
+#[test]
+fn dump_map_round_trips() -> Result {
+	with_services("dump-map", Options::default(), async |services| {
+		let name = "bannedroomids";
+		if !services.db.keys().any(|key| *key == name) {
+			return Err!("{name} is not listed among the database maps");
+		}
+
+		let mut expected: Vec<_> = (0_u32..16)
+			.map(|i| (format!("!dump{i}:example.com").into_bytes(), i.to_be_bytes().to_vec()))
+			.collect();
+
+		for (key, val) in &expected {
+			services.db[name].insert(key, val);
+		}
+
+		let mut dumped = Vec::new();
+		services
+			.db
+			.dump_map(name)?
+			.ready_for_each(|item| dumped.push(item.map(|(k, v)| (k.to_vec(), v.to_vec()))))
+			.await;
+
+		let dumped = dumped.into_iter().collect::<Result<Vec<_>>>()?;
+
+		expected.sort();
+		if dumped != expected {
+			return Err!("dump of {name} differs: {dumped:?}");
+		}
+
+		if services.db.dump_map("no_such_map").is_ok() {
+			return Err!("dumping an unknown map succeeded");
+		}
+
+		Ok(())
+	})
+}
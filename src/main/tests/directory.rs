@@ -0,0 +1,40 @@
+#![cfg(test)]
+
+mod common;
+
+use tuwunel_core::{Err, Result};
+
+use self::common::{Options, run_admin_command, with_services};
+
+/// Publishing and unpublishing an existing room through the admin commands is
+/// reflected by the room directory.
+#[test]
+fn directory_publish_toggles_visibility() -> Result {
+	with_services("publish-toggle", Options::default(), async |services| {
+		let room_id = services.admin.get_admin_room().await?;
+
+		let expected = [
+			("publish", "is now published", true),
+			("publish", "is already published", true),
+			("unpublish", "is no longer published", false),
+		];
+
+		for (action, message, expected) in expected {
+			let body =
+				run_admin_command(services, &format!("rooms directory {action} {room_id}"))
+					.await?;
+
+			if !body.contains(message) {
+				return Err!("unexpected directory output: {body}");
+			}
+
+			let published = services.directory.is_public_room(&room_id).await;
+
+			if published != expected {
+				return Err!("directory shows published={published} after {message:?}");
+			}
+		}
+
+		Ok(())
+	})
+}
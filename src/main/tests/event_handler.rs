@@ -0,0 +1,144 @@
+#![cfg(test)]
+
+mod common;
+
+use tuwunel_core::{
+	Err, Result,
+	matrix::pdu::PduBuilder,
+	metrics::PduOutcome,
+	ruma::{RoomId, events::room::message::RoomMessageEventContent, server_name},
+};
+
+use self::common::{Options, run_admin_command, with_services};
+
+#[test]
+fn incoming_pdu_latency_recorded_by_outcome() -> Result {
+	with_services("incoming-pdu-metrics", Options::default(), async |services| {
+		let metrics = &services.server.metrics.incoming_pdu;
+		let origin = server_name!("remote.example");
+		let room_id = services.admin.get_admin_room().await?;
+		let latest = services
+			.timeline
+			.latest_pdu_in_room(&room_id)
+			.await?;
+
+		let pdu = services
+			.timeline
+			.get_pdu_json(&latest.event_id)
+			.await?;
+
+		// An event already in the timeline is accepted without reprocessing.
+		services
+			.event_handler
+			.handle_incoming_pdu(origin, &room_id, &latest.event_id, pdu.clone(), true)
+			.await?;
+
+		// An event for a room unknown to us is rejected.
+		let unknown = RoomId::parse(format!("!unknown:{}", services.globals.server_name()))?;
+		let rejected = services
+			.event_handler
+			.handle_incoming_pdu(origin, &unknown, &latest.event_id, pdu, true)
+			.await;
+
+		if rejected.is_ok() {
+			return Err!("event for an unknown room was not rejected");
+		}
+
+		for (outcome, expected) in [
+			(PduOutcome::Accepted, 1),
+			(PduOutcome::Outlier, 0),
+			(PduOutcome::SoftFailed, 0),
+			(PduOutcome::Rejected, 1),
+		] {
+			let count = metrics.get(outcome).count();
+			if count != expected {
+				return Err!("expected {expected} {outcome} samples, recorded {count}");
+			}
+		}
+
+		Ok(())
+	})
+}
+
+/// An outlier whose auth and prev events are all present is accepted into
+/// the timeline when reprocessed.
+#[test]
+fn reprocess_outlier_accepts_when_deps_present() -> Result {
+	with_services("reprocess-outlier", Options::default(), async |services| {
+		let room_id = services.admin.get_admin_room().await?;
+		let server_user = &services.globals.server_user;
+		let content = RoomMessageEventContent::text_plain("outlier");
+
+		let state_lock = services.state.mutex.lock(&room_id).await;
+		let (outlier, outlier_json) = services
+			.timeline
+			.create_hash_and_sign_event(
+				PduBuilder::timeline(&content),
+				server_user,
+				&room_id,
+				&state_lock,
+			)
+			.await?;
+		drop(state_lock);
+
+		services
+			.timeline
+			.add_pdu_outlier(&outlier.event_id, &outlier_json);
+
+		if !services
+			.event_handler
+			.reprocess_outlier(&outlier.event_id)
+			.await?
+		{
+			return Err!("outlier was not accepted");
+		}
+
+		if services
+			.timeline
+			.get_pdu_id(&outlier.event_id)
+			.await
+			.is_err()
+		{
+			return Err!("accepted outlier is missing from the timeline");
+		}
+
+		if services
+			.event_handler
+			.reprocess_outlier(&outlier.event_id)
+			.await?
+		{
+			return Err!("accepted event was reported as newly accepted");
+		}
+
+		Ok(())
+	})
+}
+
+/// Resolving the forward extremities of an undisturbed room reproduces the
+/// state stored for it.
+#[test]
+fn resolve_state_matches_stored_state() -> Result {
+	with_services("resolve-state", Options::default(), async |services| {
+		let room_id = services.admin.get_admin_room().await?;
+
+		let body = run_admin_command(services, &format!("debug resolve-state {room_id}")).await?;
+		if !body.contains("matches the stored current state") {
+			return Err!("recomputed state diverged: {body}");
+		}
+
+		let current = services
+			.state
+			.get_room_shortstatehash(&room_id)
+			.await?;
+
+		// Both the recomputed and the stored state name the same shortstatehash.
+		if body
+			.matches(&format!("shortstatehash {current}."))
+			.count() != 2
+		{
+			return Err!("recomputed state did not map to the stored shortstatehash: {body}");
+		}
+
+		Ok(())
+	})
+}
@@ -0,0 +1,180 @@
+#![cfg(test)]
+
+mod common;
+
+use std::{
+	fs::{remove_file, write},
+	iter::once,
+	path::PathBuf,
+	process::id as process_id,
+};
+
+use tuwunel_core::{
+	Err, Result,
+	ruma::{RoomId, UserId},
+};
+
+use self::common::{Options, with_services};
+
+#[test]
+fn local_alias_checked_rejects_reserved() -> Result {
+	with_services("alias-reserved", Options::default(), async |services| {
+		let server_name = services.globals.server_name();
+
+		for localpart in ["admins", "alice-userroom"] {
+			if services
+				.globals
+				.local_alias_checked(localpart)
+				.is_ok()
+			{
+				return Err!("reserved localpart {localpart:?} was accepted");
+			}
+		}
+
+		let alias = services.globals.local_alias_checked("lobby")?;
+		if alias.as_str() != format!("#lobby:{server_name}") {
+			return Err!("unexpected alias formed: {alias}");
+		}
+
+		// The server's own uses bypass the reservation.
+		services.globals.local_alias("admins")?;
+
+		Ok(())
+	})
+}
+
+#[test]
+fn read_only_mode_rejects_writes_until_disabled() -> Result {
+	with_services("read-only-mode", Options::default(), async |services| {
+		let globals = &services.globals;
+		let stored = || {
+			services.db["global"]
+				.get_blocking(b"read_only_mode")
+				.is_ok()
+		};
+
+		globals.check_writable()?;
+
+		globals.set_read_only_mode(true, false);
+		if globals.check_writable().is_ok() {
+			return Err!("write accepted in read-only mode");
+		}
+
+		if stored() {
+			return Err!("read-only mode persisted without being asked to");
+		}
+
+		globals.set_read_only_mode(true, true);
+		if !stored() {
+			return Err!("read-only mode was not persisted");
+		}
+
+		globals.set_read_only_mode(false, false);
+		globals.check_writable()?;
+
+		if stored() {
+			return Err!("disabling read-only mode left it persisted");
+		}
+
+		Ok(())
+	})
+}
+
+#[test]
+fn capabilities_follow_local_presence() -> Result {
+	let options = ["allow_local_presence=true".to_owned()];
+	with_services("capabilities", Options::config(&options), async |services| {
+		if !services.globals.capabilities().local_presence {
+			return Err!("local presence enabled but not reported");
+		}
+
+		let mut config = (**services.server.config).clone();
+		config.allow_local_presence = false;
+		services.server.config.update(config)?;
+
+		if services.globals.capabilities().local_presence {
+			return Err!("local presence disabled but still reported");
+		}
+
+		Ok(())
+	})
+}
+
+#[test]
+fn system_users_and_rooms_are_recognized() -> Result {
+	with_services("system", Options::default(), async |services| {
+		let globals = &services.globals;
+		let server_name = globals.server_name();
+
+		if !globals.is_system_user(&globals.server_user) {
+			return Err!("server user is not a system user");
+		}
+
+		let alice = UserId::parse_with_server_name("alice", server_name)?;
+		if globals.is_system_user(&alice) {
+			return Err!("{alice} is reported as a system user");
+		}
+
+		let admin_room = services.admin.get_admin_room().await?;
+		if !globals.is_system_room(&admin_room).await {
+			return Err!("admin room is not a system room");
+		}
+
+		let room_id = RoomId::new_v1(server_name);
+		if globals.is_system_room(&room_id).await {
+			return Err!("{room_id} is reported as a system room");
+		}
+
+		Ok(())
+	})
+}
+
+/// A runtime reload applies options which may change while running and reports
+/// the others as needing a restart, leaving them as they were.
+#[test]
+fn reload_config_applies_reloadable_options() -> Result {
+	let path = PathBuf::from(format!("/tmp/tuwunel-test-globals-reload-{}.toml", process_id()));
+	write(&path, "")?;
+
+	let options = Options {
+		config_path: Some(&path),
+		..Options::default()
+	};
+	let result = with_services("reload", options, async |services| {
+		let request_timeout = services.server.config.request_timeout;
+		if !services.server.config.allow_room_creation {
+			return Err!("room creation is not allowed by default");
+		}
+
+		let config = &services.server.config;
+		write(
+			&path,
+			format!(
+				"server_name = \"{}\"\ndatabase_path = \"{}\"\nallow_room_creation = \
+				 false\nrequest_timeout = {}\n",
+				config.server_name,
+				config.database_path.display(),
+				request_timeout.saturating_add(1)
+			),
+		)?;
+
+		let (applied, ignored) = services.config.reload(once(path.as_path()))?;
+		if !applied.contains(&"allow_room_creation") || !ignored.contains(&"request_timeout") {
+			return Err!("unexpected changes reported: {applied:?}, {ignored:?}");
+		}
+
+		if services.server.config.allow_room_creation {
+			return Err!("reloadable option was not applied");
+		}
+
+		if services.server.config.request_timeout != request_timeout {
+			return Err!("option requiring a restart was applied");
+		}
+
+		Ok(())
+	});
+
+	remove_file(&path).ok();
+
+	result
+}
@@ -0,0 +1,104 @@
+#![cfg(test)]
+
+mod common;
+
+use tuwunel_core::{
+	Err, Result,
+	ruma::{RoomId, UserId, serde::Raw},
+};
+
+use self::common::{Options, with_services};
+
+/// A client holding the current etag is told nothing changed; one holding an
+/// etag from before the last upload receives every key.
+#[test]
+fn get_all_if_changed_honours_etag() -> Result {
+	with_services("etag-conditional", Options::default(), async |services| {
+		let server_name = services.globals.server_name();
+		let alice = UserId::parse_with_server_name("alice", server_name)?;
+		let room_id = RoomId::parse(format!("!room:{server_name}"))?;
+
+		let algorithm = Raw::from_json_string(
+			r#"{"algorithm":"m.megolm_backup.v1.curve25519-aes-sha2","auth_data":{}}"#.to_owned(),
+		)?;
+		let key_data = Raw::from_json_string(
+			r#"{"first_message_index":0,"forwarded_count":0,"is_verified":true,
+			"session_data":{"ephemeral":"e","ciphertext":"c","mac":"m"}}"#
+				.to_owned(),
+		)?;
+
+		let backups = &services.key_backups;
+		let version = backups.create_backup(&alice, &algorithm)?;
+		let stale_etag = backups.get_etag(&alice, &version).await;
+
+		backups
+			.add_key(&alice, &version, &room_id, "session", &key_data)
+			.await?;
+
+		let etag = backups.get_etag(&alice, &version).await;
+		if backups
+			.get_all_if_changed(&alice, &version, &etag)
+			.await
+			.is_some()
+		{
+			return Err!("matching etag still returned the backup");
+		}
+
+		let Some(rooms) = backups
+			.get_all_if_changed(&alice, &version, &stale_etag)
+			.await
+		else {
+			return Err!("stale etag did not return the backup");
+		};
+
+		if !rooms
+			.get(&room_id)
+			.is_some_and(|room| room.sessions.contains_key("session"))
+		{
+			return Err!("backup is missing the uploaded key: {rooms:?}");
+		}
+
+		Ok(())
+	})
+}
+
+/// Deleting a backup reports whether it existed and takes its keys with it.
+#[test]
+fn delete_backup_reports_existence() -> Result {
+	with_services("delete-backup", Options::default(), async |services| {
+		let server_name = services.globals.server_name();
+		let alice = UserId::parse_with_server_name("alice", server_name)?;
+		let room_id = RoomId::parse(format!("!room:{server_name}"))?;
+
+		let algorithm = Raw::from_json_string(
+			r#"{"algorithm":"m.megolm_backup.v1.curve25519-aes-sha2","auth_data":{}}"#.to_owned(),
+		)?;
+		let key_data = Raw::from_json_string(
+			r#"{"first_message_index":0,"forwarded_count":0,"is_verified":true,
+			"session_data":{"ephemeral":"e","ciphertext":"c","mac":"m"}}"#
+				.to_owned(),
+		)?;
+
+		let backups = &services.key_backups;
+		let version = backups.create_backup(&alice, &algorithm)?;
+		backups
+			.add_key(&alice, &version, &room_id, "session", &key_data)
+			.await?;
+
+		if !backups.delete_backup(&alice, &version).await? {
+			return Err!("existing backup was reported missing");
+		}
+
+		if backups.get_backup(&alice, &version).await.is_ok()
+			|| backups.count_keys(&alice, &version).await != 0
+		{
+			return Err!("deleted backup is still present");
+		}
+
+		if backups.delete_backup(&alice, &version).await? {
+			return Err!("deleting a missing backup was reported as existing");
+		}
+
+		Ok(())
+	})
+}
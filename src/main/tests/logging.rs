@@ -0,0 +1,74 @@
+#![cfg(test)]
+
+mod common;
+
+use tuwunel_core::{Err, Result};
+
+use self::common::{Options, run_admin_command, with_services};
+
+/// A valid filter is applied to the console and reported back; an invalid one
+/// is rejected with the parse error.
+#[test]
+fn log_filter_applies_and_validates() -> Result {
+	with_services("log-filter", Options::default(), async |services| {
+		let body =
+			run_admin_command(services, "server log-filter info,tuwunel_service=debug").await?;
+		if !body.contains("Applied log filter: ") {
+			return Err!("unexpected log-filter output: {body}");
+		}
+
+		// The console handle is only installed when logging is enabled.
+		if services
+			.server
+			.log
+			.reload
+			.current("console")
+			.is_some()
+		{
+			let body = run_admin_command(services, "server log-filter").await?;
+			if !body.contains("tuwunel_service=debug") {
+				return Err!("filter was not applied: {body}");
+			}
+		}
+
+		let output = run_admin_command(services, "server log-filter tuwunel=loud").await;
+		let Err(error) = output else {
+			return Err!("invalid filter was accepted: {output:?}");
+		};
+
+		if !error.to_string().contains("Tracing filter error") {
+			return Err!("unexpected error for invalid filter: {error}");
+		}
+
+		Ok(())
+	})
+}
+
+/// The debug command shares the reload and keeps its own error for an invalid
+/// filter.
+#[test]
+fn change_log_level_validates() -> Result {
+	with_services("change-log-level", Options::default(), async |services| {
+		let body =
+			run_admin_command(services, "debug change-log-level info,tuwunel_service=debug")
+				.await?;
+
+		if !body.contains("Successfully changed log level to ") {
+			return Err!("unexpected change-log-level output: {body}");
+		}
+
+		let output = run_admin_command(services, "debug change-log-level tuwunel=loud").await;
+		let Err(error) = output else {
+			return Err!("invalid filter was accepted: {output:?}");
+		};
+
+		if !error
+			.to_string()
+			.contains("Invalid log level filter specified")
+		{
+			return Err!("unexpected error for invalid filter: {error}");
+		}
+
+		Ok(())
+	})
+}
@@ -0,0 +1,145 @@
+#![cfg(test)]
+
+mod common;
+
+use std::{
+	io::ErrorKind,
+	net::{TcpListener, TcpStream},
+	sync::{
+		Arc,
+		atomic::{AtomicBool, Ordering},
+		mpsc,
+	},
+	thread,
+	time::Duration,
+};
+
+use tuwunel_core::{Err, Result, utils::timepoint_from_now};
+use tuwunel_service::oauth::Session;
+
+use self::common::{Options, with_services};
+
+/// A session whose access token is about to lapse is refreshed by a sweep; one
+/// with plenty of time left is not touched.
+#[test]
+fn sweep_refreshes_expiring_session() -> Result {
+	// A throwaway listener stands in for the identity provider, answering its
+	// discovery document and token endpoint, and hands back each token request.
+	let listener = TcpListener::bind("127.0.0.1:0")?;
+	let issuer = format!("http://127.0.0.1:{}", listener.local_addr()?.port());
+	listener.set_nonblocking(true)?;
+
+	let done = Arc::new(AtomicBool::new(false));
+	let (sender, receiver) = mpsc::channel();
+	let provider = thread::spawn({
+		let issuer = issuer.clone();
+		let done = done.clone();
+		move || {
+			while !done.load(Ordering::Relaxed) {
+				match listener.accept() {
+					| Ok((stream, _)) =>
+						if let Ok(Some(body)) = respond(stream, &issuer) {
+							sender.send(body).ok();
+						},
+					| Err(e) if e.kind() == ErrorKind::WouldBlock => {
+						thread::sleep(Duration::from_millis(10));
+					},
+					| Err(_) => return,
+				}
+			}
+		}
+	});
+
+	let options = [
+		"identity_provider.test.brand=\"test\"".to_owned(),
+		"identity_provider.test.client_id=\"tuwunel\"".to_owned(),
+		"identity_provider.test.client_secret=\"secret\"".to_owned(),
+		format!("identity_provider.test.issuer_url=\"{issuer}\""),
+	];
+
+	let result = with_services("sweep", Options::config(&options), async |services| {
+		let sessions = &services.oauth.sessions;
+		let expiring = Session {
+			idp_id: Some("tuwunel".to_owned()),
+			sess_id: Some("expiring".to_owned()),
+			access_token: Some("stale".to_owned()),
+			expires_at: Some(timepoint_from_now(Duration::from_secs(30))?),
+			refresh_token: Some("refresh-expiring".to_owned()),
+			..Default::default()
+		};
+
+		let fresh = Session {
+			sess_id: Some("fresh".to_owned()),
+			access_token: Some("fresh".to_owned()),
+			expires_at: Some(timepoint_from_now(Duration::from_hours(1))?),
+			refresh_token: Some("refresh-fresh".to_owned()),
+			..expiring.clone()
+		};
+
+		sessions.put(&expiring).await;
+		sessions.put(&fresh).await;
+
+		let refreshed = services
+			.oauth
+			.refresh_expiring_tokens(Duration::from_secs(300))
+			.await;
+
+		if refreshed != 1 {
+			return Err!("expected one session refreshed, got {refreshed}");
+		}
+
+		let Ok(request) = receiver.recv_timeout(Duration::from_secs(10)) else {
+			return Err!("no refresh was attempted");
+		};
+
+		for expected in ["grant_type=refresh_token", "refresh_token=refresh-expiring"] {
+			if !request.contains(expected) {
+				return Err!("refresh request is missing {expected:?}: {request}");
+			}
+		}
+
+		let expiring = sessions.get("expiring").await?;
+		if expiring.access_token.as_deref() != Some("refreshed")
+			|| expiring.refresh_token.as_deref() != Some("refresh-expiring")
+		{
+			return Err!("refreshed tokens were not stored: {expiring:?}");
+		}
+
+		if receiver.try_recv().is_ok() {
+			return Err!("session with time left was refreshed");
+		}
+
+		let fresh = sessions.get("fresh").await?;
+		if fresh.access_token.as_deref() != Some("fresh") {
+			return Err!("session with time left was changed: {fresh:?}");
+		}
+
+		Ok(())
+	});
+
+	done.store(true, Ordering::Relaxed);
+	provider.join().ok();
+
+	result
+}
+
+/// Answers one request as the identity provider, returning the body of a token
+/// request.
+fn respond(stream: TcpStream, issuer: &str) -> Result<Option<String>> {
+	let discovery = |request: &str| request.starts_with("GET /.well-known/openid-configuration ");
+	let request = common::respond(stream, |request| {
+		let body = if discovery(request) {
+			format!(r#"{{"issuer":"{issuer}","token_endpoint":"{issuer}/token"}}"#)
+		} else {
+			r#"{"access_token":"refreshed","expires_in":3600}"#.to_owned()
+		};
+
+		format!(
+			"HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: \
+			 {}\r\nconnection: close\r\n\r\n{body}",
+			body.len()
+		)
+	})?;
+
+	Ok((!discovery(&request)).then_some(request))
+}
@@ -0,0 +1,235 @@
+#![cfg(test)]
+
+mod common;
+
+use tuwunel_core::{
+	Err, Result,
+	matrix::PduCount,
+	ruma::{
+		RoomId, ServerName, UInt, UserId,
+		events::{
+			presence::PresenceEventContent,
+			room::member::{MembershipState, RoomMemberEventContent},
+		},
+		presence::PresenceState,
+		uint,
+	},
+	utils::millis_since_unix_epoch,
+};
+use tuwunel_service::{Services, users::Register};
+
+use self::common::{Options, run_admin_command, with_services};
+
+/// A remote update older than the stored presence is dropped; a newer one
+/// replaces it, as does a state change older only by transit jitter.
+#[test]
+fn stale_remote_presence_is_ignored() -> Result {
+	with_services("stale-remote", Options::default(), async |services| {
+		let carol = UserId::parse("@carol:remote.example")?;
+		let presence = &services.presence;
+		let now = millis_since_unix_epoch();
+
+		if !presence
+			.ingest_remote_presence(&carol, &content(PresenceState::Online, uint!(1_000)), now)
+			.await?
+		{
+			return Err!("first remote update was rejected");
+		}
+
+		if presence
+			.ingest_remote_presence(&carol, &content(PresenceState::Offline, uint!(60_000)), now)
+			.await?
+		{
+			return Err!("stale remote update was applied");
+		}
+
+		let stored = presence.get_presence(&carol).await?;
+		if stored.content.presence != PresenceState::Online {
+			return Err!("stale update replaced presence: {:?}", stored.content.presence);
+		}
+
+		if !presence
+			.ingest_remote_presence(&carol, &content(PresenceState::Unavailable, uint!(0)), now)
+			.await?
+		{
+			return Err!("newer remote update was rejected");
+		}
+
+		let stored = presence.get_presence(&carol).await?;
+		if stored.content.presence != PresenceState::Unavailable {
+			return Err!("newer update was not stored: {:?}", stored.content.presence);
+		}
+
+		if !presence
+			.ingest_remote_presence(&carol, &content(PresenceState::Online, uint!(2_000)), now)
+			.await?
+		{
+			return Err!("state change within the jitter slack was rejected");
+		}
+
+		let stored = presence.get_presence(&carol).await?;
+		if stored.content.presence != PresenceState::Online {
+			return Err!("jittered state change was not stored: {:?}", stored.content.presence);
+		}
+
+		Ok(())
+	})
+}
+
+/// An over-long status message is truncated and stripped of control
+/// characters before it is stored.
+#[test]
+fn long_status_msg_is_truncated() -> Result {
+	with_services("status-msg-truncated", Options::default(), async |services| {
+		let alice = UserId::parse_with_server_name("alice", services.globals.server_name())?;
+		let status_msg = format!("{}\u{7}\n{}", "a".repeat(200), "b".repeat(200));
+
+		services
+			.presence
+			.set_presence(&alice, &PresenceState::Online, Some(true), None, Some(status_msg))
+			.await?;
+
+		let stored = services.presence.get_presence(&alice).await?;
+		let expected = format!("{}{}", "a".repeat(200), "b".repeat(56));
+		if stored.content.status_msg.as_deref() != Some(expected.as_str()) {
+			return Err!("status_msg was not sanitized: {:?}", stored.content.status_msg);
+		}
+
+		Ok(())
+	})
+}
+
+/// The admin reset puts every online local user offline, but only when
+/// confirmed.
+#[test]
+fn presence_reset_sets_everyone_offline() -> Result {
+	with_services("presence-reset", Options::default(), async |services| {
+		let server_name = services.globals.server_name();
+		let mut users = Vec::new();
+		for (localpart, state) in [
+			("alice", PresenceState::Online),
+			("bob", PresenceState::Unavailable),
+			("carol", PresenceState::Online),
+		] {
+			let user_id = UserId::parse_with_server_name(localpart, server_name)?;
+			services
+				.users
+				.full_register(Register {
+					user_id: Some(&user_id),
+					password: Some("a-strong-test-password"),
+					..Default::default()
+				})
+				.await?;
+
+			services
+				.presence
+				.set_presence(&user_id, &state, Some(true), None, None)
+				.await?;
+
+			users.push(user_id);
+		}
+
+		if run_admin_command(services, "server presence-reset")
+			.await
+			.is_ok_and(|body| body.contains("Reset the presence"))
+		{
+			return Err!("presence was reset without confirmation");
+		}
+
+		let body =
+			run_admin_command(services, "server presence-reset --yes-i-want-to-do-this").await?;
+
+		if !body.contains("Reset the presence of 3 local user(s)") {
+			return Err!("unexpected presence-reset output: {body}");
+		}
+
+		for user_id in &users {
+			let stored = services.presence.get_presence(user_id).await?;
+			if stored.content.presence != PresenceState::Offline {
+				return Err!("{user_id} is still {:?}", stored.content.presence);
+			}
+		}
+
+		Ok(())
+	})
+}
+
+/// With the fan-out capped, presence only federates to the servers sharing the
+/// most rooms with the user.
+#[test]
+fn presence_fan_out_respects_server_limit() -> Result {
+	let options = ["presence_federation_server_limit=2".to_owned()];
+	with_services("fan-out-limit", Options::config(&options), async |services| {
+		let server_name = services.globals.server_name();
+		let alice = UserId::parse_with_server_name("alice", server_name)?;
+
+		// one.example shares three rooms with alice, two.example two and
+		// three.example one.
+		let rooms = [
+			("a", ["@x:one.example", "@y:two.example", "@z:three.example"].as_slice()),
+			("b", ["@x:one.example", "@y:two.example"].as_slice()),
+			("c", ["@x:one.example"].as_slice()),
+		];
+
+		for (localpart, members) in rooms {
+			let room_id = RoomId::parse(format!("!{localpart}:{server_name}"))?;
+			join(services, &alice, &room_id).await?;
+			for member in members {
+				join(services, &UserId::parse(*member)?, &room_id).await?;
+			}
+		}
+
+		let servers = services
+			.presence
+			.presence_servers(&alice, 0)
+			.await;
+		let expected = ["one.example", "two.example", "three.example"];
+		if servers
+			.iter()
+			.map(|server| server.as_str())
+			.ne(expected)
+		{
+			return Err!("unexpected presence servers: {servers:?}");
+		}
+
+		for (server, federates) in
+			[("one.example", true), ("two.example", true), ("three.example", false)]
+		{
+			let server = ServerName::parse(server)?;
+			if services
+				.presence
+				.presence_federates_to(&server, &alice)
+				.await != federates
+			{
+				return Err!("presence to {server} should federate: {federates}");
+			}
+		}
+
+		Ok(())
+	})
+}
+
+async fn join(services: &Services, user_id: &UserId, room_id: &RoomId) -> Result {
+	let count = PduCount::Normal(*services.globals.next_count());
+
+	services
+		.state_cache
+		.update_membership(
+			room_id,
+			user_id,
+			RoomMemberEventContent::new(MembershipState::Join),
+			user_id,
+			None,
+			None,
+			true,
+			count,
+		)
+		.await
+}
+
+fn content(state: PresenceState, last_active_ago: UInt) -> PresenceEventContent {
+	let mut content = PresenceEventContent::new(state);
+	content.currently_active = Some(false);
+	content.last_active_ago = Some(last_active_ago);
+	content
+}
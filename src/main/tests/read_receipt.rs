@@ -0,0 +1,125 @@
+#![cfg(test)]
+
+mod common;
+
+use std::collections::BTreeMap;
+
+use tuwunel_core::{
+	Err, Result,
+	ruma::{
+		OwnedRoomId, OwnedUserId, RoomId, UserId, event_id,
+		events::receipt::{
+			Receipt, ReceiptEvent, ReceiptEventContent, ReceiptThread, ReceiptType,
+		},
+	},
+	utils::stream::ReadyExt,
+};
+use tuwunel_service::Services;
+
+use self::common::{Options, with_services};
+
+/// Receipts of several rooms are yielded with their own room and only when
+/// newer than `since` in that room.
+#[test]
+fn readreceipts_since_rooms_tags_each_room() -> Result {
+	with_services("since-rooms", Options::default(), async |services| {
+		let rooms: Vec<OwnedRoomId> =
+			["!a:remote.example", "!b:remote.example", "!c:remote.example"]
+				.into_iter()
+				.map(RoomId::parse)
+				.collect::<Result<_, _>>()?;
+
+		let old = UserId::parse("@old:remote.example")?;
+		for room_id in &rooms {
+			update_receipt(services, &old, room_id).await;
+		}
+
+		let since = services.globals.current_count();
+
+		let mut expected = BTreeMap::new();
+		for (i, room_id) in rooms.iter().enumerate() {
+			let user_id = UserId::parse(format!("@user{i}:remote.example"))?;
+			update_receipt(services, &user_id, room_id).await;
+			expected.insert(room_id.clone(), vec![user_id]);
+		}
+
+		let mut received: BTreeMap<OwnedRoomId, Vec<OwnedUserId>> = BTreeMap::new();
+		services
+			.read_receipt
+			.readreceipts_since_rooms(rooms.iter().map(|room_id| &**room_id), since)
+			.ready_for_each(|(room_id, (user_id, ..))| {
+				received
+					.entry(room_id)
+					.or_default()
+					.push(user_id.to_owned());
+			})
+			.await;
+
+		if received != expected {
+			return Err!("unexpected receipts per room: {received:?}");
+		}
+
+		Ok(())
+	})
+}
+
+/// Each room is yielded in the order given with the count of its latest
+/// receipt.
+#[test]
+fn last_receipt_counts_follow_room_order() -> Result {
+	with_services("last-counts", Options::default(), async |services| {
+		let rooms: Vec<OwnedRoomId> =
+			["!a:remote.example", "!b:remote.example", "!c:remote.example"]
+				.into_iter()
+				.map(RoomId::parse)
+				.collect::<Result<_, _>>()?;
+
+		let user_id = UserId::parse("@user:remote.example")?;
+		let mut expected = Vec::new();
+		for room_id in rooms.iter().rev() {
+			update_receipt(services, &user_id, room_id).await;
+			expected.push((room_id.clone(), services.globals.current_count()));
+		}
+
+		expected.reverse();
+
+		let mut received = Vec::new();
+		services
+			.read_receipt
+			.last_receipt_counts(rooms.iter().map(|room_id| &**room_id), None, None)
+			.ready_for_each(|(room_id, count)| received.push((room_id, count)))
+			.await;
+
+		let received = received
+			.into_iter()
+			.map(|(room_id, count)| count.map(|count| (room_id, count)))
+			.collect::<Result<Vec<_>>>()?;
+
+		if received != expected {
+			return Err!("unexpected receipt counts: {received:?}, expected {expected:?}");
+		}
+
+		Ok(())
+	})
+}
+
+async fn update_receipt(services: &Services, user_id: &UserId, room_id: &RoomId) {
+	let receipt = Receipt {
+		ts: None,
+		thread: ReceiptThread::Unthreaded,
+	};
+	let content = ReceiptEventContent(BTreeMap::from_iter([(
+		event_id!("$read:remote.example").to_owned(),
+		BTreeMap::from_iter([(
+			ReceiptType::Read,
+			BTreeMap::from_iter([(user_id.to_owned(), receipt)]),
+		)]),
+	)]));
+
+	let event = ReceiptEvent { content, room_id: room_id.to_owned() };
+
+	services
+		.read_receipt
+		.readreceipt_update(user_id, room_id, &event)
+		.await;
+}
@@ -0,0 +1,74 @@
+#![cfg(test)]
+
+mod common;
+
+use tuwunel_core::{
+	Err, Result,
+	matrix::{Event, pdu::PduBuilder},
+	ruma::{OwnedEventId, RoomId, events::room::message::RoomMessageEventContent},
+};
+use tuwunel_service::Services;
+
+use self::common::{Options, with_services};
+
+/// Appended messages are indexed by word; a search yields those containing
+/// every word of the query, newest first.
+#[test]
+fn search_room_finds_indexed_messages() -> Result {
+	with_services("search-room", Options::default(), async |services| {
+		let room_id = services.admin.get_admin_room().await?;
+
+		let lunch = send(services, &room_id, "Lunch at noon?").await?;
+		send(services, &room_id, "Dinner at eight").await?;
+		let later = send(services, &room_id, "Maybe a late LUNCH instead").await?;
+
+		let found: Vec<_> = services
+			.search
+			.search_room(&room_id, "lunch", 10)
+			.await
+			.into_iter()
+			.map(|(_, pdu)| pdu.event_id().to_owned())
+			.collect();
+
+		if found != [later.clone(), lunch] {
+			return Err!("unexpected results for \"lunch\": {found:?}");
+		}
+
+		let found = services
+			.search
+			.search_room(&room_id, "late lunch", 10)
+			.await;
+
+		let [(_, pdu)] = found.as_slice() else {
+			return Err!("expected one result for \"late lunch\": {found:?}");
+		};
+
+		if pdu.event_id() != &*later {
+			return Err!("unexpected result for \"late lunch\": {}", pdu.event_id());
+		}
+
+		let found = services
+			.search
+			.search_room(&room_id, "breakfast", 10)
+			.await;
+
+		if !found.is_empty() {
+			return Err!("unexpected results for \"breakfast\": {found:?}");
+		}
+
+		Ok(())
+	})
+}
+
+async fn send(services: &Services, room_id: &RoomId, body: &str) -> Result<OwnedEventId> {
+	let state_lock = services.state.mutex.lock(room_id).await;
+	services
+		.timeline
+		.build_and_append_pdu(
+			PduBuilder::timeline(&RoomMessageEventContent::text_plain(body)),
+			&services.globals.server_user,
+			room_id,
+			&state_lock,
+		)
+		.await
+}
@@ -0,0 +1,545 @@
+#![cfg(test)]
+
+mod common;
+
+use std::{
+	io::{ErrorKind, Read},
+	iter::once,
+	net::TcpListener,
+	sync::mpsc,
+	thread,
+	time::Duration,
+};
+
+use tuwunel_core::{
+	Err, Result,
+	matrix::pdu::{MAX_PDU_BYTES, PduBuilder},
+	ruma::{OwnedServerName, events::room::message::RoomMessageEventContent, server_name},
+	utils::{IterStream, stream::ReadyExt},
+};
+use tuwunel_service::{
+	federation::Classification,
+	sending::{Destination, SendingEvent},
+};
+
+use self::common::{Options, respond, run_admin_command, with_services};
+
+/// Acknowledgement from a stand-in endpoint.
+const ACK: &str = "HTTP/1.1 204 No Content\r\ncontent-length: 0\r\n\r\n";
+
+/// Exhausting `sender_max_retries` against a destination gives up on its
+/// transaction and POSTs the destination, the dropped events and the last error
+/// to the configured webhook.
+#[test]
+fn abandoned_transaction_notifies_webhook() -> Result {
+	// A throwaway listener stands in for the operator's alerting endpoint and
+	// hands back the body of the first request it receives.
+	let listener = TcpListener::bind("127.0.0.1:0")?;
+	let port = listener.local_addr()?.port();
+	listener.set_nonblocking(true)?;
+
+	let (sender, receiver) = mpsc::channel();
+	let endpoint = thread::spawn(move || {
+		for _ in 0..3000 {
+			match listener.accept() {
+				| Ok((stream, _)) => {
+					sender
+						.send(respond(stream, |_| ACK.to_owned()))
+						.ok();
+					return;
+				},
+				| Err(e) if e.kind() == ErrorKind::WouldBlock => {
+					thread::sleep(Duration::from_millis(10));
+				},
+				| Err(_) => return,
+			}
+		}
+	});
+
+	let options = [
+		format!("federation_failure_webhook_url=\"http://127.0.0.1:{port}/hook\""),
+		"sender_max_retries=1".to_owned(),
+	];
+
+	let result =
+		with_services("abandoned-webhook", Options::config(&options), async |services| {
+			let room_id = services.admin.get_admin_room().await?;
+			let server_user = &services.globals.server_user;
+
+			let state_lock = services.state.mutex.lock(&room_id).await;
+			let event_id = services
+				.timeline
+				.build_and_append_pdu(
+					PduBuilder::timeline(&RoomMessageEventContent::text_plain("undeliverable")),
+					server_user,
+					&room_id,
+					&state_lock,
+				)
+				.await?;
+			drop(state_lock);
+
+			// Nothing listens on the destination, so its first attempt fails and
+			// the only retry allowed is used up.
+			let server = server_name!("127.0.0.1:1");
+			let pdu_id = services.timeline.get_pdu_id(&event_id).await?;
+			services
+				.sending
+				.send_pdu_servers(once(server).stream(), &pdu_id)
+				.await?;
+
+			let mut request = None;
+			for _ in 0..1000 {
+				if let Ok(received) = receiver.try_recv() {
+					request = Some(received?);
+					break;
+				}
+
+				tokio::time::sleep(Duration::from_millis(10)).await;
+			}
+
+			let Some(request) = request else {
+				return Err!("webhook was not called");
+			};
+
+			if !request.starts_with("POST /hook ") {
+				return Err!("unexpected webhook request: {request}");
+			}
+
+			for expected in [server.as_str(), event_id.as_str()] {
+				if !request.contains(expected) {
+					return Err!("webhook payload is missing {expected:?}: {request}");
+				}
+			}
+
+			Ok(())
+		});
+
+	endpoint.join().ok();
+
+	result
+}
+
+/// A retried appservice transaction keeps its id; new content after delivery
+/// is allocated the next one.
+#[test]
+fn appservice_retry_reuses_txn_id() -> Result {
+	with_services("appservice-txn-id", Options::default(), async |services| {
+		let db = &services.sending.db;
+
+		let first = db.appservice_txn_id("bridge", b"first");
+		let retry = db.appservice_txn_id("bridge", b"first");
+		if retry != first {
+			return Err!("retry allocated txn id {retry} instead of reusing {first}");
+		}
+
+		db.appservice_txn_done("bridge");
+
+		let second = db.appservice_txn_id("bridge", b"second");
+		if second != first.saturating_add(1) {
+			return Err!("expected txn id after {first}, got {second}");
+		}
+
+		db.appservice_txn_done("bridge");
+
+		let repeat = db.appservice_txn_id("bridge", b"second");
+		if repeat == second {
+			return Err!("delivered txn id {second} was reused for a new transaction");
+		}
+
+		let other = db.appservice_txn_id("other", b"first");
+		if other != 1 {
+			return Err!("txn ids are not tracked per appservice: {other}");
+		}
+
+		Ok(())
+	})
+}
+
+/// Abandoned events land in the dead-letter store and requeueing puts them
+/// back into the destination's queue.
+#[test]
+fn abandoned_events_are_dead_lettered() -> Result {
+	with_services("dead-letter", Options::default(), async |services| {
+		let sending = &services.sending;
+		let dest = Destination::Federation(server_name!("remote.example").to_owned());
+		let edu = br#"{"edu_type":"m.typing"}"#;
+
+		let mut key = b"remote.example\xFF".to_vec();
+		key.extend_from_slice(&services.globals.next_count().to_be_bytes());
+		services.db["servercurrentevent_data"].insert(&key, edu);
+
+		sending
+			.db
+			.abandon_active_requests_for(&dest, "remote unreachable")
+			.await;
+
+		if sending
+			.db
+			.active_requests_for(&dest)
+			.ready_any(|_| true)
+			.await
+		{
+			return Err!("abandoned request is still active");
+		}
+
+		let mut letters = Vec::new();
+		sending
+			.list_dead_letters()
+			.ready_for_each(|letter| letters.push(letter))
+			.await;
+
+		let [letter] = letters.as_slice() else {
+			return Err!("expected one dead letter, found {letters:?}");
+		};
+
+		if letter.dest != dest
+			|| letter.event != SendingEvent::Edu(edu.as_slice().into())
+			|| letter.error != "remote unreachable"
+		{
+			return Err!("unexpected dead letter: {letter:?}");
+		}
+
+		sending.requeue_dead_letter(letter.id).await?;
+
+		if sending
+			.list_dead_letters()
+			.ready_any(|_| true)
+			.await
+		{
+			return Err!("requeued dead letter was not removed");
+		}
+
+		// The sender may already have picked the request up again.
+		let requeued = |(queued, _): (Vec<u8>, _)| queued == key;
+		let queued = sending
+			.db
+			.queued_requests(&dest)
+			.ready_any(requeued)
+			.await;
+
+		let active = sending
+			.db
+			.active_requests_for(&dest)
+			.ready_any(requeued)
+			.await;
+
+		if !queued && !active {
+			return Err!("dead letter was not requeued");
+		}
+
+		if sending
+			.requeue_dead_letter(letter.id)
+			.await
+			.is_ok()
+		{
+			return Err!("dead letter was requeued twice");
+		}
+
+		Ok(())
+	})
+}
+
+/// Kicking a backed-off server picks its queued events up for sending right
+/// away.
+#[test]
+fn kick_dispatches_queued_events() -> Result {
+	with_services("kick", Options::default(), async |services| {
+		let sending = &services.sending;
+		let server = server_name!("remote.example");
+		let dest = Destination::Federation(server.to_owned());
+		services
+			.federation
+			.record_failure(server, Classification::Transient);
+
+		// Queue without dispatching, as left behind by a restart or a backoff.
+		let mut keys = Vec::new();
+		for edu in [br#"{"edu_type":"m.typing"}"#.as_slice(), br#"{"edu_type":"m.presence"}"#] {
+			let mut key = b"remote.example\xFF".to_vec();
+			key.extend_from_slice(&services.globals.next_count().to_be_bytes());
+			services.db["servernameevent_data"].insert(&key, edu);
+			keys.push(key);
+		}
+
+		let body = run_admin_command(services, &format!("federation kick {server}")).await?;
+		if !body.contains("Dispatched 2 queued event(s)") {
+			return Err!("unexpected kick output: {body}");
+		}
+
+		for _ in 0..500 {
+			let picked_up = sending
+				.db
+				.active_requests_for(&dest)
+				.ready_any(|(active, _)| keys.contains(&active))
+				.await;
+
+			if picked_up {
+				return Ok(());
+			}
+
+			tokio::time::sleep(Duration::from_millis(10)).await;
+		}
+
+		Err!("no send was attempted after kick")
+	})
+}
+
+/// The queue entry of an undelivered event is reported along with the failed
+/// attempts against its destination and when the next may be made.
+#[test]
+fn queue_show_reports_queued_event() -> Result {
+	with_services("queue-show", Options::default(), async |services| {
+		let room_id = services.admin.get_admin_room().await?;
+		let server_user = &services.globals.server_user;
+
+		let state_lock = services.state.mutex.lock(&room_id).await;
+		let event_id = services
+			.timeline
+			.build_and_append_pdu(
+				PduBuilder::timeline(&RoomMessageEventContent::text_plain("stuck")),
+				server_user,
+				&room_id,
+				&state_lock,
+			)
+			.await?;
+		drop(state_lock);
+
+		let server = server_name!("remote.example");
+		services
+			.federation
+			.record_failure(server, Classification::Transient);
+
+		// Queue without dispatching, as left behind by a backoff.
+		let pdu_id = services.timeline.get_pdu_id(&event_id).await?;
+		let mut queue_id = b"remote.example\xFF".to_vec();
+		queue_id.extend_from_slice(pdu_id.as_ref());
+		services.db["servernameevent_data"].insert(&queue_id, b"".as_slice());
+
+		let body =
+			run_admin_command(services, &format!("federation queue-show {server} {event_id}"))
+				.await?;
+
+		let expected = [
+			format!("Event {event_id} is queued for {server}."),
+			format!("- queue id: {}", queue_id.escape_ascii()),
+			"- retries: 1".to_owned(),
+		];
+
+		if let Some(missing) = expected
+			.iter()
+			.find(|line| !body.contains(line.as_str()))
+		{
+			return Err!("queue-show output is missing {missing:?}: {body}");
+		}
+
+		if !body
+			.lines()
+			.any(|line| line.starts_with("- next attempt: ") && !line.ends_with("now"))
+		{
+			return Err!("queue-show did not report the backoff: {body}");
+		}
+
+		Ok(())
+	})
+}
+
+/// With a relay configured, a transaction for one server is delivered to the
+/// relay instead.
+#[test]
+fn relay_receives_transactions_for_other_destinations() -> Result {
+	// A throwaway listener stands in for the relay and hands back the first
+	// bytes of the first connection it receives: the TLS handshake opening the
+	// relayed transaction.
+	let listener = TcpListener::bind("127.0.0.1:0")?;
+	let port = listener.local_addr()?.port();
+	listener.set_nonblocking(true)?;
+
+	let (sender, receiver) = mpsc::channel();
+	let relay = thread::spawn(move || {
+		for _ in 0..3000 {
+			match listener.accept() {
+				| Ok((mut stream, _)) => {
+					let mut buf = [0_u8; 1];
+					let read = stream
+						.set_nonblocking(false)
+						.and_then(|()| stream.set_read_timeout(Some(Duration::from_secs(10))))
+						.and_then(|()| stream.read_exact(&mut buf));
+
+					sender.send(read.map(|()| buf[0])).ok();
+					return;
+				},
+				| Err(e) if e.kind() == ErrorKind::WouldBlock => {
+					thread::sleep(Duration::from_millis(10));
+				},
+				| Err(_) => return,
+			}
+		}
+	});
+
+	let options = [
+		format!("federation_relay_server=\"127.0.0.1:{port}\""),
+		"ip_range_denylist=[]".to_owned(),
+	];
+
+	let result = with_services("relay", Options::config(&options), async |services| {
+		// Nothing resolves for the nominal destination, so only the relay
+		// can be reached.
+		let server = server_name!("remote.invalid");
+		let edu = br#"{"edu_type":"m.typing"}"#;
+		services
+			.sending
+			.send_edu_server(server, edu.as_slice().into())?;
+
+		for _ in 0..1000 {
+			if let Ok(received) = receiver.try_recv() {
+				// A TLS handshake record opens the connection.
+				return match received? {
+					| 0x16 => Ok(()),
+					| byte => Err!("relay received {byte:#04x} instead of a TLS handshake"),
+				};
+			}
+
+			tokio::time::sleep(Duration::from_millis(10)).await;
+		}
+
+		Err!("no transaction for {server} reached the relay")
+	});
+
+	relay.join().ok();
+
+	result
+}
+
+/// A PDU too large for any transaction is dead-lettered instead of being
+/// retried, so the destination's queue moves past it.
+#[test]
+fn oversized_pdu_is_dead_lettered() -> Result {
+	with_services("oversized-pdu", Options::default(), async |services| {
+		let sending = &services.sending;
+		let room_id = services.admin.get_admin_room().await?;
+		let server_user = &services.globals.server_user;
+
+		let body = "x".repeat(MAX_PDU_BYTES);
+		let state_lock = services.state.mutex.lock(&room_id).await;
+		let event_id = services
+			.timeline
+			.build_and_append_pdu(
+				PduBuilder::timeline(&RoomMessageEventContent::text_plain(body)),
+				server_user,
+				&room_id,
+				&state_lock,
+			)
+			.await?;
+		drop(state_lock);
+
+		let pdu_id = services.timeline.get_pdu_id(&event_id).await?;
+		let server = server_name!("remote.example");
+		let dest = Destination::Federation(server.to_owned());
+		sending
+			.send_pdu_servers(once(server).stream(), &pdu_id)
+			.await?;
+
+		let oversized = SendingEvent::Pdu(pdu_id);
+		for _ in 0..500 {
+			let dead_lettered = sending
+				.list_dead_letters()
+				.ready_any(|letter| letter.dest == dest && letter.event == oversized)
+				.await;
+
+			let pending = sending
+				.db
+				.active_requests_for(&dest)
+				.ready_any(|_| true)
+				.await || sending
+				.db
+				.queued_requests(&dest)
+				.ready_any(|_| true)
+				.await;
+
+			if dead_lettered && !pending {
+				return Ok(());
+			}
+
+			tokio::time::sleep(Duration::from_millis(10)).await;
+		}
+
+		Err!("oversized PDU was not dead-lettered")
+	})
+}
+
+/// Events queued for a destination which never answers go out at most
+/// `federation_max_inflight_per_dest` transactions at a time; the rest stay
+/// queued behind them.
+#[test]
+fn inflight_transactions_are_capped_per_destination() -> Result {
+	const MAX_INFLIGHT: usize = 3;
+	const EVENTS: usize = 8;
+
+	// Connections wait in the backlog of a listener which never accepts them,
+	// so no transaction to it returns while the test runs.
+	let listener = TcpListener::bind("127.0.0.1:0")?;
+	let server =
+		OwnedServerName::try_from(format!("127.0.0.1:{}", listener.local_addr()?.port()))?;
+
+	let options = [
+		format!("federation_max_inflight_per_dest={MAX_INFLIGHT}"),
+		"ip_range_denylist=[]".to_owned(),
+	];
+
+	let result = with_services("inflight-cap", Options::config(&options), async |services| {
+		let sending = &services.sending;
+		let room_id = services.admin.get_admin_room().await?;
+		let server_user = &services.globals.server_user;
+		let dest = Destination::Federation(server.clone());
+
+		// Each event is dispatched on its own, asking for a transaction each.
+		for i in 0..EVENTS {
+			let state_lock = services.state.mutex.lock(&room_id).await;
+			let event_id = services
+				.timeline
+				.build_and_append_pdu(
+					PduBuilder::timeline(&RoomMessageEventContent::text_plain(format!("{i}"))),
+					server_user,
+					&room_id,
+					&state_lock,
+				)
+				.await?;
+			drop(state_lock);
+
+			let pdu_id = services.timeline.get_pdu_id(&event_id).await?;
+			sending
+				.send_pdu_servers(once(&*server).stream(), &pdu_id)
+				.await?;
+		}
+
+		let (mut active, mut queued) = (0, 0);
+		for _ in 0..500 {
+			active = sending
+				.db
+				.active_requests_for(&dest)
+				.ready_fold(0_usize, |count, _| count.saturating_add(1))
+				.await;
+
+			queued = sending
+				.db
+				.queued_requests(&dest)
+				.ready_fold(0_usize, |count, _| count.saturating_add(1))
+				.await;
+
+			if active > MAX_INFLIGHT {
+				return Err!("{active} transactions in flight to {server}");
+			}
+
+			if active == MAX_INFLIGHT && queued == EVENTS.saturating_sub(MAX_INFLIGHT) {
+				return Ok(());
+			}
+
+			tokio::time::sleep(Duration::from_millis(10)).await;
+		}
+
+		Err!("expected {MAX_INFLIGHT} in flight with the rest queued, found {active} + {queued}")
+	});
+
+	drop(listener);
+
+	result
+}
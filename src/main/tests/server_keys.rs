@@ -0,0 +1,128 @@
+#![cfg(test)]
+
+mod common;
+
+use tuwunel_core::{
+	Err, Result,
+	ruma::{CanonicalJsonObject, CanonicalJsonValue, RoomVersionId},
+};
+use tuwunel_service::{
+	Services,
+	server_keys::{HashCheck, SignatureCheck},
+};
+
+use self::common::{Options, with_services};
+
+#[test]
+fn check_event_reports_signed_event_valid() -> Result {
+	with_services("check-event-valid", Options::default(), async |services| {
+		let mut event = test_event(services);
+		services
+			.server_keys
+			.hash_and_sign_event(&mut event, &RoomVersionId::V11)?;
+
+		let verification = services
+			.server_keys
+			.check_event(&event, &RoomVersionId::V11)
+			.await?;
+
+		if verification.content_hash != HashCheck::Valid {
+			return Err!("content hash of signed event failed: {verification:?}");
+		}
+
+		let server_name = services.globals.server_name().as_str();
+		let ours = |(server, _, check): &(String, String, SignatureCheck)| {
+			server == server_name && *check == SignatureCheck::Valid
+		};
+
+		if verification.signatures.len() != 1 || !verification.signatures.iter().any(ours) {
+			return Err!("expected exactly our valid signature: {verification:?}");
+		}
+
+		Ok(())
+	})
+}
+
+#[test]
+fn check_event_reports_tampering() -> Result {
+	with_services("check-event-tampered", Options::default(), async |services| {
+		let mut event = test_event(services);
+		services
+			.server_keys
+			.hash_and_sign_event(&mut event, &RoomVersionId::V11)?;
+
+		// Content is redacted away before signing, so altering it only breaks the
+		// content hash.
+		let mut content = event.clone();
+		content.insert("content".into(), object([("body", string("tampered"))]));
+
+		let verification = services
+			.server_keys
+			.check_event(&content, &RoomVersionId::V11)
+			.await?;
+
+		if verification.content_hash != HashCheck::Mismatch {
+			return Err!("tampered content was not detected: {verification:?}");
+		}
+
+		if verification
+			.signatures
+			.iter()
+			.any(|(.., check)| *check != SignatureCheck::Valid)
+		{
+			return Err!("signature should survive a content change: {verification:?}");
+		}
+
+		// The sender survives redaction, so altering it breaks the signature too.
+		let mut sender = event.clone();
+		sender.insert("sender".into(), string("@mallory:example.com"));
+
+		let verification = services
+			.server_keys
+			.check_event(&sender, &RoomVersionId::V11)
+			.await?;
+
+		if verification.content_hash != HashCheck::Mismatch {
+			return Err!("tampered sender was not detected by hash: {verification:?}");
+		}
+
+		if verification
+			.signatures
+			.iter()
+			.any(|(.., check)| *check != SignatureCheck::Invalid)
+		{
+			return Err!("tampered sender was not detected by signature: {verification:?}");
+		}
+
+		Ok(())
+	})
+}
+
+fn test_event(services: &Services) -> CanonicalJsonObject {
+	let server_name = services.globals.server_name();
+
+	[
+		("auth_events", CanonicalJsonValue::Array(Vec::new())),
+		("content", object([("body", string("hello"))])),
+		("depth", CanonicalJsonValue::Integer(1_u32.into())),
+		("origin_server_ts", CanonicalJsonValue::Integer(1_u32.into())),
+		("prev_events", CanonicalJsonValue::Array(Vec::new())),
+		("room_id", string(&format!("!room:{server_name}"))),
+		("sender", string(&format!("@alice:{server_name}"))),
+		("type", string("m.room.message")),
+	]
+	.into_iter()
+	.map(|(key, value)| (key.to_owned(), value))
+	.collect()
+}
+
+fn object<const N: usize>(entries: [(&str, CanonicalJsonValue); N]) -> CanonicalJsonValue {
+	CanonicalJsonValue::Object(
+		entries
+			.into_iter()
+			.map(|(key, value)| (key.to_owned(), value))
+			.collect(),
+	)
+}
+
+fn string(value: &str) -> CanonicalJsonValue { CanonicalJsonValue::String(value.to_owned()) }
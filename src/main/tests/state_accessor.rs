@@ -0,0 +1,424 @@
+#![cfg(test)]
+
+mod common;
+
+use tuwunel_core::{
+	Err, Result,
+	matrix::{Event, pdu::PduBuilder},
+	ruma::{
+		OwnedEventId, OwnedRoomId, RoomId, RoomVersionId, UserId,
+		events::{
+			StateEventType,
+			room::{
+				create::RoomCreateEventContent,
+				guest_access::{GuestAccess, RoomGuestAccessEventContent},
+				history_visibility::{HistoryVisibility, RoomHistoryVisibilityEventContent},
+				member::{MembershipState, RoomMemberEventContent},
+				message::RoomMessageEventContent,
+				power_levels::RoomPowerLevelsEventContent,
+			},
+		},
+		int,
+		room_version_rules::AuthorizationRules,
+	},
+	utils::stream::ReadyExt,
+};
+use tuwunel_service::{Services, rooms::state_accessor::VisibilityFlags};
+
+use self::common::{Options, with_services};
+
+#[test]
+fn visibility_flags_many_reports_each_room() -> Result {
+	with_services("visibility-flags-many", Options::default(), async |services| {
+		let public =
+			create_room(services, HistoryVisibility::WorldReadable, GuestAccess::CanJoin).await?;
+		let private =
+			create_room(services, HistoryVisibility::Joined, GuestAccess::Forbidden).await?;
+
+		let mut flags = Vec::new();
+		services
+			.state_accessor
+			.visibility_flags_many([&*public, &*private])
+			.ready_for_each(|item| flags.push(item))
+			.await;
+
+		flags.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+		let mut expected = vec![
+			(public, VisibilityFlags {
+				world_readable: true,
+				guest_can_join: true,
+				history_visibility: HistoryVisibility::WorldReadable,
+			}),
+			(private, VisibilityFlags {
+				world_readable: false,
+				guest_can_join: false,
+				history_visibility: HistoryVisibility::Joined,
+			}),
+		];
+
+		expected.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+		if flags != expected {
+			return Err!("unexpected visibility flags: {flags:?}");
+		}
+
+		Ok(())
+	})
+}
+
+#[test]
+fn joined_member_can_see_event() -> Result {
+	with_services("can-see-joined", Options::default(), async |services| {
+		let room_id =
+			create_room(services, HistoryVisibility::Joined, GuestAccess::Forbidden).await?;
+		let server_user = &services.globals.server_user;
+		let stranger = UserId::parse_with_server_name("bob", services.globals.server_name())?;
+		let event_id = send_message(services, &room_id).await?;
+
+		let state_accessor = &services.state_accessor;
+		if !state_accessor
+			.user_can_see_event(server_user, &room_id, &event_id)
+			.await
+		{
+			return Err!("joined member cannot see the event");
+		}
+
+		if state_accessor
+			.user_can_see_event(&stranger, &room_id, &event_id)
+			.await
+		{
+			return Err!("non-member can see an event in a joined-only room");
+		}
+
+		Ok(())
+	})
+}
+
+#[test]
+fn anyone_can_see_world_readable_event() -> Result {
+	with_services("can-see-world-readable", Options::default(), async |services| {
+		let room_id =
+			create_room(services, HistoryVisibility::WorldReadable, GuestAccess::Forbidden)
+				.await?;
+		let stranger = UserId::parse_with_server_name("bob", services.globals.server_name())?;
+		let event_id = send_message(services, &room_id).await?;
+
+		if !services
+			.state_accessor
+			.user_can_see_event(&stranger, &room_id, &event_id)
+			.await
+		{
+			return Err!("non-member cannot see a world-readable event");
+		}
+
+		Ok(())
+	})
+}
+
+#[test]
+fn invited_member_sees_events_since_invite() -> Result {
+	with_services("can-see-invited", Options::default(), async |services| {
+		let room_id =
+			create_room(services, HistoryVisibility::Invited, GuestAccess::Forbidden).await?;
+		let server_user = &services.globals.server_user;
+		let invitee = UserId::parse_with_server_name("bob", services.globals.server_name())?;
+		let before = send_message(services, &room_id).await?;
+
+		let state_lock = services.state.mutex.lock(&room_id).await;
+		services
+			.timeline
+			.build_and_append_pdu(
+				PduBuilder::state(
+					invitee.to_string(),
+					&RoomMemberEventContent::new(MembershipState::Invite),
+				),
+				server_user,
+				&room_id,
+				&state_lock,
+			)
+			.await?;
+		drop(state_lock);
+
+		let after = send_message(services, &room_id).await?;
+
+		let state_accessor = &services.state_accessor;
+		if state_accessor
+			.user_can_see_event(&invitee, &room_id, &before)
+			.await
+		{
+			return Err!("invitee can see an event from before the invite");
+		}
+
+		if !state_accessor
+			.user_can_see_event(&invitee, &room_id, &after)
+			.await
+		{
+			return Err!("invitee cannot see an event sent while invited");
+		}
+
+		Ok(())
+	})
+}
+
+/// Events resolve to the history visibility in effect where they were sent,
+/// not the room's current one.
+#[test]
+fn history_visibility_at_follows_changes() -> Result {
+	with_services("history-visibility-at", Options::default(), async |services| {
+		let room_id =
+			create_room(services, HistoryVisibility::Shared, GuestAccess::Forbidden).await?;
+		let before = send_message(services, &room_id).await?;
+
+		let state_lock = services.state.mutex.lock(&room_id).await;
+		services
+			.timeline
+			.build_and_append_pdu(
+				PduBuilder::state(
+					String::new(),
+					&RoomHistoryVisibilityEventContent::new(HistoryVisibility::Joined),
+				),
+				&services.globals.server_user,
+				&room_id,
+				&state_lock,
+			)
+			.await?;
+		drop(state_lock);
+
+		let after = send_message(services, &room_id).await?;
+
+		let state_accessor = &services.state_accessor;
+		let visibility = state_accessor
+			.history_visibility_at(&room_id, &before)
+			.await;
+		if visibility != HistoryVisibility::Shared {
+			return Err!("event before the change resolved to {visibility:?}");
+		}
+
+		let visibility = state_accessor
+			.history_visibility_at(&room_id, &after)
+			.await;
+		if visibility != HistoryVisibility::Joined {
+			return Err!("event after the change resolved to {visibility:?}");
+		}
+
+		Ok(())
+	})
+}
+
+/// The current state map of a freshly created room holds its initial state.
+#[test]
+fn current_state_map_holds_initial_state() -> Result {
+	with_services("current-state-map", Options::default(), async |services| {
+		let room_id = services.admin.get_admin_room().await?;
+		let state = services
+			.state_accessor
+			.current_state_map(&room_id)
+			.await?;
+
+		let server_user = services.globals.server_user.as_str();
+		for (kind, state_key) in [
+			(StateEventType::RoomCreate, ""),
+			(StateEventType::RoomPowerLevels, ""),
+			(StateEventType::RoomJoinRules, ""),
+			(StateEventType::RoomMember, server_user),
+		] {
+			let Some(pdu) = state.get(&(kind.clone(), state_key.into())) else {
+				return Err!("state map is missing ({kind}, {state_key:?})");
+			};
+
+			if pdu.kind().to_cow_str() != kind.to_cow_str() || *pdu.room_id() != *room_id {
+				return Err!("({kind}, {state_key:?}) maps to the wrong event: {pdu:?}");
+			}
+		}
+
+		Ok(())
+	})
+}
+
+/// Batched member lookups answer each user in the order asked, with the
+/// content of their current member event.
+#[test]
+fn get_members_returns_content_in_order() -> Result {
+	with_services("get-members", Options::default(), async |services| {
+		let room_id =
+			create_room(services, HistoryVisibility::Joined, GuestAccess::Forbidden).await?;
+		let server_name = services.globals.server_name();
+		let server_user = &services.globals.server_user;
+		let bob = UserId::parse_with_server_name("bob", server_name)?;
+		let carol = UserId::parse_with_server_name("carol", server_name)?;
+		let stranger = UserId::parse_with_server_name("dave", server_name)?;
+
+		let state_lock = services.state.mutex.lock(&room_id).await;
+		for (user_id, displayname) in [(&bob, "Bob"), (&carol, "Carol")] {
+			services
+				.timeline
+				.build_and_append_pdu(
+					PduBuilder::state(user_id.to_string(), &RoomMemberEventContent {
+						displayname: Some(displayname.to_owned()),
+						..RoomMemberEventContent::new(MembershipState::Invite)
+					}),
+					server_user,
+					&room_id,
+					&state_lock,
+				)
+				.await?;
+		}
+		drop(state_lock);
+
+		let requested = [&carol, server_user, &bob, &stranger];
+		let mut members = Vec::new();
+		services
+			.state_accessor
+			.get_members(&room_id, requested.iter().map(|user_id| &***user_id))
+			.ready_for_each(|member| members.push(member))
+			.await;
+
+		let [
+			(first, carol_content),
+			(second, server_content),
+			(third, bob_content),
+			(fourth, missing),
+		] = members.as_slice()
+		else {
+			return Err!("expected four members, got {members:?}");
+		};
+
+		if [first, second, third, fourth] != requested {
+			return Err!("members out of order: {members:?}");
+		}
+
+		let (Ok(carol_content), Ok(bob_content), Ok(server_content)) =
+			(carol_content, bob_content, server_content)
+		else {
+			return Err!("member content is missing: {members:?}");
+		};
+
+		if carol_content.displayname.as_deref() != Some("Carol")
+			|| bob_content.displayname.as_deref() != Some("Bob")
+			|| carol_content.membership != MembershipState::Invite
+		{
+			return Err!("unexpected invitee content: {carol_content:?} {bob_content:?}");
+		}
+
+		if server_content.membership != MembershipState::Join {
+			return Err!("unexpected server user content: {server_content:?}");
+		}
+
+		if missing.is_ok() {
+			return Err!("stranger has member content: {missing:?}");
+		}
+
+		Ok(())
+	})
+}
+
+/// Power levels are served from the cache while the room's state is unchanged
+/// and recomputed once it changes.
+#[test]
+fn power_levels_cached_until_state_changes() -> Result {
+	with_services("power-levels-cache", Options::default(), async |services| {
+		let room_id =
+			create_room(services, HistoryVisibility::Joined, GuestAccess::Forbidden).await?;
+		let state_accessor = &services.state_accessor;
+		let usage = || {
+			let (_, hits, misses) = state_accessor.get_power_levels_cache_usage();
+			(hits, misses)
+		};
+
+		state_accessor.get_power_levels(&room_id).await?;
+		let (hits, misses) = usage();
+		state_accessor.get_power_levels(&room_id).await?;
+		if usage() != (hits.saturating_add(1), misses) {
+			return Err!("unchanged state missed the cache: {:?}", usage());
+		}
+
+		let server_user = &services.globals.server_user;
+		let mut content = RoomPowerLevelsEventContent::new(&AuthorizationRules::V6);
+		content
+			.users
+			.insert(server_user.clone(), int!(100));
+		content.users_default = int!(5);
+
+		let state_lock = services.state.mutex.lock(&room_id).await;
+		services
+			.timeline
+			.build_and_append_pdu(
+				PduBuilder::state(String::new(), &content),
+				server_user,
+				&room_id,
+				&state_lock,
+			)
+			.await?;
+		drop(state_lock);
+
+		let (hits, misses) = usage();
+		let power_levels = state_accessor.get_power_levels(&room_id).await?;
+		if usage() != (hits, misses.saturating_add(1)) {
+			return Err!("changed state hit the cache: {:?}", usage());
+		}
+
+		if power_levels.users_default != int!(5) {
+			return Err!("stale power levels returned: {power_levels:?}");
+		}
+
+		Ok(())
+	})
+}
+
+/// Send a message to the room as the server user.
+async fn send_message(services: &Services, room_id: &RoomId) -> Result<OwnedEventId> {
+	let state_lock = services.state.mutex.lock(room_id).await;
+
+	services
+		.timeline
+		.build_and_append_pdu(
+			PduBuilder::timeline(&RoomMessageEventContent::text_plain("visible?")),
+			&services.globals.server_user,
+			room_id,
+			&state_lock,
+		)
+		.await
+}
+
+/// Create a local room owned by the server user with the given history
+/// visibility and guest access.
+async fn create_room(
+	services: &Services,
+	history_visibility: HistoryVisibility,
+	guest_access: GuestAccess,
+) -> Result<OwnedRoomId> {
+	let room_id = RoomId::new_v1(services.globals.server_name());
+	let server_user = &services.globals.server_user;
+
+	services
+		.short
+		.get_or_create_shortroomid(&room_id)
+		.await;
+
+	let state_lock = services.state.mutex.lock(&room_id).await;
+	let events = [
+		PduBuilder::state(String::new(), &RoomCreateEventContent {
+			room_version: RoomVersionId::V11,
+			..RoomCreateEventContent::new_v11()
+		}),
+		PduBuilder::state(
+			server_user.to_string(),
+			&RoomMemberEventContent::new(MembershipState::Join),
+		),
+		PduBuilder::state(
+			String::new(),
+			&RoomHistoryVisibilityEventContent::new(history_visibility),
+		),
+		PduBuilder::state(String::new(), &RoomGuestAccessEventContent::new(guest_access)),
+	];
+
+	for event in events {
+		services
+			.timeline
+			.build_and_append_pdu(event, server_user, &room_id, &state_lock)
+			.await?;
+	}
+
+	Ok(room_id)
+}
@@ -0,0 +1,897 @@
+#![cfg(test)]
+
+mod common;
+
+use tuwunel_core::{
+	Err, Result,
+	matrix::{PduCount, pdu::PduBuilder},
+	ruma::{
+		OwnedRoomId, RoomId, RoomVersionId, ServerName, UserId,
+		events::room::{
+			create::RoomCreateEventContent,
+			member::{MembershipState, RoomMemberEventContent},
+		},
+	},
+	utils::stream::ReadyExt,
+};
+use tuwunel_service::{
+	Services,
+	sending::{Destination, SendingEvent},
+	users::Register,
+};
+
+use self::common::{Options, with_services};
+
+#[test]
+fn is_joined_any_matches_one_of_many() -> Result {
+	with_services("is-joined-any", Options::default(), async |services| {
+		let server_name = services.globals.server_name();
+		let user_id = UserId::parse_with_server_name("alice", server_name)?;
+		let rooms = test_rooms(services, &["a", "b", "c"])?;
+		let others = test_rooms(services, &["x", "y", "z"])?;
+
+		update_membership(services, &user_id, &rooms[1], MembershipState::Join).await?;
+
+		if !services
+			.state_cache
+			.is_joined_any(&user_id, rooms.iter().map(|room_id| &**room_id))
+			.await
+		{
+			return Err!("user joined to one of the rooms was not matched");
+		}
+
+		if services
+			.state_cache
+			.is_joined_any(&user_id, others.iter().map(|room_id| &**room_id))
+			.await
+		{
+			return Err!("user joined to none of the rooms was matched");
+		}
+
+		Ok(())
+	})
+}
+
+#[test]
+fn are_joined_preserves_input_order() -> Result {
+	with_services("are-joined", Options::default(), async |services| {
+		let server_name = services.globals.server_name();
+		let alice = UserId::parse_with_server_name("alice", server_name)?;
+		let bob = UserId::parse_with_server_name("bob", server_name)?;
+		let rooms = test_rooms(services, &["a", "b", "c"])?;
+
+		update_membership(services, &alice, &rooms[0], MembershipState::Join).await?;
+		update_membership(services, &alice, &rooms[2], MembershipState::Join).await?;
+		update_membership(services, &bob, &rooms[1], MembershipState::Join).await?;
+		update_membership(services, &bob, &rooms[2], MembershipState::Leave).await?;
+
+		let pairs = [
+			(&*alice, &*rooms[0]),
+			(&*bob, &*rooms[0]),
+			(&*alice, &*rooms[1]),
+			(&*bob, &*rooms[1]),
+			(&*alice, &*rooms[2]),
+			(&*bob, &*rooms[2]),
+		];
+
+		let mut results = Vec::new();
+		services
+			.state_cache
+			.are_joined(pairs.iter().copied())
+			.ready_for_each(|result| results.push(result))
+			.await;
+
+		let expected: Vec<_> = pairs
+			.iter()
+			.copied()
+			.zip([true, false, false, true, true, false])
+			.collect();
+
+		if results != expected {
+			return Err!("unexpected batched membership results: {results:?}");
+		}
+
+		Ok(())
+	})
+}
+
+#[test]
+fn local_users_count_invalidated_on_leave() -> Result {
+	with_services("local-users-count", Options::default(), async |services| {
+		let server_name = services.globals.server_name();
+		let alice = UserId::parse_with_server_name("alice", server_name)?;
+		let bob = UserId::parse_with_server_name("bob", server_name)?;
+		let remote = UserId::parse("@carol:remote.example")?;
+		let rooms = test_rooms(services, &["a"])?;
+		let room_id = &rooms[0];
+
+		for user_id in [&alice, &bob, &remote] {
+			update_membership(services, user_id, room_id, MembershipState::Join).await?;
+		}
+
+		let count = services
+			.state_cache
+			.local_users_count(room_id)
+			.await;
+		if count != 2 {
+			return Err!("expected 2 local users before leave, counted {count}");
+		}
+
+		update_membership(services, &bob, room_id, MembershipState::Leave).await?;
+
+		let count = services
+			.state_cache
+			.local_users_count(room_id)
+			.await;
+		if count != 1 {
+			return Err!("expected 1 local user after leave, counted {count}");
+		}
+
+		Ok(())
+	})
+}
+
+#[test]
+fn rooms_joined_since_returns_newer_joins() -> Result {
+	with_services("rooms-joined-since", Options::default(), async |services| {
+		let server_name = services.globals.server_name();
+		let alice = UserId::parse_with_server_name("alice", server_name)?;
+		let rooms = test_rooms(services, &["a", "b", "c"])?;
+
+		update_membership(services, &alice, &rooms[0], MembershipState::Join).await?;
+		let since = services.globals.current_count();
+
+		update_membership(services, &alice, &rooms[1], MembershipState::Join).await?;
+		update_membership(services, &alice, &rooms[2], MembershipState::Join).await?;
+
+		let mut joined: Vec<OwnedRoomId> = Vec::new();
+		services
+			.state_cache
+			.rooms_joined_since(&alice, since)
+			.ready_for_each(|room_id| joined.push(room_id.to_owned()))
+			.await;
+
+		joined.sort();
+		if joined != rooms[1..] {
+			return Err!("expected only the newer joins, got {joined:?}");
+		}
+
+		Ok(())
+	})
+}
+
+#[test]
+fn rooms_invited_changed_yields_only_window() -> Result {
+	with_services("rooms-invited-changed", Options::default(), async |services| {
+		let server_name = services.globals.server_name();
+		let alice = UserId::parse_with_server_name("alice", server_name)?;
+		let rooms = test_rooms(services, &["a", "b", "c", "d"])?;
+
+		update_membership(services, &alice, &rooms[0], MembershipState::Invite).await?;
+		let since = services.globals.current_count();
+
+		update_membership(services, &alice, &rooms[1], MembershipState::Invite).await?;
+		update_membership(services, &alice, &rooms[2], MembershipState::Invite).await?;
+		let until = services.globals.current_count();
+
+		update_membership(services, &alice, &rooms[3], MembershipState::Invite).await?;
+
+		let mut changed = Vec::new();
+		services
+			.state_cache
+			.rooms_invited_changed(&alice, since, until)
+			.ready_for_each(|item| changed.push(item))
+			.await;
+
+		changed.sort_by(|(a, _), (b, _)| a.cmp(b));
+		let invited: Vec<OwnedRoomId> = changed
+			.iter()
+			.map(|(room_id, _)| room_id.clone())
+			.collect();
+
+		if invited != rooms[1..3] {
+			return Err!("expected only invites within the window, got {invited:?}");
+		}
+
+		for (room_id, state) in &changed {
+			let stored = services
+				.state_cache
+				.invite_state(&alice, room_id)
+				.await?;
+
+			if state.len() != stored.len() {
+				return Err!("invite state of {room_id} differs from the stored state");
+			}
+		}
+
+		Ok(())
+	})
+}
+
+#[test]
+fn rooms_knocked_since_returns_window() -> Result {
+	with_services("rooms-knocked-since", Options::default(), async |services| {
+		let server_name = services.globals.server_name();
+		let alice = UserId::parse_with_server_name("alice", server_name)?;
+		let rooms = test_rooms(services, &["a", "b", "c", "d"])?;
+
+		update_membership(services, &alice, &rooms[0], MembershipState::Knock).await?;
+		let since = services.globals.current_count();
+
+		update_membership(services, &alice, &rooms[1], MembershipState::Knock).await?;
+		update_membership(services, &alice, &rooms[2], MembershipState::Knock).await?;
+		let to = services.globals.current_count();
+
+		update_membership(services, &alice, &rooms[3], MembershipState::Knock).await?;
+
+		let mut knocked: Vec<OwnedRoomId> = Vec::new();
+		services
+			.state_cache
+			.rooms_knocked_since(&alice, since, to)
+			.ready_for_each(|(room_id, _)| knocked.push(room_id))
+			.await;
+
+		knocked.sort();
+		if knocked != rooms[1..3] {
+			return Err!("expected only knocks within the window, got {knocked:?}");
+		}
+
+		Ok(())
+	})
+}
+
+#[test]
+fn membership_delta_reflects_net_changes() -> Result {
+	with_services("membership-delta", Options::default(), async |services| {
+		let server_name = services.globals.server_name();
+		let alice = UserId::parse_with_server_name("alice", server_name)?;
+		let rooms = test_rooms(services, &["a", "b", "c", "d"])?;
+
+		update_membership(services, &alice, &rooms[0], MembershipState::Join).await?;
+		update_membership(services, &alice, &rooms[1], MembershipState::Join).await?;
+		let since = services.globals.current_count();
+
+		// Joined then left inside the window: only the leave remains.
+		update_membership(services, &alice, &rooms[1], MembershipState::Leave).await?;
+		update_membership(services, &alice, &rooms[2], MembershipState::Join).await?;
+		update_membership(services, &alice, &rooms[3], MembershipState::Join).await?;
+		update_membership(services, &alice, &rooms[3], MembershipState::Leave).await?;
+		let until = services.globals.current_count();
+
+		// A later change moves the room out of the window.
+		update_membership(services, &alice, &rooms[2], MembershipState::Leave).await?;
+		let later = services.globals.current_count();
+
+		let mut delta = services
+			.state_cache
+			.membership_delta(&alice, since, until)
+			.await;
+
+		delta.sort_by(|(a, _), (b, _)| a.cmp(b));
+		let expected = vec![
+			(rooms[1].clone(), MembershipState::Leave),
+			(rooms[3].clone(), MembershipState::Leave),
+		];
+
+		if delta != expected {
+			return Err!("unexpected delta within the window: {delta:?}");
+		}
+
+		let mut delta = services
+			.state_cache
+			.membership_delta(&alice, until, later)
+			.await;
+		if delta != [(rooms[2].clone(), MembershipState::Leave)] {
+			return Err!("unexpected delta after the window: {delta:?}");
+		}
+
+		Ok(())
+	})
+}
+
+#[test]
+fn server_delta_reports_joined_and_departed_servers() -> Result {
+	with_services("server-delta", Options::default(), async |services| {
+		let server_name = services.globals.server_name();
+		let alice = UserId::parse_with_server_name("alice", server_name)?;
+		let carol = UserId::parse("@carol:remote.example")?;
+		let dave = UserId::parse("@dave:departed.example")?;
+		let rooms = test_rooms(services, &["a"])?;
+		let room_id = &rooms[0];
+
+		for user_id in [&alice, &dave] {
+			update_membership(services, user_id, room_id, MembershipState::Join).await?;
+		}
+
+		let since = services.globals.current_count();
+		update_membership(services, &carol, room_id, MembershipState::Join).await?;
+		update_membership(services, &dave, room_id, MembershipState::Leave).await?;
+
+		let (added, removed) = services
+			.state_cache
+			.server_delta(room_id, since)
+			.await;
+
+		if added != [carol.server_name().to_owned()] {
+			return Err!("expected the new server to be added, got {added:?}");
+		}
+
+		if removed != [dave.server_name().to_owned()] {
+			return Err!("expected the departed server to be removed, got {removed:?}");
+		}
+
+		let (added, removed) = services
+			.state_cache
+			.server_delta(room_id, services.globals.current_count())
+			.await;
+
+		if !added.is_empty() || !removed.is_empty() {
+			return Err!("no servers changed since now: {added:?} {removed:?}");
+		}
+
+		Ok(())
+	})
+}
+
+#[test]
+fn users_visible_to_server_counts_distinct_local_users() -> Result {
+	with_services("users-visible", Options::default(), async |services| {
+		let server_name = services.globals.server_name();
+		let alice = UserId::parse_with_server_name("alice", server_name)?;
+		let bob = UserId::parse_with_server_name("bob", server_name)?;
+		let carol = UserId::parse("@carol:remote.example")?;
+		let rooms = test_rooms(services, &["a", "b", "c"])?;
+
+		// alice shares both rooms a and b with the remote; bob only room b.
+		for user_id in [&alice, &carol] {
+			update_membership(services, user_id, &rooms[0], MembershipState::Join).await?;
+		}
+
+		for user_id in [&alice, &bob, &carol] {
+			update_membership(services, user_id, &rooms[1], MembershipState::Join).await?;
+		}
+
+		// A local user in a room the remote is not in stays unseen.
+		let dan = UserId::parse_with_server_name("dan", server_name)?;
+		update_membership(services, &dan, &rooms[2], MembershipState::Join).await?;
+
+		let visible = services
+			.state_cache
+			.users_visible_to_server(carol.server_name())
+			.await;
+
+		if visible != 2 {
+			return Err!("expected 2 local users visible to the remote, got {visible}");
+		}
+
+		let unknown = ServerName::parse("unknown.example")?;
+		let visible = services
+			.state_cache
+			.users_visible_to_server(&unknown)
+			.await;
+
+		if visible != 0 {
+			return Err!("server sharing no rooms sees {visible} users");
+		}
+
+		Ok(())
+	})
+}
+
+#[test]
+fn server_room_consistency_detects_and_repairs() -> Result {
+	with_services("server-room-consistency", Options::default(), async |services| {
+		let server_name = services.globals.server_name();
+		let alice = UserId::parse_with_server_name("alice", server_name)?;
+		let carol = UserId::parse("@carol:remote.example")?;
+		let remote = carol.server_name();
+		let stray = ServerName::parse("stray.example")?;
+		let rooms = test_rooms(services, &["a"])?;
+		let room_id = &*rooms[0];
+
+		for user_id in [&alice, &carol] {
+			update_membership(services, user_id, room_id, MembershipState::Join).await?;
+		}
+
+		let state_cache = &services.state_cache;
+		if !state_cache
+			.verify_server_room_consistency(room_id, false)
+			.await?
+			.is_consistent()
+		{
+			return Err!("fresh server maps reported inconsistent");
+		}
+
+		// Lose the reverse entry of a joined server and gain one for a server
+		// without members.
+		services.db["serverroomids"].del((remote, room_id));
+		services.db["serverroomids"].put_raw((&*stray, room_id), []);
+
+		let report = state_cache
+			.verify_server_room_consistency(room_id, false)
+			.await?;
+
+		if report.missing_reverse != [remote.to_owned()]
+			|| report.missing_forward != [stray.clone()]
+			|| report.repaired
+		{
+			return Err!("mismatches not reported: {report:?}");
+		}
+
+		if state_cache.server_in_room(remote, room_id).await {
+			return Err!("report-only check must not repair");
+		}
+
+		let report = state_cache
+			.verify_server_room_consistency(room_id, true)
+			.await?;
+
+		if !report.repaired {
+			return Err!("repair was not applied: {report:?}");
+		}
+
+		if !state_cache.server_in_room(remote, room_id).await
+			|| state_cache.server_in_room(&stray, room_id).await
+		{
+			return Err!("repair did not follow joined members");
+		}
+
+		if !state_cache
+			.verify_server_room_consistency(room_id, false)
+			.await?
+			.is_consistent()
+		{
+			return Err!("server maps still inconsistent after repair");
+		}
+
+		Ok(())
+	})
+}
+
+#[test]
+fn membership_changes_are_broadcast() -> Result {
+	with_services("membership-broadcast", Options::default(), async |services| {
+		let server_name = services.globals.server_name();
+		let alice = UserId::parse_with_server_name("alice", server_name)?;
+		let rooms = test_rooms(services, &["a"])?;
+		let room_id = &rooms[0];
+
+		let mut receiver = services.state_cache.subscribe_membership();
+
+		update_membership(services, &alice, room_id, MembershipState::Join).await?;
+
+		let Ok(change) = receiver.try_recv() else {
+			return Err!("subscriber did not receive the join");
+		};
+
+		if change != (room_id.clone(), alice.clone(), MembershipState::Join) {
+			return Err!("unexpected membership change: {change:?}");
+		}
+
+		Ok(())
+	})
+}
+
+#[test]
+fn our_real_users_follows_membership() -> Result {
+	with_services("our-real-users", Options::default(), async |services| {
+		let server_name = services.globals.server_name();
+		let alice = UserId::parse_with_server_name("alice", server_name)?;
+		let bob = UserId::parse_with_server_name("bob", server_name)?;
+		let remote = UserId::parse("@carol:remote.example")?;
+		let rooms = test_rooms(services, &["a"])?;
+		let room_id = &rooms[0];
+
+		services
+			.users
+			.full_register(Register {
+				user_id: Some(&alice),
+				password: Some("a-strong-test-password"),
+				..Default::default()
+			})
+			.await?;
+
+		update_membership(services, &remote, room_id, MembershipState::Join).await?;
+
+		let state_cache = &services.state_cache;
+		if !state_cache
+			.our_real_users(room_id)
+			.await
+			.is_empty()
+		{
+			return Err!("room without local members has local users");
+		}
+
+		update_membership(services, &alice, room_id, MembershipState::Join).await?;
+
+		let users = state_cache.our_real_users(room_id).await;
+		if users.len() != 1 || !users.contains(&alice) {
+			return Err!("join was not reflected: {users:?}");
+		}
+
+		update_membership(services, &alice, room_id, MembershipState::Leave).await?;
+
+		let users = state_cache.our_real_users(room_id).await;
+		if !users.is_empty() {
+			return Err!("leave was not reflected: {users:?}");
+		}
+
+		Ok(())
+	})
+}
+
+/// Once the last local user leaves, the room's membership and servers are
+/// dropped while the user's left state remains.
+#[test]
+fn last_local_leave_forgets_room() -> Result {
+	with_services("forget-room", Options::default(), async |services| {
+		let server_name = services.globals.server_name();
+		let alice = UserId::parse_with_server_name("alice", server_name)?;
+		let bob = UserId::parse_with_server_name("bob", server_name)?;
+		let remote = UserId::parse("@carol:remote.example")?;
+		let rooms = test_rooms(services, &["a"])?;
+		let room_id = &rooms[0];
+
+		update_membership(services, &remote, room_id, MembershipState::Join).await?;
+		update_membership(services, &alice, room_id, MembershipState::Join).await?;
+
+		let state_cache = &services.state_cache;
+		if state_cache.maybe_forget_room(room_id).await {
+			return Err!("room was forgotten while a local user is joined");
+		}
+
+		update_membership(services, &bob, room_id, MembershipState::Knock).await?;
+		update_membership(services, &alice, room_id, MembershipState::Leave).await?;
+
+		if state_cache.maybe_forget_room(room_id).await {
+			return Err!("room was forgotten while a local user is knocking");
+		}
+
+		update_membership(services, &bob, room_id, MembershipState::Leave).await?;
+
+		if !state_cache.maybe_forget_room(room_id).await {
+			return Err!("room was not forgotten after the last local user left");
+		}
+
+		if state_cache
+			.room_servers(room_id)
+			.ready_any(|_| true)
+			.await
+		{
+			return Err!("forgotten room still lists servers");
+		}
+
+		if !state_cache.is_left(&alice, room_id).await {
+			return Err!("left state was dropped with the room");
+		}
+
+		Ok(())
+	})
+}
+
+/// Kicking or banning the last local user forgets the room as part of the
+/// membership change.
+#[test]
+fn last_local_kick_or_ban_forgets_room() -> Result {
+	with_services("forget-kicked", Options::default(), async |services| {
+		let state_cache = &services.state_cache;
+		let server_name = services.globals.server_name();
+		let alice = UserId::parse_with_server_name("alice", server_name)?;
+		let remote = UserId::parse("@carol:remote.example")?;
+		let rooms = test_rooms(services, &["kicked", "banned"])?;
+
+		for (room_id, membership) in
+			[(&rooms[0], MembershipState::Leave), (&rooms[1], MembershipState::Ban)]
+		{
+			update_membership(services, &remote, room_id, MembershipState::Join).await?;
+			update_membership(services, &alice, room_id, MembershipState::Join).await?;
+
+			let count = PduCount::Normal(*services.globals.next_count());
+			state_cache
+				.update_membership(
+					room_id,
+					&alice,
+					RoomMemberEventContent::new(membership.clone()),
+					&remote,
+					None,
+					None,
+					true,
+					count,
+				)
+				.await?;
+
+			if state_cache
+				.room_servers(room_id)
+				.ready_any(|_| true)
+				.await
+			{
+				return Err!(
+					"room still lists servers after {membership} of the last local user"
+				);
+			}
+
+			if state_cache.is_joined(&remote, room_id).await {
+				return Err!(
+					"room still tracks members after {membership} of the last local user"
+				);
+			}
+		}
+
+		Ok(())
+	})
+}
+
+/// The leave of our last user is still sent to the remaining servers, though
+/// appending it forgets the room.
+#[test]
+fn last_local_leave_is_sent_before_forgetting() -> Result {
+	with_services("forget-leave-sent", Options::default(), async |services| {
+		let server_user = &services.globals.server_user;
+		let remote = UserId::parse("@carol:remote.example")?;
+		let room_id = RoomId::new_v1(services.globals.server_name());
+
+		services
+			.short
+			.get_or_create_shortroomid(&room_id)
+			.await;
+
+		let state_lock = services.state.mutex.lock(&room_id).await;
+		let events = [
+			PduBuilder::state(String::new(), &RoomCreateEventContent {
+				room_version: RoomVersionId::V11,
+				..RoomCreateEventContent::new_v11()
+			}),
+			PduBuilder::state(
+				server_user.to_string(),
+				&RoomMemberEventContent::new(MembershipState::Join),
+			),
+		];
+
+		for event in events {
+			services
+				.timeline
+				.build_and_append_pdu(event, server_user, &room_id, &state_lock)
+				.await?;
+		}
+
+		update_membership(services, &remote, &room_id, MembershipState::Join).await?;
+
+		let leave = PduBuilder::state(
+			server_user.to_string(),
+			&RoomMemberEventContent::new(MembershipState::Leave),
+		);
+
+		let event_id = services
+			.timeline
+			.build_and_append_pdu(leave, server_user, &room_id, &state_lock)
+			.await?;
+
+		let state_cache = &services.state_cache;
+		if state_cache
+			.room_servers(&room_id)
+			.ready_any(|_| true)
+			.await
+		{
+			return Err!("room was not forgotten after the last local user left");
+		}
+
+		let pdu_id = services.timeline.get_pdu_id(&event_id).await?;
+
+		let is_leave = |(_, event): (Vec<u8>, SendingEvent)| matches!(event, SendingEvent::Pdu(id) if id == pdu_id);
+
+		// The sender may already have picked the leave up.
+		let dest = Destination::Federation(remote.server_name().to_owned());
+		let queued = services
+			.sending
+			.db
+			.queued_requests(&dest)
+			.ready_any(is_leave)
+			.await;
+
+		let active = services
+			.sending
+			.db
+			.active_requests_for(&dest)
+			.ready_any(is_leave)
+			.await;
+
+		if !queued && !active {
+			return Err!("leave was not sent to {}", remote.server_name());
+		}
+
+		Ok(())
+	})
+}
+
+/// Memberships dropped when a room was forgotten are restored from its state.
+#[test]
+fn rebuild_memberships_restores_forgotten_room() -> Result {
+	with_services("rebuild-memberships", Options::default(), async |services| {
+		let server_user = &services.globals.server_user;
+		let room_id = RoomId::new_v1(services.globals.server_name());
+
+		services
+			.short
+			.get_or_create_shortroomid(&room_id)
+			.await;
+
+		let state_lock = services.state.mutex.lock(&room_id).await;
+		let events = [
+			PduBuilder::state(String::new(), &RoomCreateEventContent {
+				room_version: RoomVersionId::V11,
+				..RoomCreateEventContent::new_v11()
+			}),
+			PduBuilder::state(
+				server_user.to_string(),
+				&RoomMemberEventContent::new(MembershipState::Join),
+			),
+		];
+
+		for event in events {
+			services
+				.timeline
+				.build_and_append_pdu(event, server_user, &room_id, &state_lock)
+				.await?;
+		}
+
+		let state_cache = &services.state_cache;
+		update_membership(services, server_user, &room_id, MembershipState::Leave).await?;
+		if !state_cache.maybe_forget_room(&room_id).await {
+			return Err!("room was not forgotten after the last local user left");
+		}
+
+		services
+			.state
+			.rebuild_memberships(&room_id, &state_lock)
+			.await?;
+
+		if !state_cache.is_joined(server_user, &room_id).await {
+			return Err!("membership in the room state was not restored");
+		}
+
+		if !state_cache
+			.server_in_room(services.globals.server_name(), &room_id)
+			.await
+		{
+			return Err!("our server was not restored to the room");
+		}
+
+		Ok(())
+	})
+}
+
+#[test]
+fn leave_and_ban_reasons_are_kept() -> Result {
+	with_services("left-reasons", Options::default(), async |services| {
+		let state_cache = &services.state_cache;
+		let server_name = services.globals.server_name();
+		let alice = UserId::parse_with_server_name("alice", server_name)?;
+		let bob = UserId::parse_with_server_name("bob", server_name)?;
+		let rooms = test_rooms(services, &["left", "banned"])?;
+
+		for (room_id, membership, reason) in [
+			(&rooms[0], MembershipState::Leave, "moving on"),
+			(&rooms[1], MembershipState::Ban, "spam"),
+		] {
+			update_membership(services, &alice, room_id, MembershipState::Join).await?;
+
+			let count = PduCount::Normal(*services.globals.next_count());
+			state_cache
+				.update_membership(
+					room_id,
+					&alice,
+					RoomMemberEventContent {
+						reason: Some(reason.to_owned()),
+						..RoomMemberEventContent::new(membership)
+					},
+					&bob,
+					None,
+					None,
+					true,
+					count,
+				)
+				.await?;
+		}
+
+		let leave = state_cache.leave_reason(&alice, &rooms[0]).await;
+		if leave.as_deref() != Some("moving on") {
+			return Err!("unexpected leave reason: {leave:?}");
+		}
+
+		let ban = state_cache.ban_reason(&alice, &rooms[1]).await;
+		if ban.as_deref() != Some("spam") {
+			return Err!("unexpected ban reason: {ban:?}");
+		}
+
+		if let Some(reason) = state_cache.ban_reason(&alice, &rooms[0]).await {
+			return Err!("leave was reported as a ban: {reason:?}");
+		}
+
+		Ok(())
+	})
+}
+
+/// Every room with tracked membership is listed once, and a forgotten room
+/// drops out.
+#[test]
+fn all_rooms_lists_tracked_rooms() -> Result {
+	with_services("all-rooms", Options::default(), async |services| {
+		let state_cache = &services.state_cache;
+		let server_name = services.globals.server_name();
+		let alice = UserId::parse_with_server_name("alice", server_name)?;
+		let rooms = test_rooms(services, &["a", "b", "c"])?;
+
+		let mut existing = Vec::new();
+		state_cache
+			.all_rooms()
+			.ready_for_each(|room_id| existing.push(room_id.to_owned()))
+			.await;
+
+		for room_id in &rooms {
+			update_membership(services, &alice, room_id, MembershipState::Join).await?;
+		}
+
+		let mut listed = Vec::new();
+		state_cache
+			.all_rooms()
+			.ready_for_each(|room_id| {
+				if !existing
+					.iter()
+					.any(|existing| existing == room_id)
+				{
+					listed.push(room_id.to_owned());
+				}
+			})
+			.await;
+
+		listed.sort_unstable();
+		if listed != rooms {
+			return Err!("expected rooms {rooms:?}, listed {listed:?}");
+		}
+
+		update_membership(services, &alice, &rooms[1], MembershipState::Leave).await?;
+		state_cache.maybe_forget_room(&rooms[1]).await;
+
+		if state_cache
+			.all_rooms()
+			.ready_any(|room_id| room_id == rooms[1])
+			.await
+		{
+			return Err!("forgotten room is still listed");
+		}
+
+		Ok(())
+	})
+}
+
+fn test_rooms(services: &Services, localparts: &[&str]) -> Result<Vec<OwnedRoomId>> {
+	let server_name = services.globals.server_name();
+
+	localparts
+		.iter()
+		.map(|localpart| RoomId::parse(format!("!{localpart}:{server_name}")).map_err(Into::into))
+		.collect()
+}
+
+async fn update_membership(
+	services: &Services,
+	user_id: &UserId,
+	room_id: &RoomId,
+	membership: MembershipState,
+) -> Result {
+	let count = PduCount::Normal(*services.globals.next_count());
+
+	services
+		.state_cache
+		.update_membership(
+			room_id,
+			user_id,
+			RoomMemberEventContent::new(membership),
+			user_id,
+			None,
+			None,
+			true,
+			count,
+		)
+		.await
+}
@@ -0,0 +1,223 @@
+#![cfg(test)]
+
+mod common;
+
+use tuwunel_core::{
+	Err, Result,
+	matrix::pdu::PduBuilder,
+	ruma::{
+		OwnedRoomId, OwnedUserId, RoomId, RoomVersionId, UserId, device_id,
+		events::{
+			relation::Thread,
+			room::{
+				create::RoomCreateEventContent,
+				member::{MembershipState, RoomMemberEventContent},
+				message::{Relation, RoomMessageEventContent},
+			},
+		},
+	},
+};
+use tuwunel_service::users::Register;
+
+use self::common::{Options, with_services};
+
+/// An initial sync over the room limit returns only that many rooms; the rest
+/// are handed out in limit-sized batches afterwards, and a retried sync is
+/// handed the same batch again.
+#[test]
+fn initial_sync_room_limit_defers_rooms() -> Result {
+	let options = ["initial_sync_room_limit=2".to_owned()];
+	with_services("initial-room-limit", Options::config(&options), async |services| {
+		let server_name = services.globals.server_name();
+		let alice = UserId::parse_with_server_name("alice", server_name)?;
+		let device_id = Some(device_id!("DEVICE"));
+		let rooms: Vec<OwnedRoomId> = ["a", "b", "c", "d", "e"]
+			.iter()
+			.map(|localpart| RoomId::parse(format!("!{localpart}:{server_name}")))
+			.collect::<Result<_, _>>()?;
+
+		let sync = &services.sync;
+		let included = sync
+			.limit_initial_rooms(&alice, device_id, rooms.clone(), 10)
+			.await;
+
+		if included.len() != 2 {
+			return Err!("expected 2 rooms in the initial sync, got {included:?}");
+		}
+
+		let (first, _) = sync
+			.take_deferred_rooms(&alice, device_id, 10, 11)
+			.await;
+
+		// The response carrying the first batch was lost; the sync is retried.
+		let (retried, withheld) = sync
+			.take_deferred_rooms(&alice, device_id, 10, 12)
+			.await;
+
+		if first.len() != 2 || retried != first {
+			return Err!("retry was handed {retried:?} instead of {first:?}");
+		}
+
+		if withheld.len() != 1 {
+			return Err!("expected 1 room withheld after the batch, got {withheld:?}");
+		}
+
+		let (last, withheld) = sync
+			.take_deferred_rooms(&alice, device_id, 12, 13)
+			.await;
+
+		if last.len() != 1 || !withheld.is_empty() {
+			return Err!(
+				"expected a final batch of 1 room, got {last:?} withholding {withheld:?}"
+			);
+		}
+
+		if !sync
+			.deferred_rooms(&alice, device_id, 13)
+			.await
+			.is_empty()
+		{
+			return Err!("rooms still deferred after every batch was taken");
+		}
+
+		let mut seen: Vec<_> = included
+			.into_iter()
+			.chain(first)
+			.chain(last)
+			.collect();
+		seen.sort();
+		if seen != rooms {
+			return Err!("deferred batches did not cover every room: {seen:?}");
+		}
+
+		Ok(())
+	})
+}
+
+/// Rooms within the limit are all sent and nothing is deferred.
+#[test]
+fn initial_sync_under_limit_defers_nothing() -> Result {
+	let options = ["initial_sync_room_limit=2".to_owned()];
+	with_services("initial-under-limit", Options::config(&options), async |services| {
+		let server_name = services.globals.server_name();
+		let alice = UserId::parse_with_server_name("alice", server_name)?;
+		let rooms = vec![RoomId::parse(format!("!a:{server_name}"))?];
+
+		let included = services
+			.sync
+			.limit_initial_rooms(&alice, None, rooms.clone(), 10)
+			.await;
+
+		if included != rooms {
+			return Err!("expected every room in the initial sync, got {included:?}");
+		}
+
+		if !services
+			.sync
+			.deferred_rooms(&alice, None, 10)
+			.await
+			.is_empty()
+		{
+			return Err!("rooms were deferred under the limit");
+		}
+
+		Ok(())
+	})
+}
+
+/// Room-wide notification totals are the main timeline's counts plus those of
+/// each thread, every event being counted in exactly one bucket.
+#[test]
+fn notification_totals_sum_main_and_threads() -> Result {
+	with_services("notification-totals", Options::default(), async |services| {
+		let server_user = &services.globals.server_user;
+		let alice = UserId::parse_with_server_name("alice", services.globals.server_name())?;
+		services
+			.users
+			.full_register(Register {
+				user_id: Some(&alice),
+				password: Some("a-strong-test-password"),
+				..Default::default()
+			})
+			.await?;
+
+		let room_id = RoomId::new_v1(services.globals.server_name());
+		services
+			.short
+			.get_or_create_shortroomid(&room_id)
+			.await;
+
+		let state_lock = services.state.mutex.lock(&room_id).await;
+		let member = |user_id: &OwnedUserId, membership| {
+			PduBuilder::state(user_id.to_string(), &RoomMemberEventContent::new(membership))
+		};
+
+		let setup = [
+			(
+				server_user,
+				PduBuilder::state(String::new(), &RoomCreateEventContent {
+					room_version: RoomVersionId::V11,
+					..RoomCreateEventContent::new_v11()
+				}),
+			),
+			(server_user, member(server_user, MembershipState::Join)),
+			(server_user, member(&alice, MembershipState::Invite)),
+			(&alice, member(&alice, MembershipState::Join)),
+		];
+
+		for (sender, event) in setup {
+			services
+				.timeline
+				.build_and_append_pdu(event, sender, &room_id, &state_lock)
+				.await?;
+		}
+
+		// Start from nothing unread; the invite itself notified.
+		services
+			.pusher
+			.reset_notification_counts(&alice, &room_id);
+
+		let append = async |content: &RoomMessageEventContent| {
+			services
+				.timeline
+				.build_and_append_pdu(
+					PduBuilder::timeline(content),
+					server_user,
+					&room_id,
+					&state_lock,
+				)
+				.await
+		};
+
+		append(&RoomMessageEventContent::text_plain("main")).await?;
+		let root = append(&RoomMessageEventContent::text_plain("root")).await?;
+		for body in ["first", "second"] {
+			let mut content = RoomMessageEventContent::text_plain(body);
+			content.relates_to =
+				Some(Relation::Thread(Thread::plain(root.clone(), root.clone())));
+
+			append(&content).await?;
+		}
+		drop(state_lock);
+
+		let counts = services
+			.pusher
+			.notification_counts(&alice, &room_id)
+			.await;
+
+		if counts.main.0 != 2 {
+			return Err!("expected the two main timeline messages, got {counts:?}");
+		}
+
+		if counts.threads.get(&root).map(|&(n, _)| n) != Some(2) || counts.threads.len() != 1 {
+			return Err!("expected the two thread replies under {root}, got {counts:?}");
+		}
+
+		let thread_highlights = counts.threads[&root].1;
+		if counts.total() != (4, counts.main.1.saturating_add(thread_highlights)) {
+			return Err!("room totals {:?} disagree with {counts:?}", counts.total());
+		}
+
+		Ok(())
+	})
+}
@@ -0,0 +1,779 @@
+#![cfg(test)]
+
+mod common;
+
+use std::{
+	collections::BTreeMap,
+	iter::once,
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+
+use tuwunel_core::{
+	Err, Error, Result,
+	matrix::{PduCount, pdu::PduBuilder},
+	ruma::{
+		MilliSecondsSinceUnixEpoch, OwnedRoomId, RoomId, RoomVersionId, UserId,
+		api::Direction,
+		event_id,
+		events::{
+			StateEventType, TimelineEventType,
+			reaction::ReactionEventContent,
+			relation::{Annotation, InReplyTo, RelationType, Reply, Thread},
+			room::{
+				create::RoomCreateEventContent,
+				member::{MembershipState, RoomMemberEventContent},
+				message::{Relation, RoomMessageEventContent},
+				redaction::RoomRedactionEventContent,
+			},
+		},
+		uint,
+	},
+	utils::stream::ReadyExt,
+};
+use tuwunel_service::Services;
+
+use self::common::{Options, with_services};
+
+#[test]
+fn event_id_at_count_resolves_known_counts() -> Result {
+	with_services("event-id-at-count", Options::default(), async |services| {
+		let room_id = services.admin.get_admin_room().await?;
+		let (first_count, first) = services
+			.timeline
+			.first_item_in_room(&room_id)
+			.await?;
+
+		let latest = services
+			.timeline
+			.latest_pdu_in_room(&room_id)
+			.await?;
+
+		let latest_count = services
+			.timeline
+			.get_pdu_count(&latest.event_id)
+			.await?;
+
+		for (count, expected) in
+			[(first_count, &first.event_id), (latest_count, &latest.event_id)]
+		{
+			let event_id = services
+				.timeline
+				.event_id_at_count(&room_id, count)
+				.await?;
+
+			if event_id != *expected {
+				return Err!("count {count:?} resolved to {event_id} not {expected}");
+			}
+		}
+
+		let missing = services
+			.timeline
+			.event_id_at_count(&room_id, PduCount::max())
+			.await;
+
+		if !missing.as_ref().is_err_and(Error::is_not_found) {
+			return Err!("expected NotFound for a count without an event: {missing:?}");
+		}
+
+		Ok(())
+	})
+}
+
+#[test]
+fn event_type_counts_tally_room_events() -> Result {
+	with_services("event-type-counts", Options::default(), async |services| {
+		let room_id = services.admin.get_admin_room().await?;
+		let server_user = &services.globals.server_user;
+		let before = services
+			.timeline
+			.event_type_counts(&room_id)
+			.await;
+
+		let state_lock = services.state.mutex.lock(&room_id).await;
+		for body in ["one", "two", "three"] {
+			let content = RoomMessageEventContent::text_plain(body);
+			services
+				.timeline
+				.build_and_append_pdu(
+					PduBuilder::timeline(&content),
+					server_user,
+					&room_id,
+					&state_lock,
+				)
+				.await?;
+		}
+		drop(state_lock);
+
+		let after = services
+			.timeline
+			.event_type_counts(&room_id)
+			.await;
+
+		let messages = |counts: &BTreeMap<TimelineEventType, u64>| {
+			counts
+				.get(&TimelineEventType::RoomMessage)
+				.copied()
+				.unwrap_or(0)
+		};
+
+		if messages(&after) != messages(&before).saturating_add(3) {
+			return Err!("expected three more messages: {before:?} -> {after:?}");
+		}
+
+		if after.get(&TimelineEventType::RoomCreate) != Some(&1) {
+			return Err!("expected exactly one create event: {after:?}");
+		}
+
+		let mut total = 0_u64;
+		services
+			.timeline
+			.all_pdus(server_user, &room_id)
+			.ready_for_each(|_| total = total.saturating_add(1))
+			.await;
+
+		let tallied = after
+			.values()
+			.copied()
+			.fold(0, u64::saturating_add);
+		if tallied != total {
+			return Err!("tallied {tallied} events but the room holds {total}");
+		}
+
+		Ok(())
+	})
+}
+
+/// Appending the same event twice at once leaves exactly one timeline entry.
+#[test]
+fn concurrent_duplicate_append_inserts_once() -> Result {
+	with_services("duplicate-append", Options::default(), async |services| {
+		let room_id = services.admin.get_admin_room().await?;
+		let server_user = &services.globals.server_user;
+		let content = RoomMessageEventContent::text_plain("once");
+
+		let state_lock = services.state.mutex.lock(&room_id).await;
+		let (pdu, pdu_json) = services
+			.timeline
+			.create_hash_and_sign_event(
+				PduBuilder::timeline(&content),
+				server_user,
+				&room_id,
+				&state_lock,
+			)
+			.await?;
+
+		let append = || {
+			services.timeline.append_pdu(
+				&pdu,
+				pdu_json.clone(),
+				once(pdu.event_id()),
+				&state_lock,
+			)
+		};
+
+		let (first, second) = tokio::join!(append(), append());
+		drop(state_lock);
+
+		if first.is_ok() == second.is_ok() {
+			return Err!("expected exactly one append to succeed: {first:?} {second:?}");
+		}
+
+		let mut entries = 0_usize;
+		services
+			.timeline
+			.all_pdus(server_user, &room_id)
+			.ready_for_each(|(_, appended)| {
+				if appended.event_id == pdu.event_id {
+					entries = entries.saturating_add(1);
+				}
+			})
+			.await;
+
+		if entries != 1 {
+			return Err!("event was appended {entries} times");
+		}
+
+		Ok(())
+	})
+}
+
+/// Batched json reads return accepted and outlier events in request order.
+#[test]
+fn get_pdus_json_returns_accepted_and_outliers() -> Result {
+	with_services("get-pdus-json", Options::default(), async |services| {
+		let room_id = services.admin.get_admin_room().await?;
+		let server_user = &services.globals.server_user;
+		let content = RoomMessageEventContent::text_plain("outlier");
+
+		let state_lock = services.state.mutex.lock(&room_id).await;
+		let (outlier, outlier_json) = services
+			.timeline
+			.create_hash_and_sign_event(
+				PduBuilder::timeline(&content),
+				server_user,
+				&room_id,
+				&state_lock,
+			)
+			.await?;
+		drop(state_lock);
+
+		services
+			.timeline
+			.add_pdu_outlier(&outlier.event_id, &outlier_json);
+
+		let (_, first) = services
+			.timeline
+			.first_item_in_room(&room_id)
+			.await?;
+
+		let latest = services
+			.timeline
+			.latest_pdu_in_room(&room_id)
+			.await?;
+
+		let missing = event_id!("$missing:example.com");
+		let requested = [&*latest.event_id, &*outlier.event_id, &*first.event_id, missing];
+
+		let mut results = Vec::new();
+		services
+			.timeline
+			.get_pdus_json(requested.iter().copied())
+			.ready_for_each(|result| results.push(result))
+			.await;
+
+		let returned: Vec<_> = results
+			.iter()
+			.map(|(event_id, _)| &**event_id)
+			.collect();
+
+		if returned != requested {
+			return Err!("order not preserved: {returned:?}");
+		}
+
+		for (event_id, pdu_json) in &results[..3] {
+			let expected = services.timeline.get_pdu_json(event_id).await?;
+			if pdu_json.as_ref().ok() != Some(&expected) {
+				return Err!("unexpected json for {event_id}: {pdu_json:?}");
+			}
+		}
+
+		if !results[3]
+			.1
+			.as_ref()
+			.is_err_and(Error::is_not_found)
+		{
+			return Err!("expected NotFound for a missing event: {:?}", results[3].1);
+		}
+
+		Ok(())
+	})
+}
+
+/// Redacting an event drops the reactions relating to it while replies keep
+/// relating to it.
+#[test]
+fn redaction_removes_reaction_relations() -> Result {
+	with_services("redact-relations", Options::default(), async |services| {
+		let room_id = services.admin.get_admin_room().await?;
+		let server_user = &services.globals.server_user;
+		let state_lock = services.state.mutex.lock(&room_id).await;
+		let append = async |builder| {
+			services
+				.timeline
+				.build_and_append_pdu(builder, server_user, &room_id, &state_lock)
+				.await
+		};
+
+		let target =
+			append(PduBuilder::timeline(&RoomMessageEventContent::text_plain("target"))).await?;
+
+		let reaction = ReactionEventContent::new(Annotation::new(target.clone(), "👍".into()));
+		append(PduBuilder::timeline(&reaction)).await?;
+
+		let mut reply = RoomMessageEventContent::text_plain("reply");
+		reply.relates_to = Some(Relation::Reply(Reply {
+			in_reply_to: InReplyTo { event_id: target.clone() },
+		}));
+		append(PduBuilder::timeline(&reply)).await?;
+
+		let has_relation = async |rel_type: Option<&RelationType>| {
+			services
+				.pdu_metadata
+				.event_has_relation(&target, None, rel_type, None)
+				.await
+		};
+
+		if !has_relation(Some(&RelationType::Annotation)).await {
+			return Err!("reaction relation was not indexed");
+		}
+
+		append(PduBuilder {
+			redacts: Some(target.clone()),
+			..PduBuilder::timeline(&RoomRedactionEventContent {
+				redacts: Some(target.clone()),
+				reason: None,
+			})
+		})
+		.await?;
+
+		drop(state_lock);
+
+		if has_relation(Some(&RelationType::Annotation)).await {
+			return Err!("reaction still relates to the redacted event");
+		}
+
+		if !has_relation(None).await {
+			return Err!("reply no longer relates to the redacted event");
+		}
+
+		Ok(())
+	})
+}
+
+/// A registered append hook sees each event built and appended locally.
+#[test]
+fn append_hook_fires_with_appended_event() -> Result {
+	with_services("append-hook", Options::default(), async |services| {
+		let seen = Arc::new(Mutex::new(Vec::new()));
+		services.timeline.register_append_hook({
+			let seen = seen.clone();
+			Box::new(move |pdu| {
+				seen.lock()
+					.expect("locked")
+					.push(pdu.event_id.clone());
+			})
+		});
+
+		let room_id = services.admin.get_admin_room().await?;
+		let server_user = &services.globals.server_user;
+		let state_lock = services.state.mutex.lock(&room_id).await;
+		let event_id = services
+			.timeline
+			.build_and_append_pdu(
+				PduBuilder::timeline(&RoomMessageEventContent::text_plain("hooked")),
+				server_user,
+				&room_id,
+				&state_lock,
+			)
+			.await?;
+
+		drop(state_lock);
+
+		let seen = seen.lock().expect("locked").clone();
+		if seen.last() != Some(&event_id) {
+			return Err!("hook did not see {event_id}: {seen:?}");
+		}
+
+		Ok(())
+	})
+}
+
+/// A thread's stream holds its root and replies in order, skipping other
+/// events relating to the root.
+#[test]
+fn thread_pdus_returns_only_the_thread() -> Result {
+	with_services("thread-pdus", Options::default(), async |services| {
+		let room_id = services.admin.get_admin_room().await?;
+		let server_user = &services.globals.server_user;
+		let state_lock = services.state.mutex.lock(&room_id).await;
+		let append = async |content: &RoomMessageEventContent| {
+			services
+				.timeline
+				.build_and_append_pdu(
+					PduBuilder::timeline(content),
+					server_user,
+					&room_id,
+					&state_lock,
+				)
+				.await
+		};
+
+		let root = append(&RoomMessageEventContent::text_plain("root")).await?;
+		let in_thread = |body| {
+			let mut content = RoomMessageEventContent::text_plain(body);
+			content.relates_to =
+				Some(Relation::Thread(Thread::plain(root.clone(), root.clone())));
+			content
+		};
+
+		let first = append(&in_thread("first")).await?;
+		append(&RoomMessageEventContent::text_plain("unrelated")).await?;
+
+		let mut reply = RoomMessageEventContent::text_plain("reply");
+		reply.relates_to = Some(Relation::Reply(Reply {
+			in_reply_to: InReplyTo { event_id: root.clone() },
+		}));
+		append(&reply).await?;
+
+		let second = append(&in_thread("second")).await?;
+		drop(state_lock);
+
+		let thread = async |from| {
+			let mut events = Vec::new();
+			services
+				.timeline
+				.thread_pdus(&room_id, &root, from)
+				.ready_for_each(|item| events.push(item))
+				.await;
+
+			events
+				.into_iter()
+				.map(|item| item.map(|(count, pdu)| (count, pdu.event_id)))
+				.collect::<Result<Vec<_>>>()
+		};
+
+		let events = thread(None).await?;
+		let event_ids: Vec<_> = events.iter().map(|(_, id)| id).collect();
+		if event_ids != [&root, &first, &second] {
+			return Err!("unexpected thread events: {event_ids:?}");
+		}
+
+		let first_count = events[1].0;
+		let after: Vec<_> = thread(Some(first_count))
+			.await?
+			.into_iter()
+			.map(|(_, event_id)| event_id)
+			.collect();
+
+		if after != [second.clone()] {
+			return Err!("unexpected thread events after the first reply: {after:?}");
+		}
+
+		Ok(())
+	})
+}
+
+/// Only outliers received longer ago than the threshold which nothing refers
+/// to are pruned; the sender's timestamp does not count towards their age.
+#[test]
+fn prune_outliers_keeps_recent_and_referenced() -> Result {
+	with_services("prune-outliers", Options::default(), async |services| {
+		let room_id = services.admin.get_admin_room().await?;
+		let server_user = &services.globals.server_user;
+		let old = MilliSecondsSinceUnixEpoch(uint!(1_600_000_000_000));
+
+		let state_lock = services.state.mutex.lock(&room_id).await;
+		let add_outlier = async |body: &str, received: Option<u64>| {
+			let content = RoomMessageEventContent::text_plain(body);
+			let (pdu, pdu_json) = services
+				.timeline
+				.create_hash_and_sign_event(
+					PduBuilder {
+						timestamp: Some(old),
+						..PduBuilder::timeline(&content)
+					},
+					server_user,
+					&room_id,
+					&state_lock,
+				)
+				.await?;
+
+			services
+				.timeline
+				.add_pdu_outlier(&pdu.event_id, &pdu_json);
+
+			if let Some(received) = received {
+				services.db["eventid_outlierreceived"].raw_put(&pdu.event_id, received);
+			}
+
+			Ok::<_, Error>(pdu.event_id)
+		};
+
+		let received = Some(u64::from(old.get()));
+		let unreferenced = add_outlier("old", received).await?;
+		let referenced = add_outlier("referenced", received).await?;
+		let historic_state = add_outlier("historic state", received).await?;
+		let recent = add_outlier("recent", None).await?;
+		drop(state_lock);
+
+		services
+			.pdu_metadata
+			.mark_as_referenced(&room_id, once(&*referenced));
+
+		// A superseded state snapshot still points at the outlier.
+		let shortstatehash = services
+			.state
+			.get_room_shortstatehash(&room_id)
+			.await?;
+		let mut snapshot = services
+			.state_compressor
+			.load_shortstatehash_info(shortstatehash)
+			.await?
+			.pop()
+			.map(|info| (*info.full_state).clone())
+			.unwrap_or_default();
+		let shortstatekey = services
+			.short
+			.get_or_create_shortstatekey(&StateEventType::RoomTopic, "")
+			.await;
+		snapshot.insert(
+			services
+				.state_compressor
+				.compress_state_event(shortstatekey, &historic_state)
+				.await,
+		);
+		services
+			.state_compressor
+			.save_state(&room_id, snapshot.into())
+			.await?;
+
+		let pruned = services
+			.timeline
+			.prune_outliers(Duration::from_secs(86_400))
+			.await;
+
+		if pruned != 1 {
+			return Err!("expected one outlier pruned, pruned {pruned}");
+		}
+
+		if services
+			.timeline
+			.outlier_pdu_exists(&unreferenced)
+			.await
+			.is_ok()
+		{
+			return Err!("old unreferenced outlier was kept");
+		}
+
+		for kept in [&referenced, &historic_state, &recent] {
+			if services
+				.timeline
+				.outlier_pdu_exists(kept)
+				.await
+				.is_err()
+			{
+				return Err!("outlier {kept} was pruned");
+			}
+		}
+
+		Ok(())
+	})
+}
+
+/// The latest event of each joined room comes back most recent first, cut off
+/// at the limit.
+#[test]
+fn recent_events_for_user_orders_rooms_by_latest() -> Result {
+	with_services("recent-events-for-user", Options::default(), async |services| {
+		let server_user = &services.globals.server_user;
+		let rooms = [
+			create_room(services).await?,
+			create_room(services).await?,
+			create_room(services).await?,
+		];
+
+		// Activity order differs from creation order: b, then c, then a.
+		let mut latest = Vec::new();
+		for room_id in [&rooms[1], &rooms[2], &rooms[0]] {
+			let state_lock = services.state.mutex.lock(room_id).await;
+			let event_id = services
+				.timeline
+				.build_and_append_pdu(
+					PduBuilder::timeline(&RoomMessageEventContent::text_plain("recent")),
+					server_user,
+					room_id,
+					&state_lock,
+				)
+				.await?;
+
+			latest.push((room_id.clone(), event_id));
+		}
+
+		let recent = services
+			.timeline
+			.recent_events_for_user(server_user, 2)
+			.await;
+
+		let got: Vec<_> = recent
+			.iter()
+			.map(|(room_id, _, pdu)| (room_id.clone(), pdu.event_id.clone()))
+			.collect();
+
+		let expected: Vec<_> = latest.into_iter().rev().take(2).collect();
+		if got != expected {
+			return Err!("expected {expected:?}, got {got:?}");
+		}
+
+		// Our three rooms and the admin room.
+		let all = services
+			.timeline
+			.recent_events_for_user(server_user, 10)
+			.await;
+
+		if all.len() != 4 {
+			return Err!("expected one event per joined room, got {all:?}");
+		}
+
+		Ok(())
+	})
+}
+
+/// Only the events of the requested sender are returned, in order.
+#[test]
+fn pdus_by_sender_returns_only_their_events() -> Result {
+	with_services("pdus-by-sender", Options::default(), async |services| {
+		let room_id = create_room(services).await?;
+		let server_user = &services.globals.server_user;
+		let bob = UserId::parse_with_server_name("bob", services.globals.server_name())?;
+
+		let state_lock = services.state.mutex.lock(&room_id).await;
+		let membership = [(server_user, MembershipState::Invite), (&bob, MembershipState::Join)];
+
+		for (sender, membership) in membership {
+			services
+				.timeline
+				.build_and_append_pdu(
+					PduBuilder::state(bob.to_string(), &RoomMemberEventContent::new(membership)),
+					sender,
+					&room_id,
+					&state_lock,
+				)
+				.await?;
+		}
+
+		let mut sent = Vec::new();
+		for (sender, body) in [(&bob, "one"), (server_user, "two"), (&bob, "three")] {
+			let event_id = services
+				.timeline
+				.build_and_append_pdu(
+					PduBuilder::timeline(&RoomMessageEventContent::text_plain(body)),
+					sender,
+					&room_id,
+					&state_lock,
+				)
+				.await?;
+
+			if sender == &bob {
+				sent.push(event_id);
+			}
+		}
+		drop(state_lock);
+
+		let mut events = Vec::new();
+		services
+			.timeline
+			.pdus_by_sender(&room_id, &bob, None)
+			.ready_for_each(|item| events.push(item))
+			.await;
+
+		let events: Vec<_> = events
+			.into_iter()
+			.map(|item| item.map(|(_, pdu)| pdu))
+			.collect::<Result<_>>()?;
+
+		if events.iter().any(|pdu| pdu.sender != bob) {
+			return Err!("events of other senders were returned: {events:?}");
+		}
+
+		// Bob's own join comes first, then his two messages.
+		let messages: Vec<_> = events
+			.iter()
+			.filter(|pdu| pdu.kind == TimelineEventType::RoomMessage)
+			.map(|pdu| pdu.event_id.clone())
+			.collect();
+
+		if events.len() != 3 || messages != sent {
+			return Err!("expected bob's join and messages {sent:?}, got {events:?}");
+		}
+
+		Ok(())
+	})
+}
+
+#[test]
+fn neighbor_pdu_steps_forward_and_backward() -> Result {
+	with_services("neighbor-pdu", Options::default(), async |services| {
+		let room_id = create_room(services).await?;
+		let server_user = &services.globals.server_user;
+
+		let state_lock = services.state.mutex.lock(&room_id).await;
+		let mut sent = Vec::new();
+		for body in ["first", "middle", "last"] {
+			let event_id = services
+				.timeline
+				.build_and_append_pdu(
+					PduBuilder::timeline(&RoomMessageEventContent::text_plain(body)),
+					server_user,
+					&room_id,
+					&state_lock,
+				)
+				.await?;
+
+			sent.push(event_id);
+		}
+		drop(state_lock);
+
+		let timeline = &services.timeline;
+		let next = timeline
+			.neighbor_pdu(&room_id, &sent[1], Direction::Forward)
+			.await?;
+
+		if next.event_id != sent[2] {
+			return Err!("expected {} after the middle event, got {}", sent[2], next.event_id);
+		}
+
+		let prev = timeline
+			.neighbor_pdu(&room_id, &sent[1], Direction::Backward)
+			.await?;
+
+		if prev.event_id != sent[0] {
+			return Err!("expected {} before the middle event, got {}", sent[0], prev.event_id);
+		}
+
+		let past_end = timeline
+			.neighbor_pdu(&room_id, &sent[2], Direction::Forward)
+			.await;
+
+		if !past_end.as_ref().is_err_and(Error::is_not_found) {
+			return Err!("expected NotFound past the latest event: {past_end:?}");
+		}
+
+		let (_, create) = timeline.first_item_in_room(&room_id).await?;
+		let before_start = timeline
+			.neighbor_pdu(&room_id, &create.event_id, Direction::Backward)
+			.await;
+
+		if !before_start
+			.as_ref()
+			.is_err_and(Error::is_not_found)
+		{
+			return Err!("expected NotFound before the create event: {before_start:?}");
+		}
+
+		Ok(())
+	})
+}
+
+/// Create a local room holding only the create event and the server user's
+/// join.
+async fn create_room(services: &Services) -> Result<OwnedRoomId> {
+	let room_id = RoomId::new_v1(services.globals.server_name());
+	let server_user = &services.globals.server_user;
+
+	services
+		.short
+		.get_or_create_shortroomid(&room_id)
+		.await;
+
+	let state_lock = services.state.mutex.lock(&room_id).await;
+	let events = [
+		PduBuilder::state(String::new(), &RoomCreateEventContent {
+			room_version: RoomVersionId::V11,
+			..RoomCreateEventContent::new_v11()
+		}),
+		PduBuilder::state(
+			server_user.to_string(),
+			&RoomMemberEventContent::new(MembershipState::Join),
+		),
+	];
+
+	for event in events {
+		services
+			.timeline
+			.build_and_append_pdu(event, server_user, &room_id, &state_lock)
+			.await?;
+	}
+
+	Ok(room_id)
+}
@@ -0,0 +1,106 @@
+#![cfg(test)]
+
+mod common;
+
+use tuwunel_core::{
+	Err, Result,
+	matrix::PduCount,
+	ruma::{
+		RoomId, UserId,
+		events::room::member::{MembershipState, RoomMemberEventContent},
+		server_name,
+	},
+	utils::{self, stream::ReadyExt},
+};
+use tuwunel_service::{
+	Services,
+	sending::{Destination, SendingEvent},
+};
+
+use self::common::{Options, with_services};
+
+#[test]
+fn leave_federates_typing_stop() -> Result {
+	with_services("leave-stop", Options::default(), async |services| {
+		let server_name = services.globals.server_name();
+		let alice = UserId::parse_with_server_name("alice", server_name)?;
+		let carol = UserId::parse("@carol:remote.example")?;
+		let room_id = RoomId::parse(format!("!typing:{server_name}"))?;
+
+		update_membership(services, &carol, &room_id, MembershipState::Join).await?;
+		update_membership(services, &alice, &room_id, MembershipState::Join).await?;
+
+		let timeout = utils::millis_since_unix_epoch().saturating_add(30_000);
+		services
+			.typing
+			.typing_add(&alice, &room_id, timeout)
+			.await?;
+
+		update_membership(services, &alice, &room_id, MembershipState::Leave).await?;
+
+		let typing = services
+			.typing
+			.typing_users_for_user(&room_id, &carol)
+			.await?;
+
+		if typing.contains(&alice) {
+			return Err!("user still typing after leaving the room");
+		}
+
+		// The stop EDU may still be queued or already in flight to the remote.
+		let dest = Destination::Federation(server_name!("remote.example").to_owned());
+		let is_stop = |event: &SendingEvent| {
+			let SendingEvent::Edu(edu) = event else {
+				return false;
+			};
+
+			let edu = String::from_utf8_lossy(edu);
+			edu.contains("m.typing")
+				&& edu.contains(alice.as_str())
+				&& edu.contains("\"typing\":false")
+		};
+
+		let queued = services
+			.sending
+			.db
+			.queued_requests(&dest)
+			.ready_any(|(_, event)| is_stop(&event))
+			.await;
+
+		let active = services
+			.sending
+			.db
+			.active_requests_for(&dest)
+			.ready_any(|(_, event)| is_stop(&event))
+			.await;
+
+		if !queued && !active {
+			return Err!("no typing stop EDU was produced for the remote server");
+		}
+
+		Ok(())
+	})
+}
+
+async fn update_membership(
+	services: &Services,
+	user_id: &UserId,
+	room_id: &RoomId,
+	membership: MembershipState,
+) -> Result {
+	let count = PduCount::Normal(*services.globals.next_count());
+
+	services
+		.state_cache
+		.update_membership(
+			room_id,
+			user_id,
+			RoomMemberEventContent::new(membership),
+			user_id,
+			None,
+			None,
+			true,
+			count,
+		)
+		.await
+}
@@ -0,0 +1,211 @@
+#![cfg(test)]
+
+mod common;
+
+use std::time::Duration;
+
+use tuwunel_core::{
+	Err, Result,
+	ruma::{
+		CanonicalJsonValue, UserId,
+		api::client::uiaa::{
+			AuthData, AuthFlow, AuthType, FallbackAcknowledgement, MatrixUserIdentifier,
+			Password, UiaaInfo, UserIdentifier,
+		},
+		serde::JsonObject,
+	},
+};
+use tuwunel_service::uiaa::APPSERVICE_AUTH_TYPE;
+
+use self::common::{Options, with_services};
+
+/// A completed reusable session authorizes a follow-up request to the same
+/// endpoint within its window, while an ordinary session is consumed by the
+/// request completing it.
+#[test]
+fn reusable_session_authorizes_follow_up() -> Result {
+	with_services("reusable-session", Options::default(), async |services| {
+		let uiaa = &services.uiaa;
+		let user_id = UserId::parse_with_server_name("alice", services.globals.server_name())?;
+		let device_id = "ALICEDEVICE".into();
+		let body = CanonicalJsonValue::Object(Default::default());
+
+		services
+			.users
+			.create(&user_id, Some("correct-horse"), None)
+			.await?;
+
+		let uiaainfo = |session: &str| UiaaInfo {
+			flows: vec![AuthFlow::new(vec![AuthType::Password])],
+			session: Some(session.to_owned()),
+			..Default::default()
+		};
+
+		let password = |session: &str| {
+			let mut password = Password::new(
+				UserIdentifier::Matrix(MatrixUserIdentifier::new(user_id.localpart().to_owned())),
+				"correct-horse".to_owned(),
+			);
+			password.session = Some(session.to_owned());
+			AuthData::Password(password)
+		};
+
+		let acknowledge = |session: &str| {
+			AuthData::FallbackAcknowledgement(FallbackAcknowledgement::new(session.to_owned()))
+		};
+
+		let endpoint = Some("upload_signing_keys");
+		for (session, reusable) in [("reusable", endpoint), ("single", None)] {
+			uiaa.create(&user_id, device_id, &uiaainfo(session), &body, reusable);
+
+			let (worked, _) = uiaa
+				.try_auth(
+					&user_id,
+					device_id,
+					&password(session),
+					&uiaainfo(session),
+					None,
+					reusable,
+				)
+				.await?;
+
+			if !worked {
+				return Err!("password did not complete the {session} session");
+			}
+
+			let follow_up = uiaa
+				.try_auth(
+					&user_id,
+					device_id,
+					&acknowledge(session),
+					&uiaainfo(session),
+					None,
+					endpoint,
+				)
+				.await;
+
+			match (reusable.is_some(), follow_up) {
+				| (true, Ok((true, _))) | (false, Err(_)) => {},
+				| (_, follow_up) => {
+					return Err!("unexpected follow-up on the {session} session: {follow_up:?}");
+				},
+			}
+		}
+
+		// A follow-up to another endpoint is refused.
+		if uiaa
+			.try_auth(
+				&user_id,
+				device_id,
+				&acknowledge("reusable"),
+				&uiaainfo("reusable"),
+				None,
+				Some("delete_devices"),
+			)
+			.await
+			.is_ok()
+		{
+			return Err!("reused session authorized a different endpoint");
+		}
+
+		// A follow-up needing stages the session never completed is refused.
+		let stricter = UiaaInfo {
+			flows: vec![AuthFlow::new(vec![AuthType::Password, AuthType::Dummy])],
+			..uiaainfo("reusable")
+		};
+
+		if uiaa
+			.try_auth(&user_id, device_id, &acknowledge("reusable"), &stricter, None, endpoint)
+			.await
+			.is_ok()
+		{
+			return Err!("reused session satisfied stages it did not complete");
+		}
+
+		Ok(())
+	})
+}
+
+/// A request authenticated by an appservice completes the appservice stage for
+/// a user in its exclusive namespace, and nobody else.
+#[test]
+fn appservice_completes_stage_in_exclusive_namespace() -> Result {
+	let options = [
+		"appservice.bridge.as_token=\"bridge-as-token\"".to_owned(),
+		"appservice.bridge.hs_token=\"bridge-hs-token\"".to_owned(),
+		"appservice.bridge.sender_localpart=\"bridgebot\"".to_owned(),
+		"appservice.bridge.users=[{exclusive=true, regex=\"@bridge_.*\"}, {exclusive=false, \
+		 regex=\"@shared_.*\"}]"
+			.to_owned(),
+	];
+
+	with_services("appservice", Options::config(&options), async |services| {
+		let uiaa = &services.uiaa;
+		let server_name = services.globals.server_name();
+		let device_id = "BRIDGEDEVICE".into();
+		let body = CanonicalJsonValue::Object(Default::default());
+
+		// Configured appservices are loaded by the appservice worker.
+		let mut bridge = None;
+		for _ in 0..500 {
+			bridge = services
+				.appservice
+				.find_from_access_token("bridge-as-token")
+				.await
+				.ok();
+
+			if bridge.is_some() {
+				break;
+			}
+
+			tokio::time::sleep(Duration::from_millis(10)).await;
+		}
+
+		if bridge.is_none() {
+			return Err!("appservice registration was not loaded");
+		}
+
+		let uiaainfo = |session: &str| UiaaInfo {
+			flows: vec![AuthFlow::new(vec![AuthType::from(APPSERVICE_AUTH_TYPE)])],
+			session: Some(session.to_owned()),
+			..Default::default()
+		};
+
+		let appservice = |session: &str| {
+			AuthData::new(APPSERVICE_AUTH_TYPE, Some(session.to_owned()), JsonObject::new())
+		};
+
+		let cases = [
+			("bridge_alice", bridge.as_ref(), true),
+			("shared_carol", bridge.as_ref(), false),
+			("alice", bridge.as_ref(), false),
+			("bridge_bob", None, false),
+		];
+
+		for (localpart, from, expected) in cases {
+			let user_id = UserId::parse_with_server_name(localpart, server_name)?;
+			uiaa.create(&user_id, device_id, &uiaainfo(localpart), &body, None);
+
+			let (worked, info) = uiaa
+				.try_auth(
+					&user_id,
+					device_id,
+					&appservice(localpart)?,
+					&uiaainfo(localpart),
+					from,
+					None,
+				)
+				.await?;
+
+			if worked != expected {
+				return Err!("appservice stage for {user_id} completed: {worked}");
+			}
+
+			if !worked && info.auth_error.is_none() {
+				return Err!("rejected appservice stage for {user_id} carries no error");
+			}
+		}
+
+		Ok(())
+	})
+}
@@ -113,7 +113,7 @@ async fn reauth(services: &Arc<Services>) -> Result {
 
 	let (worked, _) = services
 		.uiaa
-		.try_auth(&user_id, "ALICEDEVICE".into(), &auth, &UiaaInfo::default())
+		.try_auth(&user_id, "ALICEDEVICE".into(), &auth, &UiaaInfo::default(), None, None)
 		.await?;
 
 	if worked {
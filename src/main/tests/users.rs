@@ -0,0 +1,350 @@
+#![cfg(test)]
+
+mod common;
+
+use std::{
+	fs::{read_to_string, remove_file},
+	process::id as process_id,
+};
+
+use tuwunel_core::{
+	Err, Result,
+	matrix::PduCount,
+	ruma::{
+		OwnedUserId, RoomId, UserId,
+		api::{
+			client::push::{HttpPusherData, PusherIds, PusherInit, PusherKind, set_pusher},
+			error::ErrorKind,
+		},
+		device_id,
+		events::{
+			room::member::{MembershipState, RoomMemberEventContent},
+			tag::TagName,
+		},
+	},
+	utils::stream::ReadyExt,
+};
+use tuwunel_service::{oauth::Session, sending::Destination, users::Register};
+
+use self::common::{Options, run_admin_command, with_services};
+
+#[test]
+fn logout_all_revokes_every_device_token() -> Result {
+	with_services("logout-all", Options::default(), async |services| {
+		let user_id = UserId::parse_with_server_name("alice", services.globals.server_name())?;
+		services
+			.users
+			.full_register(Register {
+				user_id: Some(&user_id),
+				password: Some("a-strong-test-password"),
+				..Default::default()
+			})
+			.await?;
+
+		let tokens = [
+			"logout-all-test-access-token-device-one",
+			"logout-all-test-access-token-device-two",
+		];
+
+		for token in tokens {
+			services
+				.users
+				.create_device(&user_id, None, (Some(token), None), None, None, None)
+				.await?;
+
+			services.users.find_from_token(token).await?;
+		}
+
+		services.users.remove_all_tokens(&user_id).await;
+
+		for token in tokens {
+			if services
+				.users
+				.find_from_token(token)
+				.await
+				.is_ok()
+			{
+				return Err!("token {token:?} still authenticates after logout-all");
+			}
+		}
+
+		Ok(())
+	})
+}
+
+#[test]
+fn oauth_unbind_removes_session_bindings() -> Result {
+	with_services("oauth-unbind", Options::default(), async |services| {
+		let user_id = UserId::parse_with_server_name("alice", services.globals.server_name())?;
+		let sess_id = "oauth-unbind-test-session";
+		services
+			.oauth
+			.sessions
+			.put(&Session {
+				sess_id: Some(sess_id.to_owned()),
+				idp_id: Some("migrated-away".to_owned()),
+				user_id: Some(user_id.clone()),
+				..Default::default()
+			})
+			.await;
+
+		let listed = run_admin_command(services, &format!("users oauth-list {user_id}")).await?;
+		if !listed.contains(sess_id) {
+			return Err!("binding missing from oauth-list: {listed}");
+		}
+
+		run_admin_command(services, &format!("users oauth-unbind {user_id}")).await?;
+
+		if services.oauth.sessions.get(sess_id).await.is_ok() {
+			return Err!("session {sess_id:?} still exists after oauth-unbind");
+		}
+
+		if services
+			.oauth
+			.sessions
+			.exists_for_user(&user_id)
+			.await
+		{
+			return Err!("{user_id} is still bound after oauth-unbind");
+		}
+
+		Ok(())
+	})
+}
+
+#[test]
+fn pusher_remove_cleans_push_queue() -> Result {
+	with_services("pusher-remove", Options::default(), async |services| {
+		let user_id = UserId::parse_with_server_name("alice", services.globals.server_name())?;
+		services
+			.users
+			.full_register(Register {
+				user_id: Some(&user_id),
+				password: Some("a-strong-test-password"),
+				..Default::default()
+			})
+			.await?;
+
+		let (app_id, pushkey) = ("org.example.push", "pusher-remove-test-pushkey");
+		let pusher = PusherInit {
+			ids: PusherIds::new(pushkey.to_owned(), app_id.to_owned()),
+			kind: PusherKind::Http(HttpPusherData::new(
+				"https://push.example.org/_matrix/push/v1/notify".to_owned(),
+			)),
+			app_display_name: "Example".to_owned(),
+			device_display_name: "Phone".to_owned(),
+			profile_tag: None,
+			lang: "en".to_owned(),
+		};
+
+		services
+			.pusher
+			.set_pusher(
+				&user_id,
+				device_id!("PUSHERDEVICE"),
+				&set_pusher::v3::Request::post(pusher.into()).action,
+			)
+			.await?;
+
+		// Queue without dispatching, as left pending for an unreachable gateway.
+		let dest = Destination::Push(user_id.clone(), pushkey.to_owned());
+		let mut key = format!("${user_id}").into_bytes();
+		key.push(0xFF);
+		key.extend_from_slice(pushkey.as_bytes());
+		key.push(0xFF);
+		key.extend_from_slice(&services.globals.next_count().to_be_bytes());
+		services.db["servernameevent_data"].insert(&key, br#"{}"#);
+
+		let listed = run_admin_command(services, &format!("users pushers {user_id}")).await?;
+		for expected in [app_id, pushkey, "https://push.example.org"] {
+			if !listed.contains(expected) {
+				return Err!("{expected:?} missing from pushers: {listed}");
+			}
+		}
+
+		services
+			.pusher
+			.delete_pusher(&user_id, pushkey)
+			.await;
+
+		if services
+			.pusher
+			.get_pusher(&user_id, pushkey)
+			.await
+			.is_ok()
+		{
+			return Err!("pusher still registered after pusher-remove");
+		}
+
+		if services
+			.sending
+			.db
+			.queued_requests(&dest)
+			.ready_any(|_| true)
+			.await
+		{
+			return Err!("push queue was not cleaned after pusher-remove");
+		}
+
+		Ok(())
+	})
+}
+
+/// Room account data is exported whatever the membership, here an invite.
+#[test]
+fn export_account_data_writes_global_and_room_data() -> Result {
+	with_services("export-account-data", Options::default(), async |services| {
+		let server_name = services.globals.server_name();
+		let user_id = UserId::parse_with_server_name("alice", server_name)?;
+		let room_id = RoomId::parse(format!("!export:{server_name}"))?;
+		let path = format!("/tmp/tuwunel-test-export-account-data-{}.json", process_id());
+
+		// Registration stores the default push rules as global account data.
+		services
+			.users
+			.full_register(Register {
+				user_id: Some(&user_id),
+				password: Some("a-strong-test-password"),
+				..Default::default()
+			})
+			.await?;
+
+		services
+			.state_cache
+			.update_membership(
+				&room_id,
+				&user_id,
+				RoomMemberEventContent::new(MembershipState::Invite),
+				&user_id,
+				None,
+				None,
+				true,
+				PduCount::Normal(*services.globals.next_count()),
+			)
+			.await?;
+
+		services
+			.account_data
+			.set_room_tag(&user_id, &room_id, TagName::Favorite, None)
+			.await?;
+
+		// An existing file is left alone.
+		let command = format!("users export-account-data {user_id} {path}");
+		let output = run_admin_command(services, &command).await;
+		let again = run_admin_command(services, &command).await;
+
+		let exported = read_to_string(&path);
+		remove_file(&path).ok();
+
+		if again.is_ok_and(|again| again.contains("Exported")) {
+			return Err!("export overwrote an existing file");
+		}
+
+		let output = output?;
+		if !output.contains("Exported 2 account data event(s)") {
+			return Err!("unexpected export-account-data output: {output}");
+		}
+
+		let exported = exported?;
+		for expected in [user_id.as_str(), "m.push_rules", room_id.as_str(), "m.favourite"] {
+			if !exported.contains(expected) {
+				return Err!("export is missing {expected:?}: {exported}");
+			}
+		}
+
+		Ok(())
+	})
+}
+
+#[test]
+fn additional_creators_must_be_active() -> Result {
+	with_services("additional-creators", Options::default(), async |services| {
+		let server_name = services.globals.server_name();
+		let alice = UserId::parse_with_server_name("alice", server_name)?;
+		let bob = UserId::parse_with_server_name("bob", server_name)?;
+		let remote: OwnedUserId = "@carol:remote.example".try_into()?;
+
+		for user_id in [&alice, &bob] {
+			services
+				.users
+				.full_register(Register {
+					user_id: Some(user_id),
+					password: Some("a-strong-test-password"),
+					..Default::default()
+				})
+				.await?;
+		}
+
+		services.users.deactivate_account(&bob).await?;
+
+		services
+			.users
+			.check_additional_creators(&[alice.clone(), remote])
+			.await?;
+
+		match services
+			.users
+			.check_additional_creators(&[alice, bob])
+			.await
+		{
+			| Err(e) if matches!(e.kind(), ErrorKind::InvalidParam) => Ok(()),
+			| result => Err!("deactivated creator was not rejected: {result:?}"),
+		}
+	})
+}
+
+#[test]
+fn check_all_users_flags_corrupt_hash() -> Result {
+	with_services("check-all-users", Options::default(), async |services| {
+		let server_name = services.globals.server_name();
+		let alice = UserId::parse_with_server_name("alice", server_name)?;
+		let bob = UserId::parse_with_server_name("bob", server_name)?;
+
+		for user_id in [&alice, &bob] {
+			services
+				.users
+				.full_register(Register {
+					user_id: Some(user_id),
+					password: Some("a-strong-test-password"),
+					..Default::default()
+				})
+				.await?;
+
+			services
+				.users
+				.create_device(user_id, None, (None, None), None, None, None)
+				.await?;
+		}
+
+		services.db["userid_password"].insert(bob.as_bytes(), b"not-a-password-hash");
+
+		// A guest has no password but is not deactivated.
+		let carol = UserId::parse_with_server_name("carol", server_name)?;
+		services
+			.users
+			.full_register(Register {
+				user_id: Some(&carol),
+				is_guest: true,
+				..Default::default()
+			})
+			.await?;
+
+		services
+			.users
+			.create_device(&carol, None, (None, None), None, None, None)
+			.await?;
+
+		let body = run_admin_command(services, "check check-all-users").await?;
+		if !body.contains(&format!("{bob}\tmalformed password hash")) {
+			return Err!("corrupt hash was not flagged: {body}");
+		}
+
+		for user_id in [&alice, &carol] {
+			if body.contains(&format!("{user_id}\t")) {
+				return Err!("valid user {user_id} was flagged: {body}");
+			}
+		}
+
+		Ok(())
+	})
+}
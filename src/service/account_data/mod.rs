@@ -183,6 +183,37 @@ pub fn changes_since<'a>(
 		.ignore_err()
 }
 
+/// Rooms in which the user has account data and a membership of any kind,
+/// including left rooms not yet forgotten. The tables are keyed by room before
+/// user, so each of the user's rooms is checked by prefix rather than scanning
+/// the data of every user.
+#[implement(Service)]
+pub fn rooms_with_data<'a>(
+	&'a self,
+	user_id: &'a UserId,
+) -> impl Stream<Item = &'a RoomId> + Send + 'a {
+	let state_cache = &self.services.state_cache;
+
+	state_cache
+		.rooms_joined(user_id)
+		.chain(state_cache.rooms_invited(user_id))
+		.chain(state_cache.rooms_knocked(user_id))
+		.chain(state_cache.rooms_left(user_id))
+		.filter(move |&room_id| self.has_room_data(room_id, user_id))
+}
+
+#[implement(Service)]
+async fn has_room_data(&self, room_id: &RoomId, user_id: &UserId) -> bool {
+	let prefix = (Some(room_id), user_id, Interfix);
+
+	self.db
+		.roomusertype_roomuserdataid
+		.keys_prefix_raw(&prefix)
+		.ignore_err()
+		.ready_any(|_| true)
+		.await
+}
+
 /// MSC4025: erase all account data for a user in the given namespace
 /// (global if `room_id` is `None`, otherwise a single room). Mirrors
 /// `threads::delete_all_rooms_threads`: prefix-scan the keys and
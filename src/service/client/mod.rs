@@ -126,7 +126,7 @@ macro_rules! with {
 
 		synapse: with!(cb => cb
 			.dns_resolver(Arc::clone(&services.resolver.resolver.hooked))
-			.read_timeout(Duration::from_secs(305))
+			.read_timeout(Duration::from_secs(services.config.federation_synapse_timeout_s))
 			.pool_max_idle_per_host(0)
 			.redirect(redirect::Policy::limited(3))),
 
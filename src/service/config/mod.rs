@@ -4,7 +4,7 @@
 use tuwunel_core::{
 	Result, Server,
 	config::{Config, check},
-	error, implement,
+	error, implement, info,
 };
 
 pub struct Service {
@@ -65,14 +65,24 @@ fn handle_reload(&self) -> Result {
 	Ok(())
 }
 
+/// Re-reads the configuration and applies the options documented as
+/// `reloadable: yes`, such as timeouts, rate limits and feature toggles. Every
+/// other option keeps its current value until a restart. Returns the names of
+/// the options applied, and of the changed options left for a restart.
 #[implement(Service)]
-pub fn reload<'a, I>(&self, paths: I) -> Result<Arc<Config>>
+pub fn reload<'a, I>(&self, paths: I) -> Result<(Vec<&'static str>, Vec<&'static str>)>
 where
 	I: Iterator<Item = &'a Path>,
 {
 	let old = self.server.config.clone();
 	let new = Config::load(paths).and_then(|raw| Config::new(&raw))?;
 
-	check::reload(&old, &new)?;
-	self.server.config.update(new)
+	let mut config = (*old).clone();
+	let (applied, ignored) = config.reload_from(&new);
+
+	check::reload(&old, &config)?;
+	self.server.config.update(config)?;
+	info!(?applied, ?ignored, "Reloaded configuration");
+
+	Ok((applied, ignored))
 }
@@ -96,20 +96,46 @@ pub async fn should_attempt(&self, server: &ServerName) -> ShouldAttempt {
 		};
 	}
 
+	let streak = self.streak_from(server, now_bucket).await;
+
+	ShouldAttempt::No {
+		earliest_retry: self.earliest_retry(now_bucket, streak),
+	}
+}
+
+/// Number of consecutive windows, up to the present one, in which a failure
+/// to reach `server` was recorded. Zero when the latest attempt succeeded or
+/// the present window saw no failure.
+#[implement(super::Service)]
+#[tracing::instrument(skip(self), fields(%server), level = "trace")]
+pub async fn failure_streak(&self, server: &ServerName) -> u32 {
+	let now_bucket = self.current_bucket();
+	if !self
+		.statuses
+		.contains(&(server, now_bucket))
+		.await
+	{
+		return 0;
+	}
+
+	self.streak_from(server, now_bucket).await
+}
+
+/// Streak of failed windows ending at the populated `bucket`.
+#[implement(super::Service)]
+async fn streak_from(&self, server: &ServerName, bucket: u64) -> u32 {
 	// streak walks back until the first gap; async `contains` predicate
 	// forces an imperative loop rather than `take_while`.
 	let mut streak: u32 = 1;
 	while streak < self.n_max {
-		let prior = now_bucket.saturating_sub(u64::from(streak));
+		let prior = bucket.saturating_sub(u64::from(streak));
 		if !self.statuses.contains(&(server, prior)).await {
 			break;
 		}
 		streak = streak.saturating_add(1);
 	}
 
-	ShouldAttempt::No {
-		earliest_retry: self.earliest_retry(now_bucket, streak),
-	}
+	streak
 }
 
 /// Yields one tuple per populated bucket, ordered by `(server, bucket_start)`.
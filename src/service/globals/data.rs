@@ -20,6 +20,7 @@ pub struct Data {
 type Callback = Box<dyn Fn(u64) -> Result + Send + Sync>;
 
 const COUNTER: &[u8] = b"c";
+const READ_ONLY_MODE: &[u8] = b"read_only_mode";
 
 impl Data {
 	pub(super) fn new(args: &crate::Args<'_>) -> Self {
@@ -30,8 +31,9 @@ pub(super) fn new(args: &crate::Args<'_>) -> Self {
 			db: args.db.clone(),
 			global: args.db["global"].clone(),
 			retires: retires.clone(),
-			counter: Counter::new(
+			counter: Counter::with_batch(
 				count,
+				args.server.config.counter_checkpoint_batch,
 				Box::new(move |count| Self::store_count(&db, &db["global"], count)),
 				Box::new(move |count| Self::handle_retire(&retires, count)),
 			),
@@ -100,6 +102,18 @@ pub fn bump_database_version(&self, new_version: u64) {
 		self.global.raw_put(b"version", new_version);
 	}
 
+	pub(super) fn stored_read_only_mode(&self) -> bool {
+		self.global.get_blocking(READ_ONLY_MODE).is_ok()
+	}
+
+	pub(super) fn store_read_only_mode(&self, enabled: bool) {
+		if enabled {
+			self.global.insert(READ_ONLY_MODE, []);
+		} else {
+			self.global.remove(READ_ONLY_MODE);
+		}
+	}
+
 	pub async fn database_version(&self) -> u64 {
 		self.global
 			.get(b"version")
@@ -1,19 +1,39 @@
 mod data;
 
-use std::{ops::Range, sync::Arc};
+use std::{
+	ops::Range,
+	sync::{
+		Arc,
+		atomic::{AtomicBool, Ordering},
+	},
+};
 
 use data::Data;
-use ruma::{OwnedUserId, RoomAliasId, ServerName, UserId};
-use tuwunel_core::{Result, Server, err, error};
+use ruma::{OwnedRoomAliasId, OwnedUserId, RoomAliasId, RoomId, ServerName, UserId};
+use serde::Serialize;
+use tuwunel_core::{Err, Result, Server, err, error, utils::stream::ReadyExt};
 
 use crate::service;
 
 pub struct Service {
 	pub db: Data,
 	server: Arc<Server>,
+	services: Arc<crate::services::OnceServices>,
 
 	pub server_user: OwnedUserId,
 	pub turn_secret: Option<String>,
+	read_only_mode: AtomicBool,
+}
+
+/// Optional features enabled by the server's configuration, advertised to
+/// clients under a custom key of `/capabilities`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+pub struct Capabilities {
+	pub local_presence: bool,
+	pub guest_access: bool,
+	pub registration: bool,
+	pub encryption: bool,
+	pub federation: bool,
 }
 
 impl crate::Service for Service {
@@ -33,15 +53,19 @@ fn build(args: &crate::Args<'_>) -> Result<Arc<Self>> {
 			})
 			.or_else(|| config.turn_secret.clone());
 
+		let read_only_mode = AtomicBool::new(db.stored_read_only_mode());
+
 		Ok(Arc::new(Self {
 			db,
 			server: args.server.clone(),
+			services: args.services.clone(),
 			server_user: UserId::parse_with_server_name(
 				String::from("conduit"),
 				&args.server.name,
 			)
 			.expect("@conduit:server_name is valid"),
 			turn_secret,
+			read_only_mode,
 		}))
 	}
 
@@ -96,6 +120,51 @@ pub fn alias_is_local(&self, alias: &RoomAliasId) -> bool {
 		self.server_is_ours(alias.server_name())
 	}
 
+	/// Forms an alias on this server from `localpart` without any policy
+	/// checks; reserved for the server's own aliases.
+	pub fn local_alias(&self, localpart: &str) -> Result<OwnedRoomAliasId> {
+		let alias = format!("#{localpart}:{}", self.server_name());
+
+		OwnedRoomAliasId::try_from(alias).map_err(Into::into)
+	}
+
+	/// Like `local_alias` but rejects localparts matching
+	/// `reserved_alias_patterns`.
+	pub fn local_alias_checked(&self, localpart: &str) -> Result<OwnedRoomAliasId> {
+		if self
+			.server
+			.config
+			.reserved_alias_patterns
+			.is_match(localpart)
+		{
+			return Err!(Request(Exclusive("Room alias {localpart:?} is reserved.")));
+		}
+
+		self.local_alias(localpart)
+	}
+
+	/// Whether `user_id` is managed by the server itself rather than a person,
+	/// i.e. the server user.
+	#[inline]
+	#[must_use]
+	pub fn is_system_user(&self, user_id: &UserId) -> bool { user_id == self.server_user }
+
+	/// Whether `room_id` is managed by the server itself: the admin room, or a
+	/// room holding one of the aliases reserved for the server's own use by
+	/// `reserved_alias_patterns`, such as the user rooms.
+	pub async fn is_system_room(&self, room_id: &RoomId) -> bool {
+		if self.services.admin.is_admin_room(room_id).await {
+			return true;
+		}
+
+		let reserved = &self.server.config.reserved_alias_patterns;
+		self.services
+			.alias
+			.local_aliases_for_room(room_id)
+			.ready_any(|alias| reserved.is_match(alias.alias()))
+			.await
+	}
+
 	#[inline]
 	#[must_use]
 	pub fn server_is_ours(&self, server_name: &ServerName) -> bool {
@@ -106,6 +175,49 @@ pub fn server_is_ours(&self, server_name: &ServerName) -> bool {
 	#[must_use]
 	pub fn is_read_only(&self) -> bool { self.db.db.is_read_only() }
 
+	/// Whether writes from clients and federation were paused at runtime with
+	/// `set_read_only_mode`. Unlike `is_read_only` the database itself is still
+	/// writable.
+	#[inline]
+	#[must_use]
+	pub fn read_only_mode(&self) -> bool { self.read_only_mode.load(Ordering::Acquire) }
+
+	/// Pause or resume writes. With `persist` the mode is also stored so it
+	/// survives a restart; disabling always clears the stored mode.
+	pub fn set_read_only_mode(&self, enabled: bool, persist: bool) {
+		self.read_only_mode
+			.store(enabled, Ordering::Release);
+
+		if persist || !enabled {
+			self.db.store_read_only_mode(enabled);
+		}
+	}
+
+	/// Rejects a write while `read_only_mode` is enabled.
+	pub fn check_writable(&self) -> Result {
+		if self.read_only_mode() {
+			return Err!(Request(Forbidden(
+				"The server is in read-only mode for maintenance; try again later."
+			)));
+		}
+
+		Ok(())
+	}
+
+	/// Snapshot of the configured feature flags; follows config reloads.
+	#[must_use]
+	pub fn capabilities(&self) -> Capabilities {
+		let config = &self.server.config;
+
+		Capabilities {
+			local_presence: config.allow_local_presence,
+			guest_access: config.allow_guest_registration,
+			registration: config.allow_registration,
+			encryption: config.allow_encryption,
+			federation: config.allow_federation,
+		}
+	}
+
 	pub fn init_rustls_provider(&self) -> Result {
 		if rustls::crypto::CryptoProvider::get_default().is_none() {
 			rustls::crypto::aws_lc_rs::default_provider()
@@ -58,9 +58,21 @@ pub fn create_backup(
 	Ok(version_string)
 }
 
+/// Deletes a backup and all its keys. Returns false when no such backup
+/// existed.
 #[implement(Service)]
-pub async fn delete_backup(&self, user_id: &UserId, version: &str) {
+pub async fn delete_backup(&self, user_id: &UserId, version: &str) -> Result<bool> {
 	let key = (user_id, version);
+	if self
+		.db
+		.backupid_algorithm
+		.qry(&key)
+		.await
+		.is_err()
+	{
+		return Ok(false);
+	}
+
 	self.db.backupid_algorithm.del(key);
 	self.db.backupid_etag.del(key);
 
@@ -73,6 +85,8 @@ pub async fn delete_backup(&self, user_id: &UserId, version: &str) {
 			self.db.backupkeyid_backup.remove(outdated_key);
 		})
 		.await;
+
+	Ok(true)
 }
 
 #[implement(Service)]
@@ -288,6 +302,22 @@ pub async fn get_all(
 	rooms
 }
 
+/// Like `get_all` but returns `None` when the backup's etag still equals the
+/// one the client already holds, sparing it a download of unchanged keys.
+#[implement(Service)]
+pub async fn get_all_if_changed(
+	&self,
+	user_id: &UserId,
+	version: &str,
+	client_etag: &str,
+) -> Option<BTreeMap<OwnedRoomId, RoomKeyBackup>> {
+	if self.get_etag(user_id, version).await == client_etag {
+		return None;
+	}
+
+	Some(self.get_all(user_id, version).await)
+}
+
 #[implement(Service)]
 pub async fn get_room(
 	&self,
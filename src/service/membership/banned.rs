@@ -0,0 +1,86 @@
+use std::net::IpAddr;
+
+use futures::FutureExt;
+use ruma::{RoomId, RoomOrAliasId, UserId};
+use tuwunel_core::{Err, Result, implement, result::LogErr, warn};
+
+use super::Service;
+
+/// Checks if the room is banned in any way possible and the sender user is not
+/// an admin.
+///
+/// Performs automatic deactivation if `auto_deactivate_banned_room_attempts` is
+/// enabled
+#[implement(Service)]
+#[tracing::instrument(skip(self))]
+pub async fn banned_room_check(
+	&self,
+	user_id: &UserId,
+	room_id: &RoomId,
+	orig_room_id: Option<&RoomOrAliasId>,
+	client_ip: IpAddr,
+) -> Result {
+	if self.services.admin.user_is_admin(user_id).await {
+		return Ok(());
+	}
+
+	// room id is banned ...
+	if self.services.metadata.is_banned(room_id).await
+		// ... or legacy room id server is banned ...
+		|| room_id.server_name().is_some_and(|server_name| {
+			self.services
+				.config
+				.is_forbidden_remote_server_name(server_name)
+		})
+		// ... or alias server is banned
+		|| orig_room_id.is_some_and(|orig_room_id| {
+			orig_room_id.server_name().is_some_and(|orig_server_name| {
+			self.services
+				.config
+				.is_forbidden_remote_server_name(orig_server_name)
+		})
+	}) {
+		warn!(
+			"User {user_id} who is not an admin attempted to send an invite for or attempted to \
+			 join a banned room or banned room server name: {room_id}"
+		);
+
+		self.maybe_deactivate(user_id, client_ip)
+			.await
+			.log_err()
+			.ok();
+
+		return Err!(Request(Forbidden("This room is banned on this homeserver.")));
+	}
+
+	Ok(())
+}
+
+#[implement(Service)]
+async fn maybe_deactivate(&self, user_id: &UserId, client_ip: IpAddr) -> Result {
+	if self
+		.services
+		.server
+		.config
+		.auto_deactivate_banned_room_attempts
+	{
+		let notice = format!(
+			"Automatically deactivating user {user_id} due to attempted banned room join from \
+			 IP {client_ip}"
+		);
+
+		warn!("{notice}");
+
+		if self.services.server.config.admin_room_notices {
+			self.services.admin.send_text(&notice).await;
+		}
+
+		self.services
+			.deactivate
+			.full_deactivate(user_id, false)
+			.boxed()
+			.await?;
+	}
+
+	Ok(())
+}
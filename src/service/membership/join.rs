@@ -692,6 +692,20 @@ pub async fn join_local(
 ) -> Result {
 	debug_info!("We can join locally");
 
+	// The memberships of a forgotten room are restored before anything relies
+	// on them.
+	if !self
+		.services
+		.state_cache
+		.server_in_room(self.services.globals.server_name(), room_id)
+		.await
+	{
+		self.services
+			.state
+			.rebuild_memberships(room_id, &state_lock)
+			.await?;
+	}
+
 	let join_rules_event_content = self
 		.services
 		.state_accessor
@@ -1,4 +1,5 @@
 mod ban;
+mod banned;
 mod invite;
 mod join;
 mod kick;
@@ -563,7 +563,7 @@ async fn retroactively_fix_bad_data_from_roomuserid_joined(services: &Services)
 			let count = services.globals.next_count();
 			services
 				.state_cache
-				.mark_as_left(user_id, room_id, PduCount::Normal(*count));
+				.mark_as_left(user_id, room_id, PduCount::Normal(*count), None);
 		}
 	}
 
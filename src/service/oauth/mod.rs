@@ -1,4 +1,5 @@
 pub mod providers;
+mod refresh;
 pub mod server;
 pub mod sessions;
 pub mod token_response;
@@ -8,9 +9,10 @@
 	collections::HashMap,
 	net::IpAddr,
 	sync::{Arc, Mutex},
-	time::Instant,
+	time::{Duration, Instant},
 };
 
+use async_trait::async_trait;
 use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD as b64encode};
 use futures::{Stream, StreamExt, TryStreamExt};
 use http::StatusCode;
@@ -44,6 +46,10 @@
 /// Per-client-IP token-bucket table: last-refill instant and remaining tokens.
 type Ratelimiter = Mutex<HashMap<IpAddr, (Instant, f64)>>;
 
+/// Sessions whose token refresh failed: time of the last failure and the
+/// number of failures in a row.
+type RefreshFailures = Mutex<HashMap<SessionId, (Instant, u32)>>;
+
 pub struct Service {
 	services: SelfServices,
 	pub providers: Arc<Providers>,
@@ -51,8 +57,10 @@ pub struct Service {
 	pub server: Option<Arc<Server>>,
 	ratelimiter: Ratelimiter,
 	device_ratelimiter: Ratelimiter,
+	refresh_failures: RefreshFailures,
 }
 
+#[async_trait]
 impl crate::Service for Service {
 	fn build(args: &crate::Args<'_>) -> Result<Arc<Self>> {
 		let providers = Arc::new(Providers::build(args));
@@ -66,9 +74,31 @@ fn build(args: &crate::Args<'_>) -> Result<Arc<Self>> {
 			server,
 			ratelimiter: Mutex::new(HashMap::new()),
 			device_ratelimiter: Mutex::new(HashMap::new()),
+			refresh_failures: Mutex::new(HashMap::new()),
 		}))
 	}
 
+	async fn worker(self: Arc<Self>) -> Result {
+		self.providers.prefetch().await;
+
+		loop {
+			let lead_seconds = self
+				.services
+				.config
+				.oauth_token_refresh_lead_seconds;
+
+			if lead_seconds != 0 {
+				self.refresh_expiring_tokens(Duration::from_secs(lead_seconds))
+					.await;
+			}
+
+			tokio::select! {
+				() = tokio::time::sleep(Duration::from_mins(1)) => {},
+				() = self.services.server.until_shutdown() => return Ok(())
+			};
+		}
+	}
+
 	fn name(&self) -> &str { crate::service::make_name(std::module_path!()) }
 }
 
@@ -304,6 +334,53 @@ struct TokenQuery<'a> {
 		.ok_or_else(|| err!(Config("token_url", "Missing token URL in config")))?;
 
 	self.request((Some(provider), Some(session)), Method::POST, url, Some(query))
+		.await
+		.and_then(|value| serde_json::from_value(value).map_err(Into::into))
+		.and_then(|response: TokenResponse| {
+			response.verify_nonce(session.query_nonce.as_deref())?;
+			Ok(response)
+		})
+		.log_err()
+}
+
+/// Network request to a Provider to obtain a new access token for a Session
+/// using its refresh token.
+#[implement(Service)]
+#[tracing::instrument(level = "debug", skip_all, ret)]
+pub async fn refresh_token(
+	&self,
+	(provider, session): (&Provider, &Session),
+) -> Result<TokenResponse> {
+	#[derive(Debug, Serialize)]
+	struct RefreshQuery<'a> {
+		client_id: &'a str,
+		client_secret: &'a str,
+		grant_type: &'a str,
+		refresh_token: &'a str,
+	}
+
+	let refresh_token = session
+		.refresh_token
+		.as_deref()
+		.ok_or_else(|| err!(Request(NotFound("No refresh token for this session."))))?;
+
+	let client_secret = provider.get_client_secret().await?;
+
+	let query = RefreshQuery {
+		client_id: &provider.client_id,
+		client_secret: &client_secret,
+		grant_type: "refresh_token",
+		refresh_token,
+	};
+
+	let url = provider
+		.token_url
+		.clone()
+		.ok_or_else(|| err!(Config("token_url", "Missing token URL in config")))?;
+
+	// The access token may already have lapsed; the refresh grant is
+	// authenticated by the client credentials instead.
+	self.request((Some(provider), None), Method::POST, url, Some(query))
 		.await
 		.and_then(|value| serde_json::from_value(value).map_err(Into::into))
 		.log_err()
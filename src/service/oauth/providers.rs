@@ -1,9 +1,12 @@
+#[cfg(test)]
+mod tests;
+
 use std::collections::BTreeMap;
 
 use serde_json::{Map as JsonObject, Value as JsonValue};
 use tokio::sync::RwLock;
 pub use tuwunel_core::config::IdentityProvider as Provider;
-use tuwunel_core::{Err, Result, debug, debug::INFO_SPAN_LEVEL, err, implement};
+use tuwunel_core::{Err, Result, debug, debug::INFO_SPAN_LEVEL, debug_warn, err, implement};
 use url::Url;
 
 use crate::{SelfServices, client::read_response_capped};
@@ -47,6 +50,29 @@ pub async fn get(&self, id: &str) -> Result<Provider> {
 	Ok(provider)
 }
 
+/// Warm the cache by performing discovery for every configured provider
+/// ahead of the first authorization. Failures are logged and otherwise left
+/// for `get()` to retry on demand.
+#[implement(Providers)]
+#[tracing::instrument(level = "debug", skip(self))]
+pub async fn prefetch(&self) {
+	let ids: Vec<_> = self
+		.services
+		.config
+		.identity_provider
+		.values()
+		.filter(|config| config.discovery)
+		.map(Provider::id)
+		.map(ToOwned::to_owned)
+		.collect();
+
+	for id in ids {
+		if let Err(e) = self.get(&id).await {
+			debug_warn!(?id, "Failed to discover identity provider: {e}");
+		}
+	}
+}
+
 /// Get the admin-configured Provider which exists prior to any
 /// reconciliation with the well-known discovery (the server's config is
 /// immutable); though it is important to note the server config can be
@@ -164,13 +190,51 @@ async fn configure(&self, mut provider: Provider) -> Result<Provider> {
 		})
 		.and_then(|response| check_issuer(response, &provider))?;
 
+	apply_discovery(&mut provider, &response)?;
+
+	if provider.callback_url.is_none()
+		&& let Some(server_url) = self.services.config.well_known.client.as_ref()
+	{
+		let callback_path =
+			format!("_matrix/client/unstable/login/sso/callback/{}", provider.client_id);
+
+		provider.callback_url = Some(server_url.join(&callback_path)?);
+	}
+
+	Ok(provider)
+}
+
+/// Send a network request to a provider at the computed location of the
+/// `.well-known/openid-configuration`, returning the configuration.
+#[implement(Providers)]
+#[tracing::instrument(level = "debug", ret(level = "trace"), skip(self))]
+pub async fn discover(&self, provider: &Provider) -> Result<JsonValue> {
+	let limit = self.services.config.max_response_size;
+	let response = self
+		.services
+		.client
+		.oauth
+		.get(discovery_url(provider)?)
+		.send()
+		.await?
+		.error_for_status()?;
+
+	let body = read_response_capped(response, limit).await?;
+
+	serde_json::from_slice(&body).map_err(Into::into)
+}
+
+/// Populate any endpoint URLs missing from the provider's config with those
+/// found in its discovery document, falling back to locations derived from the
+/// issuer. URLs explicitly configured by the admin always take precedence.
+fn apply_discovery(provider: &mut Provider, response: &JsonObject<String, JsonValue>) -> Result {
 	if provider.authorization_url.is_none() {
 		response
 			.get("authorization_endpoint")
 			.and_then(JsonValue::as_str)
 			.map(Url::parse)
 			.transpose()?
-			.or_else(|| make_url(&provider, "authorize").ok())
+			.or_else(|| make_url(provider, "authorize").ok())
 			.map(|url| provider.authorization_url.replace(url));
 	}
 
@@ -180,7 +244,7 @@ async fn configure(&self, mut provider: Provider) -> Result<Provider> {
 			.and_then(JsonValue::as_str)
 			.map(Url::parse)
 			.transpose()?
-			.or_else(|| make_url(&provider, "revocation").ok())
+			.or_else(|| make_url(provider, "revocation").ok())
 			.map(|url| provider.revocation_url.replace(url));
 	}
 
@@ -190,7 +254,7 @@ async fn configure(&self, mut provider: Provider) -> Result<Provider> {
 			.and_then(JsonValue::as_str)
 			.map(Url::parse)
 			.transpose()?
-			.or_else(|| make_url(&provider, "introspection").ok())
+			.or_else(|| make_url(provider, "introspection").ok())
 			.map(|url| provider.introspection_url.replace(url));
 	}
 
@@ -202,7 +266,7 @@ async fn configure(&self, mut provider: Provider) -> Result<Provider> {
 			.transpose()?
 			.or_else(|| match provider.brand.as_str() {
 				| "github" => "https://api.github.com/user".try_into().ok(),
-				| _ => make_url(&provider, "userinfo").ok(),
+				| _ => make_url(provider, "userinfo").ok(),
 			})
 			.map(|url| provider.userinfo_url.replace(url));
 	}
@@ -220,41 +284,12 @@ async fn configure(&self, mut provider: Provider) -> Result<Provider> {
 					"token"
 				};
 
-				make_url(&provider, path).ok()
+				make_url(provider, path).ok()
 			})
 			.map(|url| provider.token_url.replace(url));
 	}
 
-	if provider.callback_url.is_none()
-		&& let Some(server_url) = self.services.config.well_known.client.as_ref()
-	{
-		let callback_path =
-			format!("_matrix/client/unstable/login/sso/callback/{}", provider.client_id);
-
-		provider.callback_url = Some(server_url.join(&callback_path)?);
-	}
-
-	Ok(provider)
-}
-
-/// Send a network request to a provider at the computed location of the
-/// `.well-known/openid-configuration`, returning the configuration.
-#[implement(Providers)]
-#[tracing::instrument(level = "debug", ret(level = "trace"), skip(self))]
-pub async fn discover(&self, provider: &Provider) -> Result<JsonValue> {
-	let limit = self.services.config.max_response_size;
-	let response = self
-		.services
-		.client
-		.oauth
-		.get(discovery_url(provider)?)
-		.send()
-		.await?
-		.error_for_status()?;
-
-	let body = read_response_capped(response, limit).await?;
-
-	serde_json::from_slice(&body).map_err(Into::into)
+	Ok(())
 }
 
 /// Compute the location of the `/.well-known/openid-configuration` based on the
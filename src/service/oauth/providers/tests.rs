@@ -0,0 +1,98 @@
+use serde_json::{Value as JsonValue, json};
+use url::Url;
+
+use super::{Provider, apply_discovery, check_issuer};
+
+const ISSUER: &str = "https://idp.example.com/realms/test";
+
+fn provider(extra: JsonValue) -> Provider {
+	let mut config = json!({
+		"brand": "keycloak",
+		"client_id": "tuwunel",
+		"issuer_url": ISSUER,
+	});
+
+	config
+		.as_object_mut()
+		.expect("object")
+		.extend(extra.as_object().cloned().unwrap_or_default());
+
+	serde_json::from_value(config).expect("valid provider config")
+}
+
+fn url(url: Option<&Url>) -> Option<&str> { url.map(Url::as_str) }
+
+fn discovery_document() -> JsonValue {
+	json!({
+		"issuer": ISSUER,
+		"authorization_endpoint": "https://idp.example.com/auth",
+		"token_endpoint": "https://idp.example.com/token",
+		"userinfo_endpoint": "https://idp.example.com/userinfo",
+		"revocation_endpoint": "https://idp.example.com/revoke",
+		"introspection_endpoint": "https://idp.example.com/introspect",
+	})
+}
+
+#[test]
+fn discovery_populates_missing_endpoints() {
+	let mut provider = provider(json!({}));
+	let response = discovery_document()
+		.as_object()
+		.cloned()
+		.expect("object");
+
+	let response = check_issuer(response, &provider).expect("issuer matches");
+	apply_discovery(&mut provider, &response).expect("discovery applied");
+
+	assert_eq!(url(provider.authorization_url.as_ref()), Some("https://idp.example.com/auth"));
+	assert_eq!(url(provider.token_url.as_ref()), Some("https://idp.example.com/token"));
+	assert_eq!(url(provider.userinfo_url.as_ref()), Some("https://idp.example.com/userinfo"));
+	assert_eq!(url(provider.revocation_url.as_ref()), Some("https://idp.example.com/revoke"));
+	assert_eq!(
+		url(provider.introspection_url.as_ref()),
+		Some("https://idp.example.com/introspect")
+	);
+}
+
+#[test]
+fn configured_endpoints_take_precedence() {
+	let mut provider = provider(json!({
+		"token_url": "https://override.example.com/token",
+	}));
+
+	let response = discovery_document()
+		.as_object()
+		.cloned()
+		.expect("object");
+
+	apply_discovery(&mut provider, &response).expect("discovery applied");
+
+	assert_eq!(url(provider.token_url.as_ref()), Some("https://override.example.com/token"));
+	assert_eq!(url(provider.userinfo_url.as_ref()), Some("https://idp.example.com/userinfo"));
+}
+
+#[test]
+fn undiscovered_endpoints_derive_from_issuer() {
+	let mut provider = provider(json!({}));
+	let response = serde_json::Map::new();
+
+	apply_discovery(&mut provider, &response).expect("discovery applied");
+
+	assert_eq!(
+		url(provider.token_url.as_ref()),
+		Some("https://idp.example.com/realms/test/token")
+	);
+}
+
+#[test]
+fn mismatched_issuer_is_rejected() {
+	let provider = provider(json!({}));
+	let mut response = discovery_document()
+		.as_object()
+		.cloned()
+		.expect("object");
+
+	response.insert("issuer".into(), json!("https://evil.example.com"));
+
+	assert!(check_issuer(response, &provider).is_err());
+}
@@ -0,0 +1,108 @@
+use std::time::{Duration, Instant};
+
+use futures::StreamExt;
+use tuwunel_core::{
+	Result, debug, debug_warn, implement,
+	utils::{
+		continue_exponential_backoff_secs, stream::ReadyExt, timepoint_from_now,
+		timepoint_has_passed,
+	},
+};
+
+use super::Session;
+
+/// Refresh the access token of every session due to expire within `lead`,
+/// ahead of it lapsing. Sessions without a usable refresh token are skipped;
+/// providers which do not support refreshing never issue one. A session whose
+/// refresh keeps failing is retried with a growing backoff rather than on every
+/// sweep. Returns the number of sessions refreshed.
+#[implement(super::Service)]
+#[tracing::instrument(level = "debug", skip(self))]
+pub async fn refresh_expiring_tokens(&self, lead: Duration) -> usize {
+	let Ok(deadline) = timepoint_from_now(lead) else {
+		return 0;
+	};
+
+	let expiring: Vec<_> = self
+		.sessions
+		.stream()
+		.ready_filter(|session| {
+			session
+				.expires_at
+				.is_some_and(|at| at <= deadline)
+		})
+		.ready_filter(|session| session.refresh_token.is_some())
+		.ready_filter(|session| {
+			!session
+				.refresh_token_expires_at
+				.is_some_and(timepoint_has_passed)
+		})
+		.ready_filter(|session| !self.refresh_backing_off(session))
+		.collect()
+		.await;
+
+	let mut refreshed = 0_usize;
+	for session in expiring {
+		let sess_id = session.sess_id.clone();
+		let result = self.refresh_session(session).await;
+		self.record_refresh(sess_id.as_deref(), result.is_ok());
+		match result {
+			| Ok(()) => refreshed = refreshed.saturating_add(1),
+			| Err(e) => debug_warn!(?sess_id, "Failed to refresh access token: {e}"),
+		}
+	}
+
+	debug!(?refreshed, "Finished refreshing expiring access tokens");
+	refreshed
+}
+
+/// Whether the last failed refresh of `session` is too recent to try again.
+#[implement(super::Service)]
+fn refresh_backing_off(&self, session: &Session) -> bool {
+	const MIN_DELAY: u64 = 60;
+	const MAX_DELAY: u64 = 60 * 60 * 24;
+
+	let Some(sess_id) = session.sess_id.as_deref() else {
+		return false;
+	};
+
+	self.refresh_failures
+		.lock()
+		.expect("locked")
+		.get(sess_id)
+		.is_some_and(|(time, tries)| {
+			continue_exponential_backoff_secs(MIN_DELAY, MAX_DELAY, time.elapsed(), *tries)
+		})
+}
+
+#[implement(super::Service)]
+fn record_refresh(&self, sess_id: Option<&str>, succeeded: bool) {
+	let Some(sess_id) = sess_id else {
+		return;
+	};
+
+	let mut failures = self.refresh_failures.lock().expect("locked");
+	if succeeded {
+		failures.remove(sess_id);
+		return;
+	}
+
+	failures
+		.entry(sess_id.to_owned())
+		.and_modify(|(time, tries)| {
+			*time = Instant::now();
+			*tries = tries.saturating_add(1);
+		})
+		.or_insert((Instant::now(), 1));
+}
+
+#[implement(super::Service)]
+async fn refresh_session(&self, session: Session) -> Result {
+	let provider = self.sessions.provider(&session).await?;
+	let response = self.refresh_token((&provider, &session)).await?;
+	let session = response.into_session(session)?;
+
+	self.sessions.put(&session).await;
+
+	Ok(())
+}
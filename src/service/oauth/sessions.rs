@@ -82,7 +82,8 @@ pub struct Session {
 	/// Random string passed exclusively in the grant session cookie.
 	pub cookie_nonce: Option<String>,
 
-	/// Random single-use string passed in the provider redirect.
+	/// Random single-use string passed in the provider redirect; the id_token
+	/// must carry it back as its `nonce` claim.
 	pub query_nonce: Option<String>,
 
 	/// Point in time the authorization grant session expires.
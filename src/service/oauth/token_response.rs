@@ -1,4 +1,10 @@
+use std::time::Duration;
+
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD as b64};
 use serde::Deserialize;
+use tuwunel_core::{Err, Result, utils::timepoint_from_now};
+
+use super::Session;
 
 /// Deserialization target for the upstream provider's `/token` JSON response.
 /// Kept distinct from `Session` because some providers emit `expires_at` as a
@@ -27,3 +33,136 @@ pub struct TokenResponse {
 	/// Signed JWT containing the user's identity claims (OIDC).
 	pub id_token: Option<String>,
 }
+
+#[derive(Deserialize)]
+struct IdTokenNonce {
+	nonce: Option<String>,
+}
+
+impl TokenResponse {
+	/// Update `session` with the tokens granted by this response, converting
+	/// the relative lifetimes into points in time. What the response leaves out
+	/// is kept from the session: refresh responses commonly omit the id_token
+	/// and scope, and providers which do not rotate the refresh token omit it
+	/// while the one we hold remains valid.
+	pub fn into_session(self, session: Session) -> Result<Session> {
+		let expires_at = self
+			.expires_in
+			.map(Duration::from_secs)
+			.map(timepoint_from_now)
+			.transpose()?;
+
+		let (refresh_token, refresh_token_expires_at) = match self.refresh_token {
+			| Some(refresh_token) => (
+				Some(refresh_token),
+				self.refresh_token_expires_in
+					.map(Duration::from_secs)
+					.map(timepoint_from_now)
+					.transpose()?,
+			),
+			| None => (session.refresh_token.clone(), session.refresh_token_expires_at),
+		};
+
+		Ok(Session {
+			scope: self.scope.or(session.scope.clone()),
+			token_type: self.token_type.or(session.token_type.clone()),
+			access_token: self.access_token,
+			id_token: self.id_token.or(session.id_token.clone()),
+			expires_at,
+			refresh_token,
+			refresh_token_expires_at,
+			..session
+		})
+	}
+
+	/// Rejects an `id_token` whose `nonce` claim differs from the nonce sent in
+	/// the session's authorization request, so an id_token issued for another
+	/// session cannot be replayed into this one. Responses without an
+	/// id_token, or sessions which sent no nonce, pass.
+	pub fn verify_nonce(&self, expected: Option<&str>) -> Result {
+		let (Some(id_token), Some(expected)) = (self.id_token.as_deref(), expected) else {
+			return Ok(());
+		};
+
+		let nonce = id_token
+			.split('.')
+			.nth(1)
+			.and_then(|payload| b64.decode(payload).ok())
+			.and_then(|payload| serde_json::from_slice::<IdTokenNonce>(&payload).ok())
+			.and_then(|claims| claims.nonce);
+
+		if nonce.as_deref() != Some(expected) {
+			return Err!(Request(Forbidden("id_token nonce does not match the session.")));
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD as b64};
+
+	use super::TokenResponse;
+	use crate::oauth::Session;
+
+	fn response(claims: &str) -> TokenResponse {
+		TokenResponse {
+			token_type: None,
+			access_token: Some("access".to_owned()),
+			expires_in: None,
+			refresh_token: None,
+			refresh_token_expires_in: None,
+			scope: None,
+			id_token: Some(format!("header.{}.signature", b64.encode(claims))),
+		}
+	}
+
+	#[test]
+	fn matching_nonce_is_accepted() {
+		let response = response(r#"{"sub":"alice","nonce":"expected"}"#);
+		assert!(response.verify_nonce(Some("expected")).is_ok());
+	}
+
+	#[test]
+	fn mismatched_nonce_is_rejected() {
+		let response = response(r#"{"sub":"alice","nonce":"replayed"}"#);
+		let error = response
+			.verify_nonce(Some("expected"))
+			.expect_err("mismatched nonce must be rejected");
+
+		assert!(error.to_string().contains("nonce"), "unexpected error: {error}");
+	}
+
+	#[test]
+	fn missing_nonce_claim_is_rejected() {
+		let response = response(r#"{"sub":"alice"}"#);
+		assert!(response.verify_nonce(Some("expected")).is_err());
+	}
+
+	#[test]
+	fn unsent_nonce_is_not_checked() {
+		let response = response(r#"{"sub":"alice","nonce":"anything"}"#);
+		assert!(response.verify_nonce(None).is_ok());
+	}
+
+	#[test]
+	fn omitted_fields_are_kept_from_session() {
+		let session = Session {
+			id_token: Some("original-id-token".to_owned()),
+			scope: Some("openid profile".to_owned()),
+			refresh_token: Some("original-refresh".to_owned()),
+			..Default::default()
+		};
+
+		let response = TokenResponse { id_token: None, ..response("{}") };
+		let session = response
+			.into_session(session)
+			.expect("session updated");
+
+		assert_eq!(session.access_token.as_deref(), Some("access"));
+		assert_eq!(session.id_token.as_deref(), Some("original-id-token"));
+		assert_eq!(session.scope.as_deref(), Some("openid profile"));
+		assert_eq!(session.refresh_token.as_deref(), Some("original-refresh"));
+	}
+}
@@ -1,18 +1,23 @@
 use std::sync::Arc;
 
-use futures::Stream;
-use ruma::{UInt, UserId, events::presence::PresenceEvent, presence::PresenceState};
+use futures::{Stream, StreamExt, future::Either};
+use ruma::{OwnedUserId, UInt, UserId, events::presence::PresenceEvent, presence::PresenceState};
 use tuwunel_core::{
 	Result, debug_warn, utils,
-	utils::{ReadyExt, stream::TryIgnore},
+	utils::{
+		ReadyExt,
+		stream::{IterStream, TryIgnore},
+	},
 };
 use tuwunel_database::{Deserialized, Json, Map};
 
+use super::recent::Recent;
 use crate::presence::Presence;
 
 pub(crate) struct Data {
 	presenceid_presence: Arc<Map>,
 	userid_presenceid: Arc<Map>,
+	recent: Recent,
 	services: Arc<crate::services::OnceServices>,
 }
 
@@ -22,6 +27,7 @@ pub(super) fn new(args: &crate::Args<'_>) -> Self {
 		Self {
 			presenceid_presence: db["presenceid_presence"].clone(),
 			userid_presenceid: db["userid_presenceid"].clone(),
+			recent: Recent::new(args.server.config.presence_cache_size),
 			services: args.services.clone(),
 		}
 	}
@@ -131,6 +137,12 @@ pub(super) async fn set_presence(
 		let count = self.services.globals.next_count();
 		let key = presenceid_key(*count, user_id);
 
+		if self.recent.is_enabled()
+			&& let Ok(bytes) = serde_json::to_vec(&presence)
+		{
+			self.recent.insert(user_id, *count, bytes);
+		}
+
 		self.userid_presenceid.raw_put(user_id, *count);
 		self.presenceid_presence
 			.raw_put(key, Json(presence));
@@ -157,15 +169,20 @@ pub(super) async fn remove_presence(&self, user_id: &UserId) {
 		let key = presenceid_key(count, user_id);
 		self.presenceid_presence.remove(&key);
 		self.userid_presenceid.remove(user_id);
+		self.recent.remove(user_id);
 	}
 
-	#[inline]
 	pub(super) fn presence_since(
 		&self,
 		since: u64,
 		to: Option<u64>,
-	) -> impl Stream<Item = (&UserId, u64, &[u8])> + Send + '_ {
-		self.presenceid_presence
+	) -> impl Stream<Item = (OwnedUserId, u64, Vec<u8>)> + Send + '_ {
+		if let Some(updates) = self.recent.since(since, to) {
+			return Either::Left(updates.into_iter().stream());
+		}
+
+		let updates = self
+			.presenceid_presence
 			.raw_stream()
 			.ignore_err()
 			.ready_filter_map(move |(key, presence)| {
@@ -173,6 +190,9 @@ pub(super) fn presence_since(
 				(count > since && to.is_none_or(|to| count <= to))
 					.then_some((user_id, count, presence))
 			})
+			.map(|(user_id, count, presence)| (user_id.to_owned(), count, presence.to_vec()));
+
+		Either::Right(updates)
 	}
 }
 
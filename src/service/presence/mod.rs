@@ -2,8 +2,13 @@
 mod data;
 // Write/update pipeline lives in pipeline.rs.
 mod pipeline;
+mod recent;
 
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{
+	collections::{HashMap, HashSet},
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
 
 use async_trait::async_trait;
 use futures::{
@@ -13,7 +18,7 @@
 };
 use loole::{Receiver, Sender};
 use ruma::{
-	OwnedUserId, UInt, UserId,
+	OwnedServerName, OwnedUserId, ServerName, UInt, UserId,
 	events::presence::{PresenceEvent, PresenceEventContent},
 	presence::PresenceState,
 };
@@ -23,7 +28,7 @@
 	Result, checked, debug, debug_warn, err,
 	result::LogErr,
 	trace,
-	utils::{self, TryFutureExtExt},
+	utils::{self, TryFutureExtExt, stream::ReadyExt},
 };
 
 use self::{aggregate::PresenceAggregator, data::Data};
@@ -54,8 +59,17 @@ pub struct Service {
 	services: Arc<crate::services::OnceServices>,
 	last_sync_seen: RwLock<HashMap<OwnedUserId, u64>>,
 	device_presence: PresenceAggregator,
+	federation_servers: Mutex<FederationServers>,
 }
 
+/// The servers each local user's presence was last found to federate to under
+/// `presence_federation_server_limit`, and when.
+type FederationServers = HashMap<OwnedUserId, (Instant, Arc<HashSet<OwnedServerName>>)>;
+
+/// How long a computed `FederationServers` entry is used before the rooms the
+/// user shares are counted again.
+const FEDERATION_SERVERS_TTL: Duration = Duration::from_mins(1);
+
 type TimerType = (OwnedUserId, Duration, u64);
 type TimerFired = (OwnedUserId, u64);
 
@@ -74,6 +88,7 @@ fn build(args: &crate::Args<'_>) -> Result<Arc<Self>> {
 			services: args.services.clone(),
 			last_sync_seen: RwLock::new(HashMap::new()),
 			device_presence: PresenceAggregator::new(),
+			federation_servers: Mutex::new(HashMap::new()),
 		}))
 	}
 
@@ -196,14 +211,17 @@ pub async fn remove_presence(&self, user_id: &UserId) {
 		self.db.remove_presence(user_id).await;
 	}
 
-	// Unset online/unavailable presence to offline on startup
-	async fn unset_all_presence(&self) {
+	/// Resets every local user's online, unavailable or busy presence to
+	/// offline; run on startup and on demand by admins. Returns the number of
+	/// users reset.
+	pub async fn unset_all_presence(&self) -> usize {
 		if !self.services.server.config.allow_local_presence || self.services.db.is_read_only() {
-			return;
+			return 0;
 		}
 
 		let _cork = self.services.db.cork();
 
+		let mut reset = 0_usize;
 		for user_id in &self
 			.services
 			.users
@@ -229,7 +247,7 @@ async fn unset_all_presence(&self) {
 
 			trace!(?user_id, ?presence, "Resetting presence to offline");
 
-			_ = self
+			match self
 				.set_presence(
 					user_id,
 					&PresenceState::Offline,
@@ -238,23 +256,123 @@ async fn unset_all_presence(&self) {
 					presence.status_msg.clone(),
 				)
 				.await
-				.inspect_err(|e| {
-					debug_warn!(
-						?presence,
-						"{user_id} has invalid presence in database and failed to reset it to \
-						 offline: {e}"
-					);
-				});
+			{
+				| Ok(()) => reset = reset.saturating_add(1),
+				| Err(e) => debug_warn!(
+					?presence,
+					"{user_id} has invalid presence in database and failed to reset it to \
+					 offline: {e}"
+				),
+			}
+		}
+
+		reset
+	}
+
+	/// Whether a local user's presence is federated to `server_name`. Every
+	/// server sharing a room with the user qualifies unless
+	/// `presence_federation_server_limit` caps the fan-out; the capped set is
+	/// computed once per user for all destinations and reused for
+	/// `FEDERATION_SERVERS_TTL`.
+	pub async fn presence_federates_to(
+		&self,
+		server_name: &ServerName,
+		user_id: &UserId,
+	) -> bool {
+		let limit = self
+			.services
+			.config
+			.presence_federation_server_limit;
+
+		if limit == 0 {
+			return self
+				.services
+				.state_cache
+				.server_sees_user(server_name, user_id)
+				.await;
+		}
+
+		self.federation_servers(user_id, limit)
+			.await
+			.contains(server_name)
+	}
+
+	async fn federation_servers(
+		&self,
+		user_id: &UserId,
+		limit: usize,
+	) -> Arc<HashSet<OwnedServerName>> {
+		let cached = self
+			.federation_servers
+			.lock()
+			.expect("locked")
+			.get(user_id)
+			.filter(|(computed, _)| computed.elapsed() < FEDERATION_SERVERS_TTL)
+			.map(|(_, servers)| servers.clone());
+
+		if let Some(servers) = cached {
+			return servers;
+		}
+
+		let servers: Arc<HashSet<_>> = Arc::new(
+			self.presence_servers(user_id, limit)
+				.await
+				.into_iter()
+				.collect(),
+		);
+
+		let mut cache = self.federation_servers.lock().expect("locked");
+		cache.retain(|_, (computed, _)| computed.elapsed() < FEDERATION_SERVERS_TTL);
+		cache.insert(user_id.to_owned(), (Instant::now(), servers.clone()));
+
+		servers
+	}
+
+	/// The remote servers sharing a room with a local user, those sharing the
+	/// most rooms first, and at most `limit` of them when non-zero.
+	pub async fn presence_servers(&self, user_id: &UserId, limit: usize) -> Vec<OwnedServerName> {
+		let rooms: Vec<_> = self
+			.services
+			.state_cache
+			.rooms_joined(user_id)
+			.map(ToOwned::to_owned)
+			.collect()
+			.await;
+
+		let mut shared = HashMap::<OwnedServerName, usize>::new();
+		for room_id in &rooms {
+			self.services
+				.state_cache
+				.room_servers(room_id)
+				.ready_filter(|server| !self.services.globals.server_is_ours(server))
+				.ready_for_each(|server| {
+					let count = shared.entry(server.to_owned()).or_default();
+					*count = count.saturating_add(1);
+				})
+				.await;
 		}
+
+		let mut servers: Vec<_> = shared.into_iter().collect();
+		servers.sort_unstable_by(|(a, a_rooms), (b, b_rooms)| {
+			b_rooms.cmp(a_rooms).then_with(|| a.cmp(b))
+		});
+
+		let limit = if limit == 0 { usize::MAX } else { limit };
+		servers
+			.into_iter()
+			.take(limit)
+			.map(|(server, _)| server)
+			.collect()
 	}
 
 	/// Returns the most recent presence updates that happened after the event
-	/// with id `since`.
+	/// with id `since`. Recent requests are served from memory when
+	/// `presence_cache_size` is configured.
 	pub fn presence_since(
 		&self,
 		since: u64,
 		to: Option<u64>,
-	) -> impl Stream<Item = (&UserId, u64, &[u8])> + Send + '_ {
+	) -> impl Stream<Item = (OwnedUserId, u64, Vec<u8>)> + Send + '_ {
 		self.db.presence_since(since, to)
 	}
 
@@ -8,7 +8,9 @@
 
 use futures::TryFutureExt;
 use ruma::{
-	DeviceId, OwnedUserId, UInt, UserId, events::presence::PresenceEvent, presence::PresenceState,
+	DeviceId, OwnedUserId, UInt, UserId,
+	events::presence::{PresenceEvent, PresenceEventContent},
+	presence::PresenceState,
 };
 use tokio::time::sleep;
 use tuwunel_core::{
@@ -23,6 +25,11 @@
 	aggregate::{self, StatusMsg},
 };
 
+/// How much older than the stored presence a remote update may appear while
+/// still changing the state, absorbing transit delay and clock skew between
+/// the servers which `last_active_ago` is measured on.
+const REMOTE_PRESENCE_SLACK_MS: u64 = 30_000;
+
 impl Service {
 	fn device_key(device_id: Option<&DeviceId>, is_remote: bool) -> aggregate::DeviceKey {
 		if is_remote {
@@ -277,25 +284,51 @@ pub async fn set_presence_for_device(
 		.await
 	}
 
-	/// Applies a presence update received over federation.
-	pub async fn set_presence_from_federation(
+	/// Applies a presence update received over federation unless it is older
+	/// than the presence already stored for the user. An update changing the
+	/// state is still applied when it is older by no more than
+	/// `REMOTE_PRESENCE_SLACK_MS`. The update's `last_active_ago` is taken
+	/// relative to `origin_ts`. Returns whether the update was applied; timers
+	/// for remote users are only armed with `presence_timeout_remote_users`.
+	pub async fn ingest_remote_presence(
 		&self,
 		user_id: &UserId,
-		state: &PresenceState,
-		currently_active: bool,
-		last_active_ago: UInt,
-		status_msg: Option<String>,
-	) -> Result {
+		content: &PresenceEventContent,
+		origin_ts: u64,
+	) -> Result<bool> {
+		let last_active_ago: u64 = content.last_active_ago.unwrap_or_default().into();
+		let last_active_ts = origin_ts.saturating_sub(last_active_ago);
+
+		if let Ok((_, stored)) = self.db.get_presence_raw(user_id).await
+			&& last_active_ts < stored.last_active_ts
+			&& (content.presence == stored.state
+				|| stored
+					.last_active_ts
+					.saturating_sub(last_active_ts)
+					> REMOTE_PRESENCE_SLACK_MS)
+		{
+			debug!(
+				?user_id,
+				last_active_ts,
+				stored_last_active_ts = stored.last_active_ts,
+				"Ignoring stale remote presence"
+			);
+			return Ok(false);
+		}
+
+		let now = tuwunel_core::utils::millis_since_unix_epoch();
 		self.apply_device_presence_update(
 			user_id,
 			Self::device_key(None, true),
-			state,
-			Some(currently_active),
-			Some(last_active_ago),
-			StatusMsg::Set(status_msg),
+			&content.presence,
+			content.currently_active,
+			Some(UInt::new_saturating(now.saturating_sub(last_active_ts))),
+			StatusMsg::Set(content.status_msg.clone()),
 			None,
 		)
-		.await
+		.await?;
+
+		Ok(true)
 	}
 
 	/// Adds a presence event which will be saved until a new event replaces it.
@@ -312,6 +345,14 @@ pub async fn set_presence(
 			| &_ => state,
 		};
 
+		let max_len = self
+			.services
+			.server
+			.config
+			.presence_status_msg_max_len;
+
+		let status_msg = status_msg.map(|msg| sanitize_status_msg(msg, max_len));
+
 		let count = self
 			.db
 			.set_presence(user_id, presence_state, currently_active, last_active_ago, status_msg)
@@ -478,3 +519,20 @@ fn timer_stale_detection() {
 		assert!(!Service::timer_is_stale(2, 2));
 	}
 }
+
+/// Strip control characters from a status message and truncate it to
+/// `max_len` characters; zero leaves the length unbounded.
+fn sanitize_status_msg(msg: String, max_len: usize) -> String {
+	let max_len = if max_len == 0 { usize::MAX } else { max_len };
+	if !msg.chars().any(char::is_control) && msg.chars().count() <= max_len {
+		return msg;
+	}
+
+	let mut chars = msg.chars().filter(|c| !c.is_control());
+	let sanitized: String = chars.by_ref().take(max_len).collect();
+	if chars.next().is_some() {
+		debug!(max_len, "Truncating presence status_msg");
+	}
+
+	sanitized
+}
@@ -0,0 +1,162 @@
+//! Ring of recent presence updates.
+//!
+//! Incremental syncs and federation senders ask for presence updates since a
+//! recent count. Rather than scanning the whole presence table each time, the
+//! most recent updates are kept here, coalesced to one entry per user just
+//! like the database. Queries older than the window return `None` so the
+//! caller falls back to the database.
+
+use std::{collections::VecDeque, sync::Mutex};
+
+use ruma::{OwnedUserId, UserId};
+
+pub(super) type Update = (OwnedUserId, u64, Vec<u8>);
+
+#[derive(Debug)]
+pub(super) struct Recent {
+	inner: Mutex<Ring>,
+	capacity: usize,
+}
+
+#[derive(Debug, Default)]
+struct Ring {
+	updates: VecDeque<Update>,
+
+	/// Every update with a count above the floor is in the ring. Unset until
+	/// the first update is recorded.
+	floor: Option<u64>,
+}
+
+impl Recent {
+	/// Create a ring holding at most `capacity` updates; zero disables it.
+	pub(super) fn new(capacity: usize) -> Self {
+		Self {
+			inner: Mutex::new(Ring::default()),
+			capacity,
+		}
+	}
+
+	#[inline]
+	pub(super) fn is_enabled(&self) -> bool { self.capacity > 0 }
+
+	/// Record a presence update, replacing any previous one for the user.
+	pub(super) fn insert(&self, user_id: &UserId, count: u64, bytes: Vec<u8>) {
+		if !self.is_enabled() {
+			return;
+		}
+
+		let mut ring = self.inner.lock().expect("locked for writing");
+		ring.updates
+			.retain(|(cached, ..)| cached != user_id);
+		ring.updates
+			.push_back((user_id.to_owned(), count, bytes));
+
+		ring.floor
+			.get_or_insert_with(|| count.saturating_sub(1));
+
+		while ring.updates.len() > self.capacity {
+			let Some((_, evicted, _)) = ring.updates.pop_front() else {
+				break;
+			};
+
+			ring.floor = ring.floor.max(Some(evicted));
+		}
+	}
+
+	/// Forget the update for a user whose presence was removed.
+	pub(super) fn remove(&self, user_id: &UserId) {
+		self.inner
+			.lock()
+			.expect("locked for writing")
+			.updates
+			.retain(|(cached, ..)| cached != user_id);
+	}
+
+	/// Updates after `since` and up to `to`, in count order. Returns `None`
+	/// when `since` predates the window and the database must be consulted.
+	pub(super) fn since(&self, since: u64, to: Option<u64>) -> Option<Vec<Update>> {
+		let ring = self.inner.lock().expect("locked for reading");
+		if ring.floor.is_none_or(|floor| since < floor) {
+			return None;
+		}
+
+		let mut updates: Vec<_> = ring
+			.updates
+			.iter()
+			.filter(|(_, count, _)| *count > since && to.is_none_or(|to| *count <= to))
+			.cloned()
+			.collect();
+
+		updates.sort_unstable_by_key(|(_, count, _)| *count);
+
+		Some(updates)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use ruma::user_id;
+
+	use super::Recent;
+
+	fn counts(updates: &[super::Update]) -> Vec<u64> {
+		updates
+			.iter()
+			.map(|(_, count, _)| *count)
+			.collect()
+	}
+
+	#[test]
+	fn recent_since_hits_ring() {
+		let recent = Recent::new(4);
+		recent.insert(user_id!("@a:example.com"), 10, b"a".to_vec());
+		recent.insert(user_id!("@b:example.com"), 11, b"b".to_vec());
+		recent.insert(user_id!("@c:example.com"), 12, b"c".to_vec());
+
+		let updates = recent.since(10, None).expect("served from ring");
+		assert_eq!(counts(&updates), [11, 12], "only updates after since");
+
+		let updates = recent
+			.since(9, Some(11))
+			.expect("served from ring");
+		assert_eq!(counts(&updates), [10, 11], "bounded by to");
+	}
+
+	#[test]
+	fn old_since_falls_back() {
+		let recent = Recent::new(2);
+		assert!(recent.since(0, None).is_none(), "empty ring must fall back");
+
+		recent.insert(user_id!("@a:example.com"), 10, b"a".to_vec());
+		recent.insert(user_id!("@b:example.com"), 11, b"b".to_vec());
+		recent.insert(user_id!("@c:example.com"), 12, b"c".to_vec());
+
+		assert!(recent.since(5, None).is_none(), "since predates the ring");
+		assert!(recent.since(9, None).is_none(), "evicted update must fall back");
+
+		let updates = recent.since(10, None).expect("served from ring");
+		assert_eq!(counts(&updates), [11, 12], "window after eviction");
+	}
+
+	#[test]
+	fn updates_coalesce_per_user() {
+		let recent = Recent::new(4);
+		recent.insert(user_id!("@a:example.com"), 10, b"old".to_vec());
+		recent.insert(user_id!("@b:example.com"), 11, b"b".to_vec());
+		recent.insert(user_id!("@a:example.com"), 12, b"new".to_vec());
+
+		let updates = recent.since(9, None).expect("served from ring");
+		assert_eq!(counts(&updates), [11, 12], "older update for user replaced");
+
+		recent.remove(user_id!("@b:example.com"));
+		let updates = recent.since(9, None).expect("served from ring");
+		assert_eq!(counts(&updates), [12], "removed user dropped");
+	}
+
+	#[test]
+	fn disabled_always_falls_back() {
+		let recent = Recent::new(0);
+		recent.insert(user_id!("@a:example.com"), 10, b"a".to_vec());
+		assert!(recent.since(10, None).is_none(), "disabled ring never serves");
+	}
+}
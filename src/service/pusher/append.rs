@@ -17,7 +17,9 @@
 		event::Event,
 		pdu::{Count, Pdu, PduId, RawPduId},
 	},
-	utils::{BoolExt, ReadyExt, future::TryExtExt, option::OptionExt, time::now_millis},
+	utils::{
+		BoolExt, IterStream, ReadyExt, future::TryExtExt, option::OptionExt, time::now_millis,
+	},
 };
 use tuwunel_database::{Deserialized, Json, Map};
 
@@ -47,19 +49,23 @@ pub struct Notified {
 #[tracing::instrument(name = "append", level = "debug", skip_all)]
 pub(crate) async fn append_pdu(&self, pdu_id: RawPduId, pdu: &Pdu) -> Result {
 	// Don't notify the sender of their own events, and dont send from ignored users
-	let push_target = self
+	let our_real_users = self
 		.services
 		.state_cache
-		.active_local_users_in_room(pdu.room_id())
-		.map(ToOwned::to_owned)
-		.ready_filter(|user| *user != pdu.sender())
+		.our_real_users(pdu.room_id())
+		.await;
+
+	let push_target = our_real_users
+		.iter()
+		.stream()
+		.ready_filter(|user| **user != pdu.sender())
 		.filter_map(async |recipient_user| {
 			self.services
 				.users
-				.user_is_ignored(pdu.sender(), &recipient_user)
+				.user_is_ignored(pdu.sender(), recipient_user)
 				.await
 				.is_false()
-				.then_some(recipient_user)
+				.then(|| recipient_user.clone())
 		})
 		.collect::<HashSet<_>>();
 
@@ -28,7 +28,10 @@
 };
 use tuwunel_database::{Database, Deserialized, Ignore, Interfix, Json, Map};
 
-pub use self::append::Notified;
+pub use self::{
+	append::Notified,
+	notification::{NotificationCounts, ThreadCounts},
+};
 
 pub struct Service {
 	services: Arc<crate::services::OnceServices>,
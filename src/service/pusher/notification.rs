@@ -1,6 +1,6 @@
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, iter::once};
 
-use futures::{StreamExt, stream::select};
+use futures::{StreamExt, future::join3, stream::select};
 use ruma::{EventId, OwnedEventId, RoomId, UserId, events::receipt::ReceiptThread};
 use tuwunel_core::{
 	Result, implement, trace,
@@ -9,7 +9,16 @@
 use tuwunel_database::{Deserialized, Ignore, Interfix};
 
 /// Per-thread unread counts: `(notification, highlight)` keyed by thread root.
-type ThreadCounts = BTreeMap<OwnedEventId, (u64, u64)>;
+pub type ThreadCounts = BTreeMap<OwnedEventId, (u64, u64)>;
+
+/// Unread `(notification, highlight)` counts of a room for one user, split
+/// between the main timeline and each thread. Every event is counted in
+/// exactly one of them, so the room-wide total is their sum.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct NotificationCounts {
+	pub main: (u64, u64),
+	pub threads: ThreadCounts,
+}
 
 /// Per-thread last-read counts keyed by thread root. Used by sync v3 to
 /// gate emission of `unread_thread_notifications` to threads whose read
@@ -158,6 +167,26 @@ pub async fn highlight_count(&self, user_id: &UserId, room_id: &RoomId) -> u64 {
 		.unwrap_or(0)
 }
 
+/// Main timeline and per-thread counts for one room and user, read together
+/// so the room-wide total can be derived from them.
+#[implement(super::Service)]
+#[tracing::instrument(level = "debug", skip(self))]
+pub async fn notification_counts(
+	&self,
+	user_id: &UserId,
+	room_id: &RoomId,
+) -> NotificationCounts {
+	let notifications = self.notification_count(user_id, room_id);
+	let highlights = self.highlight_count(user_id, room_id);
+	let threads = self.thread_notification_counts(user_id, room_id);
+	let (notifications, highlights, threads) = join3(notifications, highlights, threads).await;
+
+	NotificationCounts {
+		main: (notifications, highlights),
+		threads,
+	}
+}
+
 /// Per-thread `(notification, highlight)` counts for one room and user.
 /// `Interfix` excludes the legacy 2-tuple main row from the scan; only
 /// 3-tuple `(user, room, root)` rows match.
@@ -188,6 +217,19 @@ pub async fn thread_notification_counts(
 		.await
 }
 
+impl NotificationCounts {
+	/// Room-wide `(notification, highlight)` counts: the main timeline's plus
+	/// those of every thread.
+	#[must_use]
+	pub fn total(&self) -> (u64, u64) {
+		once(&self.main)
+			.chain(self.threads.values())
+			.fold((0, 0), |(notifications, highlights), &(n, h)| {
+				(notifications.saturating_add(n), highlights.saturating_add(h))
+			})
+	}
+}
+
 fn notification_kv(
 	(key, notifications): ((&UserId, &RoomId, OwnedEventId), u64),
 ) -> (OwnedEventId, (u64, u64)) {
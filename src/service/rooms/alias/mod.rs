@@ -146,7 +146,8 @@ pub async fn resolve_alias(
 		return self.remote_resolve(room_alias).await;
 	}
 
-	async fn remote_resolve(
+	/// Query the alias's server over federation, bypassing any local lookup.
+	pub async fn remote_resolve(
 		&self,
 		room_alias: &RoomAliasId,
 	) -> Result<(OwnedRoomId, Vec<OwnedServerName>)> {
@@ -249,7 +250,12 @@ async fn who_created_alias(&self, alias: &RoomAliasId) -> Result<OwnedUserId> {
 			.deserialized()
 	}
 
-	async fn resolve_appservice_alias(&self, room_alias: &RoomAliasId) -> Result<OwnedRoomId> {
+	/// Ask the appservices whose namespaces match the alias to create it, then
+	/// resolve it locally.
+	pub async fn resolve_appservice_alias(
+		&self,
+		room_alias: &RoomAliasId,
+	) -> Result<OwnedRoomId> {
 		use ruma::api::appservice::query::query_room_alias;
 
 		self.check_alias_local(room_alias)?;
@@ -2,13 +2,7 @@
 
 use futures::{FutureExt, StreamExt};
 use ruma::RoomId;
-use tuwunel_core::{
-	Result, debug,
-	result::LogErr,
-	trace,
-	utils::{ReadyExt, future::BoolExt},
-	warn,
-};
+use tuwunel_core::{Result, debug, result::LogErr, trace, utils::ReadyExt, warn};
 
 use crate::rooms::timeline::RoomMutexGuard;
 
@@ -31,23 +25,12 @@ pub async fn delete_if_empty_local(&self, room_id: &RoomId, state_lock: RoomMute
 			"Caller must checking if delete_rooms_after_leave configured."
 		);
 
-		let has_local_users = self
+		if !self
 			.services
 			.state_cache
-			.local_users_in_room(room_id)
-			.boxed()
-			.into_future()
-			.map(|(next, ..)| next.as_ref().is_some());
-
-		let has_local_invites = self
-			.services
-			.state_cache
-			.local_users_invited_to_room(room_id)
-			.boxed()
-			.into_future()
-			.map(|(next, ..)| next.as_ref().is_some());
-
-		if has_local_users.or(has_local_invites).await {
+			.maybe_forget_room(room_id)
+			.await
+		{
 			trace!(?room_id, "Not deleting with local joined or invited");
 			return;
 		}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Instant};
 
 use futures::{FutureExt, TryStreamExt, future::try_join5};
 use ruma::{
@@ -14,6 +14,7 @@
 	debug::INFO_SPAN_LEVEL,
 	debug_warn, err, implement,
 	matrix::{Event, PduCount, PduEvent, pdu::MAX_PREV_EVENTS, room_version::from_create_event},
+	metrics::PduOutcome,
 	smallvec::SmallVec,
 	trace,
 	utils::{
@@ -74,6 +75,36 @@ pub async fn handle_incoming_pdu<'a>(
 	event_id: &'a EventId,
 	pdu: CanonicalJsonObject,
 	is_timeline_event: bool,
+) -> Result<Handled> {
+	let timer = Instant::now();
+	let result = self
+		.process_incoming_pdu(origin, room_id, event_id, pdu, is_timeline_event)
+		.await;
+
+	let outcome = match &result {
+		| Ok(Some(_)) => PduOutcome::Accepted,
+		| Ok(None) => PduOutcome::Outlier,
+		| Err(e) if e.is_soft_failed() => PduOutcome::SoftFailed,
+		| Err(_) => PduOutcome::Rejected,
+	};
+
+	self.services
+		.server
+		.metrics
+		.incoming_pdu
+		.record(outcome, timer.elapsed());
+
+	result
+}
+
+#[implement(super::Service)]
+async fn process_incoming_pdu<'a>(
+	&'a self,
+	origin: &'a ServerName,
+	room_id: &'a RoomId,
+	event_id: &'a EventId,
+	pdu: CanonicalJsonObject,
+	is_timeline_event: bool,
 ) -> Result<Handled> {
 	// 1. Skip the PDU if we already have it as a timeline event
 	if let Ok(pdu_id) = self.services.timeline.get_pdu_id(event_id).await {
@@ -330,6 +361,11 @@ async fn handle_rescinded_invite(
 		)
 		.await?;
 
+	self.services
+		.state_cache
+		.maybe_forget_room(room_id)
+		.await;
+
 	debug!(%room_id, %target, %sender, "Applied a federated invite rescission.");
 
 	Ok(true)
@@ -9,6 +9,7 @@
 mod outlier_state;
 mod parse_incoming_pdu;
 mod policy_server;
+mod reprocess_outlier;
 mod resolve_state;
 mod state_at_incoming;
 mod upgrade_outlier_pdu;
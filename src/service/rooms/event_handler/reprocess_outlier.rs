@@ -0,0 +1,73 @@
+use futures::FutureExt;
+use ruma::{EventId, events::StateEventType};
+use tuwunel_core::{
+	Err, Error, Result, debug, err, implement,
+	matrix::{Event, PduEvent, room_version::from_create_event},
+};
+
+/// Re-run the upgrade of a stored outlier into the timeline, e.g. once the
+/// auth and prev events it was missing have since been obtained. Returns true
+/// when the event was accepted into the timeline by this call; false when it
+/// was already accepted or was soft-failed.
+#[implement(super::Service)]
+#[tracing::instrument(skip(self), level = "debug")]
+pub async fn reprocess_outlier(&self, event_id: &EventId) -> Result<bool> {
+	if self
+		.services
+		.timeline
+		.get_pdu_id(event_id)
+		.await
+		.is_ok()
+	{
+		debug!("Already accepted.");
+		return Ok(false);
+	}
+
+	let Ok(pdu_json) = self
+		.services
+		.timeline
+		.get_outlier_pdu_json(event_id)
+		.await
+	else {
+		return Err!(Request(NotFound("Event {event_id} is not a known outlier.")));
+	};
+
+	let pdu: PduEvent = self
+		.services
+		.timeline
+		.get_outlier_pdu(event_id)
+		.await?;
+
+	let room_id = pdu.room_id().to_owned();
+	let create_event = self
+		.services
+		.state_accessor
+		.room_state_get(&room_id, &StateEventType::RoomCreate, "")
+		.await
+		.map_err(|_| err!(Request(NotFound("Room {room_id} is unknown to this server."))))?;
+
+	let room_version = from_create_event(&create_event)?;
+	let origin = pdu.sender().server_name().to_owned();
+
+	let mutex_lock = self.mutex_federation.lock(&room_id).await;
+	let result = self
+		.upgrade_outlier_to_timeline_pdu(
+			&origin,
+			&room_id,
+			pdu,
+			pdu_json,
+			&room_version,
+			0,
+			create_event.event_id(),
+		)
+		.boxed()
+		.await;
+
+	drop(mutex_lock);
+
+	match result {
+		| Ok(upgraded) => Ok(upgraded.is_some_and(|(_, accepted)| accepted)),
+		| Err(Error::SoftFailed(..)) => Ok(false),
+		| Err(e) => Err(e),
+	}
+}
@@ -1,9 +1,9 @@
 use std::{borrow::Borrow, collections::HashMap, sync::Arc};
 
-use futures::{FutureExt, Stream, StreamExt, TryFutureExt, TryStreamExt};
+use futures::{FutureExt, Stream, StreamExt, TryFutureExt, TryStreamExt, future::try_join};
 use ruma::{OwnedEventId, RoomId, RoomVersionId};
 use tuwunel_core::{
-	Result, err, implement,
+	Err, Result, err, implement,
 	matrix::room_version,
 	trace,
 	utils::stream::{IterStream, ReadyExt, TryWidebandExt, WidebandExt},
@@ -77,6 +77,72 @@ pub async fn resolve_state(
 		.await?;
 
 	trace!("State resolution done.");
+	let new_room_state = self.compress_resolved_state(&state).await;
+
+	Ok(Arc::new(new_room_state))
+}
+
+/// Re-run state resolution over the current forward extremities of a room,
+/// as for a new event referencing all of them. The room's state is left
+/// untouched; the result is for comparison against it.
+#[implement(super::Service)]
+#[tracing::instrument(level = "debug", skip(self))]
+pub async fn resolve_forward_extremities(
+	&self,
+	room_id: &RoomId,
+) -> Result<Arc<CompressedState>> {
+	let room_version = self
+		.services
+		.state
+		.get_room_version(room_id)
+		.await?;
+
+	let extremities: Vec<OwnedEventId> = self
+		.services
+		.state
+		.get_forward_extremities(room_id)
+		.map(ToOwned::to_owned)
+		.collect()
+		.await;
+
+	if extremities.is_empty() {
+		return Err!(Request(NotFound("Room {room_id} has no forward extremities.")));
+	}
+
+	trace!(extremities = extremities.len(), "Calculating fork states...");
+	let (fork_states, auth_chain_sets): (Vec<_>, Vec<_>) = extremities
+		.iter()
+		.try_stream()
+		.wide_and_then(|event_id| {
+			let sstatehash = self.services.state.pdu_shortstatehash(event_id);
+
+			let pdu = self.services.timeline.get_pdu(event_id);
+
+			try_join(sstatehash, pdu)
+		})
+		.wide_and_then(|(sstatehash, pdu)| {
+			self.state_at_incoming_fork(room_id, &room_version, sstatehash, pdu)
+		})
+		.try_collect::<Vec<_>>()
+		.await?
+		.into_iter()
+		.unzip();
+
+	trace!("Resolving state");
+	let state = self
+		.state_resolution(
+			room_id,
+			&room_version,
+			fork_states.into_iter().stream(),
+			auth_chain_sets.into_iter().stream(),
+		)
+		.await?;
+
+	Ok(Arc::new(self.compress_resolved_state(&state).await))
+}
+
+#[implement(super::Service)]
+async fn compress_resolved_state(&self, state: &StateMap<OwnedEventId>) -> CompressedState {
 	let state_events: Vec<_> = state
 		.iter()
 		.stream()
@@ -90,8 +156,7 @@ pub async fn resolve_state(
 		.await;
 
 	trace!("Compressing state...");
-	let new_room_state: CompressedState = self
-		.services
+	self.services
 		.state_compressor
 		.compress_state_events(
 			state_events
@@ -99,9 +164,7 @@ pub async fn resolve_state(
 				.map(|(ssk, eid)| (ssk, (*eid).borrow())),
 		)
 		.collect()
-		.await;
-
-	Ok(Arc::new(new_room_state))
+		.await
 }
 
 #[implement(super::Service)]
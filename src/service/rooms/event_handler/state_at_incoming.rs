@@ -186,7 +186,7 @@ pub(super) async fn state_at_incoming_resolved<Pdu>(
 		prev_event = ?prev_event.event_id(),
 	)
 )]
-async fn state_at_incoming_fork<Pdu>(
+pub(super) async fn state_at_incoming_fork<Pdu>(
 	&self,
 	room_id: &RoomId,
 	room_version: &RoomVersionId,
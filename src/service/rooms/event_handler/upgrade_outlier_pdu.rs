@@ -6,7 +6,7 @@
 	events::StateEventType, room_version_rules::RoomVersionRules,
 };
 use tuwunel_core::{
-	Err, Result, debug, debug_info, err, implement, is_equal_to,
+	Error, Result, debug, debug_info, err, implement, is_equal_to,
 	matrix::{Event, EventTypeExt, PduEvent, StateKey, pdu::check_rules, room_version},
 	trace,
 	utils::stream::{BroadbandExt, ReadyExt},
@@ -57,7 +57,10 @@ pub(super) async fn upgrade_outlier_to_timeline_pdu(
 		.is_event_soft_failed(incoming_pdu.event_id())
 		.await
 	{
-		return Err!(Request(InvalidParam("Event has been soft failed")));
+		return Err(Error::SoftFailed(
+			incoming_pdu.event_id().to_owned(),
+			"previously soft failed".into(),
+		));
 	}
 
 	trace!("Upgrading to timeline pdu");
@@ -171,7 +174,10 @@ pub(super) async fn upgrade_outlier_to_timeline_pdu(
 			incoming_pdu.event_id()
 		);
 
-		return Err!(Request(InvalidParam("Event has been soft failed")));
+		return Err(Error::SoftFailed(
+			incoming_pdu.event_id().to_owned(),
+			"failed auth at current state".into(),
+		));
 	}
 
 	drop(state_lock);
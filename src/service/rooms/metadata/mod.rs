@@ -9,7 +9,7 @@
 		stream::{TryIgnore, WidebandExt},
 	},
 };
-use tuwunel_database::Map;
+use tuwunel_database::{Deserialized, Map};
 
 pub struct Service {
 	db: Data,
@@ -101,9 +101,39 @@ pub async fn is_public(&self, room_id: &RoomId) -> bool {
 #[inline]
 pub fn enable_room(&self, room_id: &RoomId) { self.db.disabledroomids.remove(room_id); }
 
+/// Marks the value of `disabledroomids` for rooms disabled by banning them.
+const DISABLED_BY_BAN: &[u8] = b"ban";
+
+/// Disables the room as part of banning it, unless it is disabled already;
+/// lifting the ban re-enables only a room disabled this way.
+#[implement(Service)]
+pub async fn disable_banned_room(&self, room_id: &RoomId) {
+	if !self.is_disabled(room_id).await {
+		self.db
+			.disabledroomids
+			.insert(room_id, DISABLED_BY_BAN);
+	}
+}
+
+/// Whether the room was disabled by `disable_banned_room` rather than on its
+/// own.
+#[implement(Service)]
+pub async fn is_disabled_by_ban(&self, room_id: &RoomId) -> bool {
+	self.db
+		.disabledroomids
+		.get(room_id)
+		.await
+		.is_ok_and(|value| *value == *DISABLED_BY_BAN)
+}
+
+/// Bans the room, recording `reason` for the operator; see `ban_reason`.
 #[implement(Service)]
 #[inline]
-pub fn ban_room(&self, room_id: &RoomId) { self.db.bannedroomids.insert(room_id, []); }
+pub fn ban_room(&self, room_id: &RoomId, reason: Option<&str>) {
+	self.db
+		.bannedroomids
+		.insert(room_id, reason.unwrap_or_default());
+}
 
 #[implement(Service)]
 #[inline]
@@ -125,3 +155,15 @@ pub async fn is_disabled(&self, room_id: &RoomId) -> bool {
 pub async fn is_banned(&self, room_id: &RoomId) -> bool {
 	self.db.bannedroomids.get(room_id).await.is_ok()
 }
+
+/// The reason the room was banned for, if it is banned and one was given.
+#[implement(Service)]
+pub async fn ban_reason(&self, room_id: &RoomId) -> Option<String> {
+	self.db
+		.bannedroomids
+		.get(room_id)
+		.await
+		.deserialized::<String>()
+		.ok()
+		.filter(|reason| !reason.is_empty())
+}
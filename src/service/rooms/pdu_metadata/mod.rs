@@ -10,7 +10,7 @@
 use tuwunel_core::{
 	PduId, Result,
 	arrayvec::ArrayVec,
-	implement, is_equal_to,
+	at, implement, is_equal_to,
 	matrix::{Event, Pdu, PduCount, RawPduId, event::RelationTypeEqual},
 	result::LogErr,
 	trace,
@@ -148,6 +148,17 @@ pub async fn add_typed_relation<E: Event>(
 		.aput_raw::<TYPED_KEY_LEN, _, _>(key.as_slice(), child_short.to_be_bytes());
 }
 
+#[implement(Service)]
+#[tracing::instrument(skip(self, from, to), level = "debug")]
+pub fn delete_relation(&self, from: PduCount, to: PduCount) {
+	const BUFSIZE: usize = size_of::<u64>() * 2;
+
+	if let (PduCount::Normal(from), PduCount::Normal(to)) = (from, to) {
+		let key: &[u64] = &[to, from];
+		self.db.tofrom_relation.adel::<BUFSIZE, _>(key);
+	}
+}
+
 /// Query relations of an event to determine if matching any of the trailing
 /// arguments. When all criteria are None the mere presence of a relation causes
 /// this function to return true.
@@ -548,6 +559,57 @@ pub async fn delete_typed_relation(&self, child_id: &RawPduId, child: &Canonical
 	self.db.relatesto_typed.remove(key.as_slice());
 }
 
+/// Drop the `tofrom_relation` rows invalidated by redacting `pdu`: its own
+/// relation to a parent or replied-to event, which the redaction strips from
+/// its content, and the annotations (reactions) relating to it. Other children
+/// such as replies keep relating to the redacted event. Call before the content
+/// is stripped, while its relation fields are still readable.
+#[implement(Service)]
+#[tracing::instrument(skip_all, level = "debug")]
+pub async fn delete_redacted_relations(&self, pdu_id: &RawPduId, pdu: &CanonicalJsonObject) {
+	let shortroomid = u64_from_u8(&pdu_id.shortroomid());
+	let count = pdu_id.pdu_count();
+
+	let relates_to = pdu
+		.get("content")
+		.and_then(CanonicalJsonValue::as_object)
+		.and_then(|content| content.get("m.relates_to"))
+		.and_then(CanonicalJsonValue::as_object);
+
+	let in_reply_to = relates_to
+		.and_then(|relates_to| relates_to.get("m.in_reply_to"))
+		.and_then(CanonicalJsonValue::as_object);
+
+	let parents = [relates_to, in_reply_to]
+		.into_iter()
+		.flatten()
+		.filter_map(|relation| relation.get("event_id"))
+		.filter_map(CanonicalJsonValue::as_str)
+		.filter_map(|parent| EventId::parse(parent).ok());
+
+	for parent in parents {
+		if let Ok(parent_count) = self
+			.services
+			.timeline
+			.get_pdu_count(&parent)
+			.await
+		{
+			self.delete_relation(count, parent_count);
+		}
+	}
+
+	let annotations: Vec<PduCount> = self
+		.get_relations(shortroomid, count, None, Direction::Forward, None)
+		.ready_filter(|(_, child)| RelationType::Annotation.relation_type_equal(child))
+		.map(at!(0))
+		.collect()
+		.await;
+
+	for child in annotations {
+		self.delete_relation(child, count);
+	}
+}
+
 #[implement(Service)]
 #[tracing::instrument(skip(self), level = "debug")]
 pub async fn delete_all_relatesto_typed_for_room(&self, room_id: &RoomId) -> Result {
@@ -4,9 +4,9 @@
 
 use std::{collections::BTreeMap, sync::Arc};
 
-use futures::{Stream, StreamExt};
+use futures::{FutureExt, Stream, StreamExt};
 use ruma::{
-	OwnedEventId, OwnedUserId, RoomId, UserId,
+	OwnedEventId, OwnedRoomId, OwnedUserId, RoomId, UserId,
 	api::appservice::event::push_events::v1::EphemeralData,
 	events::{
 		AnySyncEphemeralRoomEvent, SyncEphemeralRoomEvent,
@@ -25,7 +25,10 @@
 	smallstr::SmallString,
 	smallvec::SmallVec,
 	trace,
-	utils::IterStream,
+	utils::{
+		IterStream,
+		stream::{BroadbandExt, WidebandExt},
+	},
 	warn,
 };
 
@@ -192,6 +195,26 @@ pub fn readreceipts_since<'a>(
 		self.db.readreceipts_since(room_id, since, to)
 	}
 
+	/// Returns the read_receipts after `since` in each of `rooms`, tagged with
+	/// their room. Rooms are scanned concurrently so receipts of different
+	/// rooms interleave in no particular order.
+	#[tracing::instrument(skip(self, rooms), level = "debug")]
+	pub fn readreceipts_since_rooms<'a, I>(
+		&'a self,
+		rooms: I,
+		since: u64,
+	) -> impl Stream<Item = (OwnedRoomId, ReceiptItem<'a>)> + Send + 'a
+	where
+		I: IntoIterator<Item = &'a RoomId> + Send + 'a,
+		<I as IntoIterator>::IntoIter: Send + 'a,
+	{
+		rooms.stream().broad_flat_map(move |room_id| {
+			self.readreceipts_since(room_id, since, None)
+				.map(move |receipt| (room_id.to_owned(), receipt))
+				.boxed()
+		})
+	}
+
 	/// Sets a private read marker at PDU `count` for the given thread.
 	/// Unthreaded writes supersede prior per-thread rows so the room-wide
 	/// receipt subsumes thread state.
@@ -255,6 +278,25 @@ pub async fn last_receipt_count(
 			.await
 	}
 
+	/// `last_receipt_count` for each of `rooms`, yielded in the order given.
+	/// The rooms are scanned concurrently rather than one after another.
+	pub fn last_receipt_counts<'a, I>(
+		&'a self,
+		rooms: I,
+		user_id: Option<&'a UserId>,
+		since: Option<u64>,
+	) -> impl Stream<Item = (OwnedRoomId, Result<u64>)> + Send + 'a
+	where
+		I: IntoIterator<Item = &'a RoomId> + Send + 'a,
+		<I as IntoIterator>::IntoIter: Send + 'a,
+	{
+		rooms.stream().wide_then(move |room_id| {
+			self.db
+				.last_receipt_count(room_id, since, user_id)
+				.map(move |count| (room_id.to_owned(), count))
+		})
+	}
+
 	pub async fn delete_all_read_receipts(&self, room_id: &RoomId) -> Result {
 		self.db.delete_all_read_receipts(room_id).await
 	}
@@ -6,7 +6,10 @@
 	PduCount, Result,
 	arrayvec::ArrayVec,
 	implement,
-	matrix::event::{Event, Matches},
+	matrix::{
+		PduEvent,
+		event::{Event, Matches},
+	},
 	trace,
 	utils::{
 		ArrayVecExt, IterStream, ReadyExt, set,
@@ -57,6 +60,10 @@ fn name(&self) -> &str { crate::service::make_name(std::module_path!()) }
 
 #[implement(Service)]
 pub fn index_pdu(&self, shortroomid: ShortRoomId, pdu_id: &RawPduId, message_body: &str) {
+	if !self.services.config.enable_message_search {
+		return;
+	}
+
 	let batch = tokenize(message_body)
 		.map(|word| {
 			let mut key = shortroomid.to_be_bytes().to_vec();
@@ -121,6 +128,43 @@ pub async fn search_pdus<'a>(
 	Ok((count, pdus))
 }
 
+/// Events in the room whose body contains every word of `query`, newest
+/// first and at most `limit` of them. Visibility is not checked; callers
+/// acting for a user must filter the results themselves.
+#[implement(Service)]
+#[tracing::instrument(skip(self), level = "debug")]
+pub async fn search_room(
+	&self,
+	room_id: &RoomId,
+	query: &str,
+	limit: usize,
+) -> Vec<(PduCount, PduEvent)> {
+	let Ok(shortroomid) = self.services.short.get_shortroomid(room_id).await else {
+		return Vec::new();
+	};
+
+	let pdu_ids = self
+		.search_pdu_ids_query_room(query, shortroomid)
+		.await;
+
+	let iters = pdu_ids.into_iter().map(IntoIterator::into_iter);
+
+	set::intersection(iters)
+		.stream()
+		.wide_filter_map(async |pdu_id: RawPduId| {
+			self.services
+				.timeline
+				.get_pdu_from_id(&pdu_id)
+				.await
+				.ok()
+				.map(|pdu| (pdu_id.pdu_count(), pdu))
+		})
+		.ready_filter(|(_, pdu)| !pdu.is_redacted())
+		.take(limit)
+		.collect()
+		.await
+}
+
 // result is modeled as a stream such that callers don't have to be refactored
 // though an additional async/wrap still exists for now
 #[implement(Service)]
@@ -135,7 +179,7 @@ pub async fn search_pdu_ids(
 		.await?;
 
 	let pdu_ids = self
-		.search_pdu_ids_query_room(query, shortroomid)
+		.search_pdu_ids_query_room(&query.criteria.search_term, shortroomid)
 		.await;
 
 	let iters = pdu_ids.into_iter().map(IntoIterator::into_iter);
@@ -146,10 +190,10 @@ pub async fn search_pdu_ids(
 #[implement(Service)]
 async fn search_pdu_ids_query_room(
 	&self,
-	query: &RoomQuery<'_>,
+	search_term: &str,
 	shortroomid: ShortRoomId,
 ) -> Vec<Vec<RawPduId>> {
-	tokenize(&query.criteria.search_term)
+	tokenize(search_term)
 		.stream()
 		.wide_then(async |word| {
 			self.search_pdu_ids_query_words(shortroomid, &word)
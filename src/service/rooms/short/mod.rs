@@ -146,6 +146,21 @@ pub async fn get_shortstatekey(
 		.deserialized()
 }
 
+#[implement(Service)]
+pub fn multi_get_shortstatekey<'a, S>(
+	&'a self,
+	event_type: &'a StateEventType,
+	state_keys: S,
+) -> impl Stream<Item = Result<ShortStateKey>> + Send + 'a
+where
+	S: Stream<Item = &'a str> + Send + 'a,
+{
+	state_keys
+		.map(move |state_key| (event_type, state_key))
+		.qry(&self.db.statekey_shortstatekey)
+		.map(Deserialized::deserialized)
+}
+
 #[implement(Service)]
 pub async fn get_eventid_from_short<Id>(&self, shorteventid: ShortEventId) -> Result<Id>
 where
@@ -209,6 +224,17 @@ pub fn multi_get_statekey_from_short<'a, S>(
 		.map(Deserialized::deserialized)
 }
 
+/// Returns the shortstatehash already assigned to `state_hash`, without
+/// assigning one.
+#[implement(Service)]
+pub async fn get_shortstatehash(&self, state_hash: &[u8]) -> Result<ShortStateHash> {
+	self.db
+		.statehash_shortstatehash
+		.get(state_hash)
+		.await
+		.deserialized()
+}
+
 /// Returns (shortstatehash, already_existed)
 #[implement(Service)]
 pub async fn get_or_create_shortstatehash(&self, state_hash: &[u8]) -> (ShortStateHash, bool) {
@@ -1,4 +1,4 @@
-use futures::{StreamExt, pin_mut, stream::FuturesUnordered};
+use futures::{Future, StreamExt, pin_mut};
 use ruma::{
 	OwnedServerName, RoomId,
 	api::federation::space::{
@@ -7,7 +7,10 @@
 	},
 	room::RoomType,
 };
-use tuwunel_core::{Err, Result, debug, implement, trace};
+use tuwunel_core::{
+	Err, Result, debug, implement, trace,
+	utils::{IterStream, stream::ReadyExt},
+};
 
 use super::{
 	Accessibility,
@@ -35,25 +38,28 @@ pub(super) async fn get_summary_and_children_federation(
 		suggested_only: false,
 	};
 
-	let requests: FuturesUnordered<_> = via
-		.iter()
-		.map(|server| {
-			self.services
-				.federation
-				.execute(server, request.clone())
-		})
-		.collect();
+	let limit = self
+		.services
+		.config
+		.space_hierarchy_federation_concurrency;
+
+	let requests = via.iter().map(|server| {
+		self.services
+			.federation
+			.execute(server, request.clone())
+	});
 
-	pin_mut!(requests);
 	debug!(
 		?current_room,
 		?sender,
 		?via,
-		requests = requests.len(),
+		requests = via.len(),
+		limit,
 		"waiting for federation response"
 	);
 
-	let Some(Ok(Response { room, children, inaccessible_children })) = requests.next().await
+	let Some(Response { room, children, inaccessible_children }) =
+		first_success(requests, limit).await
 	else {
 		self.cache_put(current_room, None);
 		return Err!(Request(NotFound("Space room not found over federation.")));
@@ -92,3 +98,22 @@ pub(super) async fn get_summary_and_children_federation(
 		.then(|| Ok(Accessible(room)))
 		.unwrap_or(Ok(Inaccessible))
 }
+
+/// Races the `requests` with at most `limit` in flight, yielding the first to
+/// succeed. Requests not yet started when one succeeds are never issued.
+pub(super) async fn first_success<I, F, T>(requests: I, limit: usize) -> Option<T>
+where
+	I: IntoIterator<Item = F> + Send,
+	I::IntoIter: Send,
+	F: Future<Output = Result<T>> + Send,
+	T: Send,
+{
+	let responses = requests
+		.into_iter()
+		.stream()
+		.buffer_unordered(limit.max(1))
+		.ready_filter_map(Result::ok);
+
+	pin_mut!(responses);
+	responses.next().await
+}
@@ -1,4 +1,7 @@
-use std::str::FromStr;
+use std::{
+	str::FromStr,
+	sync::atomic::{AtomicUsize, Ordering},
+};
 
 use ruma::{
 	UInt,
@@ -6,8 +9,9 @@
 	owned_room_id, owned_server_name,
 	room::{JoinRuleSummary, RoomSummary},
 };
+use tuwunel_core::Err;
 
-use crate::rooms::spaces::{PaginationToken, get_parent_children_via};
+use crate::rooms::spaces::{PaginationToken, federation::first_success, get_parent_children_via};
 
 #[test]
 fn get_summary_children() {
@@ -145,3 +149,38 @@ fn pagination_token_to_string() {
 		"9,34_3_1_true"
 	);
 }
+
+#[tokio::test]
+async fn federation_fan_out_is_bounded() {
+	const VIA: usize = 50;
+	const LIMIT: usize = 3;
+
+	let in_flight = AtomicUsize::new(0);
+	let peak = AtomicUsize::new(0);
+	let requests = (0..VIA).map(|server| {
+		let (in_flight, peak) = (&in_flight, &peak);
+		async move {
+			let current = in_flight
+				.fetch_add(1, Ordering::SeqCst)
+				.saturating_add(1);
+			peak.fetch_max(current, Ordering::SeqCst);
+
+			for _ in 0..4 {
+				tokio::task::yield_now().await;
+			}
+
+			in_flight.fetch_sub(1, Ordering::SeqCst);
+			if server < VIA.saturating_sub(1) {
+				return Err!("server {server} unreachable");
+			}
+
+			Ok(server)
+		}
+	});
+
+	let found = first_success(requests, LIMIT).await;
+
+	assert_eq!(found, Some(VIA.saturating_sub(1)), "last server answers");
+	assert!(peak.load(Ordering::SeqCst) <= LIMIT, "too many requests in flight");
+	assert_eq!(peak.load(Ordering::SeqCst), LIMIT, "requests raced up to the limit");
+}
@@ -92,7 +92,61 @@ pub async fn force_state(
 	_statediffremoved: Arc<CompressedState>,
 	state_lock: &RoomMutexGuard,
 ) -> Result {
-	statediffnew
+	// A room our server left has no memberships left to diff against; when
+	// rejoining it every member of the new state has to be restored.
+	let rejoining = !self
+		.services
+		.state_cache
+		.server_in_room(self.services.globals.server_name(), room_id)
+		.await;
+
+	let state = if rejoining {
+		self.services
+			.state_compressor
+			.load_shortstatehash_info(shortstatehash)
+			.await?
+			.pop()
+			.map_or(statediffnew, |info| info.full_state)
+	} else {
+		statediffnew
+	};
+
+	self.apply_state_caches(room_id, &state).await?;
+
+	self.set_room_state(room_id, shortstatehash, state_lock);
+
+	Ok(())
+}
+
+/// Restore the memberships of a room from its current state. Used when our
+/// server rejoins a room it had forgotten, whose memberships were dropped when
+/// the last local user left.
+#[implement(Service)]
+#[tracing::instrument(skip(self, _state_lock), level = "debug")]
+pub async fn rebuild_memberships(
+	&self,
+	room_id: &RoomId,
+	_state_lock: &RoomMutexGuard,
+) -> Result {
+	let shortstatehash = self.get_room_shortstatehash(room_id).await?;
+	let Some(info) = self
+		.services
+		.state_compressor
+		.load_shortstatehash_info(shortstatehash)
+		.await?
+		.pop()
+	else {
+		return Ok(());
+	};
+
+	self.apply_state_caches(room_id, &info.full_state)
+		.await
+}
+
+/// Update the membership and space caches for the given state events.
+#[implement(Service)]
+async fn apply_state_caches(&self, room_id: &RoomId, state: &CompressedState) -> Result {
+	state
 		.iter()
 		.stream()
 		.map(|&new| parse_compressed_state_event(new).1)
@@ -157,8 +211,6 @@ pub async fn force_state(
 		.update_joined_count(room_id)
 		.await;
 
-	self.set_room_state(room_id, shortstatehash, state_lock);
-
 	Ok(())
 }
 
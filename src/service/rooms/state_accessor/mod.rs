@@ -3,12 +3,22 @@
 mod state;
 mod user_can;
 
-use std::sync::Arc;
+use std::{
+	fmt::Write,
+	sync::{
+		Arc, Mutex,
+		atomic::{AtomicU64, Ordering},
+	},
+};
 
 use async_trait::async_trait;
-use futures::{FutureExt, TryFutureExt, future::try_join};
+use futures::{
+	FutureExt, Stream, TryFutureExt,
+	future::{join, try_join},
+};
+use lru_cache::LruCache;
 use ruma::{
-	EventEncryptionAlgorithm, OwnedRoomAliasId, RoomId, UserId,
+	EventEncryptionAlgorithm, EventId, OwnedRoomAliasId, OwnedRoomId, RoomId, UserId,
 	events::{
 		StateEventType,
 		room::{
@@ -30,19 +40,58 @@
 use tuwunel_core::{
 	Result, err, is_true,
 	matrix::{Pdu, room_version},
-	utils::BoolExt,
+	utils::{BoolExt, IterStream, math::usize_from_f64, stream::BroadbandExt},
 };
 
-use crate::rooms::state_res::events::RoomCreateEvent;
+use crate::rooms::{short::ShortStateHash, state_res::events::RoomCreateEvent};
+
+/// Room visibility as needed for summaries and the directory; see
+/// `visibility_flags_many`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VisibilityFlags {
+	pub world_readable: bool,
+	pub guest_can_join: bool,
+	pub history_visibility: HistoryVisibility,
+}
 
 pub struct Service {
+	power_levels_cache: Mutex<PowerLevelsLruCache>,
+	power_levels_cache_hits: AtomicU64,
+	power_levels_cache_misses: AtomicU64,
 	services: Arc<crate::services::OnceServices>,
 }
 
+/// Effective power levels by the shortstatehash they were computed at; any
+/// state change moves the room on to a new shortstatehash.
+type PowerLevelsLruCache = LruCache<ShortStateHash, RoomPowerLevels>;
+
 #[async_trait]
 impl crate::Service for Service {
 	fn build(args: &crate::Args<'_>) -> Result<Arc<Self>> {
-		Ok(Arc::new(Self { services: args.services.clone() }))
+		let config = &args.server.config;
+		let cache_capacity =
+			f64::from(config.power_levels_cache_capacity) * config.cache_capacity_modifier;
+
+		Ok(Arc::new(Self {
+			power_levels_cache: LruCache::new(usize_from_f64(cache_capacity)?).into(),
+			power_levels_cache_hits: AtomicU64::new(0),
+			power_levels_cache_misses: AtomicU64::new(0),
+			services: args.services.clone(),
+		}))
+	}
+
+	async fn memory_usage(&self, out: &mut (dyn Write + Send)) -> Result {
+		let (len, hits, misses) = self.get_power_levels_cache_usage();
+		writeln!(out, "- power_levels_cache: {len} entries, {hits} hits, {misses} misses")?;
+
+		Ok(())
+	}
+
+	async fn clear_cache(&self) {
+		self.power_levels_cache
+			.lock()
+			.expect("locked")
+			.clear();
 	}
 
 	fn name(&self) -> &str { crate::service::make_name(std::module_path!()) }
@@ -50,11 +99,52 @@ fn name(&self) -> &str { crate::service::make_name(std::module_path!()) }
 
 impl Service {
 	/// Gets the effective power levels of a room, regardless of if there is an
-	/// `m.room.power_levels` state.
+	/// `m.room.power_levels` state. Results are cached by the room's current
+	/// shortstatehash.
 	pub async fn get_power_levels(&self, room_id: &RoomId) -> Result<RoomPowerLevels> {
-		let create = self.get_create(room_id);
+		let shortstatehash = self
+			.services
+			.state
+			.get_room_shortstatehash(room_id)
+			.await?;
+
+		let cached = self
+			.power_levels_cache
+			.lock()
+			.expect("locked")
+			.get_mut(&shortstatehash)
+			.cloned();
+
+		if let Some(power_levels) = cached {
+			self.power_levels_cache_hits
+				.fetch_add(1, Ordering::Relaxed);
+
+			return Ok(power_levels);
+		}
+
+		self.power_levels_cache_misses
+			.fetch_add(1, Ordering::Relaxed);
+
+		let power_levels = self.state_power_levels(shortstatehash).await?;
+		self.power_levels_cache
+			.lock()
+			.expect("locked")
+			.insert(shortstatehash, power_levels.clone());
+
+		Ok(power_levels)
+	}
+
+	/// Gets the effective power levels at a state.
+	pub async fn state_power_levels(
+		&self,
+		shortstatehash: ShortStateHash,
+	) -> Result<RoomPowerLevels> {
+		let create = self
+			.state_get(shortstatehash, &StateEventType::RoomCreate, "")
+			.map_ok(RoomCreateEvent::new);
+
 		let power_levels = self
-			.room_state_get_content(room_id, &StateEventType::RoomPowerLevels, "")
+			.state_get_content(shortstatehash, &StateEventType::RoomPowerLevels, "")
 			.map_ok(|c: RoomPowerLevelsEventContent| c)
 			.map(Result::ok)
 			.map(Ok);
@@ -68,6 +158,25 @@ pub async fn get_power_levels(&self, room_id: &RoomId) -> Result<RoomPowerLevels
 		Ok(RoomPowerLevels::new(power_levels.into(), &rules.authorization, creators))
 	}
 
+	/// Returns the entries, hits and misses of the power levels cache.
+	pub fn get_power_levels_cache_usage(&self) -> (usize, u64, u64) {
+		let len = self
+			.power_levels_cache
+			.lock()
+			.expect("locked")
+			.len();
+
+		let hits = self
+			.power_levels_cache_hits
+			.load(Ordering::Relaxed);
+
+		let misses = self
+			.power_levels_cache_misses
+			.load(Ordering::Relaxed);
+
+		(len, hits, misses)
+	}
+
 	pub async fn get_create(&self, room_id: &RoomId) -> Result<RoomCreateEvent<Pdu>> {
 		self.room_state_get(room_id, &StateEventType::RoomCreate, "")
 			.await
@@ -118,6 +227,30 @@ pub async fn is_world_readable(&self, room_id: &RoomId) -> bool {
 			.unwrap_or(false)
 	}
 
+	/// The room's history visibility in the state at `event_id`, `Shared` if
+	/// none was set there or the event is not in the room's timeline.
+	#[tracing::instrument(skip(self), level = "debug")]
+	pub async fn history_visibility_at(
+		&self,
+		room_id: &RoomId,
+		event_id: &EventId,
+	) -> HistoryVisibility {
+		let timeline = &self.services.timeline;
+		let Ok(shortstatehash) = timeline
+			.get_pdu_count(event_id)
+			.and_then(|count| timeline.get_shortstatehash(room_id, count))
+			.await
+		else {
+			return HistoryVisibility::Shared;
+		};
+
+		self.state_get_content(shortstatehash, &StateEventType::RoomHistoryVisibility, "")
+			.await
+			.map_or(HistoryVisibility::Shared, |c: RoomHistoryVisibilityEventContent| {
+				c.history_visibility
+			})
+	}
+
 	/// Checks if guests are able to join a given room
 	pub async fn guest_can_join(&self, room_id: &RoomId) -> bool {
 		self.room_state_get_content(room_id, &StateEventType::RoomGuestAccess, "")
@@ -126,6 +259,41 @@ pub async fn guest_can_join(&self, room_id: &RoomId) -> bool {
 			.unwrap_or(false)
 	}
 
+	/// Gets the visibility flags of each of `rooms`, looking up the history
+	/// visibility and guest access of a room concurrently. Rooms without the
+	/// respective state default to `shared` and forbidden guest access.
+	pub fn visibility_flags_many<'a, I>(
+		&'a self,
+		rooms: I,
+	) -> impl Stream<Item = (OwnedRoomId, VisibilityFlags)> + Send + 'a
+	where
+		I: IntoIterator<Item = &'a RoomId> + Send + 'a,
+		<I as IntoIterator>::IntoIter: Send + 'a,
+	{
+		rooms.stream().broad_then(async |room_id| {
+			let history_visibility = self
+				.room_state_get_content(room_id, &StateEventType::RoomHistoryVisibility, "")
+				.map_ok(|c: RoomHistoryVisibilityEventContent| c.history_visibility)
+				.map(|res| res.unwrap_or(HistoryVisibility::Shared));
+
+			let guest_can_join = self
+				.room_state_get_content(room_id, &StateEventType::RoomGuestAccess, "")
+				.map_ok(|c: RoomGuestAccessEventContent| c.guest_access == GuestAccess::CanJoin)
+				.map(|res| res.unwrap_or(false));
+
+			let (history_visibility, guest_can_join) =
+				join(history_visibility, guest_can_join).await;
+
+			let flags = VisibilityFlags {
+				world_readable: history_visibility == HistoryVisibility::WorldReadable,
+				guest_can_join,
+				history_visibility,
+			};
+
+			(room_id.to_owned(), flags)
+		})
+	}
+
 	/// Gets the primary alias from canonical alias event
 	pub async fn get_canonical_alias(&self, room_id: &RoomId) -> Result<OwnedRoomAliasId> {
 		self.room_state_get_content(room_id, &StateEventType::RoomCanonicalAlias, "")
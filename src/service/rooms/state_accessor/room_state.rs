@@ -1,9 +1,20 @@
-use futures::{Stream, StreamExt, TryFutureExt};
-use ruma::{OwnedEventId, RoomId, events::StateEventType};
+use std::collections::HashMap;
+
+use futures::{FutureExt, Stream, StreamExt, TryFutureExt};
+use ruma::{
+	OwnedEventId, OwnedUserId, RoomId, UserId,
+	events::{StateEventType, room::member::RoomMemberEventContent},
+};
 use serde::Deserialize;
 use tuwunel_core::{
-	Result, err, implement,
+	Err, Result, at, err, implement,
 	matrix::{Event, Pdu, StateKey},
+	utils::stream::{BroadbandExt, IterStream, ReadyExt, TryIgnore},
+};
+
+use crate::rooms::{
+	short::ShortEventId,
+	state_compressor::{compress_state_event, parse_compressed_state_event},
 };
 
 /// Returns a single PDU from `room_id` with key (`event_type`,`state_key`).
@@ -143,3 +154,143 @@ pub async fn room_state_get(
 		.and_then(|shortstatehash| self.state_get(shortstatehash, event_type, state_key))
 		.await
 }
+
+/// Returns the member event content of each of `user_ids` in the current
+/// state of `room_id`, in the order requested. The batched form of
+/// `get_member` for looking up several members at once, such as a room's
+/// heroes: the state is loaded once and the state keys, event ids and events
+/// are each resolved in a single batch.
+#[implement(super::Service)]
+#[tracing::instrument(skip(self, user_ids), level = "debug")]
+pub fn get_members<'a, I>(
+	&'a self,
+	room_id: &'a RoomId,
+	user_ids: I,
+) -> impl Stream<Item = (OwnedUserId, Result<RoomMemberEventContent>)> + Send + 'a
+where
+	I: Iterator<Item = &'a UserId> + Send + 'a,
+{
+	let user_ids: Vec<_> = user_ids.collect();
+
+	async move {
+		let full_state = self
+			.services
+			.state
+			.get_room_shortstatehash(room_id)
+			.and_then(|shortstatehash| self.load_full_state(shortstatehash))
+			.await
+			.ok();
+
+		let shorteventids: Vec<Option<ShortEventId>> = self
+			.services
+			.short
+			.multi_get_shortstatekey(
+				&StateEventType::RoomMember,
+				user_ids
+					.iter()
+					.map(|user_id| user_id.as_str())
+					.stream(),
+			)
+			.map(|shortstatekey| {
+				let shortstatekey = shortstatekey.ok()?;
+				let start = compress_state_event(shortstatekey, 0);
+				let end = compress_state_event(shortstatekey, u64::MAX);
+
+				full_state
+					.as_ref()?
+					.range(start..=end)
+					.next()
+					.copied()
+					.map(parse_compressed_state_event)
+					.map(at!(1))
+			})
+			.collect()
+			.await;
+
+		let resolved: Vec<Result<OwnedEventId>> = self
+			.services
+			.short
+			.multi_get_eventid_from_short(shorteventids.iter().flatten().copied().stream())
+			.collect()
+			.await;
+
+		let mut resolved = resolved.into_iter();
+		let event_ids: Vec<Option<OwnedEventId>> = shorteventids
+			.iter()
+			.map(|shorteventid| shorteventid.and_then(|_| resolved.next()?.ok()))
+			.collect();
+
+		let contents: Vec<Result<RoomMemberEventContent>> = self
+			.services
+			.timeline
+			.get_pdus_json(event_ids.iter().flatten().map(AsRef::as_ref))
+			.map(|(_, json)| json.and_then(Pdu::from_object)?.get_content())
+			.collect()
+			.await;
+
+		let mut contents = contents.into_iter();
+		user_ids
+			.into_iter()
+			.zip(event_ids)
+			.map(|(user_id, event_id)| {
+				let content = event_id
+					.and_then(|_| contents.next())
+					.unwrap_or_else(|| Err!(Request(NotFound("Not found in room state"))));
+
+				(user_id.to_owned(), content)
+			})
+			.collect::<Vec<_>>()
+	}
+	.map(IterStream::stream)
+	.flatten_stream()
+}
+
+/// Returns the full current state of the room as a map keyed by type and
+/// state key.
+///
+/// Every state event is loaded and held at once: for a large room that is a
+/// PDU per member. It is meant for admin and debug tooling; request paths
+/// should prefer the streaming accessors.
+#[implement(super::Service)]
+#[tracing::instrument(skip(self), level = "debug")]
+pub async fn current_state_map(
+	&self,
+	room_id: &RoomId,
+) -> Result<HashMap<(StateEventType, StateKey), Pdu>> {
+	let shortstatehash = self
+		.services
+		.state
+		.get_room_shortstatehash(room_id)
+		.map_err(|e| err!(Database("Missing state for {room_id:?}: {e:?}")))
+		.await?;
+
+	let (shortstatekeys, shorteventids): (Vec<_>, Vec<_>) = self
+		.state_full_shortids(shortstatehash)
+		.ignore_err()
+		.unzip()
+		.await;
+
+	let statekeys = self
+		.services
+		.short
+		.multi_get_statekey_from_short(shortstatekeys.into_iter().stream());
+
+	self.services
+		.short
+		.multi_get_eventid_from_short(shorteventids.into_iter().stream())
+		.zip(statekeys)
+		.ready_filter_map(|(event_id, statekey)| Some((statekey.ok()?, event_id.ok()?)))
+		.broad_filter_map(async |(statekey, event_id): (_, OwnedEventId)| {
+			let pdu = self
+				.services
+				.timeline
+				.get_pdu(&event_id)
+				.await
+				.ok()?;
+
+			Some((statekey, pdu))
+		})
+		.collect()
+		.map(Ok)
+		.await
+}
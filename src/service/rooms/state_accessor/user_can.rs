@@ -1,4 +1,4 @@
-use futures::pin_mut;
+use futures::{FutureExt, future::join, pin_mut};
 use ruma::{
 	EventId, RoomId, UserId,
 	events::{
@@ -80,8 +80,9 @@ pub async fn user_can_redact(
 	}
 }
 
-/// Whether a user is allowed to see an event, based on
-/// the room's history_visibility at that event's state.
+/// Whether a user is allowed to see an event. Considers the user's membership
+/// and the room's history_visibility at that event's state, falling back to
+/// the user's current membership for shared history.
 #[implement(super::Service)]
 #[tracing::instrument(skip_all, level = "trace")]
 pub async fn user_can_see_event(
@@ -101,23 +102,27 @@ pub async fn user_can_see_event(
 
 	let history_visibility = self
 		.state_get_content(shortstatehash, &StateEventType::RoomHistoryVisibility, "")
-		.await
-		.map_or(HistoryVisibility::Shared, |c: RoomHistoryVisibilityEventContent| {
-			c.history_visibility
+		.map(|content| {
+			content.map_or(HistoryVisibility::Shared, |c: RoomHistoryVisibilityEventContent| {
+				c.history_visibility
+			})
 		});
 
+	let membership = self.user_membership(shortstatehash, user_id);
+
+	let (history_visibility, membership) = join(history_visibility, membership).await;
+
+	// Members joined at the event see it regardless of the visibility.
+	if membership == MembershipState::Join {
+		return true;
+	}
+
 	match history_visibility {
 		| HistoryVisibility::WorldReadable => true,
 
-		// Allow if any member on requesting server was AT LEAST invited, else deny
-		| HistoryVisibility::Invited =>
-			self.user_was_invited(shortstatehash, user_id)
-				.await,
+		| HistoryVisibility::Invited => membership == MembershipState::Invite,
 
-		// Allow if any member on requested server was joined, else deny
-		| HistoryVisibility::Joined =>
-			self.user_was_joined(shortstatehash, user_id)
-				.await,
+		| HistoryVisibility::Joined => false,
 
 		| HistoryVisibility::Shared | _ =>
 			self.services
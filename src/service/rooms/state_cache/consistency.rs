@@ -0,0 +1,104 @@
+use std::collections::BTreeSet;
+
+use futures::StreamExt;
+use ruma::{OwnedServerName, RoomId, ServerName};
+use tuwunel_core::{
+	Result, implement,
+	utils::stream::{ReadyExt, TryIgnore},
+	warn,
+};
+
+/// Disagreement between the `roomserverids` and `serverroomids` pairs for a
+/// room, as found by `verify_server_room_consistency`.
+#[derive(Debug, Default)]
+pub struct ConsistencyReport {
+	/// Servers listed for the room without the reverse entry.
+	pub missing_reverse: Vec<OwnedServerName>,
+
+	/// Servers listing the room without the forward entry.
+	pub missing_forward: Vec<OwnedServerName>,
+
+	/// Whether the mismatches were repaired.
+	pub repaired: bool,
+}
+
+impl ConsistencyReport {
+	#[must_use]
+	pub fn is_consistent(&self) -> bool {
+		self.missing_reverse.is_empty() && self.missing_forward.is_empty()
+	}
+}
+
+/// Cross-check the forward and reverse server maps of a room. With `repair`
+/// each mismatched pair is restored when the server still has a joined member
+/// and removed otherwise.
+///
+/// Finding reverse entries without a forward entry scans every server's rooms,
+/// so this is intended for administration only.
+#[implement(super::Service)]
+#[tracing::instrument(skip(self), level = "debug")]
+pub async fn verify_server_room_consistency(
+	&self,
+	room_id: &RoomId,
+	repair: bool,
+) -> Result<ConsistencyReport> {
+	let forward: BTreeSet<OwnedServerName> = self
+		.room_servers(room_id)
+		.map(ToOwned::to_owned)
+		.collect()
+		.await;
+
+	let reverse: BTreeSet<OwnedServerName> = self
+		.db
+		.serverroomids
+		.keys()
+		.ignore_err()
+		.ready_filter_map(|(server, room): (&ServerName, &RoomId)| {
+			(room == room_id).then(|| server.to_owned())
+		})
+		.collect()
+		.await;
+
+	let mut report = ConsistencyReport {
+		missing_reverse: forward.difference(&reverse).cloned().collect(),
+		missing_forward: reverse.difference(&forward).cloned().collect(),
+		repaired: false,
+	};
+
+	if report.is_consistent() || !repair {
+		return Ok(report);
+	}
+
+	let joined: BTreeSet<OwnedServerName> = self
+		.room_members(room_id)
+		.map(|user_id| user_id.server_name().to_owned())
+		.collect()
+		.await;
+
+	for server in report
+		.missing_reverse
+		.iter()
+		.chain(report.missing_forward.iter())
+	{
+		let roomserver_id = (room_id, server);
+		let serverroom_id = (server, room_id);
+		if joined.contains(server) {
+			self.db.roomserverids.put_raw(roomserver_id, []);
+			self.db.serverroomids.put_raw(serverroom_id, []);
+		} else {
+			self.db.roomserverids.del(roomserver_id);
+			self.db.serverroomids.del(serverroom_id);
+		}
+	}
+
+	warn!(
+		%room_id,
+		missing_reverse = report.missing_reverse.len(),
+		missing_forward = report.missing_forward.len(),
+		"Repaired server maps of room."
+	);
+
+	report.repaired = true;
+
+	Ok(report)
+}
@@ -1,32 +1,44 @@
+mod consistency;
 mod update;
 mod via;
 
 use std::{
-	collections::HashMap,
-	sync::{Arc, RwLock},
+	collections::{HashMap, HashSet},
+	sync::{Arc, Mutex, RwLock},
+	time::{Duration, Instant},
 };
 
 use futures::{Stream, StreamExt, future::join5, pin_mut};
+use lru_cache::LruCache;
 use ruma::{
-	OwnedRoomId, RoomId, ServerName, UserId,
-	events::{AnyStrippedStateEvent, AnySyncStateEvent, room::member::MembershipState},
+	OwnedRoomId, OwnedServerName, OwnedUserId, RoomId, ServerName, UserId,
+	events::{
+		AnyStrippedStateEvent, AnySyncStateEvent,
+		room::member::{MembershipState, RoomMemberEventContent},
+	},
 	serde::Raw,
 };
+use tokio::sync::broadcast;
 use tuwunel_core::{
 	Result, implement, trace,
 	utils::{
 		self, BoolExt,
 		future::OptionStream,
-		stream::{BroadbandExt, ReadyExt, TryIgnore},
+		math::{expect_into, usize_from_f64},
+		stream::{BroadbandExt, IterStream, ReadyExt, TryIgnore},
 	},
 	warn,
 };
-use tuwunel_database::{Deserialized, Ignore, Interfix, Map};
+use tuwunel_database::{Deserialized, Ignore, Interfix, Map, Qry};
 
+pub use self::consistency::ConsistencyReport;
 use crate::appservice::RegistrationInfo;
 
 pub struct Service {
 	appservice_in_room_cache: AppServiceInRoomCache,
+	local_users_count_cache: LocalUsersCountCache,
+	our_real_users_cache: OurRealUsersCache,
+	membership_sender: broadcast::Sender<MembershipChange>,
 	services: Arc<crate::services::OnceServices>,
 	db: Data,
 }
@@ -36,6 +48,7 @@ struct Data {
 	roomid_invitedcount: Arc<Map>,
 	roomid_inviteviaservers: Arc<Map>,
 	roomid_joinedcount: Arc<Map>,
+	roomserverid_changedcount: Arc<Map>,
 	roomserverids: Arc<Map>,
 	roomuserid_invitecount: Arc<Map>,
 	roomuserid_joinedcount: Arc<Map>,
@@ -50,19 +63,48 @@ struct Data {
 }
 
 type AppServiceInRoomCache = RwLock<HashMap<OwnedRoomId, HashMap<String, bool>>>;
+type LocalUsersCountCache = RwLock<HashMap<OwnedRoomId, (usize, Instant)>>;
+type OurRealUsersCache = Mutex<OurRealUsers>;
+type OurRealUsersSet = Arc<HashSet<OwnedUserId>>;
+pub type MembershipChange = (OwnedRoomId, OwnedUserId, MembershipState);
+
+/// Cached `our_real_users` sets. `generation` advances on every invalidation,
+/// so a set computed across one is not inserted stale.
+struct OurRealUsers {
+	rooms: LruCache<OwnedRoomId, OurRealUsersSet>,
+	generation: u64,
+}
 type StrippedStateEventItem = (OwnedRoomId, Vec<Raw<AnyStrippedStateEvent>>);
 type SyncStateEventItem = (OwnedRoomId, Vec<Raw<AnySyncStateEvent>>);
 
+/// How long a memoized `local_users_count` is served before recounting.
+const LOCAL_USERS_COUNT_TTL: Duration = Duration::from_secs(10);
+
+/// Membership changes buffered per subscriber; slow subscribers lag and skip
+/// the oldest rather than holding up membership updates.
+const MEMBERSHIP_CHANNEL_CAPACITY: usize = 256;
+
 impl crate::Service for Service {
 	fn build(args: &crate::Args<'_>) -> Result<Arc<Self>> {
+		let config = &args.server.config;
+		let cache_capacity =
+			f64::from(config.our_real_users_cache_capacity) * config.cache_capacity_modifier;
+
 		Ok(Arc::new(Self {
 			appservice_in_room_cache: RwLock::new(HashMap::new()),
+			local_users_count_cache: RwLock::new(HashMap::new()),
+			our_real_users_cache: Mutex::new(OurRealUsers {
+				rooms: LruCache::new(usize_from_f64(cache_capacity)?),
+				generation: 0,
+			}),
+			membership_sender: broadcast::channel(MEMBERSHIP_CHANNEL_CAPACITY).0,
 			services: args.services.clone(),
 			db: Data {
 				roomid_knockedcount: args.db["roomid_knockedcount"].clone(),
 				roomid_invitedcount: args.db["roomid_invitedcount"].clone(),
 				roomid_inviteviaservers: args.db["roomid_inviteviaservers"].clone(),
 				roomid_joinedcount: args.db["roomid_joinedcount"].clone(),
+				roomserverid_changedcount: args.db["roomserverid_changedcount"].clone(),
 				roomserverids: args.db["roomserverids"].clone(),
 				roomuserid_invitecount: args.db["roomuserid_invitecount"].clone(),
 				roomuserid_joinedcount: args.db["roomuserid_joined"].clone(),
@@ -111,6 +153,13 @@ pub async fn appservice_in_room(&self, room_id: &RoomId, appservice: &Registrati
 	in_room
 }
 
+/// Subscribe to membership changes written by `update_membership`. Delivery
+/// is lossy: a receiver which falls behind observes `RecvError::Lagged`.
+#[implement(Service)]
+pub fn subscribe_membership(&self) -> broadcast::Receiver<MembershipChange> {
+	self.membership_sender.subscribe()
+}
+
 #[implement(Service)]
 pub fn get_appservice_in_room_cache_usage(&self) -> (usize, usize) {
 	let cache = self
@@ -152,6 +201,43 @@ pub async fn server_in_room<'a>(&'a self, server: &'a ServerName, room_id: &'a R
 	self.db.serverroomids.qry(&key).await.is_ok()
 }
 
+/// Returns the servers which came to have joined members in the room after
+/// `since`, and those which ceased to have any. A server which left and came
+/// back within the window is reported by where it stands now.
+#[implement(Service)]
+#[tracing::instrument(skip(self), level = "debug")]
+pub async fn server_delta(
+	&self,
+	room_id: &RoomId,
+	since: u64,
+) -> (Vec<OwnedServerName>, Vec<OwnedServerName>) {
+	type KeyVal<'a> = ((Ignore, &'a ServerName), u64);
+
+	let prefix = (room_id, Interfix);
+	let changed: Vec<OwnedServerName> = self
+		.db
+		.roomserverid_changedcount
+		.stream_prefix(&prefix)
+		.ignore_err()
+		.ready_filter_map(|((_, server), count): KeyVal<'_>| {
+			(count > since).then(|| server.to_owned())
+		})
+		.collect()
+		.await;
+
+	let mut added = Vec::new();
+	let mut removed = Vec::new();
+	for server in changed {
+		if self.server_in_room(&server, room_id).await {
+			added.push(server);
+		} else {
+			removed.push(server);
+		}
+	}
+
+	(added, removed)
+}
+
 /// Returns an iterator of all rooms a server participates in (as far as we
 /// know).
 #[implement(Service)]
@@ -168,6 +254,31 @@ pub fn server_rooms<'a>(
 		.map(|(_, room_id): (Ignore, &RoomId)| room_id)
 }
 
+/// Returns the number of distinct local users joined to any room shared with
+/// `server`, i.e. how many of our users that server can observe. Users in
+/// several shared rooms are counted once.
+#[implement(Service)]
+#[tracing::instrument(skip(self), level = "debug")]
+pub async fn users_visible_to_server(&self, server: &ServerName) -> u64 {
+	let users: HashSet<OwnedUserId> = self
+		.server_rooms(server)
+		.flat_map(|room_id| self.local_users_in_room(room_id))
+		.map(ToOwned::to_owned)
+		.collect()
+		.await;
+
+	expect_into(users.len())
+}
+
+/// Returns an iterator of every room with recorded membership counts, i.e.
+/// every room this server has tracked membership for. This walks the whole
+/// table and is O(rooms); it is intended for maintenance jobs.
+#[implement(Service)]
+#[tracing::instrument(skip(self), level = "debug")]
+pub fn all_rooms(&self) -> impl Stream<Item = &RoomId> + Send + '_ {
+	self.db.roomid_joinedcount.keys().ignore_err()
+}
+
 /// Returns true if server can see user by sharing at least one room.
 #[implement(Service)]
 #[tracing::instrument(skip(self), level = "trace")]
@@ -178,6 +289,40 @@ pub async fn server_sees_user(&self, server: &ServerName, user_id: &UserId) -> b
 		.await
 }
 
+/// Returns true if user is joined to at least one of the given rooms.
+#[implement(Service)]
+#[tracing::instrument(skip(self, rooms), level = "trace")]
+pub async fn is_joined_any<'a, I>(&'a self, user_id: &'a UserId, rooms: I) -> bool
+where
+	I: IntoIterator<Item = &'a RoomId> + Send + 'a,
+	<I as IntoIterator>::IntoIter: Send,
+{
+	rooms
+		.stream()
+		.broad_any(async |room_id| self.is_joined(user_id, room_id).await)
+		.await
+}
+
+/// Batched membership check for many (user, room) pairs at once. Results are
+/// yielded in the same order as the input.
+#[implement(Service)]
+#[tracing::instrument(skip(self, pairs), level = "trace")]
+pub fn are_joined<'a, I>(
+	&'a self,
+	pairs: I,
+) -> impl Stream<Item = ((&'a UserId, &'a RoomId), bool)> + Send + 'a
+where
+	I: Iterator<Item = (&'a UserId, &'a RoomId)> + Clone + Send + 'a,
+{
+	pairs
+		.clone()
+		.stream()
+		.qry(&self.db.userroomid_joinedcount)
+		.map(|handle| handle.is_ok())
+		.zip(pairs.stream())
+		.map(|(joined, pair)| (pair, joined))
+}
+
 /// Returns true if user_a and user_b share at least one room.
 #[implement(Service)]
 #[tracing::instrument(skip(self), level = "trace")]
@@ -274,6 +419,81 @@ pub fn local_users_in_room<'a>(
 		.ready_filter(|user| self.services.globals.user_is_local(user))
 }
 
+/// Returns the number of our local users joined to the room, even if they're
+/// deactivated/guests. The count is memoized briefly so bursts of requests
+/// for the same room do not each rescan the membership; membership changes
+/// invalidate it.
+#[implement(Service)]
+#[tracing::instrument(skip(self), level = "trace")]
+pub async fn local_users_count(&self, room_id: &RoomId) -> usize {
+	if let Some(&(count, counted)) = self
+		.local_users_count_cache
+		.read()
+		.expect("locked")
+		.get(room_id)
+		&& counted.elapsed() < LOCAL_USERS_COUNT_TTL
+	{
+		return count;
+	}
+
+	let count = self.local_users_in_room(room_id).count().await;
+
+	self.local_users_count_cache
+		.write()
+		.expect("locked")
+		.insert(room_id.into(), (count, Instant::now()));
+
+	count
+}
+
+#[implement(Service)]
+#[tracing::instrument(level = "trace", skip(self))]
+pub(super) fn invalidate_local_users_count(&self, room_id: &RoomId) {
+	self.local_users_count_cache
+		.write()
+		.expect("locked")
+		.remove(room_id);
+}
+
+/// Returns the set of our active local users joined to the room, as
+/// `active_local_users_in_room`. The set is cached per room until the next
+/// membership change in it, so bursts of events do not each rescan the
+/// membership; `our_real_users_cache_capacity` rooms are kept.
+#[implement(Service)]
+#[tracing::instrument(skip(self), level = "trace")]
+pub async fn our_real_users(&self, room_id: &RoomId) -> OurRealUsersSet {
+	let generation = {
+		let mut cache = self.our_real_users_cache.lock().expect("locked");
+		if let Some(users) = cache.rooms.get_mut(room_id) {
+			return users.clone();
+		}
+
+		cache.generation
+	};
+
+	let users: OurRealUsersSet = self
+		.active_local_users_in_room(room_id)
+		.map(ToOwned::to_owned)
+		.collect::<HashSet<_>>()
+		.await
+		.into();
+
+	let mut cache = self.our_real_users_cache.lock().expect("locked");
+	if cache.generation == generation {
+		cache.rooms.insert(room_id.into(), users.clone());
+	}
+
+	users
+}
+
+#[implement(Service)]
+#[tracing::instrument(level = "trace", skip(self))]
+pub(super) fn invalidate_our_real_users(&self, room_id: &RoomId) {
+	let mut cache = self.our_real_users_cache.lock().expect("locked");
+	cache.generation = cache.generation.wrapping_add(1);
+	cache.rooms.remove(room_id);
+}
+
 /// Returns an iterator of only our users invited to this room.
 #[implement(Service)]
 #[tracing::instrument(skip(self), level = "debug")]
@@ -451,6 +671,27 @@ pub fn rooms_joined<'a>(
 		.map(|(_, room_id): (Ignore, &RoomId)| room_id)
 }
 
+/// Returns an iterator over the rooms this user joined after `since`, judged
+/// by the count recorded with the join.
+#[implement(Service)]
+#[tracing::instrument(skip(self), level = "debug")]
+pub fn rooms_joined_since<'a>(
+	&'a self,
+	user_id: &'a UserId,
+	since: u64,
+) -> impl Stream<Item = &RoomId> + Send + 'a {
+	type KeyVal<'a> = ((Ignore, &'a RoomId), u64);
+
+	let prefix = (user_id, Interfix);
+	self.db
+		.userroomid_joinedcount
+		.stream_prefix(&prefix)
+		.ignore_err()
+		.ready_filter_map(move |((_, room_id), count): KeyVal<'_>| {
+			(count > since).then_some(room_id)
+		})
+}
+
 /// Returns an iterator over all rooms a user was invited to.
 #[implement(Service)]
 #[tracing::instrument(skip(self), level = "debug")]
@@ -479,6 +720,86 @@ pub fn rooms_knocked<'a>(
 		.map(|(_, room_id): (Ignore, &RoomId)| room_id)
 }
 
+/// Returns an iterator over the rooms a user was invited to with the invite
+/// count in `(since, until]`, along with the invite state. The count is checked
+/// before the state is loaded, so rooms outside the window cost no more than a
+/// key lookup.
+#[implement(Service)]
+#[tracing::instrument(skip(self), level = "debug")]
+pub fn rooms_invited_changed<'a>(
+	&'a self,
+	user_id: &'a UserId,
+	since: u64,
+	until: u64,
+) -> impl Stream<Item = StrippedStateEventItem> + Send + 'a {
+	self.rooms_invited(user_id)
+		.broad_filter_map(move |room_id| async move {
+			self.get_invite_count(room_id, user_id)
+				.await
+				.ok()
+				.filter(|&count| count > since && count <= until)?;
+
+			self.invite_state(user_id, room_id)
+				.await
+				.ok()
+				.map(|state| (room_id.to_owned(), state))
+		})
+}
+
+/// Returns an iterator over the rooms a user is knocking on with the knock
+/// count in `(since, to]`, along with the knock state. As with
+/// `rooms_invited_changed` the count is checked before the state is loaded.
+#[implement(Service)]
+#[tracing::instrument(skip(self), level = "debug")]
+pub fn rooms_knocked_since<'a>(
+	&'a self,
+	user_id: &'a UserId,
+	since: u64,
+	to: u64,
+) -> impl Stream<Item = StrippedStateEventItem> + Send + 'a {
+	self.rooms_knocked(user_id)
+		.broad_filter_map(move |room_id| async move {
+			self.get_knock_count(room_id, user_id)
+				.await
+				.ok()
+				.filter(|&count| count > since && count <= to)?;
+
+			self.knock_state(user_id, room_id)
+				.await
+				.ok()
+				.map(|state| (room_id.to_owned(), state))
+		})
+}
+
+/// Returns the rooms whose membership for the user changed in `(since, until]`
+/// along with the membership held now. Only the count of the latest change is
+/// kept for each room, so the delta reflects the net change over the window.
+#[implement(Service)]
+#[tracing::instrument(skip(self), level = "debug")]
+pub async fn membership_delta(
+	&self,
+	user_id: &UserId,
+	since: u64,
+	until: u64,
+) -> Vec<(OwnedRoomId, MembershipState)> {
+	self.all_user_memberships(user_id)
+		.broad_filter_map(move |(membership, room_id)| async move {
+			let count = match membership {
+				| MembershipState::Join => self.get_joined_count(room_id, user_id).await,
+				| MembershipState::Invite => self.get_invite_count(room_id, user_id).await,
+				| MembershipState::Knock => self.get_knock_count(room_id, user_id).await,
+				| _ => self.get_left_count(room_id, user_id).await,
+			};
+
+			count
+				.ok()
+				.filter(|&count| count > since && count <= until)
+				.map(|_| (room_id.to_owned(), membership))
+		})
+		.collect()
+		.await
+}
+
 /// Returns an iterator over all rooms a user left.
 #[implement(Service)]
 #[tracing::instrument(skip(self), level = "debug")]
@@ -604,6 +925,52 @@ pub async fn left_state(
 		})
 }
 
+/// Reason given for the leave or kick which took the user out of the room,
+/// read from the stored left state.
+#[implement(Service)]
+#[tracing::instrument(skip(self), level = "trace")]
+pub async fn leave_reason(&self, user_id: &UserId, room_id: &RoomId) -> Option<String> {
+	self.left_reason(user_id, room_id, &MembershipState::Leave)
+		.await
+}
+
+/// Reason given for the user's ban from the room, read from the stored left
+/// state.
+#[implement(Service)]
+#[tracing::instrument(skip(self), level = "trace")]
+pub async fn ban_reason(&self, user_id: &UserId, room_id: &RoomId) -> Option<String> {
+	self.left_reason(user_id, room_id, &MembershipState::Ban)
+		.await
+}
+
+#[implement(Service)]
+async fn left_reason(
+	&self,
+	user_id: &UserId,
+	room_id: &RoomId,
+	membership: &MembershipState,
+) -> Option<String> {
+	self.left_state(user_id, room_id)
+		.await
+		.ok()?
+		.iter()
+		.filter(|event| {
+			event
+				.get_field::<OwnedUserId>("state_key")
+				.ok()
+				.flatten()
+				.is_some_and(|state_key| state_key == user_id)
+		})
+		.filter_map(|event| {
+			event
+				.get_field::<RoomMemberEventContent>("content")
+				.ok()
+				.flatten()
+		})
+		.find(|content| content.membership == *membership)
+		.and_then(|content| content.reason)
+}
+
 #[implement(Service)]
 #[tracing::instrument(skip(self), level = "trace")]
 pub async fn user_membership(
@@ -701,6 +1068,16 @@ pub async fn delete_room_join_counts(&self, room_id: &RoomId, force: bool) -> Re
 		})
 		.await;
 
+	self.db
+		.roomserverid_changedcount
+		.keys_prefix(&prefix)
+		.ignore_err()
+		.ready_for_each(|key: (&RoomId, &ServerName)| {
+			trace!("Removing key: {key:?}");
+			self.db.roomserverid_changedcount.del(key);
+		})
+		.await;
+
 	self.db
 		.roomuserid_invitecount
 		.keys_prefix(&prefix)
@@ -4,8 +4,8 @@
 use ruma::{
 	OwnedServerName, RoomId, UserId,
 	events::{
-		AnyStrippedStateEvent, AnySyncStateEvent, GlobalAccountDataEventType,
-		RoomAccountDataEventType, StateEventType,
+		AnyStrippedStateEvent, GlobalAccountDataEventType, RoomAccountDataEventType,
+		StateEventType,
 		direct::DirectEvent,
 		room::{
 			create::RoomCreateEventContent,
@@ -14,7 +14,11 @@
 	},
 	serde::Raw,
 };
-use tuwunel_core::{Result, implement, is_not_empty, matrix::PduCount, utils::ReadyExt, warn};
+use serde_json::{json, value::to_raw_value};
+use tuwunel_core::{
+	Result, debug, implement, is_not_empty, matrix::PduCount, result::LogErr, trace,
+	utils::ReadyExt, warn,
+};
 use tuwunel_database::{Json, serialize_key};
 
 /// Update current membership data.
@@ -42,7 +46,9 @@ pub async fn update_membership(
 	update_joined_count: bool,
 	count: PduCount,
 ) -> Result {
-	let membership = membership_event.membership;
+	let membership = membership_event.membership.clone();
+	let local_departure = matches!(membership, MembershipState::Leave | MembershipState::Ban)
+		&& self.services.globals.user_is_local(user_id);
 
 	// Keep track what remote users exist by adding them as "deactivated" users
 	//
@@ -142,7 +148,18 @@ pub async fn update_membership(
 				.await;
 		},
 		| MembershipState::Leave | MembershipState::Ban => {
-			self.mark_as_left(user_id, room_id, count);
+			let left_state = stripped_member_event(user_id, sender, &membership_event);
+			self.mark_as_left(user_id, room_id, count, left_state);
+
+			// Remote clients would otherwise keep showing the user as typing.
+			if self.services.globals.user_is_local(user_id) {
+				self.services
+					.typing
+					.typing_leave(user_id, room_id)
+					.await
+					.log_err()
+					.ok();
+			}
 
 			if self.services.globals.user_is_local(user_id)
 				&& (self.services.config.forget_forced_upon_leave
@@ -158,8 +175,28 @@ pub async fn update_membership(
 		| _ => {},
 	}
 
+	if self.services.globals.user_is_local(user_id) {
+		self.invalidate_local_users_count(room_id);
+		self.invalidate_our_real_users(room_id);
+	}
+
+	if self
+		.membership_sender
+		.send((room_id.to_owned(), user_id.to_owned(), membership))
+		.is_err()
+	{
+		trace!("no subscribers for membership changes");
+	}
+
 	if update_joined_count {
 		self.update_joined_count(room_id).await;
+
+		// However our user went, stop participating if they were the last. A
+		// rebuild of the room's memberships leaves the count alone and is not
+		// the last word on who remains.
+		if local_departure {
+			self.maybe_forget_room(room_id).await;
+		}
 	}
 
 	Ok(())
@@ -206,6 +243,9 @@ pub async fn update_joined_count(&self, room_id: &RoomId) {
 		.roomid_knockedcount
 		.raw_put(room_id, knockedcount);
 
+	// Servers entering or leaving the room are stamped with the count at which
+	// they did so, for server_delta().
+	let count = self.services.globals.current_count();
 	self.room_servers(room_id)
 		.ready_for_each(|old_joined_server| {
 			if joined_servers.remove(old_joined_server) {
@@ -218,6 +258,9 @@ pub async fn update_joined_count(&self, room_id: &RoomId) {
 
 			self.db.roomserverids.del(roomserver_id);
 			self.db.serverroomids.del(serverroom_id);
+			self.db
+				.roomserverid_changedcount
+				.put_aput::<8, _, _>(roomserver_id, count);
 		})
 		.await;
 
@@ -228,12 +271,18 @@ pub async fn update_joined_count(&self, room_id: &RoomId) {
 
 		self.db.roomserverids.put_raw(roomserver_id, []);
 		self.db.serverroomids.put_raw(serverroom_id, []);
+		self.db
+			.roomserverid_changedcount
+			.put_aput::<8, _, _>(roomserver_id, count);
 	}
 
 	self.appservice_in_room_cache
 		.write()
 		.expect("locked")
 		.remove(room_id);
+
+	self.invalidate_local_users_count(room_id);
+	self.invalidate_our_real_users(room_id);
 }
 
 /// Direct DB function to directly mark a user as joined. It is not
@@ -275,18 +324,24 @@ pub(crate) fn mark_as_joined(&self, user_id: &UserId, room_id: &RoomId, count: P
 
 /// Direct DB function to directly mark a user as left. It is not
 /// recommended to use this directly. You most likely should use
-/// `update_membership` instead
+/// `update_membership` instead. The `leave_event` is kept as the left state
+/// so the leave or ban can be audited later.
 #[implement(super::Service)]
 #[tracing::instrument(skip(self), level = "debug")]
-pub(crate) fn mark_as_left(&self, user_id: &UserId, room_id: &RoomId, count: PduCount) {
+pub(crate) fn mark_as_left(
+	&self,
+	user_id: &UserId,
+	room_id: &RoomId,
+	count: PduCount,
+	leave_event: Option<Raw<AnyStrippedStateEvent>>,
+) {
 	let userroom_id = (user_id, room_id);
 	let userroom_id = serialize_key(userroom_id).expect("failed to serialize userroom_id");
 
 	let roomuser_id = (room_id, user_id);
 	let roomuser_id = serialize_key(roomuser_id).expect("failed to serialize roomuser_id");
 
-	// (timo) TODO
-	let leftstate = Vec::<Raw<AnySyncStateEvent>>::new();
+	let leftstate: Vec<_> = leave_event.into_iter().collect();
 
 	self.db
 		.userroomid_leftstate
@@ -371,6 +426,53 @@ pub fn forget(&self, room_id: &RoomId, user_id: &UserId) {
 	self.db.roomuserid_leftcount.del(roomuser_id);
 }
 
+/// Stop participating in a room once none of our users are joined, invited or
+/// knocking. The room's membership and server lists are dropped so nothing more
+/// is sent to it, while our users keep their left state and the room keeps its
+/// state; rejoining restores the memberships from it (see
+/// `state::rebuild_memberships`). `update_membership` calls this whenever one
+/// of our users leaves, is kicked or is banned. Returns whether the room was
+/// forgotten; purging it entirely is up to the caller per
+/// `delete_rooms_after_leave`.
+#[implement(super::Service)]
+#[tracing::instrument(skip(self), level = "debug", ret)]
+pub async fn maybe_forget_room(&self, room_id: &RoomId) -> bool {
+	if self
+		.local_users_in_room(room_id)
+		.ready_any(|_| true)
+		.await
+	{
+		return false;
+	}
+
+	if self
+		.local_users_invited_to_room(room_id)
+		.ready_any(|_| true)
+		.await
+	{
+		return false;
+	}
+
+	if self
+		.room_members_knocked(room_id)
+		.ready_any(|user| self.services.globals.user_is_local(user))
+		.await
+	{
+		return false;
+	}
+
+	debug!("Last local user left; forgetting room");
+	self.delete_room_join_counts(room_id, false)
+		.await
+		.log_err()
+		.ok();
+
+	self.invalidate_local_users_count(room_id);
+	self.invalidate_our_real_users(room_id);
+
+	true
+}
+
 #[implement(super::Service)]
 #[tracing::instrument(level = "debug", skip(self))]
 fn mark_as_once_joined(&self, user_id: &UserId, room_id: &RoomId) {
@@ -423,3 +525,19 @@ pub(crate) async fn mark_as_invited(
 			.await;
 	}
 }
+
+/// The membership event in stripped form, as kept in a user's left state.
+fn stripped_member_event(
+	user_id: &UserId,
+	sender: &UserId,
+	content: &RoomMemberEventContent,
+) -> Option<Raw<AnyStrippedStateEvent>> {
+	let event = json!({
+		"content": content,
+		"sender": sender,
+		"state_key": user_id,
+		"type": StateEventType::RoomMember,
+	});
+
+	to_raw_value(&event).map(Raw::from_json).ok()
+}
@@ -1,7 +1,8 @@
 use std::{
-	collections::{BTreeSet, HashMap},
+	collections::{BTreeSet, HashMap, HashSet},
 	fmt::{Debug, Write},
 	mem::size_of,
+	pin::pin,
 	sync::{Arc, Mutex},
 };
 
@@ -13,7 +14,11 @@
 	Result,
 	arrayvec::ArrayVec,
 	at, checked, err, expected, implement, utils,
-	utils::{bytes, math::usize_from_f64, stream::IterStream},
+	utils::{
+		bytes,
+		math::usize_from_f64,
+		stream::{IterStream, TryIgnore},
+	},
 };
 use tuwunel_database::Map;
 
@@ -448,6 +453,40 @@ pub async fn save_state(
 	})
 }
 
+/// Drop from `candidates` every event referred to by a stored state snapshot,
+/// whether current or historic. Snapshots are read one at a time and the scan
+/// ends as soon as no candidate is left.
+#[implement(Service)]
+#[tracing::instrument(skip_all, fields(candidates = candidates.len()), level = "debug")]
+pub async fn retain_unreferenced(&self, candidates: &mut HashSet<ShortEventId>) {
+	let mut shortstatehashes = pin!(
+		self.db
+			.shortstatehash_statediff
+			.raw_keys()
+			.ignore_err()
+			.map(utils::u64_from_u8)
+	);
+
+	while !candidates.is_empty()
+		&& let Some(shortstatehash) = shortstatehashes.next().await
+	{
+		let Ok(StateDiff { added, removed, .. }) = self.get_statediff(shortstatehash).await
+		else {
+			continue;
+		};
+
+		added
+			.iter()
+			.chain(removed.iter())
+			.copied()
+			.map(parse_compressed_state_event)
+			.map(at!(1))
+			.for_each(|shorteventid| {
+				candidates.remove(&shorteventid);
+			});
+	}
+}
+
 #[implement(Service)]
 #[tracing::instrument(skip(self), level = "debug", name = "get")]
 async fn get_statediff(&self, shortstatehash: ShortStateHash) -> Result<StateDiff> {
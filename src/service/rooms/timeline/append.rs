@@ -13,7 +13,7 @@
 	},
 };
 use tuwunel_core::{
-	Result, err, error, implement,
+	Err, Result, err, error, implement,
 	matrix::{
 		event::Event,
 		pdu::{PduCount, PduEvent, PduId, RawPduId},
@@ -149,6 +149,22 @@ pub async fn append_pdu<'a, Leafs>(
 		}
 	}
 
+	let insert_lock = self.mutex_insert.lock(pdu.room_id()).await;
+
+	// A concurrent append of the same event may have taken the insert lock
+	// first; a second timeline entry would double every count derived from it.
+	// Its references and extremities were recorded by that append already.
+	if self
+		.db
+		.eventid_pduid
+		.exists(pdu.event_id().as_bytes())
+		.await
+		.is_ok()
+	{
+		let event_id = pdu.event_id();
+		return Err!(Conflict("Event {event_id} is already in the timeline."));
+	}
+
 	// We must keep track of all events that have been referenced.
 	self.services
 		.pdu_metadata
@@ -159,7 +175,6 @@ pub async fn append_pdu<'a, Leafs>(
 		.set_forward_extremities(pdu.room_id(), leafs, state_lock)
 		.await;
 
-	let insert_lock = self.mutex_insert.lock(pdu.room_id()).await;
 	let next_count1 = self.services.globals.next_count();
 	let next_count2 = self.services.globals.next_count();
 
@@ -383,6 +398,10 @@ fn append_pdu_json(&self, pdu_id: &RawPduId, pdu: &PduEvent, json: &CanonicalJso
 		.eventid_outlierpdu
 		.remove(pdu.event_id.as_bytes());
 
+	self.db
+		.eventid_outlierreceived
+		.remove(pdu.event_id.as_bytes());
+
 	let ts = u64::from(pdu.origin_server_ts);
 	let count_key = bias_count(pdu_id.count());
 
@@ -1,4 +1,8 @@
-use std::{collections::HashSet, iter::once, num::NonZeroUsize};
+use std::{
+	collections::{HashMap, HashSet},
+	iter::once,
+	num::NonZeroUsize,
+};
 
 use futures::{
 	FutureExt, StreamExt, TryFutureExt,
@@ -6,15 +10,15 @@
 };
 use rand::seq::SliceRandom;
 use ruma::{
-	CanonicalJsonObject, EventId, MilliSecondsSinceUnixEpoch, OwnedEventId, RoomId, ServerName,
-	api::Direction, events::TimelineEventType,
+	CanonicalJsonObject, CanonicalJsonValue, EventId, MilliSecondsSinceUnixEpoch, OwnedEventId,
+	RoomId, ServerName, api::Direction, events::TimelineEventType,
 };
 use serde::Deserialize;
 use serde_json::value::RawValue as RawJsonValue;
 use tuwunel_core::{
 	Result, at, debug, debug_warn, implement, is_false,
 	matrix::{
-		event::Event,
+		event::{Event, gen_event_id_canonical_json},
 		pdu::{PduCount, PduId, RawPduId},
 	},
 	utils::{
@@ -109,7 +113,14 @@ pub async fn backfill_if_required(&self, room_id: &RoomId, from: PduCount) -> Re
 
 	let pdus: Vec<Box<RawJsonValue>> = serde_json::from_slice(&outcome.bytes)?;
 
-	pdus.into_iter()
+	let prev_events: Vec<_> = first_pdu
+		.prev_events()
+		.map(ToOwned::to_owned)
+		.collect();
+
+	self.limit_backfill_depth(room_id, first_pdu.event_id(), &prev_events, pdus)
+		.await
+		.into_iter()
 		.stream()
 		.for_each(async |pdu| {
 			self.backfill_pdu(room_id, &outcome.origin, pdu)
@@ -122,6 +133,119 @@ pub async fn backfill_if_required(&self, room_id: &RoomId, from: PduCount) -> Re
 	Ok(())
 }
 
+/// Drops the backfilled events lying more than `backfill_max_depth`
+/// prev_event generations behind `from`, whose own prev_events are
+/// `from_prevs` when it is not among the events.
+#[implement(super::Service)]
+async fn limit_backfill_depth(
+	&self,
+	room_id: &RoomId,
+	from: &EventId,
+	from_prevs: &[OwnedEventId],
+	pdus: Vec<Box<RawJsonValue>>,
+) -> Vec<Box<RawJsonValue>> {
+	let max_depth = self.services.server.config.backfill_max_depth;
+	if max_depth == 0 {
+		return pdus;
+	}
+
+	let Ok(room_version) = self
+		.services
+		.state
+		.get_room_version(room_id)
+		.await
+	else {
+		return pdus;
+	};
+
+	let received = pdus.len();
+	let events: Vec<_> = pdus
+		.into_iter()
+		.filter_map(|pdu| {
+			let (event_id, value) = gen_event_id_canonical_json(&pdu, &room_version).ok()?;
+
+			Some((event_id, prev_event_ids(&value), pdu))
+		})
+		.collect();
+
+	let pdus = limit_depth(from, from_prevs, events, max_depth);
+	if pdus.len() < received {
+		debug!(
+			%room_id,
+			%max_depth,
+			"Backfill depth cap reached; keeping {} of {received} events",
+			pdus.len(),
+		);
+	}
+
+	pdus
+}
+
+/// Keeps the events at most `max_depth` prev_event generations behind `from`;
+/// `from_prevs` are the prev_events of `from` should it be missing from
+/// `events`.
+fn limit_depth<T>(
+	from: &EventId,
+	from_prevs: &[OwnedEventId],
+	events: Vec<(OwnedEventId, Vec<OwnedEventId>, T)>,
+	max_depth: usize,
+) -> Vec<T> {
+	let prevs: HashMap<&EventId, &[OwnedEventId]> = events
+		.iter()
+		.map(|(event_id, prevs, _)| (event_id.as_ref(), prevs.as_slice()))
+		.collect();
+
+	let mut reached: HashSet<&EventId> = once(from).collect();
+	let mut generation: Vec<&EventId> = prevs
+		.get(from)
+		.copied()
+		.unwrap_or(from_prevs)
+		.iter()
+		.map(AsRef::as_ref)
+		.collect();
+
+	for _ in 0..max_depth {
+		generation = generation
+			.into_iter()
+			.filter(|event_id| reached.insert(*event_id))
+			.filter_map(|event_id| prevs.get(event_id))
+			.flat_map(|prevs| prevs.iter().map(AsRef::as_ref))
+			.collect();
+	}
+
+	let reached: HashSet<OwnedEventId> = reached
+		.into_iter()
+		.map(ToOwned::to_owned)
+		.collect();
+
+	events
+		.into_iter()
+		.filter(|(event_id, ..)| reached.contains(event_id))
+		.map(|(.., event)| event)
+		.collect()
+}
+
+/// The prev_events of a PDU, in either the reference form of v3+ rooms or the
+/// `[event_id, hashes]` pairs of v1/v2 rooms.
+fn prev_event_ids(value: &CanonicalJsonObject) -> Vec<OwnedEventId> {
+	let Some(CanonicalJsonValue::Array(prev_events)) = value.get("prev_events") else {
+		return Vec::new();
+	};
+
+	prev_events
+		.iter()
+		.filter_map(|prev_event| match prev_event {
+			| CanonicalJsonValue::String(event_id) => Some(event_id),
+			| CanonicalJsonValue::Array(pair) => match pair.first() {
+				| Some(CanonicalJsonValue::String(event_id)) => Some(event_id),
+				| _ => None,
+			},
+			| _ => None,
+		})
+		.filter_map(|event_id| OwnedEventId::try_from(event_id.as_str()).ok())
+		.collect()
+}
+
 #[implement(super::Service)]
 async fn backfill_candidates(&self, room_id: &RoomId) -> Candidates {
 	let canonical_alias = self
@@ -291,7 +415,9 @@ async fn backfill_event(
 
 	let pdus: Vec<Box<RawJsonValue>> = serde_json::from_slice(&outcome.bytes)?;
 
-	pdus.into_iter()
+	self.limit_backfill_depth(room_id, event_id, &[], pdus)
+		.await
+		.into_iter()
 		.stream()
 		.for_each(async |pdu| {
 			self.backfill_pdu(room_id, &outcome.origin, pdu)
@@ -428,6 +554,7 @@ fn prepend_backfill_pdu(
 	self.db.eventid_pduid.insert(event_id, pdu_id);
 
 	self.db.eventid_outlierpdu.remove(event_id);
+	self.db.eventid_outlierreceived.remove(event_id);
 
 	let count_key = bias_count(pdu_id.count());
 
@@ -435,3 +562,46 @@ fn prepend_backfill_pdu(
 		.roomid_tscount_pducount
 		.put_raw((room_id, origin_server_ts, count_key), pdu_id.count());
 }
+
+#[cfg(test)]
+mod tests {
+	use ruma::{OwnedEventId, event_id};
+
+	use super::limit_depth;
+
+	/// A linear history `$e1 <- ... <- $e9` resting on the unreturned `$e0`,
+	/// newest first as /backfill returns it, each event labelled by its index.
+	fn chain() -> Vec<(OwnedEventId, Vec<OwnedEventId>, usize)> {
+		let ids: Vec<OwnedEventId> = (0..10)
+			.map(|i| {
+				format!("$e{i}")
+					.try_into()
+					.expect("valid event id")
+			})
+			.collect();
+
+		(1..10)
+			.rev()
+			.map(|i| (ids[i].clone(), vec![ids[i.saturating_sub(1)].clone()], i))
+			.collect()
+	}
+
+	#[test]
+	fn backfill_stops_at_max_depth() {
+		let from = event_id!("$e9");
+		let kept = limit_depth(from, &[], chain(), 3);
+		assert_eq!(kept, [9, 8, 7, 6], "the starting event and three generations behind it");
+
+		let kept = limit_depth(from, &[], chain(), 100);
+		assert_eq!(kept, [9, 8, 7, 6, 5, 4, 3, 2, 1], "a deep cap keeps everything");
+	}
+
+	#[test]
+	fn backfill_depth_counts_from_local_prevs() {
+		// Backfilling from our oldest event, which the remote does not return.
+		let from = event_id!("$e10");
+		let prevs = [event_id!("$e9").to_owned()];
+		let kept = limit_depth(from, &prevs, chain(), 2);
+		assert_eq!(kept, [9, 8], "two generations behind the local event");
+	}
+}
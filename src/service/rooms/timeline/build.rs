@@ -2,7 +2,7 @@
 
 use futures::{FutureExt, StreamExt};
 use ruma::{
-	OwnedEventId, OwnedServerName, RoomId, UserId,
+	OwnedEventId, OwnedServerName, RoomId, ServerName, UserId,
 	events::{
 		TimelineEventType,
 		room::member::{MembershipState, RoomMemberEventContent},
@@ -99,6 +99,38 @@ pub async fn build_and_append_pdu(
 		.boxed()
 		.await?;
 
+	// The recipients are taken before appending, which forgets the room and its
+	// servers when this is the departure of our last user.
+	let mut servers: HashSet<OwnedServerName> = self
+		.services
+		.state_cache
+		.room_servers(pdu.room_id())
+		.map(ToOwned::to_owned)
+		.collect()
+		.await;
+
+	// In case we are kicking or banning a user, we need to inform their server of
+	// the change, and it is the one most interested in it
+	let member_server = (*pdu.kind() == TimelineEventType::RoomMember)
+		.then(|| pdu.state_key.as_ref())
+		.flatten()
+		.and_then(|state_key| UserId::parse(state_key.as_str()).ok())
+		.map(|state_key_uid| state_key_uid.server_name().to_owned());
+
+	if let Some(member_server) = &member_server {
+		servers.insert(member_server.clone());
+	}
+
+	// Remove our server from the server list since it will be added to it by
+	// room_servers() and/or the if statement above
+	servers.remove(self.services.globals.server_name());
+
+	let priority: Vec<&ServerName> = member_server
+		.iter()
+		.map(|server| &**server)
+		.filter(|server| servers.contains(*server))
+		.collect();
+
 	// We append to state before appending the pdu, so we don't have a moment in
 	// time with the pdu without it's state. This is okay because append_pdu can't
 	// fail.
@@ -122,32 +154,15 @@ pub async fn build_and_append_pdu(
 		.state
 		.set_room_state(pdu.room_id(), statehashid, state_lock);
 
-	let mut servers: HashSet<OwnedServerName> = self
-		.services
-		.state_cache
-		.room_servers(pdu.room_id())
-		.map(ToOwned::to_owned)
-		.collect()
-		.await;
-
-	// In case we are kicking or banning a user, we need to inform their server of
-	// the change
-	if *pdu.kind() == TimelineEventType::RoomMember
-		&& let Some(state_key_uid) = &pdu
-			.state_key
-			.as_ref()
-			.and_then(|state_key| UserId::parse(state_key.as_str()).ok())
-	{
-		servers.insert(state_key_uid.server_name().to_owned());
-	}
-
-	// Remove our server from the server list since it will be added to it by
-	// room_servers() and/or the if statement above
-	servers.remove(self.services.globals.server_name());
+	self.run_append_hooks(&pdu);
 
 	self.services
 		.sending
-		.send_pdu_servers(servers.iter().map(AsRef::as_ref).stream(), &pdu_id)
+		.send_pdu_servers_prioritized(
+			servers.iter().map(AsRef::as_ref).stream(),
+			&pdu_id,
+			&priority,
+		)
 		.await?;
 
 	Ok(pdu.event_id().to_owned())
@@ -0,0 +1,30 @@
+//! Hooks run after a locally built PDU has been appended to the timeline.
+//!
+//! Hooks are called synchronously from `build_and_append_pdu` once the event
+//! and the room state it produces are committed, and before the event is sent
+//! out over federation. They must not block; anything slow should be handed
+//! off to a task or channel of the subscriber's own.
+
+use tuwunel_core::{implement, matrix::pdu::PduEvent};
+
+pub type AppendHook = Box<dyn Fn(&PduEvent) + Send + Sync>;
+
+/// Register a hook called with every PDU appended by `build_and_append_pdu`.
+/// Hooks are kept for the lifetime of the service and run in registration
+/// order.
+#[implement(super::Service)]
+pub fn register_append_hook(&self, hook: AppendHook) {
+	self.append_hooks
+		.write()
+		.expect("locked for writing")
+		.push(hook);
+}
+
+#[implement(super::Service)]
+pub(super) fn run_append_hooks(&self, pdu: &PduEvent) {
+	self.append_hooks
+		.read()
+		.expect("locked for reading")
+		.iter()
+		.for_each(|hook| hook(pdu));
+}
@@ -2,14 +2,21 @@
 mod backfill;
 mod build;
 mod create;
+mod hook;
 mod pdus;
+mod prune;
 mod redact;
 
-use std::{fmt::Write, future::Future, sync::Arc};
+use std::{
+	fmt::Write,
+	future::Future,
+	sync::{Arc, RwLock},
+	time::Duration,
+};
 
 use async_trait::async_trait;
 use futures::{
-	TryFutureExt, TryStreamExt,
+	FutureExt, Stream, StreamExt, TryFutureExt, TryStreamExt,
 	future::{
 		Either::{Left, Right},
 		select_ok,
@@ -29,25 +36,30 @@
 		pdu::{PduCount, PduEvent},
 	},
 	utils::{
-		MutexMap, MutexMapGuard,
+		IterStream, MutexMap, MutexMapGuard, millis_since_unix_epoch,
 		result::{LogErr, NotFound},
-		stream::TryReadyExt,
+		stream::{BroadbandExt, TryReadyExt},
 	},
 	warn,
 };
-use tuwunel_database::{Database, Deserialized, Json, Map};
+use tuwunel_database::{Database, Deserialized, Get, Json, Map};
 
-pub use self::pdus::{PdusIterItem, bias_count};
+pub use self::{
+	hook::AppendHook,
+	pdus::{PdusIterItem, bias_count},
+};
 use crate::rooms::short::{ShortRoomId, ShortStateHash};
 
 pub struct Service {
 	services: Arc<crate::services::OnceServices>,
 	db: Data,
 	pub mutex_insert: RoomMutexMap,
+	append_hooks: RwLock<Vec<AppendHook>>,
 }
 
 struct Data {
 	eventid_outlierpdu: Arc<Map>,
+	eventid_outlierreceived: Arc<Map>,
 	eventid_pduid: Arc<Map>,
 	pduid_pdu: Arc<Map>,
 	roomid_tscount_pducount: Arc<Map>,
@@ -86,12 +98,14 @@ fn build(args: &crate::Args<'_>) -> Result<Arc<Self>> {
 			services: args.services.clone(),
 			db: Data {
 				eventid_outlierpdu: args.db["eventid_outlierpdu"].clone(),
+				eventid_outlierreceived: args.db["eventid_outlierreceived"].clone(),
 				eventid_pduid: args.db["eventid_pduid"].clone(),
 				pduid_pdu: args.db["pduid_pdu"].clone(),
 				roomid_tscount_pducount: args.db["roomid_tscount_pducount"].clone(),
 				db: args.db.clone(),
 			},
 			mutex_insert: RoomMutexMap::new(),
+			append_hooks: RwLock::new(Vec::new()),
 		}))
 	}
 
@@ -102,6 +116,21 @@ async fn memory_usage(&self, out: &mut (dyn Write + Send)) -> Result {
 		Ok(())
 	}
 
+	async fn worker(self: Arc<Self>) -> Result {
+		loop {
+			let retention_seconds = self.services.config.outlier_retention_seconds;
+			if retention_seconds != 0 {
+				self.prune_outliers(Duration::from_secs(retention_seconds))
+					.await;
+			}
+
+			tokio::select! {
+				() = tokio::time::sleep(Duration::from_hours(1)) => {},
+				() = self.services.server.until_shutdown() => return Ok(())
+			};
+		}
+	}
+
 	fn name(&self) -> &str { crate::service::make_name(std::module_path!()) }
 }
 
@@ -124,6 +153,10 @@ pub fn add_pdu_outlier(&self, event_id: &EventId, pdu: &CanonicalJsonObject) {
 	self.db
 		.eventid_outlierpdu
 		.raw_put(event_id, Json(pdu));
+
+	self.db
+		.eventid_outlierreceived
+		.raw_put(event_id, millis_since_unix_epoch());
 }
 
 #[implement(Service)]
@@ -167,6 +200,36 @@ pub async fn latest_item_in_room(
 		.ok_or_else(|| err!(Request(NotFound("No PDU's found in room"))))
 }
 
+/// The latest event of each room `user_id` is joined to, most recent first and
+/// at most `limit` of them.
+#[implement(Service)]
+#[tracing::instrument(skip(self), level = "debug")]
+pub async fn recent_events_for_user(
+	&self,
+	user_id: &UserId,
+	limit: usize,
+) -> Vec<(OwnedRoomId, PduCount, PduEvent)> {
+	let mut recent: Vec<_> = self
+		.services
+		.state_cache
+		.rooms_joined(user_id)
+		.broad_filter_map(async |room_id| {
+			let pdus_rev = self.pdus_rev(Some(user_id), room_id, None);
+
+			pin_mut!(pdus_rev);
+			let (count, pdu) = pdus_rev.try_next().await.ok()??;
+
+			Some((room_id.to_owned(), count, pdu))
+		})
+		.collect()
+		.await;
+
+	recent.sort_unstable_by(|(_, a, _), (_, b, _)| b.cmp(a));
+	recent.truncate(limit);
+
+	recent
+}
+
 /// Returns the shortstatehash of the room at the event directly preceding the
 /// exclusive `before` param. `before` does not have to be a valid count
 /// or in the room.
@@ -300,6 +363,44 @@ pub async fn next_timeline_count(&self, after: &PduId) -> Result<PduCount> {
 		.ok_or(err!(Request(NotFound("No more PDU's found in room"))))
 }
 
+/// Returns the pdu immediately adjacent to `event_id` in the room's timeline:
+/// the one following it for `Direction::Forward`, or preceding it for
+/// `Direction::Backward`. NotFound at either end of the timeline.
+#[implement(Service)]
+#[tracing::instrument(skip(self), level = "debug")]
+pub async fn neighbor_pdu(
+	&self,
+	room_id: &RoomId,
+	event_id: &EventId,
+	dir: Direction,
+) -> Result<PduEvent> {
+	let shortroomid: ShortRoomId = self
+		.services
+		.short
+		.get_shortroomid(room_id)
+		.await
+		.map_err(|e| err!(Request(NotFound("Room {room_id:?} not found: {e:?}"))))?;
+
+	let pdu_id: PduId = self
+		.get_pdu_id(event_id)
+		.await
+		.map_err(|e| err!(Request(NotFound("Event {event_id} not found in timeline: {e:?}"))))?
+		.into();
+
+	if pdu_id.shortroomid != shortroomid {
+		return Err!(Request(NotFound("Event {event_id} is not in room {room_id}")));
+	}
+
+	let count = match dir {
+		| Direction::Forward => self.next_timeline_count(&pdu_id).await?,
+		| Direction::Backward => self.prev_timeline_count(&pdu_id).await?,
+	};
+
+	let neighbor: RawPduId = PduId { shortroomid, count }.into();
+
+	self.get_pdu_from_id(&neighbor).await
+}
+
 #[implement(Service)]
 #[tracing::instrument(skip(self), level = "debug")]
 pub async fn last_timeline_count(
@@ -466,6 +567,74 @@ pub async fn get_non_outlier_pdu_json(&self, event_id: &EventId) -> Result<Canon
 	self.get_non_outlier(event_id).await
 }
 
+/// Returns the json of many pdus in the order requested, batching the
+/// database reads. Checks the `eventid_outlierpdu` Tree for those not found in
+/// the timeline.
+#[implement(Service)]
+pub fn get_pdus_json<'a, I>(
+	&'a self,
+	event_ids: I,
+) -> impl Stream<Item = (OwnedEventId, Result<CanonicalJsonObject>)> + Send + 'a
+where
+	I: Iterator<Item = &'a EventId> + Send + 'a,
+{
+	let event_ids: Vec<_> = event_ids.collect();
+
+	async move {
+		let pdu_ids: Vec<Option<RawPduId>> = event_ids
+			.iter()
+			.copied()
+			.stream()
+			.get(&self.db.eventid_pduid)
+			.map(|result| result.map(|handle| RawPduId::from(&*handle)).ok())
+			.collect()
+			.await;
+
+		let accepted: Vec<Result<CanonicalJsonObject>> = pdu_ids
+			.iter()
+			.flatten()
+			.stream()
+			.get(&self.db.pduid_pdu)
+			.map(Deserialized::deserialized)
+			.collect()
+			.await;
+
+		let mut accepted = accepted.into_iter();
+		let mut pdus: Vec<_> = pdu_ids
+			.iter()
+			.map(|pdu_id| pdu_id.and_then(|_| accepted.next()?.ok()))
+			.collect();
+
+		let outliers: Vec<Result<CanonicalJsonObject>> = event_ids
+			.iter()
+			.zip(pdus.iter())
+			.filter(|(_, pdu)| pdu.is_none())
+			.map(|(event_id, _)| *event_id)
+			.stream()
+			.get(&self.db.eventid_outlierpdu)
+			.map(Deserialized::deserialized)
+			.collect()
+			.await;
+
+		let mut outliers = outliers.into_iter();
+		event_ids
+			.into_iter()
+			.zip(pdus.iter_mut())
+			.map(|(event_id, pdu)| {
+				let pdu = pdu.take().map(Ok).unwrap_or_else(|| {
+					outliers
+						.next()
+						.unwrap_or_else(|| Err!(Request(NotFound("PDU not found."))))
+				});
+
+				(event_id.to_owned(), pdu)
+			})
+			.collect::<Vec<_>>()
+	}
+	.map(IterStream::stream)
+	.flatten_stream()
+}
+
 /// Returns the pdu as a `BTreeMap<String, CanonicalJsonValue>`.
 /// This does __NOT__ check the outliers `Tree`.
 #[implement(Service)]
@@ -600,6 +769,25 @@ pub async fn get_event_id_from_pdu_id(&self, pdu_id: &PduId) -> Result<OwnedEven
 		.await
 }
 
+/// Returns the `event_id` of the pdu at `count` in the room. Only the event_id
+/// is extracted from the stored json rather than parsing the whole PduEvent.
+#[implement(Service)]
+#[tracing::instrument(skip(self), level = "debug")]
+pub async fn event_id_at_count(&self, room_id: &RoomId, count: PduCount) -> Result<OwnedEventId> {
+	let shortroomid: ShortRoomId = self
+		.services
+		.short
+		.get_shortroomid(room_id)
+		.await
+		.map_err(|e| err!(Request(NotFound("Room {room_id:?} not found: {e:?}"))))?;
+
+	let pdu_id: RawPduId = PduId { shortroomid, count }.into();
+
+	self.get_from_id(&pdu_id)
+		.map_ok(|ExtractEventId { event_id }| event_id)
+		.await
+}
+
 /// Returns the `pdu_id` from the `shorteventid`
 #[implement(Service)]
 pub async fn get_pdu_id_from_shorteventid(&self, shorteventid: ShortEventId) -> Result<RawPduId> {
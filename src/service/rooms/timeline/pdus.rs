@@ -1,17 +1,26 @@
-use std::borrow::Borrow;
+use std::{borrow::Borrow, collections::BTreeMap};
 
 use futures::{
-	Stream, TryFutureExt, TryStreamExt,
+	Stream, StreamExt, TryFutureExt, TryStreamExt,
 	future::Either::{Left, Right},
 };
-use ruma::{MilliSecondsSinceUnixEpoch, RoomId, UInt, UserId, api::Direction};
+use ruma::{
+	EventId, MilliSecondsSinceUnixEpoch, RoomId, UInt, UserId,
+	api::Direction,
+	events::{TimelineEventType, relation::RelationType},
+};
 use tuwunel_core::{
-	Result, at, err, implement,
-	matrix::pdu::{PduCount, PduEvent},
+	Err, Result, at, err, implement,
+	matrix::{
+		Event,
+		event::RelationTypeEqual,
+		pdu::{PduCount, PduEvent},
+	},
 	trace,
 	utils::{
+		IterStream,
 		result::LogErr,
-		stream::{TryIgnore, TryReadyExt, TryWidebandExt},
+		stream::{ReadyExt, TryIgnore, TryReadyExt, TryWidebandExt},
 	},
 	warn,
 };
@@ -49,6 +58,7 @@ pub async fn delete_pdus(&self, room_id: &RoomId) -> Result {
 			self.db.pduid_pdu.remove(key);
 			self.db.eventid_pduid.remove(event_id);
 			self.db.eventid_outlierpdu.remove(event_id);
+			self.db.eventid_outlierreceived.remove(event_id);
 			self.db.roomid_tscount_pducount.del((
 				room_id,
 				ts,
@@ -120,6 +130,41 @@ pub fn pdu_ids_near_ts(
 		.try_flatten_stream()
 }
 
+/// Tallies the events in a room by type.
+///
+/// This reads every PDU in the room so its cost grows with the room's history;
+/// it is meant for operators rather than any request path.
+#[implement(super::Service)]
+#[tracing::instrument(skip(self), level = "debug")]
+pub async fn event_type_counts(&self, room_id: &RoomId) -> BTreeMap<TimelineEventType, u64> {
+	self.pdus(None, room_id, None)
+		.ignore_err()
+		.ready_fold_default(|mut counts: BTreeMap<_, u64>, (_, pdu)| {
+			let count = counts.entry(pdu.kind).or_default();
+			*count = count.saturating_add(1);
+			counts
+		})
+		.await
+}
+
+/// Returns an iterator over the events `sender` sent in a room after `from`,
+/// in order.
+///
+/// There is no index by sender, so this reads every PDU in the room from
+/// `from` onwards regardless of how few the sender contributed; it is meant
+/// for moderation and operators rather than any request path.
+#[implement(super::Service)]
+#[tracing::instrument(skip(self), level = "debug")]
+pub fn pdus_by_sender<'a>(
+	&'a self,
+	room_id: &'a RoomId,
+	sender: &'a UserId,
+	from: Option<PduCount>,
+) -> impl Stream<Item = Result<PdusIterItem>> + Send + 'a {
+	self.pdus(None, room_id, from)
+		.ready_try_filter(move |(_, pdu)| pdu.sender() == sender)
+}
+
 /// Returns an iterator over all PDUs in a room. Unknown rooms produce no
 /// items.
 #[implement(super::Service)]
@@ -179,6 +224,42 @@ pub fn pdus_rev<'a>(
 		.try_flatten_stream()
 }
 
+/// Returns the root of an MSC3440 thread followed by the events in the thread,
+/// in order, after `from`. Only the relations of the root are visited.
+#[implement(super::Service)]
+#[tracing::instrument(skip(self), level = "debug")]
+pub fn thread_pdus<'a>(
+	&'a self,
+	room_id: &'a RoomId,
+	thread_root: &'a EventId,
+	from: Option<PduCount>,
+) -> impl Stream<Item = Result<PdusIterItem>> + Send + 'a {
+	self.get_pdu_id(thread_root)
+		.and_then(async move |pdu_id| {
+			let root = self.get_pdu_from_id(&pdu_id).await?;
+			if root.room_id() != room_id {
+				return Err!(Request(NotFound("Thread root is not in this room.")));
+			}
+
+			Ok((PduId::from(pdu_id), root))
+		})
+		.map_ok(move |(root_id, root)| {
+			let root = from
+				.is_none_or(|from| root_id.count > from)
+				.then_some(Ok((root_id.count, root)));
+
+			let replies = self
+				.services
+				.pdu_metadata
+				.get_relations(root_id.shortroomid, root_id.count, from, Direction::Forward, None)
+				.ready_filter(|(_, pdu)| RelationType::Thread.relation_type_equal(pdu))
+				.map(Ok);
+
+			root.into_iter().stream().chain(replies)
+		})
+		.try_flatten_stream()
+}
+
 #[implement(super::Service)]
 pub fn pdus_raw(&self) -> impl Stream<Item = Result<Val<'_>>> + Send {
 	self.db.pduid_pdu.raw_stream().map_ok(at!(1))
@@ -0,0 +1,247 @@
+//! Garbage collection of outlier PDUs.
+//!
+//! Outliers are stored while fetching auth chains and state over federation
+//! and nothing else removes them. They are often the only copy of remote state
+//! received through a join or a state fetch, so an outlier only goes once
+//! nothing can look it up again: no stored state snapshot, current or historic,
+//! refers to it, it is neither in the auth chain of its room's current state
+//! nor a prev_event of an accepted event, and its room is not being handled by
+//! the event handler at the moment. The auth events of an accepted event were
+//! state before it, so the snapshots account for them. Age is judged by when we
+//! received it rather than by the sender's timestamp.
+//!
+//! The table is walked in batches of `BATCH` outliers. Candidates from
+//! successive batches are gathered up to `CANDIDATES` before the state
+//! snapshots are scanned for them, so a pass holds a bounded set and usually
+//! scans the snapshots only once.
+
+use std::{
+	collections::{HashMap, HashSet, hash_map::Entry},
+	mem::take,
+	time::Duration,
+};
+
+use futures::StreamExt;
+use ruma::{EventId, OwnedEventId, OwnedRoomId, RoomId};
+use serde::Deserialize;
+use tuwunel_core::{
+	at, debug, implement,
+	matrix::ShortEventId,
+	utils::{
+		millis_since_unix_epoch,
+		stream::{ReadyExt, TryIgnore},
+		time::now,
+	},
+};
+use tuwunel_database::{Deserialized, Json};
+
+/// Outliers looked at together.
+const BATCH: usize = 1024;
+
+/// Candidates gathered before the state snapshots are scanned for them.
+const CANDIDATES: usize = 64 * BATCH;
+
+#[derive(Deserialize)]
+struct ExtractOutlier {
+	room_id: OwnedRoomId,
+}
+
+type Outlier = (OwnedEventId, OwnedRoomId);
+
+type Candidates = HashMap<ShortEventId, Outlier>;
+
+/// Remove outliers received longer than `older_than` ago which nothing refers
+/// to. Returns the number of outliers removed.
+#[implement(super::Service)]
+#[tracing::instrument(skip(self), level = "debug")]
+pub async fn prune_outliers(&self, older_than: Duration) -> usize {
+	let cutoff: u64 = now()
+		.saturating_sub(older_than)
+		.as_millis()
+		.try_into()
+		.unwrap_or(u64::MAX);
+
+	let mut pruned: usize = 0;
+	let mut candidates = Candidates::new();
+	let mut after: Option<OwnedEventId> = None;
+	loop {
+		let batch = self.outlier_batch(after.as_deref()).await;
+		let exhausted = batch.len() < BATCH;
+		if let Some((last, _)) = batch.last() {
+			after = Some(last.clone());
+		}
+
+		self.gather_candidates(batch, cutoff, &mut candidates)
+			.await;
+
+		if exhausted || candidates.len() >= CANDIDATES {
+			let removed = self.prune_candidates(take(&mut candidates)).await;
+			pruned = pruned.saturating_add(removed);
+		}
+
+		if exhausted {
+			break;
+		}
+	}
+
+	debug!(pruned, "Pruned outliers");
+
+	pruned
+}
+
+/// The next `BATCH` outliers stored after `after`.
+#[implement(super::Service)]
+async fn outlier_batch(&self, after: Option<&EventId>) -> Vec<Outlier> {
+	type KeyVal<'a> = (&'a EventId, Json<ExtractOutlier>);
+
+	let from = after.map(EventId::as_bytes).unwrap_or_default();
+	self.db
+		.eventid_outlierpdu
+		.stream_raw_from(from)
+		.ignore_err()
+		.ready_filter(|(event_id, _): &KeyVal<'_>| Some(*event_id) != after)
+		.take(BATCH)
+		.map(|(event_id, Json(outlier)): KeyVal<'_>| (event_id.to_owned(), outlier.room_id))
+		.collect()
+		.await
+}
+
+/// Add the outliers of `batch` old enough to go and not referred to by other
+/// events to `candidates`, keyed by short id.
+#[implement(super::Service)]
+async fn gather_candidates(&self, batch: Vec<Outlier>, cutoff: u64, candidates: &mut Candidates) {
+	for (event_id, room_id) in batch {
+		match self
+			.db
+			.eventid_outlierreceived
+			.get(&event_id)
+			.await
+			.deserialized::<u64>()
+		{
+			| Ok(received) if received < cutoff => {},
+			| Ok(_) => continue,
+			// Stored before receive times were kept; start aging it now.
+			| Err(_) => {
+				self.db
+					.eventid_outlierreceived
+					.raw_put(&event_id, millis_since_unix_epoch());
+				continue;
+			},
+		}
+
+		// State resolution for the room may be about to use it.
+		if self
+			.services
+			.event_handler
+			.mutex_federation
+			.contains(&room_id)
+		{
+			continue;
+		}
+
+		if self
+			.services
+			.pdu_metadata
+			.is_event_referenced(&room_id, &event_id)
+			.await
+		{
+			continue;
+		}
+
+		// Without a short id it cannot be told apart from the state it may be
+		// part of.
+		let Ok(shorteventid) = self
+			.services
+			.short
+			.get_shorteventid(&event_id)
+			.await
+		else {
+			continue;
+		};
+
+		candidates.insert(shorteventid, (event_id, room_id));
+	}
+}
+
+/// Remove the `candidates` which no state snapshot nor the auth chain of
+/// their room's current state refers to. Returns the number removed.
+#[implement(super::Service)]
+async fn prune_candidates(&self, mut candidates: Candidates) -> usize {
+	if candidates.is_empty() {
+		return 0;
+	}
+
+	let mut unreferenced: HashSet<ShortEventId> = candidates.keys().copied().collect();
+	self.services
+		.state_compressor
+		.retain_unreferenced(&mut unreferenced)
+		.await;
+
+	let mut auth_chains: HashMap<OwnedRoomId, Option<HashSet<ShortEventId>>> = HashMap::new();
+	let mut pruned: usize = 0;
+	for shorteventid in unreferenced {
+		let Some((event_id, room_id)) = candidates.remove(&shorteventid) else {
+			continue;
+		};
+
+		let auth_chain = match auth_chains.entry(room_id) {
+			| Entry::Occupied(entry) => entry.into_mut(),
+			| Entry::Vacant(entry) => {
+				let auth_chain = self.state_auth_chain(entry.key()).await;
+				entry.insert(auth_chain)
+			},
+		};
+
+		// Without knowing what the room needs, keep all of its outliers.
+		let Some(auth_chain) = auth_chain else {
+			continue;
+		};
+
+		if auth_chain.contains(&shorteventid) {
+			continue;
+		}
+
+		self.db.eventid_outlierpdu.remove(&event_id);
+		self.db.eventid_outlierreceived.remove(&event_id);
+		pruned = pruned.saturating_add(1);
+	}
+
+	pruned
+}
+
+/// The auth chain of a room's current state. Rooms without local state need
+/// none of their outliers; `None` is returned when the state exists but its
+/// auth chain could not be loaded.
+#[implement(super::Service)]
+async fn state_auth_chain(&self, room_id: &RoomId) -> Option<HashSet<ShortEventId>> {
+	let Ok(shortstatehash) = self
+		.services
+		.state
+		.get_room_shortstatehash(room_id)
+		.await
+	else {
+		return Some(HashSet::new());
+	};
+
+	let room_version = self
+		.services
+		.state
+		.get_room_version(room_id)
+		.await
+		.ok()?;
+
+	let state_ids: Vec<OwnedEventId> = self
+		.services
+		.state_accessor
+		.state_full_ids(shortstatehash)
+		.map(at!(1))
+		.collect()
+		.await;
+
+	self.services
+		.auth_chain
+		.get_auth_chain(room_id, &room_version, state_ids.iter().map(AsRef::as_ref))
+		.await
+		.ok()
+		.map(|auth_chain| auth_chain.into_iter().collect())
+}
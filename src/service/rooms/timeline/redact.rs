@@ -64,6 +64,11 @@ pub async fn redact_pdu<Pdu: Event + Send + Sync>(
 		.delete_typed_relation(&pdu_id, &pdu)
 		.await;
 
+	self.services
+		.pdu_metadata
+		.delete_redacted_relations(&pdu_id, &pdu)
+		.await;
+
 	redact_in_place(
 		&mut pdu,
 		&room_version_rules.redaction,
@@ -133,6 +133,23 @@ pub async fn typing_remove(&self, user_id: &UserId, room_id: &RoomId) -> Result
 			.map(|_| ())
 	}
 
+	/// Removes a user's typing indicator when they leave the room, federating
+	/// the stop. Does nothing if they were not typing.
+	pub async fn typing_leave(&self, user_id: &UserId, room_id: &RoomId) -> Result {
+		let typing = self
+			.typing
+			.read()
+			.await
+			.get(room_id)
+			.is_some_and(|room| room.contains_key(user_id));
+
+		if !typing {
+			return Ok(());
+		}
+
+		self.typing_remove(user_id, room_id).await
+	}
+
 	pub async fn wait_for_update(&self, room_id: &RoomId) {
 		let mut receiver = self.typing_update_sender.subscribe();
 		while let Ok(next) = receiver.recv().await {
@@ -0,0 +1,191 @@
+//! Per-destination circuit breaker. Backoff spaces out attempts to a failing
+//! destination; the circuit stops them outright for a cooldown once failures
+//! keep piling up, then lets a single probe through to learn whether the
+//! destination recovered.
+
+use std::time::{Duration, Instant};
+
+use tuwunel_core::warn;
+
+use super::{Destination, Service};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CircuitState {
+	/// Transactions are dispatched as usual.
+	#[default]
+	Closed,
+
+	/// Transactions are refused until the cooldown elapses.
+	Open,
+
+	/// One probe transaction is in flight; its outcome closes or re-opens the
+	/// circuit.
+	HalfOpen,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct Circuit {
+	state: CircuitState,
+	failures: u32,
+	since: Option<Instant>,
+}
+
+impl Circuit {
+	/// Whether a transaction may be dispatched at `now`. An open circuit past
+	/// its cooldown goes half-open and admits the caller as its probe. A probe
+	/// which never reports back is replaced after another cooldown.
+	pub(super) fn allow(&mut self, now: Instant, cooldown: Duration) -> bool {
+		let cooled = self
+			.since
+			.is_none_or(|since| now.saturating_duration_since(since) >= cooldown);
+
+		match self.state {
+			| CircuitState::Closed => true,
+			| CircuitState::Open | CircuitState::HalfOpen if cooled => {
+				self.state = CircuitState::HalfOpen;
+				self.since = Some(now);
+				true
+			},
+			| CircuitState::Open | CircuitState::HalfOpen => false,
+		}
+	}
+
+	pub(super) fn success(&mut self) { *self = Self::default(); }
+
+	/// Counts a failed transaction, opening the circuit once `threshold`
+	/// consecutive failures are reached or when the half-open probe failed.
+	/// Returns true if the circuit opened.
+	pub(super) fn failure(&mut self, now: Instant, threshold: u32) -> bool {
+		self.failures = self.failures.saturating_add(1);
+		let open = self.state == CircuitState::HalfOpen
+			|| (self.state == CircuitState::Closed && self.failures >= threshold);
+
+		if open {
+			self.state = CircuitState::Open;
+			self.since = Some(now);
+		}
+
+		open
+	}
+
+	pub(super) fn state(&self) -> CircuitState { self.state }
+}
+
+impl Service {
+	/// State of the circuit to a destination.
+	#[must_use]
+	pub fn circuit_state(&self, dest: &Destination) -> CircuitState {
+		self.circuits
+			.lock()
+			.expect("locked")
+			.get(dest)
+			.map(Circuit::state)
+			.unwrap_or_default()
+	}
+
+	/// Consulted before dispatching a transaction to `dest`.
+	pub(super) fn circuit_allows(&self, dest: &Destination) -> bool {
+		let cooldown = Duration::from_secs(self.server.config.sender_circuit_cooldown);
+		self.circuits
+			.lock()
+			.expect("locked")
+			.get_mut(dest)
+			.is_none_or(|circuit| circuit.allow(Instant::now(), cooldown))
+	}
+
+	pub(super) fn circuit_success(&self, dest: &Destination) {
+		self.circuits.lock().expect("locked").remove(dest);
+	}
+
+	pub(super) fn circuit_failure(&self, dest: &Destination) {
+		let threshold = self
+			.server
+			.config
+			.sender_circuit_failure_threshold;
+
+		if threshold == 0 {
+			return;
+		}
+
+		let opened = self
+			.circuits
+			.lock()
+			.expect("locked")
+			.entry(dest.clone())
+			.or_default()
+			.failure(Instant::now(), threshold);
+
+		if opened {
+			warn!(
+				?dest,
+				"Opened circuit for {}s after repeated failures",
+				self.server.config.sender_circuit_cooldown,
+			);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::{Duration, Instant};
+
+	use super::{Circuit, CircuitState};
+
+	const COOLDOWN: Duration = Duration::from_secs(60);
+
+	fn later(now: Instant, by: Duration) -> Instant { now.checked_add(by).expect("instant") }
+
+	#[test]
+	fn trips_open_after_threshold() {
+		let now = Instant::now();
+		let mut circuit = Circuit::default();
+
+		assert!(!circuit.failure(now, 3), "first failure");
+		assert!(!circuit.failure(now, 3), "second failure");
+		assert_eq!(circuit.state(), CircuitState::Closed);
+		assert!(circuit.allow(now, COOLDOWN), "closed circuit admits");
+
+		assert!(circuit.failure(now, 3), "third failure opens");
+		assert_eq!(circuit.state(), CircuitState::Open);
+	}
+
+	#[test]
+	fn rejects_while_open() {
+		let now = Instant::now();
+		let mut circuit = Circuit::default();
+		circuit.failure(now, 1);
+
+		assert!(!circuit.allow(now, COOLDOWN));
+		assert!(!circuit.allow(later(now, COOLDOWN / 2), COOLDOWN));
+		assert_eq!(circuit.state(), CircuitState::Open);
+	}
+
+	#[test]
+	fn half_open_probe_recovers() {
+		let now = Instant::now();
+		let mut circuit = Circuit::default();
+		circuit.failure(now, 1);
+
+		let cooled = later(now, COOLDOWN);
+		assert!(circuit.allow(cooled, COOLDOWN), "probe admitted after cooldown");
+		assert_eq!(circuit.state(), CircuitState::HalfOpen);
+		assert!(!circuit.allow(cooled, COOLDOWN), "only one probe at a time");
+
+		circuit.success();
+		assert_eq!(circuit.state(), CircuitState::Closed);
+		assert!(circuit.allow(cooled, COOLDOWN));
+	}
+
+	#[test]
+	fn failed_probe_reopens() {
+		let now = Instant::now();
+		let mut circuit = Circuit::default();
+		circuit.failure(now, 1);
+
+		let cooled = later(now, COOLDOWN);
+		assert!(circuit.allow(cooled, COOLDOWN));
+		assert!(circuit.failure(cooled, 1), "failed probe reopens");
+		assert_eq!(circuit.state(), CircuitState::Open);
+		assert!(!circuit.allow(later(cooled, COOLDOWN / 2), COOLDOWN));
+	}
+}
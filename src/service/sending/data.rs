@@ -1,21 +1,48 @@
-use std::{fmt::Debug, sync::Arc};
+use std::{
+	fmt::Debug,
+	sync::{Arc, Mutex},
+};
 
 use futures::{Stream, StreamExt};
 use ruma::{OwnedServerName, ServerName, UserId};
+use serde::{Deserialize, Serialize};
 use tuwunel_core::{
-	Error, Result, at, utils,
+	Error, Result, at, err, utils,
 	utils::{ReadyExt, stream::TryIgnore},
 };
-use tuwunel_database::{Database, Deserialized, Map};
+use tuwunel_database::{Database, Deserialized, Json, Map};
 
 use super::{Destination, SendingEvent};
+use crate::rooms::timeline::RawPduId;
 
 pub(super) type OutgoingItem = (Key, SendingEvent, Destination);
 pub(super) type SendingItem = (Key, SendingEvent);
 pub(super) type QueueItem = (Key, SendingEvent);
 pub(super) type Key = Vec<u8>;
 
+/// An event given up on after exhausting its retries, kept so an operator may
+/// requeue it.
+#[derive(Debug)]
+pub struct DeadLetter {
+	pub id: u64,
+	pub dest: Destination,
+	pub event: SendingEvent,
+	pub error: String,
+}
+
+/// Stored form of a `DeadLetter`: the original queue key and value, which
+/// encode the destination and event, along with the last error.
+#[derive(Deserialize, Serialize)]
+struct DeadLetterEntry {
+	key: Vec<u8>,
+	value: Vec<u8>,
+	error: String,
+}
+
 pub struct Data {
+	appserviceid_txnid: Arc<Map>,
+	appservice_txn_lock: Mutex<()>,
+	deadletterid_data: Arc<Map>,
 	servercurrentevent_data: Arc<Map>,
 	servernameevent_data: Arc<Map>,
 	servername_educount: Arc<Map>,
@@ -27,6 +54,9 @@ impl Data {
 	pub(super) fn new(args: &crate::Args<'_>) -> Self {
 		let db = &args.db;
 		Self {
+			appserviceid_txnid: db["appserviceid_txnid"].clone(),
+			appservice_txn_lock: Mutex::new(()),
+			deadletterid_data: db["deadletterid_data"].clone(),
 			servercurrentevent_data: db["servercurrentevent_data"].clone(),
 			servernameevent_data: db["servernameevent_data"].clone(),
 			servername_educount: db["servername_educount"].clone(),
@@ -49,6 +79,74 @@ pub(super) async fn delete_all_active_requests_for(&self, destination: &Destinat
 			.await;
 	}
 
+	/// Moves every active request for `destination` into the dead-letter
+	/// store, recording `error` as the reason it was given up on.
+	pub async fn abandon_active_requests_for(&self, destination: &Destination, error: &str) {
+		let prefix = destination.get_prefix();
+		self.servercurrentevent_data
+			.raw_stream_from(&prefix)
+			.ignore_err()
+			.ready_take_while(|(key, _)| key.starts_with(&prefix))
+			.ready_for_each(|(key, value)| self.dead_letter(key, value, error))
+			.await;
+	}
+
+	/// Moves a single active PDU for `destination` into the dead-letter store,
+	/// leaving the rest of the transaction to be sent.
+	pub(super) fn abandon_active_pdu(
+		&self,
+		destination: &Destination,
+		pdu_id: &RawPduId,
+		error: &str,
+	) {
+		let mut key = destination.get_prefix();
+		key.extend_from_slice(pdu_id.as_ref());
+
+		self.dead_letter(&key, &[], error);
+	}
+
+	fn dead_letter(&self, key: &[u8], value: &[u8], error: &str) {
+		let entry = DeadLetterEntry {
+			key: key.to_vec(),
+			value: value.to_vec(),
+			error: error.to_owned(),
+		};
+
+		let id = self.services.globals.next_count();
+		self.deadletterid_data.put(*id, Json(entry));
+		self.servercurrentevent_data.remove(key);
+	}
+
+	pub fn dead_letters(&self) -> impl Stream<Item = DeadLetter> + Send + '_ {
+		type KeyVal = (u64, Json<DeadLetterEntry>);
+
+		self.deadletterid_data
+			.stream()
+			.ignore_err()
+			.ready_filter_map(|(id, Json(entry)): KeyVal| {
+				let (dest, event) = parse_servercurrentevent(&entry.key, &entry.value).ok()?;
+
+				Some(DeadLetter { id, dest, event, error: entry.error })
+			})
+	}
+
+	/// Moves a dead letter back into the queue under its original key.
+	pub(super) async fn requeue_dead_letter(&self, id: u64) -> Result<OutgoingItem> {
+		let Json(entry): Json<DeadLetterEntry> = self
+			.deadletterid_data
+			.qry(&id)
+			.await
+			.deserialized()
+			.map_err(|_| err!(Request(NotFound("No dead letter with id {id}."))))?;
+
+		let (dest, event) = parse_servercurrentevent(&entry.key, &entry.value)?;
+		self.servernameevent_data
+			.insert(&entry.key, &entry.value);
+		self.deadletterid_data.del(id);
+
+		Ok((entry.key, event, dest))
+	}
+
 	pub(super) async fn delete_all_requests_for(&self, destination: &Destination) {
 		let prefix = destination.get_prefix();
 		self.servercurrentevent_data
@@ -176,6 +274,57 @@ pub async fn get_latest_educount(&self, server_name: &ServerName) -> u64 {
 			.deserialized()
 			.unwrap_or(0)
 	}
+
+	/// Transaction id for a transaction to an appservice with content
+	/// `txn_hash`. A retry of the transaction still pending reuses its id so
+	/// the appservice can dedupe; otherwise the next id is allocated.
+	pub fn appservice_txn_id(&self, id: &str, txn_hash: &[u8]) -> u64 {
+		let _lock = self
+			.appservice_txn_lock
+			.lock()
+			.expect("locked for writing");
+
+		let (last, pending) = self.appservice_txn(id);
+		if pending.as_deref() == Some(txn_hash) {
+			return last;
+		}
+
+		let next = last.saturating_add(1);
+		let mut val = next.to_be_bytes().to_vec();
+		val.extend_from_slice(txn_hash);
+		self.appserviceid_txnid.insert(id, val);
+
+		next
+	}
+
+	/// Mark the pending transaction to an appservice as delivered so identical
+	/// content sent later is given a new id.
+	pub fn appservice_txn_done(&self, id: &str) {
+		let _lock = self
+			.appservice_txn_lock
+			.lock()
+			.expect("locked for writing");
+
+		let (last, _) = self.appservice_txn(id);
+		self.appserviceid_txnid
+			.insert(id, last.to_be_bytes());
+	}
+
+	/// Last transaction id allocated for an appservice and the content hash of
+	/// the transaction still pending under it, if any.
+	pub fn appservice_txn(&self, id: &str) -> (u64, Option<Vec<u8>>) {
+		let Ok(val) = self.appserviceid_txnid.get_blocking(id) else {
+			return (0, None);
+		};
+
+		let Some((last, pending)) = val.split_first_chunk::<8>() else {
+			return (0, None);
+		};
+
+		let pending = (!pending.is_empty()).then(|| pending.to_vec());
+
+		(u64::from_be_bytes(*last), pending)
+	}
 }
 
 fn parse_servercurrentevent(key: &[u8], value: &[u8]) -> Result<(Destination, SendingEvent)> {
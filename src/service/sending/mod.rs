@@ -1,22 +1,27 @@
+mod circuit;
 mod data;
 mod dest;
+pub mod relay;
 mod sender;
 
 use std::{
+	collections::HashMap,
 	fmt::Debug,
 	hash::{DefaultHasher, Hash, Hasher},
 	io::Write,
 	iter::once,
 	pin::pin,
-	sync::Arc,
+	sync::{Arc, Mutex},
+	time::SystemTime,
 };
 
 use async_trait::async_trait;
 use futures::{FutureExt, Stream, StreamExt};
-use ruma::{RoomId, ServerName, UserId};
+use ruma::{EventId, OwnedEventId, RoomId, ServerName, UserId};
+use serde_json::json;
 use tokio::{task, task::JoinSet};
 use tuwunel_core::{
-	Result, Server, debug, debug_warn, err, error,
+	Err, Error, Result, Server, debug, debug_warn, err, error,
 	smallvec::SmallVec,
 	utils::{
 		IterStream, ReadyExt, TryReadyExt, available_parallelism, future::BoolExt,
@@ -25,18 +30,21 @@
 	warn,
 };
 
-use self::data::Data;
+use self::{circuit::Circuit, data::Data};
 pub use self::{
+	circuit::CircuitState,
+	data::DeadLetter,
 	dest::Destination,
 	sender::{EDU_LIMIT, PDU_LIMIT},
 };
-use crate::rooms::timeline::RawPduId;
+use crate::{federation::ShouldAttempt, rooms::timeline::RawPduId};
 
 pub struct Service {
 	pub db: Data,
 	server: Arc<Server>,
 	services: Arc<crate::services::OnceServices>,
 	channels: Vec<(loole::Sender<Msg>, loole::Receiver<Msg>)>,
+	circuits: Mutex<HashMap<Destination, Circuit>>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -54,6 +62,22 @@ pub enum SendingEvent {
 	Flush,         // none
 }
 
+/// An event awaiting delivery to a destination, with the state of delivery to
+/// that destination.
+#[derive(Clone, Debug)]
+pub struct QueuedEvent {
+	pub queue_id: Vec<u8>,
+
+	/// Part of the transaction in flight rather than waiting for the next one.
+	pub active: bool,
+
+	/// Consecutive failed attempts to reach the destination.
+	pub retries: u32,
+
+	/// When the destination may be attempted again; `None` if it may be now.
+	pub next_attempt: Option<SystemTime>,
+}
+
 pub type EduBuf = SmallVec<[u8; EDU_BUF_CAP]>;
 pub type EduVec = SmallVec<[EduBuf; EDU_VEC_CAP]>;
 
@@ -71,6 +95,7 @@ fn build(args: &crate::Args<'_>) -> Result<Arc<Self>> {
 			channels: (0..num_senders)
 				.map(|_| loole::unbounded())
 				.collect(),
+			circuits: Mutex::default(),
 		}))
 	}
 
@@ -171,12 +196,31 @@ pub async fn send_pdu_servers<'a, S>(&self, servers: S, pdu_id: &RawPduId) -> Re
 	where
 		S: Stream<Item = &'a ServerName> + Send + 'a,
 	{
-		let requests = servers
+		self.send_pdu_servers_prioritized(servers, pdu_id, &[])
+			.await
+	}
+
+	/// Like `send_pdu_servers`, but the servers in `priority` are queued and
+	/// dispatched first, in the order given, ahead of the rest.
+	#[tracing::instrument(skip(self, servers, pdu_id), level = "debug")]
+	pub async fn send_pdu_servers_prioritized<'a, S>(
+		&self,
+		servers: S,
+		pdu_id: &RawPduId,
+		priority: &[&ServerName],
+	) -> Result
+	where
+		S: Stream<Item = &'a ServerName> + Send + 'a,
+	{
+		let mut servers: Vec<_> = servers.collect().await;
+		prioritize(&mut servers, priority);
+
+		let requests: Vec<_> = servers
+			.into_iter()
 			.map(|server| {
 				(Destination::Federation(server.into()), SendingEvent::Pdu(pdu_id.to_owned()))
 			})
-			.collect::<Vec<_>>()
-			.await;
+			.collect();
 
 		let _cork = self.db.db.cork();
 		let keys = self
@@ -350,6 +394,34 @@ pub async fn flush_servers<'a, S>(&self, servers: S) -> Result
 			.await
 	}
 
+	/// Re-dispatch everything queued for a server immediately, clearing its
+	/// backoff once. A transaction already in flight absorbs the dispatch, so
+	/// nothing is sent twice. Returns the number of queued events.
+	#[tracing::instrument(skip(self), level = "debug")]
+	pub async fn kick_server(&self, server: &ServerName) -> Result<usize> {
+		let dest = Destination::Federation(server.to_owned());
+		self.services.federation.record_success(server);
+
+		let queued: Vec<_> = self.db.queued_requests(&dest).collect().await;
+		let count = queued.len();
+		if queued.is_empty() {
+			// Still retry any transaction which was held back by the backoff.
+			return self
+				.dispatch(Msg {
+					dest,
+					event: SendingEvent::Flush,
+					queue_id: Vec::<u8>::new(),
+				})
+				.map(|()| count);
+		}
+
+		for (queue_id, event) in queued {
+			self.dispatch(Msg { dest: dest.clone(), event, queue_id })?;
+		}
+
+		Ok(count)
+	}
+
 	/// Clean up queued sending event data
 	///
 	/// Used after we remove an appservice registration or a user deletes a push
@@ -386,6 +458,107 @@ pub async fn cleanup_events(
 		}
 	}
 
+	/// Looks up the PDU `event_id` among the events queued or in flight for
+	/// `server`, e.g. to find out why it has not been delivered.
+	#[tracing::instrument(skip(self), level = "debug")]
+	pub async fn queued_event(
+		&self,
+		server: &ServerName,
+		event_id: &EventId,
+	) -> Result<QueuedEvent> {
+		let pdu_id = self
+			.services
+			.timeline
+			.get_pdu_id(event_id)
+			.await
+			.map_err(|_| err!(Request(NotFound("Event {event_id} is not in the timeline."))))?;
+
+		let dest = Destination::Federation(server.to_owned());
+		let event = SendingEvent::Pdu(pdu_id);
+		let active = self
+			.db
+			.active_requests_for(&dest)
+			.map(|item| (item, true));
+
+		let queued = self
+			.db
+			.queued_requests(&dest)
+			.map(|item| (item, false));
+
+		let found = pin!(
+			active
+				.chain(queued)
+				.ready_filter(|((_, queued), _)| *queued == event)
+		)
+		.next()
+		.await;
+
+		let Some(((queue_id, _), active)) = found else {
+			return Err!(Request(NotFound("Event {event_id} is not queued for {server}.")));
+		};
+
+		let federation = &self.services.federation;
+		let next_attempt = match federation.should_attempt(server).await {
+			| ShouldAttempt::No { earliest_retry } => Some(earliest_retry),
+			| ShouldAttempt::Yes | ShouldAttempt::Deprioritize => None,
+		};
+
+		Ok(QueuedEvent {
+			queue_id,
+			active,
+			retries: federation.failure_streak(server).await,
+			next_attempt,
+		})
+	}
+
+	/// Events given up on after exhausting `sender_max_retries`.
+	pub fn list_dead_letters(&self) -> impl Stream<Item = DeadLetter> + Send + '_ {
+		self.db.dead_letters()
+	}
+
+	/// Puts a dead letter back into its destination's queue for another round
+	/// of delivery attempts.
+	#[tracing::instrument(skip(self), level = "debug")]
+	pub async fn requeue_dead_letter(&self, id: u64) -> Result {
+		let (queue_id, event, dest) = self.db.requeue_dead_letter(id).await?;
+
+		self.dispatch(Msg { dest, event, queue_id })
+	}
+
+	/// Notify `federation_failure_webhook_url` that delivery to a server was
+	/// given up on. The POST happens on a detached task; failures are only
+	/// logged.
+	#[tracing::instrument(skip(self, event_ids, error), level = "debug")]
+	fn notify_abandoned(&self, server: &ServerName, event_ids: &[OwnedEventId], error: &Error) {
+		let Some(url) = self
+			.server
+			.config
+			.federation_failure_webhook_url
+			.clone()
+		else {
+			return;
+		};
+
+		let body = json!({
+			"destination": server,
+			"event_ids": event_ids,
+			"error": error.to_string(),
+		});
+
+		let client = self.services.client.default.clone();
+		self.server.runtime().spawn(async move {
+			if let Err(e) = client
+				.post(url)
+				.json(&body)
+				.send()
+				.await
+				.and_then(reqwest::Response::error_for_status)
+			{
+				debug_warn!("Failed to deliver federation failure webhook: {e}");
+			}
+		});
+	}
+
 	fn dispatch(&self, msg: Msg) -> Result {
 		let shard = self.shard_id(&msg.dest);
 		let sender = &self
@@ -415,6 +588,21 @@ pub(super) fn shard_id(&self, dest: &Destination) -> usize {
 	}
 }
 
+/// Move the servers named in `priority` to the front, in that order; the rest
+/// keep their relative order.
+fn prioritize(servers: &mut [&ServerName], priority: &[&ServerName]) {
+	if priority.is_empty() {
+		return;
+	}
+
+	servers.sort_by_key(|server| {
+		priority
+			.iter()
+			.position(|prioritized| prioritized == server)
+			.unwrap_or(priority.len())
+	});
+}
+
 fn num_senders(args: &crate::Args<'_>) -> usize {
 	const MIN_SENDERS: usize = 1;
 	// Limit the number of senders to the number of workers threads or number of
@@ -432,3 +620,30 @@ fn num_senders(args: &crate::Args<'_>) -> usize {
 		.sender_workers
 		.clamp(MIN_SENDERS, max_senders)
 }
+
+#[cfg(test)]
+mod tests {
+	use ruma::server_name;
+
+	use super::prioritize;
+
+	#[test]
+	fn prioritized_servers_come_first() {
+		let a = server_name!("a.example");
+		let b = server_name!("b.example");
+		let c = server_name!("c.example");
+		let d = server_name!("d.example");
+
+		let mut servers = [a, b, c, d];
+		prioritize(&mut servers, &[d, b]);
+		assert_eq!(servers, [d, b, a, c], "priority order first, then the rest in order");
+
+		let mut servers = [a, b, c];
+		prioritize(&mut servers, &[]);
+		assert_eq!(servers, [a, b, c], "no priority keeps the order");
+
+		let mut servers = [a, c];
+		prioritize(&mut servers, &[b, c]);
+		assert_eq!(servers, [c, a], "absent priority servers are ignored");
+	}
+}
@@ -0,0 +1,114 @@
+//! Envelope for transactions handed to `federation_relay_server`.
+//!
+//! A plain `/send` to the relay would leave it guessing where the events were
+//! meant to go, so the transaction is wrapped with its real destination and
+//! put to the relay's endpoint for forwarding. Only tuwunel serves that
+//! endpoint: the relay checks the origin against `federation_relay_clients`,
+//! then signs and sends the onward transaction itself; our signature covers
+//! only the envelope.
+
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use ruma::{
+	MilliSecondsSinceUnixEpoch, ServerName, TransactionId,
+	api::federation::transactions::{edu::Edu, send_transaction_message},
+	serde::Raw,
+};
+use serde_json::value::RawValue as RawJsonValue;
+use tuwunel_core::{Err, Result, debug, utils::calculate_hash};
+
+use super::Service;
+
+pub mod v1 {
+	use ruma::{
+		MilliSecondsSinceUnixEpoch, OwnedServerName, OwnedTransactionId,
+		api::{
+			federation::{authentication::ServerSignatures, transactions::edu::Edu},
+			request, response,
+		},
+		metadata,
+		serde::Raw,
+	};
+	use serde_json::value::RawValue as RawJsonValue;
+
+	metadata! {
+		method: PUT,
+		rate_limited: false,
+		authentication: ServerSignatures,
+		path: "/_matrix/federation/unstable/net.tuwunel.relay/send/{destination}/{transaction_id}",
+	}
+
+	#[request]
+	pub struct Request {
+		/// The server the transaction is meant for.
+		#[ruma_api(path)]
+		pub destination: OwnedServerName,
+
+		#[ruma_api(path)]
+		pub transaction_id: OwnedTransactionId,
+
+		pub origin: OwnedServerName,
+
+		pub origin_server_ts: MilliSecondsSinceUnixEpoch,
+
+		#[serde(default, skip_serializing_if = "<[_]>::is_empty")]
+		pub pdus: Vec<Box<RawJsonValue>>,
+
+		#[serde(default, skip_serializing_if = "<[_]>::is_empty")]
+		pub edus: Vec<Raw<Edu>>,
+	}
+
+	#[response]
+	pub struct Response {}
+}
+
+impl Service {
+	/// Forward a transaction `origin` handed to us as its relay on to
+	/// `destination`, sent under our own name.
+	#[tracing::instrument(level = "debug", skip(self, pdus, edus))]
+	pub async fn forward_relayed(
+		&self,
+		origin: &ServerName,
+		destination: &ServerName,
+		transaction_id: &TransactionId,
+		pdus: Vec<Box<RawJsonValue>>,
+		edus: Vec<Raw<Edu>>,
+	) -> Result {
+		if !self
+			.server
+			.config
+			.federation_relay_clients
+			.iter()
+			.any(|client| client == origin)
+		{
+			return Err!(Request(Forbidden(
+				"{origin} is not allowed to relay through this server"
+			)));
+		}
+
+		if self.services.globals.server_is_ours(destination) {
+			return Err!(Request(InvalidParam("Send transactions for this server directly")));
+		}
+
+		// Transaction ids are only unique per origin, and the onward
+		// transaction's origin is us, so fold the client into the id.
+		let txn_hash = calculate_hash([origin.as_bytes(), transaction_id.as_bytes()].into_iter());
+		let request = send_transaction_message::v1::Request {
+			transaction_id: URL_SAFE_NO_PAD.encode(txn_hash).into(),
+			origin: self.server.name.clone(),
+			origin_server_ts: MilliSecondsSinceUnixEpoch::now(),
+			pdus,
+			edus,
+		};
+
+		debug!(
+			pdus = request.pdus.len(),
+			edus = request.edus.len(),
+			"Forwarding relayed transaction"
+		);
+		self.services
+			.federation
+			.execute_on(&self.services.client.sender, destination, request)
+			.await
+			.map(|_| ())
+	}
+}
@@ -16,8 +16,8 @@
 	stream::FuturesUnordered,
 };
 use ruma::{
-	MilliSecondsSinceUnixEpoch, OwnedRoomId, OwnedServerName, OwnedUserId, RoomId, ServerName,
-	UserId,
+	MilliSecondsSinceUnixEpoch, OwnedEventId, OwnedRoomId, OwnedServerName, OwnedUserId, RoomId,
+	ServerName, UserId,
 	api::{
 		appservice::event::push_events::v1::{EphemeralData, Request as PushEventsRequest},
 		client::push::Pusher,
@@ -39,8 +39,11 @@
 	serde::Raw,
 	uint,
 };
+use serde_json::value::RawValue as RawJsonValue;
 use tuwunel_core::{
 	Error, Event, Result, debug, err, error, extract_variant,
+	matrix::pdu::MAX_PDU_BYTES,
+	metrics::{OutgoingCounts, OutgoingKind},
 	result::LogErr,
 	smallvec::SmallVec,
 	trace,
@@ -52,7 +55,10 @@
 	warn,
 };
 
-use super::{Destination, EduBuf, EduVec, Msg, SendingEvent, Service, data::QueueItem};
+use super::{
+	Destination, EduBuf, EduVec, Msg, SendingEvent, Service,
+	data::{Key, QueueItem},
+};
 use crate::{federation::ShouldAttempt, rooms::timeline::RawPduId};
 
 /// In-flight bookkeeping for one `Destination`. Cross-attempt backoff lives
@@ -60,17 +66,22 @@
 /// status because they are not server-keyed.
 #[derive(Debug)]
 enum TransactionStatus {
-	Running,
+	Running(usize),       // transactions in flight
+	Failing(usize, u32),  // transactions in flight after one failed, tries
 	Failed(u32, Instant), // push backoff: tries, last failure
 	Retrying(u32),        // number of times failed
 }
 
 type SendingError = (Destination, Error);
 type SendingResult = Result<Destination, SendingError>;
-type SendingFuture<'a> = BoxFuture<'a, SendingResult>;
+type SendingFuture<'a> = BoxFuture<'a, (SendingResult, TransactionKeys)>;
 type SendingFutures<'a> = FuturesUnordered<SendingFuture<'a>>;
 type CurTransactionStatus = HashMap<Destination, TransactionStatus>;
 
+/// Queue keys of the stored events making up one transaction, so that with
+/// several in flight to a destination each retires only its own.
+type TransactionKeys = Vec<Key>;
+
 /// Per-(room, user) bucket of `ReceiptData`. MSC3771 allows one receipt
 /// per thread context per user per EDU window; the dominant case is
 /// still a single receipt, so inline-1 fits without a heap touch.
@@ -86,6 +97,63 @@ enum TransactionStatus {
 /// common case is a single room, so inline-1 avoids a heap touch.
 type RoomReceipts = SmallVec<[(OwnedRoomId, RankedReceipts); 1]>;
 
+impl TransactionStatus {
+	/// Admits another transaction alongside those in flight, up to `max`.
+	fn admit(&mut self, max: usize) -> bool {
+		match self {
+			| Self::Running(inflight) if *inflight < max => {
+				*inflight = inflight.saturating_add(1);
+				true
+			},
+			| _ => false,
+		}
+	}
+
+	/// Accounts for a transaction which returned without another taking its
+	/// place. Returns true once the destination has nothing left in flight.
+	fn release(&mut self) -> bool {
+		match *self {
+			| Self::Running(inflight) if inflight > 1 => {
+				*self = Self::Running(inflight.saturating_sub(1));
+				false
+			},
+			| Self::Failing(inflight, tries) if inflight > 1 => {
+				*self = Self::Failing(inflight.saturating_sub(1), tries);
+				false
+			},
+			| Self::Failing(_, tries) => {
+				*self = Self::Retrying(tries);
+				false
+			},
+			| _ => true,
+		}
+	}
+
+	/// Accounts for a transaction which failed, each counting as a try. The
+	/// failure is only acted on once nothing else is in flight, returning the
+	/// tries made by then.
+	fn fail(&mut self) -> Option<u32> {
+		let (inflight, tries) = match *self {
+			| Self::Running(inflight) => (inflight, 1),
+			| Self::Failing(inflight, tries) => (inflight, tries.saturating_add(1)),
+			| Self::Failed(tries, _) | Self::Retrying(tries) => (1, tries.saturating_add(1)),
+		};
+
+		if inflight > 1 {
+			*self = Self::Failing(inflight.saturating_sub(1), tries);
+			return None;
+		}
+
+		Some(tries)
+	}
+
+	/// Whether transactions other than the one returning are in flight.
+	fn shared(&self) -> bool {
+		matches!(self, Self::Running(inflight) if *inflight > 1)
+			|| matches!(self, Self::Failing(..))
+	}
+}
+
 const SELECT_PRESENCE_LIMIT: usize = 256;
 const SELECT_RECEIPT_LIMIT: usize = 256;
 const DEQUEUE_LIMIT: usize = 48;
@@ -150,47 +218,129 @@ async fn work_loop<'a>(
 	#[tracing::instrument(name = "response", level = "debug", skip_all)]
 	async fn handle_response<'a>(
 		&'a self,
-		response: SendingResult,
+		response: (SendingResult, TransactionKeys),
 		futures: &mut SendingFutures<'a>,
 		statuses: &mut CurTransactionStatus,
 	) {
 		match response {
-			| Err((dest, e)) => Self::handle_response_err(dest, statuses, &e),
-			| Ok(dest) =>
-				self.handle_response_ok(&dest, futures, statuses)
+			| (Err((dest, e)), _) => self.handle_response_err(dest, statuses, &e).await,
+			| (Ok(dest), keys) =>
+				self.handle_response_ok(&dest, &keys, futures, statuses)
 					.await,
 		}
 	}
 
-	fn handle_response_err(dest: Destination, statuses: &mut CurTransactionStatus, e: &Error) {
+	async fn handle_response_err(
+		&self,
+		dest: Destination,
+		statuses: &mut CurTransactionStatus,
+		e: &Error,
+	) {
 		debug!(?dest, "{e:?}");
+		self.circuit_failure(&dest);
+
 		// Push backs off locally; federation defers to peer_status, appservice retries.
 		let push = matches!(dest, Destination::Push(..));
 
-		statuses.entry(dest).and_modify(|e| {
-			let tries = match e {
-				| TransactionStatus::Running => 1,
-				| TransactionStatus::Failed(n, _) | TransactionStatus::Retrying(n) =>
-					n.saturating_add(1),
-			};
+		let Some(status) = statuses.get_mut(&dest) else {
+			return;
+		};
 
-			*e = if push {
-				TransactionStatus::Failed(tries, Instant::now())
-			} else {
-				TransactionStatus::Retrying(tries)
-			};
-		});
+		// Others in flight get to return before the failed events are retried.
+		let Some(tries) = status.fail() else {
+			return;
+		};
+
+		*status = if push {
+			TransactionStatus::Failed(tries, Instant::now())
+		} else {
+			TransactionStatus::Retrying(tries)
+		};
+
+		let max_retries = self.server.config.sender_max_retries;
+		if let Destination::Federation(server) = &dest
+			&& max_retries > 0
+			&& tries >= max_retries
+		{
+			statuses.remove(&dest);
+			self.abandon_transaction(server, e).await;
+		}
+	}
+
+	/// Gives up on the transaction in flight to a server once it exhausted
+	/// `sender_max_retries`. Its events are moved to the dead-letter store so
+	/// the next transaction starts afresh.
+	#[tracing::instrument(name = "abandon", level = "debug", skip(self, error))]
+	async fn abandon_transaction(&self, server: &ServerName, error: &Error) {
+		let dest = Destination::Federation(server.to_owned());
+		let event_ids: Vec<OwnedEventId> = self
+			.db
+			.active_requests_for(&dest)
+			.ready_filter_map(|(_, event)| extract_variant!(event, SendingEvent::Pdu))
+			.broad_filter_map(async |pdu_id| {
+				self.services
+					.timeline
+					.get_pdu_from_id(&pdu_id)
+					.await
+					.ok()
+					.map(|pdu| pdu.event_id().to_owned())
+			})
+			.collect()
+			.await;
+
+		self.db
+			.abandon_active_requests_for(&dest, &error.to_string())
+			.await;
+
+		warn!(
+			%server,
+			events = event_ids.len(),
+			"Giving up sending transaction after {} attempts: {error}",
+			self.server.config.sender_max_retries,
+		);
+
+		self.notify_abandoned(server, &event_ids, error);
+	}
+
+	/// No server accepts a PDU above the spec's size limit, so retrying it
+	/// would hold up the destination indefinitely. It is moved to the
+	/// dead-letter store and the transaction goes ahead without it.
+	fn abandon_oversized_pdu(&self, server: &ServerName, pdu_id: &RawPduId, len: usize) {
+		let error = format!("PDU of {len} bytes exceeds the limit of {MAX_PDU_BYTES} bytes");
+		error!(%server, ?pdu_id, "Not sending oversized PDU: {error}");
+
+		self.db
+			.abandon_active_pdu(&Destination::Federation(server.to_owned()), pdu_id, &error);
 	}
 
 	#[expect(clippy::needless_pass_by_ref_mut)]
 	async fn handle_response_ok<'a>(
 		&'a self,
 		dest: &Destination,
+		keys: &TransactionKeys,
 		futures: &mut SendingFutures<'a>,
 		statuses: &mut CurTransactionStatus,
 	) {
+		self.circuit_success(dest);
+
 		let _cork = self.db.db.cork();
-		self.db.delete_all_active_requests_for(dest).await;
+		let status = statuses.get(dest);
+		if status.is_some_and(TransactionStatus::shared) {
+			for key in keys {
+				self.db.delete_active_request(key);
+			}
+		} else {
+			self.db.delete_all_active_requests_for(dest).await;
+		}
+
+		// A failed transaction is retried on its own once the rest returned.
+		if matches!(status, Some(TransactionStatus::Failing(..))) {
+			if let Some(status) = statuses.get_mut(dest) {
+				status.release();
+			}
+
+			return;
+		}
 
 		// Find events that have been added since starting the last request
 		let new_events = self
@@ -204,13 +354,12 @@ async fn handle_response_ok<'a>(
 		if !new_events.is_empty() {
 			self.db.mark_as_active(new_events.iter());
 
-			let new_events_vec = new_events
-				.into_iter()
-				.map(|(_, event)| event)
-				.collect();
-
-			futures.push(self.send_events(dest.clone(), new_events_vec));
-		} else {
+			let (keys, events) = new_events.into_iter().unzip();
+			futures.push(self.send_transaction(dest.clone(), keys, events));
+		} else if statuses
+			.get_mut(dest)
+			.is_none_or(TransactionStatus::release)
+		{
 			statuses.remove(dest);
 		}
 	}
@@ -224,10 +373,13 @@ async fn handle_request<'a>(
 		statuses: &mut CurTransactionStatus,
 	) {
 		let iv = vec![(msg.queue_id, msg.event)];
-		if let Ok(Some(events)) = self.select_events(&msg.dest, iv, statuses).await {
+		if let Ok(Some((keys, events))) = self.select_events(&msg.dest, iv, statuses).await {
 			if !events.is_empty() {
-				futures.push(self.send_events(msg.dest, events));
-			} else {
+				futures.push(self.send_transaction(msg.dest, keys, events));
+			} else if statuses
+				.get_mut(&msg.dest)
+				.is_none_or(TransactionStatus::release)
+			{
 				statuses.remove(&msg.dest);
 			}
 		}
@@ -254,7 +406,9 @@ async fn finish_responses<'a>(&'a self, futures: &mut SendingFutures<'a>) {
 			select! {
 				() = sleep_until(deadline) => return,
 				response = futures.next() => match response {
-					Some(Ok(dest)) => self.db.delete_all_active_requests_for(&dest).await,
+					Some((Ok(_), keys)) => {
+						keys.iter().for_each(|key| self.db.delete_active_request(key));
+					},
 					Some(_) => continue,
 					None => return,
 				},
@@ -278,7 +432,7 @@ async fn startup_netburst<'a>(
 		let keep =
 			usize::try_from(self.server.config.startup_netburst_keep).unwrap_or(usize::MAX);
 
-		let mut txns = HashMap::<Destination, Vec<SendingEvent>>::new();
+		let mut txns = HashMap::<Destination, (TransactionKeys, Vec<SendingEvent>)>::new();
 		let active = self.db.active_requests();
 
 		pin_mut!(active);
@@ -287,19 +441,20 @@ async fn startup_netburst<'a>(
 				continue;
 			}
 
-			let entry = txns.entry(dest.clone()).or_default();
-			if self.server.config.startup_netburst_keep >= 0 && entry.len() >= keep {
+			let (keys, events) = txns.entry(dest.clone()).or_default();
+			if self.server.config.startup_netburst_keep >= 0 && events.len() >= keep {
 				warn!("Dropping unsent event {dest:?} {:?}", String::from_utf8_lossy(&key));
 				self.db.delete_active_request(&key);
 			} else {
-				entry.push(event);
+				keys.push(key);
+				events.push(event);
 			}
 		}
 
-		for (dest, events) in txns {
+		for (dest, (keys, events)) in txns {
 			if self.server.config.startup_netburst && !events.is_empty() {
-				statuses.insert(dest.clone(), TransactionStatus::Running);
-				futures.push(self.send_events(dest.clone(), events));
+				statuses.insert(dest.clone(), TransactionStatus::Running(1));
+				futures.push(self.send_transaction(dest.clone(), keys, events));
 			}
 		}
 	}
@@ -318,7 +473,7 @@ async fn select_events(
 		dest: &Destination,
 		new_events: Vec<QueueItem>, // Events we want to send: event and full key
 		statuses: &mut CurTransactionStatus,
-	) -> Result<Option<Vec<SendingEvent>>> {
+	) -> Result<Option<(TransactionKeys, Vec<SendingEvent>)>> {
 		let (allow, retry) = self.select_events_current(dest, statuses).await?;
 
 		// Nothing can be done for this remote, bail out.
@@ -326,23 +481,27 @@ async fn select_events(
 			return Ok(None);
 		}
 
-		let mut events = Vec::new();
+		let (mut keys, mut events) = (Vec::new(), Vec::new());
 
 		// Must retry any previous transaction for this remote.
 		if retry {
 			self.db
 				.active_requests_for(dest)
-				.ready_for_each(|(_, e)| events.push(e))
+				.ready_for_each(|(key, e)| {
+					keys.push(key);
+					events.push(e);
+				})
 				.await;
 
-			return Ok(Some(events));
+			return Ok(Some((keys, events)));
 		}
 
 		// Compose the next transaction
 		let _cork = self.db.db.cork();
 		if !new_events.is_empty() {
 			self.db.mark_as_active(new_events.iter());
-			for (_, e) in new_events {
+			for (key, e) in new_events {
+				keys.push(key);
 				events.push(e);
 			}
 		}
@@ -359,7 +518,7 @@ async fn select_events(
 				.set_latest_educount(server_name, last_count);
 		}
 
-		Ok(Some(events))
+		Ok(Some((keys, events)))
 	}
 
 	async fn select_events_current(
@@ -380,34 +539,61 @@ async fn select_events_current(
 			}
 		}
 
+		let max_inflight = match dest {
+			| Destination::Federation(_) =>
+				self.server
+					.config
+					.federation_max_inflight_per_dest,
+			| _ => 1,
+		};
+
+		// The circuit is consulted last, once nothing else holds the transaction
+		// back, so that a half-open probe is not spent on one never sent.
+		let Some(status) = statuses.get_mut(dest) else {
+			let allow = self.circuit_allows(dest);
+			if allow {
+				statuses.insert(dest.clone(), TransactionStatus::Running(1));
+			}
+
+			return Ok((allow, false));
+		};
+
 		let (mut allow, mut retry) = (true, false);
-		statuses
-			.entry(dest.clone())
-			.and_modify(|e| match e {
-				| TransactionStatus::Running => {
-					allow = false; // already running
-				},
-				| TransactionStatus::Failed(tries, time) => {
-					// Push backoff: hold off until the exponential window elapses.
-					let min = self.server.config.sender_timeout;
-					let max = self.server.config.sender_retry_backoff_limit;
-					if continue_exponential_backoff_secs(min, max, time.elapsed(), *tries) {
-						allow = false;
-					} else {
-						retry = true;
-						*e = TransactionStatus::Retrying(*tries);
-					}
-				},
-				| TransactionStatus::Retrying(_) if matches!(dest, Destination::Push(..)) => {
-					allow = false; // push retry already in flight
-				},
-				| TransactionStatus::Retrying(_) => {
-					// Promote to Running so a concurrent select does not double-send.
+		match status {
+			| TransactionStatus::Running(inflight) => {
+				// already running, unless below the limit
+				allow = *inflight < max_inflight
+					&& self.circuit_allows(dest)
+					&& status.admit(max_inflight);
+			},
+			| TransactionStatus::Failing(..) => {
+				allow = false; // retry waits for those in flight
+			},
+			| TransactionStatus::Failed(tries, time) => {
+				// Push backoff: hold off until the exponential window elapses.
+				let min = self.server.config.sender_timeout;
+				let max = self.server.config.sender_retry_backoff_limit;
+				if continue_exponential_backoff_secs(min, max, time.elapsed(), *tries)
+					|| !self.circuit_allows(dest)
+				{
+					allow = false;
+				} else {
 					retry = true;
-					*e = TransactionStatus::Running;
-				},
-			})
-			.or_insert(TransactionStatus::Running);
+					*status = TransactionStatus::Retrying(*tries);
+				}
+			},
+			| TransactionStatus::Retrying(_) if matches!(dest, Destination::Push(..)) => {
+				allow = false; // push retry already in flight
+			},
+			| TransactionStatus::Retrying(_) if !self.circuit_allows(dest) => {
+				allow = false;
+			},
+			| TransactionStatus::Retrying(_) => {
+				// Promote to Running so a concurrent select does not double-send.
+				retry = true;
+				*status = TransactionStatus::Running(1);
+			},
+		}
 
 		Ok((allow, retry))
 	}
@@ -713,14 +899,14 @@ async fn select_edus_presence(
 			debug_assert!(count <= since.1, "exceeded upper-bound");
 
 			max_edu_count.fetch_max(count, Ordering::Relaxed);
-			if !self.services.globals.user_is_local(user_id) {
+			if !self.services.globals.user_is_local(&user_id) {
 				continue;
 			}
 
 			if !self
 				.services
-				.state_cache
-				.server_sees_user(server_name, user_id)
+				.presence
+				.presence_federates_to(server_name, &user_id)
 				.await
 			{
 				continue;
@@ -729,7 +915,7 @@ async fn select_edus_presence(
 			let Ok(presence_event) = self
 				.services
 				.presence
-				.from_json_bytes_to_event(presence_bytes, user_id)
+				.from_json_bytes_to_event(&presence_bytes, &user_id)
 				.await
 				.log_err()
 			else {
@@ -737,7 +923,7 @@ async fn select_edus_presence(
 			};
 
 			let update = PresenceUpdate {
-				user_id: user_id.into(),
+				user_id: user_id.clone(),
 				presence: presence_event.content.presence,
 				currently_active: presence_event
 					.content
@@ -750,7 +936,7 @@ async fn select_edus_presence(
 					.unwrap_or_else(|| uint!(0)),
 			};
 
-			presence_updates.insert(user_id.into(), update);
+			presence_updates.insert(user_id, update);
 			if presence_updates.len() >= SELECT_PRESENCE_LIMIT {
 				break;
 			}
@@ -776,7 +962,22 @@ async fn select_edus_presence(
 		Some(buf)
 	}
 
-	fn send_events(&self, dest: Destination, events: Vec<SendingEvent>) -> SendingFuture<'_> {
+	fn send_transaction(
+		&self,
+		dest: Destination,
+		keys: TransactionKeys,
+		events: Vec<SendingEvent>,
+	) -> SendingFuture<'_> {
+		self.send_events(dest, events)
+			.map(move |result| (result, keys))
+			.boxed()
+	}
+
+	fn send_events(
+		&self,
+		dest: Destination,
+		events: Vec<SendingEvent>,
+	) -> BoxFuture<'_, SendingResult> {
 		debug_assert!(!events.is_empty(), "sending empty transaction");
 		match dest {
 			| Destination::Federation(server) => self
@@ -859,7 +1060,10 @@ async fn send_events_dest_appservice(
 			| SendingEvent::Flush => None,
 		}));
 
-		let txn_id = &*URL_SAFE_NO_PAD.encode(txn_hash);
+		let txn_id = self
+			.db
+			.appservice_txn_id(&id, &txn_hash)
+			.to_string();
 
 		//debug_assert!(pdu_jsons.len() + edu_jsons.len() > 0, "sending empty
 		// transaction");
@@ -874,7 +1078,10 @@ async fn send_events_dest_appservice(
 			})
 			.await
 		{
-			| Ok(_) => Ok(Destination::Appservice(id)),
+			| Ok(_) => {
+				self.db.appservice_txn_done(&id);
+				Ok(Destination::Appservice(id))
+			},
 			| Err(e) => Err((Destination::Appservice(id), e)),
 		}
 	}
@@ -1271,12 +1478,23 @@ async fn send_events_dest_federation(
 				self.services
 					.timeline
 					.get_pdu_json_from_id(pdu_id)
+					.map_ok(move |pdu| (pdu_id, pdu))
 					.ok()
 			})
-			.wide_then(|pdu| {
+			.wide_then(|(pdu_id, pdu)| {
 				self.services
 					.federation
 					.format_pdu_into(pdu, None)
+					.map(move |pdu| (pdu_id, pdu))
+			})
+			.ready_filter_map(|(pdu_id, pdu)| {
+				let len = pdu.get().len();
+				if len <= MAX_PDU_BYTES {
+					return Some(pdu);
+				}
+
+				self.abandon_oversized_pdu(&server, pdu_id, len);
+				None
 			})
 			.collect()
 			.await;
@@ -1295,6 +1513,7 @@ async fn send_events_dest_federation(
 			return Ok(Destination::Federation(server));
 		}
 
+		let delivered = outgoing_counts(pdus.len(), &edus);
 		let preimage = pdus
 			.iter()
 			.map(|raw| raw.get().as_bytes())
@@ -1302,6 +1521,40 @@ async fn send_events_dest_federation(
 
 		let txn_hash = calculate_hash(preimage);
 		let txn_id = &*URL_SAFE_NO_PAD.encode(txn_hash);
+		let result = match self
+			.server
+			.config
+			.federation_relay_server
+			.as_deref()
+		{
+			| Some(relay) =>
+				self.send_via_relay(relay, &server, txn_id, pdus, edus)
+					.await,
+			| None =>
+				self.send_transaction_direct(&server, txn_id, pdus, edus)
+					.await,
+		};
+
+		match result {
+			| Ok(()) => {
+				self.server
+					.metrics
+					.outgoing
+					.record(&server, &delivered);
+
+				Ok(Destination::Federation(server))
+			},
+			| Err(error) => Err((Destination::Federation(server), error)),
+		}
+	}
+
+	async fn send_transaction_direct(
+		&self,
+		server: &ServerName,
+		txn_id: &str,
+		pdus: Vec<Box<RawJsonValue>>,
+		edus: Vec<Raw<Edu>>,
+	) -> Result {
 		let request = send_transaction_message::v1::Request {
 			transaction_id: txn_id.into(),
 			origin: self.server.name.clone(),
@@ -1310,13 +1563,13 @@ async fn send_events_dest_federation(
 			edus,
 		};
 
-		let result = self
+		let response = self
 			.services
 			.federation
-			.execute_on(&self.services.client.sender, &server, request)
-			.await;
+			.execute_on(&self.services.client.sender, server, request)
+			.await?;
 
-		for (event_id, result) in result.iter().flat_map(|resp| resp.pdus.iter()) {
+		for (event_id, result) in &response.pdus {
 			if let Err(e) = result {
 				warn!(
 					%txn_id, %server,
@@ -1325,9 +1578,201 @@ async fn send_events_dest_federation(
 			}
 		}
 
-		match result {
-			| Ok(_) => Ok(Destination::Federation(server)),
-			| Err(error) => Err((Destination::Federation(server), error)),
+		Ok(())
+	}
+
+	/// Hand the transaction to `federation_relay_server` in an envelope naming
+	/// `server` as its destination.
+	#[tracing::instrument(name = "relay", level = "debug", skip(self, pdus, edus))]
+	async fn send_via_relay(
+		&self,
+		relay: &ServerName,
+		server: &ServerName,
+		txn_id: &str,
+		pdus: Vec<Box<RawJsonValue>>,
+		edus: Vec<Raw<Edu>>,
+	) -> Result {
+		let request = super::relay::v1::Request {
+			destination: server.to_owned(),
+			transaction_id: txn_id.into(),
+			origin: self.server.name.clone(),
+			origin_server_ts: MilliSecondsSinceUnixEpoch::now(),
+			pdus,
+			edus,
+		};
+
+		self.services
+			.federation
+			.execute_on(&self.services.client.sender, relay, request)
+			.await
+			.map(|_| ())
+	}
+}
+
+/// Tally a transaction's PDUs and its EDUs by type for the outgoing
+/// federation metrics.
+fn outgoing_counts(pdus: usize, edus: &[Raw<Edu>]) -> OutgoingCounts {
+	let mut counts = OutgoingCounts::default();
+	let mut add = |kind: OutgoingKind, count: u64| {
+		if let Some(sum) = counts.get_mut(kind.index()) {
+			*sum = sum.saturating_add(count);
+		}
+	};
+
+	add(OutgoingKind::Pdu, pdus.try_into().unwrap_or(u64::MAX));
+	for edu in edus {
+		let kind = edu
+			.get_field::<&str>("edu_type")
+			.ok()
+			.flatten()
+			.map_or(OutgoingKind::Edu, OutgoingKind::of_edu);
+
+		add(kind, 1);
+	}
+
+	counts
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::VecDeque;
+
+	use ruma::{api::federation::transactions::edu::Edu, serde::Raw};
+	use tuwunel_core::metrics::OutgoingKind;
+
+	use super::{DEQUEUE_LIMIT, TransactionStatus, outgoing_counts};
+
+	const MAX_INFLIGHT: usize = 3;
+
+	#[test]
+	fn outgoing_counts_classify_edus() {
+		let edus: Vec<Raw<Edu>> = [
+			r#"{"edu_type":"m.typing","content":{}}"#,
+			r#"{"edu_type":"m.typing","content":{}}"#,
+			r#"{"edu_type":"m.receipt","content":{}}"#,
+			r#"{"edu_type":"m.device_list_update","content":{}}"#,
+		]
+		.into_iter()
+		.map(|edu| serde_json::from_str(edu).expect("valid edu"))
+		.collect();
+
+		let counts = outgoing_counts(3, &edus);
+		let count = |kind: OutgoingKind| counts[kind.index()];
+		assert_eq!(count(OutgoingKind::Pdu), 3, "pdus");
+		assert_eq!(count(OutgoingKind::Typing), 2, "typing edus");
+		assert_eq!(count(OutgoingKind::Receipt), 1, "receipt edus");
+		assert_eq!(count(OutgoingKind::Presence), 0, "presence edus");
+		assert_eq!(count(OutgoingKind::Edu), 1, "other edus");
+	}
+
+	#[test]
+	fn failure_holds_back_until_drained() {
+		let mut status = TransactionStatus::Running(1);
+		assert!(status.admit(MAX_INFLIGHT));
+		assert!(status.admit(MAX_INFLIGHT));
+		assert!(!status.admit(MAX_INFLIGHT), "admitted past the limit");
+
+		assert_eq!(status.fail(), None, "failure acted on with others in flight");
+		assert!(!status.admit(MAX_INFLIGHT), "admitted while failing");
+
+		assert!(!status.release());
+		assert!(!status.release());
+		assert!(
+			matches!(status, TransactionStatus::Retrying(1)),
+			"{status:?} is not due for a retry"
+		);
+	}
+
+	#[test]
+	fn last_failure_is_counted() {
+		let mut status = TransactionStatus::Running(2);
+		assert_eq!(status.fail(), None, "failure acted on with others in flight");
+		assert_eq!(status.fail(), Some(2), "failure of the last in flight not counted");
+	}
+
+	/// A backlog drained through the sender's bookkeeping never has more than
+	/// `MAX_INFLIGHT` transactions out at once, across a failure and its retry.
+	#[test]
+	fn backlog_drains_within_inflight_limit() {
+		const EVENTS: usize = 500;
+		const FAILED: usize = 7;
+
+		let mut status: Option<TransactionStatus> = None;
+		let mut transactions = VecDeque::new();
+		let (mut queued, mut delivered, mut peak) = (0_usize, 0_usize, 0_usize);
+
+		// Each event is dispatched as it is queued, asking for a transaction of
+		// its own as in `select_events_current`.
+		for _ in 0..EVENTS {
+			queued = queued.saturating_add(1);
+			let allow = match status.as_mut() {
+				| Some(status) => status.admit(MAX_INFLIGHT),
+				| None => {
+					status = Some(TransactionStatus::Running(1));
+					true
+				},
+			};
+
+			if allow {
+				queued = queued.saturating_sub(1);
+				transactions.push_back(1_usize);
+			}
+
+			peak = peak.max(transactions.len());
+		}
+
+		// Transactions return in turn. A success carries on with the queue as in
+		// `handle_response_ok`; one fails and holds the rest back until those in
+		// flight returned, as in `handle_response_err`.
+		let mut returned = 0_usize;
+		while let Some(events) = transactions.pop_front() {
+			returned = returned.saturating_add(1);
+			let current = status
+				.as_mut()
+				.expect("status while transactions are in flight");
+
+			if returned == FAILED {
+				queued = queued.saturating_add(events);
+				if let Some(tries) = current.fail() {
+					*current = TransactionStatus::Retrying(tries);
+				}
+			} else {
+				delivered = delivered.saturating_add(events);
+				if matches!(current, TransactionStatus::Failing(..)) {
+					current.release();
+				} else if queued > 0 {
+					let batch = queued.min(DEQUEUE_LIMIT);
+					queued = queued.saturating_sub(batch);
+					transactions.push_back(batch);
+				} else if current.release() {
+					status = None;
+				}
+			}
+
+			// The failed events are retried alone once nothing else is in flight.
+			if matches!(status, Some(TransactionStatus::Retrying(_))) {
+				assert!(transactions.is_empty(), "retried with transactions in flight");
+				status = Some(TransactionStatus::Running(1));
+
+				let batch = queued.min(DEQUEUE_LIMIT);
+				queued = queued.saturating_sub(batch);
+				transactions.push_back(batch);
+			}
+
+			peak = peak.max(transactions.len());
+			let counted = match status {
+				| Some(
+					TransactionStatus::Running(inflight)
+					| TransactionStatus::Failing(inflight, _),
+				) => inflight,
+				| _ => 0,
+			};
+
+			assert_eq!(counted, transactions.len(), "{status:?} miscounts those in flight");
 		}
+
+		assert_eq!(delivered, EVENTS, "backlog not drained");
+		assert!(status.is_none(), "{status:?} left after draining");
+		assert_eq!(peak, MAX_INFLIGHT, "peak in flight");
 	}
 }
@@ -23,6 +23,8 @@
 };
 use tuwunel_database::{Deserialized, Json, Map};
 
+pub use self::verify::{EventVerification, HashCheck, SignatureCheck};
+
 pub struct Service {
 	keypair: Box<Ed25519KeyPair>,
 	verify_keys: VerifyKeys,
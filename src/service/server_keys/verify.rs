@@ -1,12 +1,44 @@
 use ruma::{
-	CanonicalJsonObject, CanonicalJsonValue, OwnedEventId, RoomVersionId, signatures::Verified,
+	CanonicalJsonObject, CanonicalJsonValue, OwnedEventId, RoomVersionId, ServerName,
+	ServerSigningKeyId,
+	serde::{Base64, base64::Standard},
+	signatures::{Verified, to_canonical_json_string_for_signing, verify_canonical_json_bytes},
 };
 use serde_json::value::RawValue as RawJsonValue;
 use tuwunel_core::{
-	Err, Result, implement,
+	Err, Result, err, implement,
 	matrix::{event::gen_event_id_canonical_json, room_version},
+	utils::hash::sha256,
 };
 
+/// Itemized result of checking every signature and the content hash of an
+/// event, for diagnostics.
+#[derive(Debug)]
+pub struct EventVerification {
+	/// Whether the keys required by the room version are already cached.
+	pub required_keys_exist: bool,
+
+	/// Each signature found on the event as (server, key_id, outcome).
+	pub signatures: Vec<(String, String, SignatureCheck)>,
+
+	pub content_hash: HashCheck,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SignatureCheck {
+	Valid,
+	Invalid,
+	KeyNotFound,
+	Malformed,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HashCheck {
+	Valid,
+	Mismatch,
+	Missing,
+}
+
 #[implement(super::Service)]
 pub async fn validate_and_add_event_id(
 	&self,
@@ -106,3 +138,111 @@ pub async fn verify_json(
 
 	ruma::signatures::verify_json(&event_keys, event).map_err(Into::into)
 }
+
+/// Check every signature and the content hash of `event` individually rather
+/// than failing on the first error like `verify_event`.
+#[implement(super::Service)]
+pub async fn check_event(
+	&self,
+	event: &CanonicalJsonObject,
+	room_version_id: &RoomVersionId,
+) -> Result<EventVerification> {
+	let room_version_rules = room_version::rules(room_version_id)?;
+
+	let required_keys_exist = self
+		.required_keys_exist(event, &room_version_rules)
+		.await;
+
+	let redacted =
+		ruma::canonical_json::redact(event.clone(), &room_version_rules.redaction, None)
+			.map_err(|e| err!(Request(InvalidParam("Failed to redact event: {e}"))))?;
+
+	let message = to_canonical_json_string_for_signing(&redacted)
+		.map_err(|e| err!(Request(InvalidParam("Failed to canonicalize event: {e}"))))?;
+
+	let Some(CanonicalJsonValue::Object(servers)) = event.get("signatures") else {
+		return Err!(Request(InvalidParam("Event has no signatures object.")));
+	};
+
+	let mut signatures = Vec::new();
+	for (server, keys) in servers {
+		let CanonicalJsonValue::Object(keys) = keys else {
+			signatures.push((server.clone(), String::new(), SignatureCheck::Malformed));
+			continue;
+		};
+
+		for (key_id, signature) in keys {
+			let check = self
+				.check_signature(server, key_id, signature, message.as_bytes())
+				.await;
+
+			signatures.push((server.clone(), key_id.clone(), check));
+		}
+	}
+
+	Ok(EventVerification {
+		required_keys_exist,
+		signatures,
+		content_hash: check_content_hash(event),
+	})
+}
+
+#[implement(super::Service)]
+async fn check_signature(
+	&self,
+	server: &str,
+	key_id: &str,
+	signature: &CanonicalJsonValue,
+	message: &[u8],
+) -> SignatureCheck {
+	let (Ok(server), Ok(key_id), CanonicalJsonValue::String(signature)) =
+		(ServerName::parse(server), ServerSigningKeyId::parse(key_id), signature)
+	else {
+		return SignatureCheck::Malformed;
+	};
+
+	let Ok(signature) = Base64::<Standard>::parse(signature) else {
+		return SignatureCheck::Malformed;
+	};
+
+	let Ok(verify_key) = self.get_verify_key(&server, &key_id).await else {
+		return SignatureCheck::KeyNotFound;
+	};
+
+	verify_canonical_json_bytes(
+		&key_id.algorithm(),
+		verify_key.key.as_bytes(),
+		signature.as_bytes(),
+		message,
+	)
+	.map_or(SignatureCheck::Invalid, |()| SignatureCheck::Valid)
+}
+
+fn check_content_hash(event: &CanonicalJsonObject) -> HashCheck {
+	let Some(CanonicalJsonValue::Object(hashes)) = event.get("hashes") else {
+		return HashCheck::Missing;
+	};
+
+	let Some(CanonicalJsonValue::String(expected)) = hashes.get("sha256") else {
+		return HashCheck::Missing;
+	};
+
+	let Ok(expected) = Base64::<Standard>::parse(expected) else {
+		return HashCheck::Mismatch;
+	};
+
+	let mut object = event.clone();
+	for key in ["hashes", "signatures", "unsigned"] {
+		object.remove(key);
+	}
+
+	let Ok(canonical) = serde_json::to_vec(&object) else {
+		return HashCheck::Mismatch;
+	};
+
+	if sha256::hash(&canonical).as_slice() == expected.as_bytes() {
+		HashCheck::Valid
+	} else {
+		HashCheck::Mismatch
+	}
+}
@@ -0,0 +1,178 @@
+//! Joined rooms held back from an initial sync by `initial_sync_room_limit`.
+//!
+//! The remainder is kept per device and handed out in limit-sized batches by
+//! the syncs which follow, so a client in thousands of rooms receives all of
+//! them without any one response growing unbounded. What is left is recorded
+//! against the `next_batch` token of the response it was left by, and a batch
+//! is only dropped once a sync from a later token shows the response carrying
+//! it arrived; a retried sync is handed the same batch again.
+
+use futures::{FutureExt, StreamExt};
+use ruma::{DeviceId, OwnedRoomId, UserId};
+use serde::{Deserialize, Serialize};
+use tuwunel_core::{
+	debug, implement,
+	matrix::PduCount,
+	utils::{IterStream, stream::BroadbandExt},
+};
+use tuwunel_database::{Deserialized, Json};
+
+/// Rooms still to be sent to a sync from `token`.
+#[derive(Deserialize, Serialize)]
+struct Deferral {
+	token: u64,
+	rooms: Vec<OwnedRoomId>,
+}
+
+/// Split the joined rooms of an initial sync at `initial_sync_room_limit`.
+/// The most recently active rooms are returned for this response; the rest
+/// are recorded for the sync from `next_batch`, replacing anything recorded by
+/// an earlier initial sync on this device.
+#[implement(super::Service)]
+#[tracing::instrument(level = "debug", skip(self, rooms), fields(rooms = rooms.len()))]
+pub async fn limit_initial_rooms(
+	&self,
+	user_id: &UserId,
+	device_id: Option<&DeviceId>,
+	rooms: Vec<OwnedRoomId>,
+	next_batch: u64,
+) -> Vec<OwnedRoomId> {
+	let key = (user_id, device_id);
+	let limit = self.services.config.initial_sync_room_limit;
+	if limit == 0 || rooms.len() <= limit {
+		self.db.userdeviceid_syncdeferred.del(key);
+		return rooms;
+	}
+
+	let mut rooms: Vec<_> = rooms
+		.into_iter()
+		.stream()
+		.broad_then(async |room_id| {
+			let last_count = self
+				.services
+				.timeline
+				.last_timeline_count(None, &room_id, None)
+				.await
+				.unwrap_or_else(|_| PduCount::min());
+
+			(last_count, room_id)
+		})
+		.collect()
+		.boxed()
+		.await;
+
+	rooms.sort_unstable_by(|(a, _), (b, _)| b.cmp(a));
+	let deferred: Vec<_> = rooms
+		.split_off(limit)
+		.into_iter()
+		.map(|(_, room_id)| room_id)
+		.collect();
+
+	debug!(deferred = deferred.len(), "Deferring rooms from initial sync");
+	self.db
+		.userdeviceid_syncdeferred
+		.put(key, Json([Deferral { token: next_batch, rooms: deferred }]));
+
+	rooms
+		.into_iter()
+		.map(|(_, room_id)| room_id)
+		.collect()
+}
+
+/// Take the next batch of rooms deferred from this device's initial sync for
+/// the sync from `since` whose response carries `next_batch`. Returns the
+/// batch, to be sent in full as though the sync were initial for them, and the
+/// rooms still withheld after it.
+#[implement(super::Service)]
+#[tracing::instrument(level = "debug", skip(self))]
+pub async fn take_deferred_rooms(
+	&self,
+	user_id: &UserId,
+	device_id: Option<&DeviceId>,
+	since: u64,
+	next_batch: u64,
+) -> (Vec<OwnedRoomId>, Vec<OwnedRoomId>) {
+	let limit = self.services.config.initial_sync_room_limit;
+	if limit == 0 {
+		return Default::default();
+	}
+
+	let key = (user_id, device_id);
+	let Some(taken) = self
+		.pending_deferral(user_id, device_id, since)
+		.await
+	else {
+		return Default::default();
+	};
+
+	if taken.rooms.is_empty() {
+		self.db.userdeviceid_syncdeferred.del(key);
+		return Default::default();
+	}
+
+	let mut batch = taken.rooms.clone();
+	let withheld = batch.split_off(limit.min(batch.len()));
+	let left = Deferral {
+		token: next_batch,
+		rooms: withheld.clone(),
+	};
+
+	// A response which does not advance the token cannot be told apart from
+	// its retry; what is left then replaces what was taken.
+	if taken.token == next_batch {
+		self.db
+			.userdeviceid_syncdeferred
+			.put(key, Json([left]));
+	} else {
+		self.db
+			.userdeviceid_syncdeferred
+			.put(key, Json([taken, left]));
+	}
+
+	(batch, withheld)
+}
+
+/// Rooms still deferred from this device's initial sync for a sync from
+/// `since`.
+#[implement(super::Service)]
+pub async fn deferred_rooms(
+	&self,
+	user_id: &UserId,
+	device_id: Option<&DeviceId>,
+	since: u64,
+) -> Vec<OwnedRoomId> {
+	if self.services.config.initial_sync_room_limit == 0 {
+		return Vec::new();
+	}
+
+	self.pending_deferral(user_id, device_id, since)
+		.await
+		.map(|deferral| deferral.rooms)
+		.unwrap_or_default()
+}
+
+/// The deferral recorded for `since`, or the latest one for a token which is
+/// not known.
+#[implement(super::Service)]
+async fn pending_deferral(
+	&self,
+	user_id: &UserId,
+	device_id: Option<&DeviceId>,
+	since: u64,
+) -> Option<Deferral> {
+	let deferrals: Vec<Deferral> = self
+		.db
+		.userdeviceid_syncdeferred
+		.qry(&(user_id, device_id))
+		.await
+		.deserialized::<Json<_>>()
+		.map(|Json(deferrals)| deferrals)
+		.ok()?;
+
+	let position = deferrals
+		.iter()
+		.position(|deferral| deferral.token == since)
+		.unwrap_or_else(|| deferrals.len().saturating_sub(1));
+
+	deferrals.into_iter().nth(position)
+}
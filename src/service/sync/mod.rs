@@ -1,3 +1,4 @@
+mod deferred;
 mod watch;
 
 #[cfg(test)]
@@ -29,6 +30,7 @@ pub struct Service {
 
 struct Data {
 	userdeviceconnid_conn: Arc<Map>,
+	userdeviceid_syncdeferred: Arc<Map>,
 	todeviceid_events: Arc<Map>,
 	userroomid_joined: Arc<Map>,
 	userroomid_invitestate: Arc<Map>,
@@ -73,6 +75,7 @@ fn build(args: &crate::Args<'_>) -> Result<Arc<Self>> {
 		Ok(Arc::new(Self {
 			db: Data {
 				userdeviceconnid_conn: args.db["userdeviceconnid_conn"].clone(),
+				userdeviceid_syncdeferred: args.db["userdeviceid_syncdeferred"].clone(),
 				todeviceid_events: args.db["todeviceid_events"].clone(),
 				userroomid_joined: args.db["userroomid_joined"].clone(),
 				userroomid_invitestate: args.db["userroomid_invitestate"].clone(),
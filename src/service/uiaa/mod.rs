@@ -2,6 +2,7 @@
 	collections::BTreeMap,
 	ops::ControlFlow,
 	sync::{Arc, RwLock},
+	time::{Duration, Instant},
 };
 
 use futures::{TryStreamExt, pin_mut};
@@ -21,11 +22,12 @@
 };
 use tuwunel_database::{Deserialized, Json, Map};
 
-use crate::users::PASSWORD_SENTINEL;
+use crate::{appservice::RegistrationInfo, users::PASSWORD_SENTINEL};
 
 pub struct Service {
 	userdevicesessionid_uiaarequest: RwLock<RequestMap>,
 	userdevicesessionid_threepid: RwLock<ThreepidMap>,
+	userdevicesessionid_reusable: RwLock<ReusableMap>,
 	db: Data,
 	services: Arc<crate::services::OnceServices>,
 }
@@ -38,13 +40,33 @@ struct Data {
 type ThreepidMap = BTreeMap<RequestKey, ThirdpartyIdCredentials>;
 type RequestKey = (OwnedUserId, OwnedDeviceId, String);
 
+type ReusableMap = BTreeMap<RequestKey, Reusable>;
+
+/// A session which, once completed, authorizes further requests to `endpoint`.
+struct Reusable {
+	endpoint: String,
+
+	/// When the session was created, or completed once it has been.
+	since: Instant,
+	completed: bool,
+}
+
 pub const SESSION_ID_LENGTH: usize = 32;
 
+/// How long a completed reusable session keeps authorizing further requests.
+pub const REUSABLE_SESSION_WINDOW: Duration = Duration::from_mins(5);
+
+/// Stage completed by an appservice on behalf of a user in its exclusive
+/// namespace, by authenticating the request itself with its `as_token`. Only
+/// offered on the routes bridges drive for their users.
+pub const APPSERVICE_AUTH_TYPE: &str = "m.login.application_service";
+
 impl crate::Service for Service {
 	fn build(args: &crate::Args<'_>) -> Result<Arc<Self>> {
 		Ok(Arc::new(Self {
 			userdevicesessionid_uiaarequest: RwLock::new(RequestMap::new()),
 			userdevicesessionid_threepid: RwLock::new(ThreepidMap::new()),
+			userdevicesessionid_reusable: RwLock::new(ReusableMap::new()),
 			db: Data {
 				userdevicesessionid_uiaainfo: args.db["userdevicesessionid_uiaainfo"].clone(),
 			},
@@ -56,6 +78,10 @@ fn name(&self) -> &str { crate::service::make_name(std::module_path!()) }
 }
 
 /// Creates a new Uiaa session. Make sure the session token is unique.
+///
+/// Once completed, a session `reusable` on an endpoint also authorizes further
+/// requests to that endpoint requiring no more than the stages it completed,
+/// for `REUSABLE_SESSION_WINDOW`.
 #[implement(Service)]
 pub fn create(
 	&self,
@@ -63,6 +89,7 @@ pub fn create(
 	device_id: &DeviceId,
 	uiaainfo: &UiaaInfo,
 	json_body: &CanonicalJsonValue,
+	reusable: Option<&str>,
 ) {
 	// TODO: better session error handling (why is uiaainfo.session optional in
 	// ruma?)
@@ -72,10 +99,25 @@ pub fn create(
 		.expect("session should be set");
 
 	self.set_uiaa_request(user_id, device_id, session, json_body);
+	self.prune_reusable();
+
+	if let Some(endpoint) = reusable {
+		let key = (user_id.to_owned(), device_id.to_owned(), session.to_owned());
+		self.userdevicesessionid_reusable
+			.write()
+			.expect("locked for writing")
+			.insert(key, Reusable {
+				endpoint: endpoint.to_owned(),
+				since: Instant::now(),
+				completed: false,
+			});
+	}
 
 	self.update_uiaa_session(user_id, device_id, session, Some(uiaainfo));
 }
 
+/// `appservice` is the appservice the request was authenticated as, if any;
+/// `endpoint` identifies the request for reusing a completed session.
 #[implement(Service)]
 pub async fn try_auth(
 	&self,
@@ -83,10 +125,32 @@ pub async fn try_auth(
 	device_id: &DeviceId,
 	auth: &AuthData,
 	uiaainfo: &UiaaInfo,
+	appservice: Option<&RegistrationInfo>,
+	endpoint: Option<&str>,
 ) -> Result<(bool, UiaaInfo)> {
 	let mut uiaainfo = if let Some(session) = auth.session() {
-		self.get_uiaa_session(user_id, device_id, session)
-			.await?
+		let reused = self.reusable_completed(user_id, device_id, session);
+		let session_info = self
+			.get_uiaa_session(user_id, device_id, session)
+			.await?;
+
+		if let Some(reused_endpoint) = reused {
+			if endpoint != Some(reused_endpoint.as_str()) {
+				return Err!(Request(Forbidden(
+					"UIAA session was completed for a different endpoint."
+				)));
+			}
+
+			if flow_completed(uiaainfo, &session_info.completed) {
+				return Ok((true, session_info));
+			}
+
+			return Err!(Request(Forbidden(
+				"UIAA session was not completed with the stages this request requires."
+			)));
+		}
+
+		session_info
 	} else {
 		uiaainfo.clone()
 	};
@@ -187,20 +251,15 @@ pub async fn try_auth(
 				self.set_uiaa_threepid(user_id, device_id, session, thirdparty_id_creds);
 			}
 		},
+		| auth if auth.auth_type() == Some(AuthType::from(APPSERVICE_AUTH_TYPE)) =>
+			if !verify_appservice(user_id, &mut uiaainfo, appservice) {
+				return Ok((false, uiaainfo));
+			},
 		| auth => error!("AuthData type not supported: {auth:?}"),
 	}
 
 	// Check if a flow now succeeds
-	let mut completed = false;
-	'flows: for flow in &mut uiaainfo.flows {
-		for stage in &flow.stages {
-			if !uiaainfo.completed.contains(stage) {
-				continue 'flows;
-			}
-		}
-		// We didn't break, so this flow succeeded!
-		completed = true;
-	}
+	let completed = flow_completed(&uiaainfo, &uiaainfo.completed);
 
 	let session = uiaainfo
 		.session
@@ -213,12 +272,121 @@ pub async fn try_auth(
 		return Ok((false, uiaainfo));
 	}
 
-	// UIAA was successful! Remove this session and return true
-	self.update_uiaa_session(user_id, device_id, session, None);
+	// UIAA was successful! Keep a reusable session for its window, otherwise
+	// remove this session, and return true
+	if self.set_reusable_completed(user_id, device_id, session) {
+		self.update_uiaa_session(user_id, device_id, session, Some(&uiaainfo));
+	} else {
+		self.update_uiaa_session(user_id, device_id, session, None);
+	}
 
 	Ok((true, uiaainfo))
 }
 
+/// Whether every stage of any one of the flows has been completed.
+fn flow_completed(uiaainfo: &UiaaInfo, completed: &[AuthType]) -> bool {
+	uiaainfo.flows.iter().any(|flow| {
+		flow.stages
+			.iter()
+			.all(|stage| completed.contains(stage))
+	})
+}
+
+/// Marks a reusable session completed, starting its reuse window. Returns
+/// false for sessions which are not reusable.
+#[implement(Service)]
+fn set_reusable_completed(&self, user_id: &UserId, device_id: &DeviceId, session: &str) -> bool {
+	let key = (user_id.to_owned(), device_id.to_owned(), session.to_owned());
+
+	self.userdevicesessionid_reusable
+		.write()
+		.expect("locked for writing")
+		.get_mut(&key)
+		.map(|reusable| {
+			if !reusable.completed {
+				reusable.completed = true;
+				reusable.since = Instant::now();
+			}
+		})
+		.is_some()
+}
+
+/// The endpoint a reusable session may be reused on, if it was completed
+/// within the reuse window. An expired session is removed.
+#[implement(Service)]
+fn reusable_completed(
+	&self,
+	user_id: &UserId,
+	device_id: &DeviceId,
+	session: &str,
+) -> Option<String> {
+	let key = (user_id.to_owned(), device_id.to_owned(), session.to_owned());
+
+	let mut reusable = self
+		.userdevicesessionid_reusable
+		.write()
+		.expect("locked for writing");
+
+	match reusable.get(&key) {
+		| Some(entry) if entry.completed && entry.since.elapsed() < REUSABLE_SESSION_WINDOW =>
+			Some(entry.endpoint.clone()),
+		| Some(entry) if entry.completed => {
+			reusable.remove(&key);
+			self.update_uiaa_session(user_id, device_id, session, None);
+			None
+		},
+		| _ => None,
+	}
+}
+
+/// Drops reusable sessions whose window has passed, along with the sessions
+/// themselves once completed. A session not completed within the window is
+/// consumed by its completion like any other.
+#[implement(Service)]
+fn prune_reusable(&self) {
+	let mut reusable = self
+		.userdevicesessionid_reusable
+		.write()
+		.expect("locked for writing");
+
+	reusable.retain(|(user_id, device_id, session), entry| {
+		if entry.since.elapsed() < REUSABLE_SESSION_WINDOW {
+			return true;
+		}
+
+		if entry.completed {
+			self.update_uiaa_session(user_id, device_id, session, None);
+		}
+
+		false
+	});
+}
+
+/// Completes the appservice stage when the request was authenticated by an
+/// appservice whose exclusive namespace includes `user_id`, letting a bridge
+/// complete flows on behalf of its users.
+fn verify_appservice(
+	user_id: &UserId,
+	uiaainfo: &mut UiaaInfo,
+	appservice: Option<&RegistrationInfo>,
+) -> bool {
+	if !appservice.is_some_and(|info| info.is_exclusive_user_match(user_id)) {
+		uiaainfo.auth_error = Some(StandardErrorBody {
+			kind: ErrorKind::forbidden(),
+			message: "The request is not from an appservice exclusively owning this user."
+				.to_owned(),
+		});
+
+		return false;
+	}
+
+	uiaainfo
+		.completed
+		.push(AuthType::from(APPSERVICE_AUTH_TYPE));
+
+	true
+}
+
 #[implement(Service)]
 #[allow(clippy::useless_let_if_seq)]
 async fn verify_password(
@@ -84,18 +84,7 @@ pub async fn remove_device(&self, user_id: &UserId, device_id: &DeviceId) {
 		.await;
 
 	// Remove pushers
-	self.services
-		.pusher
-		.get_device_pushkeys(user_id, device_id)
-		.map(Vec::into_iter)
-		.map(IterStream::stream)
-		.flatten_stream()
-		.for_each(async |pushkey| {
-			self.services
-				.pusher
-				.delete_pusher(user_id, &pushkey)
-				.await;
-		})
+	self.remove_device_pushers(user_id, device_id)
 		.await;
 
 	// Removes the dehydrated device if the ID matches, otherwise no-op
@@ -130,6 +119,47 @@ pub async fn remove_device(&self, user_id: &UserId, device_id: &DeviceId) {
 	increment(&self.db.userid_devicelistversion, user_id.as_bytes());
 }
 
+/// Revokes the access and refresh tokens of every device of a user, leaving
+/// the devices themselves intact. Pushers registered by those devices and
+/// their pending push queues are removed as well. Returns the number of
+/// devices affected.
+#[implement(super::Service)]
+#[tracing::instrument(level = "info", skip(self))]
+pub async fn remove_all_tokens(&self, user_id: &UserId) -> usize {
+	let device_ids: Vec<OwnedDeviceId> = self
+		.all_device_ids(user_id)
+		.map(ToOwned::to_owned)
+		.collect()
+		.await;
+
+	for device_id in &device_ids {
+		self.remove_tokens(user_id, device_id).await;
+		self.remove_device_pushers(user_id, device_id)
+			.await;
+	}
+
+	device_ids.len()
+}
+
+/// Removes all pushers registered by one device of a user.
+#[implement(super::Service)]
+#[tracing::instrument(level = "debug", skip(self))]
+pub async fn remove_device_pushers(&self, user_id: &UserId, device_id: &DeviceId) {
+	self.services
+		.pusher
+		.get_device_pushkeys(user_id, device_id)
+		.map(Vec::into_iter)
+		.map(IterStream::stream)
+		.flatten_stream()
+		.for_each(async |pushkey| {
+			self.services
+				.pusher
+				.delete_pusher(user_id, &pushkey)
+				.await;
+		})
+		.await;
+}
+
 /// Returns an iterator over all device ids of this user.
 #[implement(super::Service)]
 pub fn all_device_ids<'a>(
@@ -60,6 +60,7 @@ struct Data {
 	oidcdevice_userdeviceid: Arc<Map>,
 	oidccskeybypass_userid: Arc<Map>,
 	userfilterid_filter: Arc<Map>,
+	userid_deactivated: Arc<Map>,
 	userid_dehydrateddevice: Arc<Map>,
 	userid_devicelistversion: Arc<Map>,
 	userid_lastonetimekeyupdate: Arc<Map>,
@@ -94,6 +95,7 @@ fn build(args: &crate::Args<'_>) -> Result<Arc<Self>> {
 				userdeviceid_spentrefresh: args.db["userdeviceid_spentrefresh"].clone(),
 				userdeviceidalgorithm_fallback: args.db["userdeviceidalgorithm_fallback"].clone(),
 				userfilterid_filter: args.db["userfilterid_filter"].clone(),
+				userid_deactivated: args.db["userid_deactivated"].clone(),
 				userid_dehydrateddevice: args.db["userid_dehydrateddevice"].clone(),
 				userid_devicelistversion: args.db["userid_devicelistversion"].clone(),
 				userid_lastonetimekeyupdate: args.db["userid_lastonetimekeyupdate"].clone(),
@@ -175,6 +177,12 @@ pub async fn deactivate_account(&self, user_id: &UserId) -> Result {
 		// account is deactivated.
 		self.set_password(user_id, None).await?;
 
+		// Guests and appservice users also have no password; record the
+		// deactivation itself so it can be told apart.
+		self.db
+			.userid_deactivated
+			.raw_put(user_id, utils::millis_since_unix_epoch());
+
 		// TODO: Unhook 3PID
 		Ok(())
 	}
@@ -195,6 +203,16 @@ pub async fn is_deactivated(&self, user_id: &UserId) -> Result<bool> {
 			.await
 	}
 
+	/// Whether the account was deactivated by `deactivate_account`, as opposed
+	/// to merely having no password like guests and appservice users.
+	pub async fn was_deactivated(&self, user_id: &UserId) -> bool {
+		self.db
+			.userid_deactivated
+			.get(user_id)
+			.await
+			.is_ok()
+	}
+
 	/// Check if account is active, infallible
 	pub async fn is_active(&self, user_id: &UserId) -> bool {
 		!self.is_deactivated(user_id).await.unwrap_or(true)
@@ -205,6 +223,21 @@ pub async fn is_active_local(&self, user_id: &UserId) -> bool {
 		self.services.globals.user_is_local(user_id) && self.is_active(user_id).await
 	}
 
+	/// Check that each user may be granted creator privileges in a new room.
+	/// Remote users cannot be resolved here and are accepted; local users must
+	/// have an account which is not deactivated.
+	pub async fn check_additional_creators(&self, user_ids: &[OwnedUserId]) -> Result {
+		for user_id in user_ids {
+			if self.services.globals.user_is_local(user_id) && !self.is_active(user_id).await {
+				return Err!(Request(InvalidParam(
+					"Additional creator {user_id} does not exist or is deactivated."
+				)));
+			}
+		}
+
+		Ok(())
+	}
+
 	/// MSC3823: account is suspended (read-mostly mode, sessions retained).
 	pub async fn is_suspended(&self, user_id: &UserId) -> bool {
 		self.db
@@ -357,6 +390,10 @@ pub async fn set_password(&self, user_id: &UserId, password: Option<&str>) -> Re
 			},
 		}
 
+		if password.is_some() {
+			self.db.userid_deactivated.remove(user_id);
+		}
+
 		Ok(())
 	}
 